@@ -0,0 +1,123 @@
+#![no_main]
+
+use libfuzzer_sys::{
+    arbitrary::{Result, Unstructured},
+    fuzz_target, Corpus,
+};
+use libtlafmt::*;
+
+/// Infix operators that may be given a user-configured spacing.
+const OPERATORS: &[&str] = &["++", "**", "..", "(+)", "\\oplus", "\\o", "=", "\\in", "%"];
+
+/// Derive a [`FormatOptions`] from the fuzzer input, exercising every option.
+fn options(u: &mut Unstructured<'_>) -> Result<FormatOptions> {
+    let mut opts = FormatOptions::default()
+        .with_line_width(u.int_in_range(0..=200)?)
+        .with_indent_width(u.int_in_range(0..=8)?)
+        .with_max_indent_depth(u.int_in_range(0..=300)?)
+        .with_hard_tabs(u.arbitrary()?)
+        .with_collapse_single_item_lists(u.arbitrary()?)
+        .with_align_comments(u.arbitrary()?)
+        .with_metadata_comment(u.arbitrary()?)
+        .with_align_assumptions(u.arbitrary()?)
+        .with_normalise_booleans(u.arbitrary()?)
+        .with_standard_call_wrapping(u.arbitrary()?)
+        .with_sort_record_fields(u.arbitrary()?)
+        .with_line_wrapping(u.arbitrary()?)
+        .with_strict(u.arbitrary()?)
+        .with_verify(u.arbitrary()?)
+        .with_verify_idempotent(u.arbitrary()?)
+        .with_final_newline(*u.choose(&[
+            FinalNewline::Always,
+            FinalNewline::Never,
+            FinalNewline::Preserve,
+        ])?)
+        .with_divider_length(*u.choose(&[
+            DividerLength::Fill,
+            DividerLength::Preserve,
+            DividerLength::PreserveWithHeader,
+        ])?)
+        .with_quantifier_list_layout(*u.choose(&[
+            QuantifierListLayout::NewLine,
+            QuantifierListLayout::SameLine,
+        ])?);
+
+    for _ in 0..u.int_in_range(0..=3)? {
+        let spacing = *u.choose(&[
+            OperatorSpacing::Tight,
+            OperatorSpacing::Spaced,
+            OperatorSpacing::NeverBreakBefore,
+        ])?;
+        opts = opts.with_operator_spacing(*u.choose(OPERATORS)?, spacing);
+    }
+
+    for _ in 0..u.int_in_range(0..=2)? {
+        let wrapping = *u.choose(&[CallWrapping::Unsplit, CallWrapping::BetweenArguments])?;
+        opts = opts.with_call_wrapping(*u.choose(&["Op", "F", "Len", "SubSeq"])?, wrapping);
+    }
+
+    if u.arbitrary()? {
+        opts = opts.with_assignment_width(u.int_in_range(0..=200)?);
+    }
+
+    if u.arbitrary()? {
+        opts = opts.with_record_field_order(["type", "b"]);
+    }
+
+    if u.arbitrary()? {
+        opts = opts.with_protected_comment_prefix(r"\* INCLUDE");
+    }
+
+    if u.arbitrary()? {
+        opts = opts.with_license_header("\\* Copyright 2025\n\\* SPDX-License-Identifier: MIT\n");
+    }
+
+    Ok(opts)
+}
+
+/// Returns true if `input` parses without syntax errors.
+fn is_valid(input: &str) -> bool {
+    let mut parser = tree_sitter::Parser::new();
+    parser
+        .set_language(&tree_sitter_tlaplus::LANGUAGE.into())
+        .expect("error loading TLA+ grammar");
+
+    parser
+        .parse(input, None)
+        .is_some_and(|v| !v.root_node().has_error())
+}
+
+fn format(input: &str, opts: &FormatOptions) -> Option<String> {
+    let mut out = Vec::new();
+    ParsedFile::new(input)
+        .ok()?
+        .format_with(opts, &mut out)
+        .ok()?;
+    String::from_utf8(out).ok()
+}
+
+fuzz_target!(|data: &[u8]| -> Corpus {
+    let mut u = Unstructured::new(data);
+    let Ok(opts) = options(&mut u) else {
+        return Corpus::Reject;
+    };
+    let Ok(input) = std::str::from_utf8(u.take_rest()) else {
+        return Corpus::Reject;
+    };
+
+    let Some(first) = format(input, &opts) else {
+        return Corpus::Reject;
+    };
+
+    // Specs containing syntax errors are formatted on a best-effort basis, so
+    // only valid specs are required to remain valid and be idempotent.
+    if !is_valid(input) {
+        return Corpus::Keep;
+    }
+    assert!(is_valid(&first), "formatting produced an invalid spec");
+
+    let second = format(&first, &opts).expect("formatted output must be formattable");
+    assert_eq!(first, second, "formatting is not idempotent with {opts:?}");
+
+    Corpus::Keep
+});
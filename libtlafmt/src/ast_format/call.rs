@@ -0,0 +1,130 @@
+use tree_sitter::Node;
+
+use crate::{get_str, helpers::EmptyLines, CallWrapping, Renderer, Rule};
+
+/// Returns the [`CallWrapping`] configured for the operator called by `def`, if
+/// `def` is a call that may be rewrapped.
+pub(super) fn call_wrapping<W>(
+    def: &Node<'_>,
+    input: &str,
+    writer: &Renderer<'_, W>,
+) -> Option<CallWrapping>
+where
+    W: std::io::Write,
+{
+    if def.kind() != "bound_op" {
+        return None;
+    }
+
+    let name = def.child_by_field_name("name")?;
+    let wrapping = writer.options().call_wrapping(get_str(&name, input))?;
+
+    // Removing the line breaks around a comment would comment out the code
+    // that follows it, and lists and CASE arms are aligned by their line
+    // breaks.
+    if has_layout(def) {
+        return None;
+    }
+
+    Some(wrapping)
+}
+
+/// Suppress the line breaks that `wrapping` does not permit before the child
+/// `n` of the call `def`.
+pub(super) fn wrap_call_argument<W>(
+    def: &Node<'_>,
+    n: &Node<'_>,
+    wrapping: CallWrapping,
+    empty_lines: &mut EmptyLines,
+    writer: &mut Renderer<'_, W>,
+) where
+    W: std::io::Write,
+{
+    match wrapping {
+        CallWrapping::Unsplit => {
+            // The whole call is joined when the operator name is observed.
+            if n.prev_sibling().is_none() {
+                if def.start_position().row != def.end_position().row {
+                    writer.record(Rule::CallWrapping);
+                }
+                empty_lines.suppress_within(def);
+            }
+        }
+        CallWrapping::BetweenArguments => {
+            let Some(prev) = n.prev_sibling() else {
+                return;
+            };
+            if prev.kind() == "," {
+                return;
+            }
+            if prev.end_position().row != n.start_position().row {
+                writer.record(Rule::CallWrapping);
+            }
+            empty_lines.suppress_before(n);
+        }
+    }
+}
+
+/// Returns true if `node` contains a comment, or a node that relies on its line
+/// breaks.
+pub(super) fn has_layout(node: &Node<'_>) -> bool {
+    let mut c = node.walk();
+    let children = node.named_children(&mut c).collect::<Vec<_>>();
+    children.iter().any(|v| {
+        matches!(
+            v.kind(),
+            "comment" | "block_comment" | "conj_list" | "disj_list" | "case"
+        ) || has_layout(v)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{assert_rewrite, CallWrapping, FormatOptions};
+
+    #[test]
+    fn test_standard_call_wrapping() {
+        assert_rewrite!(
+            r"
+---- MODULE Bananas ----
+A == Append(s,
+        e)
+B == Len(
+  s)
+C == SubSeq(s,
+   1, 2)
+D == SubSeq(
+    s, 1, 2
+)
+E == Cardinality({x \in S :
+        x > 1})
+F == Append(s, \* comment
+        e)
+G == Other(s,
+        e)
+====",
+            FormatOptions::default().with_standard_call_wrapping(true)
+        );
+    }
+
+    #[test]
+    fn test_call_wrapping() {
+        assert_rewrite!(
+            r"
+---- MODULE Bananas ----
+A == Append(s,
+        e)
+B == Other(s,
+        e)
+C == Op(
+    s,
+    e)
+====",
+            FormatOptions::default()
+                .with_standard_call_wrapping(true)
+                .with_call_wrapping("Append", CallWrapping::BetweenArguments)
+                .with_call_wrapping("Other", CallWrapping::Unsplit)
+                .with_call_wrapping("Op", CallWrapping::BetweenArguments)
+        );
+    }
+}
@@ -31,10 +31,16 @@ where
         {
             let orig = writer.indent_get();
             writer.indent_set(std::cmp::max(Indent::new(1), orig));
-            writer.push(Token::Comment(get_str(&def, input), Position::from(&def)))?;
+            writer.push(Token::Comment(
+                get_str(&def, input),
+                Position::of(&def, input),
+            ))?;
             writer.indent_set(orig);
         } else {
-            writer.push(Token::Comment(get_str(&def, input), Position::from(&def)))?;
+            writer.push(Token::Comment(
+                get_str(&def, input),
+                Position::of(&def, input),
+            ))?;
         }
 
         return Ok(());
@@ -43,7 +49,10 @@ where
     let orig = writer.indent_get();
 
     writer.indent_set(Indent::ZERO);
-    let ret = writer.push(Token::Comment(get_str(&def, input), Position::from(&def)));
+    let ret = writer.push(Token::Comment(
+        get_str(&def, input),
+        Position::of(&def, input),
+    ));
     writer.indent_set(orig);
 
     ret.map_err(Into::into)
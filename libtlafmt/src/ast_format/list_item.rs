@@ -1,9 +1,31 @@
 use tree_sitter::Node;
 
-use crate::{format_node, token::Token, EmptyLines, Error, Renderer};
+use crate::{
+    format_node, get_str,
+    helpers::PADDING,
+    token::{Position, Token},
+    EmptyLines, Error, QuantifierListLayout, Renderer, Rule,
+};
+
+/// The width of a list bullet and the space delimiting it from the item body.
+const BULLET_WIDTH: usize = 3;
 
 /// Render a conjunctive or disjunctive list item, indenting the body of the
 /// item by 1.
+///
+/// Comments that appear between the bullet and the body expression are placed
+/// deterministically:
+///
+///   * A comment on the same line as the bullet stays on the bullet line.
+///   * Comments on subsequent lines are rendered on their own lines, aligned
+///     with the comment on the bullet line if any, or otherwise at the
+///     indentation of the body expression.
+///
+/// The continuation lines of a multi-line block comment keep their source
+/// indentation relative to the first line of the comment.
+///
+/// The body expression is indented identically irrespective of the number of
+/// comment lines preceding it.
 pub(super) fn format_list_item<'a, W>(
     def: Node<'a>,
     input: &'a str,
@@ -14,8 +36,27 @@ where
     W: std::io::Write,
 {
     empty_lines.maybe_insert(&def, writer)?;
+
+    // Optionally render the body of a single item list without the bullet.
+    if writer.options().collapse_single_item_lists() && is_collapsible(&def) {
+        writer.record(Rule::CollapseSingleItemLists);
+        let mut c = def.walk();
+        for n in def.named_children(&mut c) {
+            if !matches!(n.kind(), "bullet_conj" | "bullet_disj") {
+                format_node(n, input, empty_lines, writer)?;
+            }
+        }
+        return Ok(());
+    }
+
     writer.push(Token::Newline)?;
 
+    // True once the body expression of the list item has been visited.
+    let mut seen_body = false;
+
+    // True if a comment follows the bullet on the bullet line.
+    let mut bullet_comment = false;
+
     let mut c = def.walk();
     let iter = def.named_children(&mut c).peekable();
     for n in iter {
@@ -28,7 +69,32 @@ where
                 writer.push(Token::Or)?;
                 writer.indent_inc();
             }
-            _ => format_node(n, input, empty_lines, writer)?,
+            // Comments at column 0 are never indented, and are handled by the
+            // generic comment formatter.
+            "comment" | "block_comment" if !seen_body && n.start_position().column != 0 => {
+                // Comments on the bullet line are space delimited from the
+                // bullet, and comments on their own lines are aligned with
+                // it, or placed at the indentation of the body.
+                //
+                // Both have a fixed position and do not participate in
+                // end-of-line comment alignment.
+                let own_line = empty_lines.maybe_insert(&n, writer)?;
+                bullet_comment |= !own_line;
+
+                if bullet_comment {
+                    // Positioned relative to the bullet.
+                    let padding = if own_line { BULLET_WIDTH } else { 1 };
+                    writer.indent_dec();
+                    format_bullet_comment(n, input, padding, BULLET_WIDTH, writer)?;
+                    writer.indent_inc();
+                } else {
+                    format_bullet_comment(n, input, 0, 0, writer)?;
+                }
+            }
+            _ => {
+                seen_body |= !matches!(n.kind(), "comment" | "block_comment");
+                format_node(n, input, empty_lines, writer)?
+            }
         }
     }
 
@@ -36,3 +102,328 @@ where
 
     Ok(())
 }
+
+/// Render the comment `n` between a bullet and the body expression, with the
+/// first line preceded by `padding` spaces.
+///
+/// Each subsequent line of a multi-line block comment is rendered `align`
+/// columns from the indentation, plus any indentation it had in the source
+/// beyond the column of the first line of the comment.
+fn format_bullet_comment<'a, W>(
+    n: Node<'a>,
+    input: &'a str,
+    padding: usize,
+    align: usize,
+    writer: &mut Renderer<'a, W>,
+) -> Result<(), Error>
+where
+    W: std::io::Write,
+{
+    let col = n.start_position().column;
+    let mut lines = get_str(&n, input).split('\n');
+
+    if let Some(first) = lines.next() {
+        writer.push(Token::Comment(first, Position::Relative(padding)))?;
+    }
+
+    for line in lines {
+        let text = line.trim_start();
+        let offset = (line.len() - text.len()).saturating_sub(col);
+
+        writer.push(Token::SourceNewline)?;
+        writer.push(Token::Comment(text, Position::Relative(align + offset)))?;
+    }
+
+    Ok(())
+}
+
+/// Render the conjunctive or disjunctive list `def` with the first item on the
+/// current line, and all subsequent items aligned beneath it.
+///
+/// Callers must check the list is suitable with [`is_same_line_list()`].
+pub(super) fn format_same_line_list<'a, W>(
+    def: Node<'a>,
+    input: &'a str,
+    empty_lines: &mut EmptyLines,
+    writer: &mut Renderer<'a, W>,
+) -> Result<(), Error>
+where
+    W: std::io::Write,
+{
+    let indent = writer.indent_get();
+    let (line_indent, offset) = writer
+        .line_offset(&bullet(&def))
+        .expect("list checked by is_same_line_list()");
+
+    if def
+        .prev_sibling()
+        .is_some_and(|v| v.end_position().row != def.start_position().row)
+    {
+        writer.record(Rule::QuantifierListSameLine);
+    }
+
+    let mut c = def.walk();
+    for (i, item) in def.named_children(&mut c).enumerate() {
+        if i == 0 {
+            empty_lines.suppress(&item);
+        } else {
+            empty_lines.maybe_insert(&item, writer)?;
+            writer.push(Token::Newline)?;
+
+            // The bullet is delimited from the padding by a single space.
+            writer.indent_set(line_indent);
+            writer.push(Token::Raw(&PADDING[..offset - 1]))?;
+        }
+
+        let mut c = item.walk();
+        for n in item.named_children(&mut c) {
+            match n.kind() {
+                "bullet_conj" | "bullet_disj" => writer.push(bullet(&def))?,
+                _ => {
+                    writer.indent_inc();
+                    format_node(n, input, empty_lines, writer)?;
+                    writer.indent_dec();
+                }
+            }
+        }
+
+        writer.indent_set(indent);
+    }
+
+    Ok(())
+}
+
+/// Returns true if the list `def` forms the body of a quantifier and should be
+/// rendered by [`format_same_line_list()`] from the current position of
+/// `writer`.
+///
+/// Only lists of items that each fit on a single line, with no comments
+/// between them, are placed on the quantifier line - all other lists start on
+/// a new line.
+pub(super) fn is_same_line_list<W>(def: &Node<'_>, writer: &Renderer<'_, W>) -> bool
+where
+    W: std::io::Write,
+{
+    if writer.options().quantifier_list_layout() != QuantifierListLayout::SameLine
+        || !matches!(def.kind(), "conj_list" | "disj_list")
+        || def
+            .parent()
+            .is_none_or(|v| v.kind() != "bounded_quantification")
+    {
+        return false;
+    }
+
+    let mut c = def.walk();
+    let single_line_items = def.named_children(&mut c).all(|item| {
+        matches!(item.kind(), "conj_item" | "disj_item")
+            && item.named_child_count() == 2
+            && item.start_position().row == item.end_position().row
+            && !breaks_line(&item)
+    });
+
+    single_line_items
+        && writer
+            .line_offset(&bullet(def))
+            .is_some_and(|(_, offset)| (1..=PADDING.len()).contains(&offset))
+}
+
+/// Returns the bullet [`Token`] of the list `def`.
+fn bullet(def: &Node<'_>) -> Token<'static> {
+    match def.kind() {
+        "conj_list" => Token::And,
+        _ => Token::Or,
+    }
+}
+
+/// Returns true if `node` contains a node that is always rendered across
+/// multiple lines.
+fn breaks_line(node: &Node<'_>) -> bool {
+    let mut c = node.walk();
+    let children = node.named_children(&mut c).collect::<Vec<_>>();
+    children
+        .iter()
+        .any(|v| matches!(v.kind(), "conj_list" | "disj_list" | "case") || breaks_line(v))
+}
+
+/// Returns true if the list item `def` is the only item in its list, and the
+/// list can be replaced by the item body without changing the meaning of the
+/// spec.
+///
+/// Items with a comment between the bullet and the body are never collapsible,
+/// as the comment is anchored to the bullet.
+pub(crate) fn is_collapsible(def: &Node<'_>) -> bool {
+    let Some(list) = def.parent() else {
+        return false;
+    };
+
+    // Only collapse lists that are not an operand of an enclosing expression,
+    // where removing the list could change the operator precedence.
+    if !list.parent().is_some_and(|v| {
+        matches!(
+            v.kind(),
+            "operator_definition" | "parentheses" | "conj_item" | "disj_item"
+        )
+    }) {
+        return false;
+    }
+
+    let mut c = list.walk();
+    let items = list
+        .named_children(&mut c)
+        .filter(|v| matches!(v.kind(), "conj_item" | "disj_item"))
+        .count();
+
+    items == 1
+        && def
+            .named_child(1)
+            .is_some_and(|v| !matches!(v.kind(), "comment" | "block_comment"))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{assert_rewrite, FormatOptions, QuantifierListLayout};
+
+    #[test]
+    fn test_conj_comment_after_bullet() {
+        assert_rewrite!(
+            r"
+---- MODULE B ----
+Op ==
+    /\ \* note
+       x = 1
+    /\ (* block *) y = 2
+    /\ \* bananas
+            \A x \in S:
+                x = 1
+    /\ \* two
+       \* lines
+       y = 1
+    /\ \* nested
+       /\ a = 1
+       /\ b = 2
+    /\ c = 3
+====
+"
+        );
+    }
+
+    #[test]
+    fn test_conj_block_comment_after_bullet() {
+        assert_rewrite!(
+            r"
+---- MODULE B ----
+Op ==
+    /\ \* note
+       (* one
+            two
+          three *)
+       x = 1
+    /\ (* four
+          five *)
+       y = 2
+    /\
+       (* six
+            seven *)
+       z = 3
+====
+"
+        );
+    }
+
+    #[test]
+    fn test_disj_comment_after_bullet() {
+        assert_rewrite!(
+            r"
+---- MODULE B ----
+Op ==
+    \/ \* note
+       x = 1
+    \/ (* block *) y = 2
+    \/ \* two
+       \* lines
+       \E x \in S:
+           x = 1
+    \/ \* nested
+       \/ a = 1
+       \/ b = 2
+    \/ c = 3
+====
+"
+        );
+    }
+
+    #[test]
+    fn test_single_item_preserved() {
+        assert_rewrite!(
+            r"
+---- MODULE B ----
+Op == /\ x = 1
+Op2 ==
+    \/ y = 2
+====
+"
+        );
+    }
+
+    #[test]
+    fn test_single_item_collapsed() {
+        assert_rewrite!(
+            r"
+---- MODULE B ----
+Op == /\ x = 1
+Op2 ==
+    \/ y = 2 \* platanos
+Op3 == /\ \/ a = 1
+       /\ b = 2
+Op4 ==
+    /\ \* bananas
+       x = 1
+Op5 == (/\ x = 1) \/ y
+Op6 == A => /\ x = 1
+====
+",
+            FormatOptions::default().with_collapse_single_item_lists(true)
+        );
+    }
+
+    #[test]
+    fn test_quantifier_list_new_line() {
+        assert_rewrite!(
+            r"
+---- MODULE B ----
+Op == \E t \in Threads: \/ A
+                        \/ B
+Op2 == \A t \in Threads:
+    /\ A
+    /\ B
+====
+"
+        );
+    }
+
+    #[test]
+    fn test_quantifier_list_same_line() {
+        assert_rewrite!(
+            r"
+---- MODULE B ----
+Op == \E t \in Threads: \/ A
+                        \/ B
+Op2 == \A t \in Threads:
+    /\ A
+
+    /\ B
+Op3 == /\ \E t \in Threads :
+          \/ A
+          \/ B = 1
+       /\ C
+Op4 == \E t \in Threads: \/ A
+                         \/ /\ B
+                            /\ C
+Op5 == \E t \in Threads: \/ A \* comment
+                         \/ B
+====
+",
+            FormatOptions::default().with_quantifier_list_layout(QuantifierListLayout::SameLine)
+        );
+    }
+}
@@ -3,12 +3,16 @@
 //! [`Token`]: crate::token::Token
 //! [`Node`]: tree_sitter::Node
 
+mod call;
 mod case;
 mod comment;
 mod list_item;
 mod module;
 mod node;
+mod record;
+mod wrap;
 
 use comment::*;
+pub(crate) use list_item::is_collapsible;
 use module::*;
 pub(crate) use node::*;
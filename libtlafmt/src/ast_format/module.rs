@@ -1,8 +1,31 @@
-use std::{io::Write, iter::Peekable};
+use std::{io::Write, iter::Peekable, sync::LazyLock};
 
 use tree_sitter::Node;
 
-use crate::{ast_format::format_node, get_str, helpers::EmptyLines, token::Token, Error, Renderer};
+use crate::{
+    ast_format::format_node,
+    get_str,
+    helpers::{self, column_at, EmptyLines, PADDING},
+    modeline::directive,
+    token::{Position, Token},
+    Diagnostic, DividerLength, Error, Location, Renderer, Rule,
+};
+
+/// The prefix identifying an existing formatting metadata comment.
+const METADATA_PREFIX: &str = r"\* formatted with tlafmt ";
+
+/// The style edition of the formatted output, changed only when the output
+/// produced for existing specs changes.
+const STYLE_EDITION: &str = "2025";
+
+/// The formatting metadata comment, recording the formatter version and style
+/// edition that produced the output.
+static METADATA_COMMENT: LazyLock<String> = LazyLock::new(|| {
+    format!(
+        "{METADATA_PREFIX}v{} (style {STYLE_EDITION})",
+        env!("CARGO_PKG_VERSION")
+    )
+});
 
 /// Format a TLA module.
 pub(super) fn format_module<'a, 'b: 'a, W>(
@@ -18,13 +41,68 @@ where
 
     let mut c = n.walk();
     let mut iter = c.node().named_children(&mut c).peekable();
+    let mut metadata = out.options().metadata_comment();
 
     while let Some(node) = iter.peek() {
+        // Place the metadata comment before the module footer, unless an
+        // existing comment has been replaced.
+        if metadata && node.kind() == "double_line" {
+            out.push(Token::SourceNewline)?;
+            out.push(Token::Comment(&METADATA_COMMENT, Position::Relative(0)))?;
+            out.record(Rule::MetadataComment);
+            metadata = false;
+        }
+
         // Emit newlines, squashing repeated newlines to at most 1 empty line.
         empty_lines.maybe_insert(node, out)?;
 
         match node.kind() {
             "header_line" => format_module_header(&mut iter, input, out),
+            // Replace any existing metadata comment in-place, unless it
+            // records the current style edition (irrespective of the version
+            // that produced it).
+            "comment" if metadata && get_str(node, input).starts_with(METADATA_PREFIX) => {
+                let existing = get_str(node, input);
+                let comment = match metadata_edition(existing) {
+                    Some(STYLE_EDITION) => existing,
+                    _ => {
+                        out.record(Rule::MetadataComment);
+                        METADATA_COMMENT.as_str()
+                    }
+                };
+                iter.next();
+                metadata = false;
+                out.push(Token::Comment(comment, Position::Relative(0)))
+                    .map_err(Into::into)
+            }
+            // Emit protected comments, and the line breaks following them,
+            // verbatim.
+            "comment" | "block_comment"
+                if out.options().is_protected_comment(get_str(node, input)) =>
+            {
+                out.record(Rule::ProtectedComments);
+                format_protected(&mut iter, input, empty_lines, out)
+            }
+            // Emit the source up to and including the next `tlafmt: on`
+            // directive verbatim.
+            _ if is_directive(node, input, "off") => {
+                format_verbatim(&mut iter, input, empty_lines, out, |v| {
+                    is_directive(v, input, "on")
+                })
+            }
+            // Emit the source up to and including the next definition or
+            // statement verbatim.
+            _ if is_directive(node, input, "ignore") => {
+                format_verbatim(&mut iter, input, empty_lines, out, |v| {
+                    !matches!(v.kind(), "comment" | "block_comment")
+                })
+            }
+            // Emit definitions and statements containing syntax errors
+            // verbatim, formatting the remainder of the module.
+            _ if node.has_error() => format_erroneous(&mut iter, input, empty_lines, out),
+            "assumption" if out.options().align_assumptions() => {
+                format_assumptions(&mut iter, input, empty_lines, out)
+            }
             _ => format_node(iter.next().unwrap(), input, empty_lines, out),
         }?;
     }
@@ -32,6 +110,16 @@ where
     Ok(())
 }
 
+/// Returns the style edition recorded by the metadata comment `s`.
+fn metadata_edition(s: &str) -> Option<&str> {
+    let (_, edition) = s
+        .strip_prefix(METADATA_PREFIX)?
+        .trim_end()
+        .strip_suffix(')')?
+        .rsplit_once("(style ")?;
+    Some(edition)
+}
+
 /// Consume the module header nodes from `iter`, printing a normalised module
 /// header.
 fn format_module_header<'a, W>(
@@ -54,24 +142,225 @@ where
 
     let ident = match iter.next_if(|v| v.kind() == "identifier") {
         Some(v) => v,
-        None => return Err(Error::ModuleHeader),
+        None => return Err(Error::ModuleHeader(Location::of(&left, input))),
     };
 
-    match iter.next_if(|v| v.kind() == "header_line") {
-        Some(_) => {}
-        None => return Err(Error::ModuleHeader),
+    let right = match iter.next_if(|v| v.kind() == "header_line") {
+        Some(v) => v,
+        None => return Err(Error::ModuleHeader(Location::of(&left, input))),
     };
 
     let name = get_str(&ident, input).trim_ascii();
 
-    out.push(Token::ModuleHeader(name))?;
+    // The dashes may retain their length from the input.
+    let dashes = match out.options().divider_length() {
+        DividerLength::PreserveWithHeader => {
+            Some((get_str(&left, input).len(), get_str(&right, input).len()))
+        }
+        DividerLength::Fill | DividerLength::Preserve => None,
+    };
+
+    let width = column_at(input, right.end_byte()) - column_at(input, left.start_byte());
+    if dashes.is_none() && width != out.options().line_width() {
+        out.record(Rule::ModuleHeader);
+    }
+
+    out.push(Token::ModuleHeader(name, dashes))?;
+
+    Ok(())
+}
+
+/// Consume a block of consecutive `ASSUME` statements from `iter`, aligning the
+/// `==` of named assumptions and separating each by exactly one empty line.
+fn format_assumptions<'a, W>(
+    iter: &mut Peekable<impl ExactSizeIterator<Item = Node<'a>>>,
+    input: &'a str,
+    empty_lines: &mut EmptyLines,
+    out: &mut Renderer<'a, W>,
+) -> Result<(), Error>
+where
+    W: Write,
+{
+    let mut block = vec![iter.next().unwrap()]; // Validated by caller.
+    while let Some(n) = iter.next_if(|v| v.kind() == "assumption" && !v.has_error()) {
+        block.push(n);
+    }
+
+    // The rendered width of the keyword and name of a named assumption.
+    let head_width = |n: &Node<'_>| {
+        let name = n.child_by_field_name("name")?;
+        let keyword = n.child(0)?;
+        let name = out.renamed(&name).unwrap_or(get_str(&name, input));
+        Some(helpers::width(get_str(&keyword, input)) + 1 + helpers::width(name))
+    };
+    let widths = block.iter().map(head_width).collect::<Vec<_>>();
+    let width = widths.iter().flatten().max().copied().unwrap_or_default();
+
+    let mut prev: Option<Node<'_>> = None;
+    for (n, head) in block.into_iter().zip(widths) {
+        if let Some(prev) = prev {
+            if n.start_position().row != prev.end_position().row + 2 {
+                out.record(Rule::AssumptionBlocks);
+            }
+            out.push(Token::SourceNewline)?;
+            out.push(Token::SourceNewline)?;
+        }
+        prev = Some(n);
+        empty_lines.suppress_before(&n);
+
+        let padding = head.map(|v| width - v);
+
+        let mut c = n.walk();
+        for child in n.children(&mut c) {
+            empty_lines.maybe_insert(&child, out)?;
+
+            match child.kind() {
+                "ASSUME" | "ASSUMPTION" | "AXIOM" => {
+                    out.push(Token::Raw(get_str(&child, input)))?;
+                    continue;
+                }
+                "def_eq" => {
+                    let want = column_at(input, n.start_byte()) + width + 1;
+                    if padding.is_some() && column_at(input, child.start_byte()) != want {
+                        out.record(Rule::AssumptionBlocks);
+                    }
+                }
+                _ => {}
+            }
+
+            out.indent_inc();
+            format_node(child, input, empty_lines, out)?;
+            out.indent_dec();
+
+            // Pad the name of the assumption to align the `==` that follows.
+            if Some(child) == n.child_by_field_name("name") {
+                if let Some(v) = padding
+                    .filter(|v| *v > 0)
+                    .and_then(|v| PADDING.get(..v - 1))
+                {
+                    out.push(Token::Raw(v))?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns true if `n` is a `tlafmt: <name>` directive comment.
+fn is_directive(n: &Node<'_>, input: &str, name: &str) -> bool {
+    matches!(n.kind(), "comment" | "block_comment") && directive(get_str(n, input)) == Some(name)
+}
+
+/// Consume the nodes from a directive comment up to and including the first
+/// node matching `end` (or the end of the module) from `iter`, emitting the
+/// source they span verbatim.
+fn format_verbatim<'a, W>(
+    iter: &mut Peekable<impl ExactSizeIterator<Item = Node<'a>>>,
+    input: &'a str,
+    empty_lines: &mut EmptyLines,
+    out: &mut Renderer<'a, W>,
+    end: impl Fn(&Node<'_>) -> bool,
+) -> Result<(), Error>
+where
+    W: Write,
+{
+    let first = iter.next().unwrap(); // Validated by caller.
+
+    let mut last = first;
+    while let Some(n) = iter.next_if(|v| v.kind() != "double_line") {
+        last = n;
+        if end(&n) {
+            break;
+        }
+    }
+
+    // Line breaks within the verbatim region are part of the raw output.
+    empty_lines.suppress(&last);
+
+    out.push(Token::Verbatim(&input[first.start_byte()..last.end_byte()]))?;
+
+    Ok(())
+}
+
+/// Consume a definition or statement containing a syntax error from `iter`,
+/// emitting the source lines it spans verbatim.
+fn format_erroneous<'a, W>(
+    iter: &mut Peekable<impl ExactSizeIterator<Item = Node<'a>>>,
+    input: &'a str,
+    empty_lines: &mut EmptyLines,
+    out: &mut Renderer<'a, W>,
+) -> Result<(), Error>
+where
+    W: Write,
+{
+    let n = iter.next().unwrap(); // Validated by caller.
+
+    // Retain the indentation of the first line, unless the node follows
+    // another on the same line.
+    let line_start = n.start_byte() - n.start_position().column;
+    let start = match input[line_start..n.start_byte()].trim().is_empty() {
+        true => line_start,
+        false => n.start_byte(),
+    };
+
+    out.warn(Diagnostic::VerbatimRegion {
+        first_line: n.start_position().row + 1,
+        last_line: n.end_position().row + 1,
+    });
+
+    // Line breaks within the verbatim region are part of the raw output.
+    empty_lines.suppress(&n);
+
+    out.push(Token::Verbatim(&input[start..n.end_byte()]))?;
+
+    Ok(())
+}
+
+/// Consume a protected comment from `iter`, emitting it verbatim (preceded by
+/// the same padding as the input) along with the line breaks between it and
+/// the following node.
+fn format_protected<'a, W>(
+    iter: &mut Peekable<impl ExactSizeIterator<Item = Node<'a>>>,
+    input: &'a str,
+    empty_lines: &mut EmptyLines,
+    out: &mut Renderer<'a, W>,
+) -> Result<(), Error>
+where
+    W: Write,
+{
+    let comment = iter.next().unwrap(); // Validated by caller.
+
+    // Retain the padding of a trailing comment rather than realigning it.
+    let padding = match comment.prev_sibling() {
+        Some(prev) if prev.end_position().row == comment.start_position().row => {
+            comment.start_byte() - prev.end_byte()
+        }
+        _ => 0,
+    };
+    out.push(Token::Comment(
+        get_str(&comment, input),
+        Position::Relative(padding),
+    ))?;
+
+    // Retain the line breaks before the next node, if it begins on a
+    // subsequent line.
+    if let Some(next) = iter.peek() {
+        if next.start_position().row > comment.end_position().row {
+            empty_lines.suppress_before(next);
+            out.push(Token::Raw(
+                &input[comment.end_byte()..next.start_byte() - next.start_position().column],
+            ))?;
+        }
+    }
 
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::assert_rewrite;
+    use super::*;
+    use crate::{assert_rewrite, FormatOptions, ParsedFile};
 
     #[test]
     fn test_module_header_length_normalisation() {
@@ -144,4 +433,240 @@ LOCAL S == 24
 ================================================================================"
         );
     }
+
+    #[test]
+    fn test_verbatim_region() {
+        assert_rewrite!(
+            r"
+---- MODULE A ----
+X    == 1
+\* tlafmt: off
+M == << 1,   2,
+        30,  4 >>
+
+T == [a |-> 1,   b |-> 2]   \* aligned
+\* tlafmt: on
+Y    == 2
+(* tlafmt: off *)
+Z    ==   3
+===="
+        );
+    }
+
+    /// Trailing whitespace and the indentation of lines in a verbatim region
+    /// are retained byte-for-byte.
+    #[test]
+    fn test_verbatim_region_whitespace() {
+        let region = "\\* tlafmt: off\nM == << 1,   2,  \n        30,  4 >>\t\n\\* tlafmt: on";
+        let input = format!("---- MODULE A ----\nX    == 1\n{region}\nY    == 2\n====");
+
+        let mut buf = Vec::new();
+        ParsedFile::new(&input).unwrap().format(&mut buf).unwrap();
+
+        let want = format!(
+            "{0} MODULE A {0}\nX == 1\n{region}\nY == 2\n{1}",
+            "-".repeat(35),
+            "=".repeat(80)
+        );
+        assert_eq!(String::from_utf8(buf).unwrap(), want);
+    }
+
+    #[test]
+    fn test_ignore_next_definition() {
+        assert_rewrite!(
+            r"
+---- MODULE A ----
+X    == 1
+\* tlafmt: ignore
+\* The table below is aligned by hand.
+M == << 1,   2,
+        30,  4 >>
+Y    == 2
+(* tlafmt: ignore *)
+Z    ==   3
+====="
+        );
+    }
+
+    #[test]
+    fn test_metadata_comment() {
+        let format = |input: &str| {
+            let mut buf = Vec::new();
+            ParsedFile::new(input)
+                .unwrap()
+                .format_with(
+                    &FormatOptions::default().with_metadata_comment(true),
+                    &mut buf,
+                )
+                .unwrap();
+            String::from_utf8(buf).unwrap()
+        };
+
+        let want = |comment: &str| {
+            format!(
+                "{0} MODULE A {0}\nX == 1\n{comment}\n{1}",
+                "-".repeat(35),
+                "=".repeat(80)
+            )
+        };
+
+        // Added before the footer, and maintained idempotently.
+        let got = format("---- MODULE A ----\nX == 1\n====\n");
+        assert_eq!(got, want(&METADATA_COMMENT));
+        assert_eq!(format(&got), got);
+
+        // A comment from a different style edition is replaced.
+        let got = format(
+            "---- MODULE A ----\nX == 1\n\\* formatted with tlafmt v0.1.0 (style 2024)\n====\n",
+        );
+        assert_eq!(got, want(&METADATA_COMMENT));
+
+        // While a comment from a different version with the same style edition
+        // is retained.
+        let comment = format!("\\* formatted with tlafmt v0.1.0 (style {STYLE_EDITION})");
+        let got = format(&want(&comment));
+        assert_eq!(got, want(&comment));
+
+        // And left untouched when disabled.
+        let want = want(&METADATA_COMMENT);
+        let mut buf = Vec::new();
+        ParsedFile::new(&want).unwrap().format(&mut buf).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), want);
+    }
+
+    #[test]
+    fn test_divider_length() {
+        let format = |input: &str, v: DividerLength| {
+            let mut buf = Vec::new();
+            ParsedFile::new(input)
+                .unwrap()
+                .format_with(&FormatOptions::default().with_divider_length(v), &mut buf)
+                .unwrap();
+            String::from_utf8(buf).unwrap()
+        };
+
+        let input = "---- MODULE A ------
+X == 1
+-----
+Y == 2
+=====
+";
+
+        let got = format(input, DividerLength::Fill);
+        assert_eq!(
+            got,
+            format!(
+                "{0} MODULE A {0}\nX == 1\n{1}\nY == 2\n{2}",
+                "-".repeat(35),
+                "-".repeat(80),
+                "=".repeat(80)
+            )
+        );
+
+        let want = format!(
+            "{0} MODULE A {0}\nX == 1\n-----\nY == 2\n=====",
+            "-".repeat(35)
+        );
+        let got = format(input, DividerLength::Preserve);
+        assert_eq!(got, want);
+        assert_eq!(format(&got, DividerLength::Preserve), want);
+
+        let want = "---- MODULE A ------\nX == 1\n-----\nY == 2\n=====";
+        let got = format(input, DividerLength::PreserveWithHeader);
+        assert_eq!(got, want);
+        assert_eq!(format(&got, DividerLength::PreserveWithHeader), want);
+    }
+
+    #[test]
+    fn test_protected_comments() {
+        assert_rewrite!(
+            r"
+---- MODULE Bananas ----
+X   ==   1      \*   INCLUDE  common_defs
+
+
+
+\*   INCLUDE  other_defs
+Y   ==   2  \* aligned
+Z   ==   3
+
+
+\* Not protected.
+
+
+W == 4
+====",
+            FormatOptions::default().with_protected_comment_prefix(r"\*   INCLUDE")
+        );
+    }
+
+    #[test]
+    fn test_align_assumptions() {
+        assert_rewrite!(
+            r"
+---- MODULE Bananas ----
+CONSTANT N, Q
+ASSUME NumNodes == N \in Nat
+ASSUME Q \in 1..N
+
+
+
+ASSUMPTION Quorum ==
+    /\ Q > 0
+    /\ Q <= N
+AXIOM A == TRUE
+AXIOM Bananas    ==    TRUE
+====",
+            FormatOptions::default().with_align_assumptions(true)
+        );
+    }
+
+    /// Definitions containing a syntax error are emitted verbatim, while the
+    /// rest of the module is formatted.
+    #[test]
+    fn test_syntax_error_verbatim() {
+        let input = "---- MODULE A ----\nX   ==   1\n  Y ==  )\nZ   ==   2\n====\n";
+        let parsed = ParsedFile::new(input).unwrap();
+
+        let mut buf = vec![];
+        let diagnostics = parsed
+            .format_with_diagnostics(&FormatOptions::default(), &mut buf)
+            .unwrap();
+
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            format!(
+                "{0} MODULE A {0}\nX == 1\n  Y ==  )\nZ == 2\n{1}",
+                "-".repeat(35),
+                "=".repeat(80)
+            )
+        );
+        assert_eq!(
+            diagnostics,
+            [
+                Diagnostic::SyntaxError {
+                    line: 3,
+                    column: 3,
+                    excerpt: "Y ==  )".into(),
+                    missing: None,
+                },
+                Diagnostic::VerbatimRegion {
+                    first_line: 3,
+                    last_line: 3
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_module_header_location() {
+        let input = "\\* A comment\n---- MODULE A B ----\n====\n";
+        let got = ParsedFile::new(input).unwrap().format(vec![]);
+
+        assert!(matches!(
+            got,
+            Err(Error::ModuleHeader(Location { line: 2, column: 1, ref excerpt }))
+                if excerpt == "---- MODULE A B ----"
+        ));
+    }
 }
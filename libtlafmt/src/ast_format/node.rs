@@ -1,11 +1,18 @@
 use tree_sitter::Node;
 
 use crate::{
-    ast_format::{case::format_case, format_comment, format_module, list_item::format_list_item},
+    ast_format::{
+        call::{call_wrapping, wrap_call_argument},
+        case::format_case,
+        format_comment, format_module,
+        list_item::{format_list_item, format_same_line_list, is_same_line_list},
+        record::sorted_record_fields,
+        wrap::{element_wrapping, wrap_after, wrap_before},
+    },
     get_str,
     helpers::EmptyLines,
     token::Token,
-    Error, Renderer,
+    Diagnostic, DividerLength, Error, FormatOptions, Location, OperatorSpacing, Renderer, Rule,
 };
 
 /// Format an arbitrary AST node.
@@ -27,8 +34,37 @@ where
     // indentation matches at the end of this fn to ensure balanced inc / dec.
     let indent_depth = writer.indent_get();
 
+    // Infix operators with user-configured spacing replace the default token.
+    if let Some(spacing) = operator_spacing(&def, input, writer.options()) {
+        writer.record(Rule::OperatorSpacing);
+        writer.push(Token::InfixOp(get_str(&def, input), spacing))?;
+        return Ok(());
+    }
+
     // Some tokens can be extracted one-to-one from the AST.
     if let Some(t) = into_output_token(&def, input) {
+        // Identifiers being renamed are emitted with their new name.
+        let t = match (t, writer.renamed(&def)) {
+            (Token::Ident(_), Some(v)) => Token::Ident(v),
+            (t, _) => t,
+        };
+
+        // Dividing lines may retain their length from the input.
+        let t = match t {
+            Token::LineDivider(c, None)
+                if writer.options().divider_length() != DividerLength::Fill =>
+            {
+                Token::LineDivider(c, Some(get_str(&def, input).len()))
+            }
+            Token::LineDivider(_, None) => {
+                if get_str(&def, input).len() != writer.options().line_width() {
+                    writer.record(Rule::Dividers);
+                }
+                t
+            }
+            t => t,
+        };
+
         match t {
             // Suppress empty an ident that results in extraneous spacing.
             Token::Ident("") => return Ok(()),
@@ -121,10 +157,14 @@ where
         // A `[ident]_vars` sequence.
         "]_" => return Ok(()), // Part of the AST that is emitted below.
         "step_expr_or_stutter" => {
-            let ident = def.named_child(0).ok_or(Error::StepOrStutter)?;
-            writer.push(Token::StepOrStutter(get_str(&ident, input)))?;
-
-            let vars = def.named_child(1).ok_or(Error::StepOrStutter)?;
+            let invalid = || Error::StepOrStutter(Location::of(&def, input));
+            let ident = def.named_child(0).ok_or_else(invalid)?;
+            let name = writer
+                .renamed(&ident)
+                .unwrap_or_else(|| get_str(&ident, input));
+            writer.push(Token::StepOrStutter(name))?;
+
+            let vars = def.named_child(1).ok_or_else(invalid)?;
             format_node(vars, input, empty_lines, writer)?;
 
             return Ok(());
@@ -136,12 +176,28 @@ where
             return format_list_item(def, input, empty_lines, writer);
         }
 
+        // The body of a list item is already indented by the bullet, even when
+        // a comment between the two causes the body to start on a subsequent
+        // line.
+        v if (may_indent.contains(&v) || matches!(v, "disj_list" | "conj_list"))
+            && is_list_item_body_after_comment(&def) =>
+        {
+            skip_indent = true;
+        }
+
+        // A list forming the body of a quantifier, placed on the same line as
+        // the quantifier.
+        "disj_list" | "conj_list" if is_same_line_list(&def, writer) => {
+            return format_same_line_list(def, input, empty_lines, writer);
+        }
+
         // These are always indented.
         "disj_list" | "conj_list" | "let_in" => skip_indent = false,
 
-        // Operators are not indented if they are the top level definition, and
-        // are indented if they are within a definition (excluding LOCALs).
-        "operator_definition"
+        // Operators and functions are not indented if they are the top level
+        // definition, and are indented if they are within a definition
+        // (excluding LOCALs).
+        "operator_definition" | "function_definition"
             if def
                 .parent()
                 .is_some_and(|v| matches!(v.kind(), "module" | "local_definition")) =>
@@ -204,6 +260,8 @@ where
         | "local_definition"
         | "set_filter"
         | "subexpr_component"
+        | "subexpr_prefix"
+        | "prefixed_op"
         | "infix_op_symbol"
         | "instance"
         | "domain"
@@ -213,41 +271,87 @@ where
 
         // Syntax errors reported by the AST parser.
         "ERROR" => {
-            #[cfg(not(fuzzing))] // No output during fuzzing for faster execs.
-            eprintln!(
-                "[ERROR] syntax parsing error for {:?} => '{}'",
-                def,
-                get_str(&def, input)
-            );
             writer.push(Token::Raw(get_str(&def, input)))?;
             return Ok(());
         }
 
         // Unformatted nodes that are printed as-is.
         _ => {
-            #[cfg(not(fuzzing))] // No output during fuzzing for faster execs.
-            eprintln!(
-                "[WARN] unformatted node {:?} => '{}'",
-                def,
-                get_str(&def, input)
-            );
+            writer.warn(Diagnostic::Unformatted {
+                kind: def.kind(),
+                line: def.start_position().row + 1,
+                snippet: get_str(&def, input)
+                    .lines()
+                    .next()
+                    .unwrap_or_default()
+                    .into(),
+            });
             writer.push(Token::Raw(get_str(&def, input)))?;
             return Ok(());
         }
     }
 
+    // Calls to operators with a wrapping hint may have line breaks removed.
+    let wrapping = call_wrapping(&def, input, writer);
+
+    // Record fields may be rendered in a different order to the input.
+    let sorted = sorted_record_fields(&def, input, writer);
+
+    // Some expressions are broken across lines when exceeding the line width.
+    let elements = element_wrapping(&def, input, writer);
+
     // Begin rewriting the definition body.
     let mut c = def.walk();
     let iter = def.children(&mut c);
 
-    for n in iter {
+    for (i, n) in iter.enumerate() {
+        if let Some(v) = wrapping {
+            wrap_call_argument(&def, &n, v, empty_lines, writer);
+        }
+
+        // Qualified references (`Inst!Op(x)`) are never split between the
+        // module prefix, the `!` and the name, only within any arguments.
+        if def.kind() == "prefixed_op" {
+            match n.kind() {
+                "subexpr_prefix" => empty_lines.suppress_within(&n),
+                _ => empty_lines.suppress_before(&n),
+            }
+        }
+
+        // Never break the line before an operator configured to follow its
+        // left operand.
+        if operator_spacing(&n, input, writer.options()) == Some(OperatorSpacing::NeverBreakBefore)
+        {
+            empty_lines.suppress(&n);
+        }
+
+        // Nor before a quantifier body placed on the same line as the
+        // quantifier.
+        if is_same_line_list(&n, writer) {
+            empty_lines.suppress_before(&n);
+        }
+
         empty_lines.maybe_insert(&n, writer)?;
 
         if !skip_indent {
             writer.indent_inc();
         }
 
-        format_node(n, input, empty_lines, writer)?;
+        if let Some(v) = elements {
+            wrap_before(&n, v, writer)?;
+        }
+
+        match sorted.as_ref().map(|v| v[i]).filter(|v| *v != n) {
+            // A field moved within the record retains its own line breaks,
+            // placed in the layout of the field it replaces.
+            Some(moved) => format_node(moved, input, &mut EmptyLines::starting_at(&moved), writer)?,
+            None => format_node(n, input, empty_lines, writer)?,
+        }
+
+        if let Some(v) = elements {
+            let rendered = sorted.as_ref().map_or(n, |v| v[i]);
+            wrap_after(&rendered, v, input, writer)?;
+        }
 
         if !skip_indent {
             writer.indent_dec();
@@ -259,8 +363,33 @@ where
     Ok(())
 }
 
+/// Returns true if `node` is the body of a conjunction / disjunction list item
+/// that is preceded by a comment.
+fn is_list_item_body_after_comment(node: &Node<'_>) -> bool {
+    node.parent()
+        .is_some_and(|p| matches!(p.kind(), "conj_item" | "disj_item"))
+        && node
+            .prev_sibling()
+            .is_some_and(|p| matches!(p.kind(), "comment" | "block_comment"))
+}
+
+/// Returns the user-configured [`OperatorSpacing`] for `node` if it is the
+/// operator symbol of an infix operator application.
+fn operator_spacing(
+    node: &Node<'_>,
+    input: &str,
+    options: &FormatOptions,
+) -> Option<OperatorSpacing> {
+    let parent = node.parent()?;
+    if parent.kind() != "bound_infix_op" || parent.child_by_field_name("symbol")? != *node {
+        return None;
+    }
+
+    options.operator_spacing(get_str(node, input))
+}
+
 /// Returns a [`Token`] if [`Node`] can be directly mapped to an output token.
-fn into_output_token<'a>(node: &Node<'_>, input: &'a str) -> Option<Token<'a>> {
+pub(crate) fn into_output_token<'a>(node: &Node<'_>, input: &'a str) -> Option<Token<'a>> {
     Some(match node.kind() {
         "LET" => Token::KeywordLet,
         "IN" => Token::KeywordIn,
@@ -330,8 +459,8 @@ fn into_output_token<'a>(node: &Node<'_>, input: &'a str) -> Option<Token<'a>> {
         "nat_number_set" => Token::Nat,
         "setminus" => Token::SetMinus,
         "slash" => Token::Divide,
-        "single_line" => Token::LineDivider('-'),
-        "double_line" => Token::LineDivider('='),
+        "single_line" => Token::LineDivider('-', None),
+        "double_line" => Token::LineDivider('=', None),
         "prime" => Token::Prime,
         "[]" => Token::Always,
         "<>" => Token::Eventually,
@@ -346,7 +475,7 @@ fn into_output_token<'a>(node: &Node<'_>, input: &'a str) -> Option<Token<'a>> {
 
 #[cfg(test)]
 mod tests {
-    use crate::assert_rewrite;
+    use crate::{assert_rewrite, FormatOptions, OperatorSpacing};
 
     #[test]
     fn test_basic_def() {
@@ -792,4 +921,44 @@ Inv == /\ TypeOK
 "#
         );
     }
+
+    #[test]
+    fn test_operator_spacing() {
+        assert_rewrite!(
+            r"
+---- MODULE B ----
+a ++ b == a
+Op == x ++ y \oplus z
+Op2 == x
+    ** y
+Op3 == x .. y
+Op4 == x ++ y..z
+====
+",
+            FormatOptions::default()
+                .with_operator_spacing("++", OperatorSpacing::Tight)
+                .with_operator_spacing("**", OperatorSpacing::NeverBreakBefore)
+                .with_operator_spacing("..", OperatorSpacing::Spaced)
+        );
+    }
+
+    #[test]
+    fn test_qualified_operator() {
+        assert_rewrite!(
+            r"
+---- MODULE B ----
+Op == Inst!VeryLongOperatorName(arg1, arg2)
+Op2 == Inst
+  !Foo(a,
+ b)
+Op3 == Inst!
+  Foo(a)
+Op4 == A!B!C(x)
+Op5 == Inst(1)!Op(2)
+Op6 == Inst!Const + 1
+Op7 == [f EXCEPT ![1] = 2, !.a = 3]
+====
+"
+        );
+    }
 }
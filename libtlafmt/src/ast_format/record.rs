@@ -0,0 +1,109 @@
+use tree_sitter::Node;
+
+use crate::{get_str, Renderer, Rule};
+
+/// Returns the children of the record literal or set of records `def` in the
+/// order they are rendered, with the fields sorted as configured.
+///
+/// Returns [`None`] if the fields are not sorted, are already in order, or if
+/// `def` contains a comment (which cannot be moved with the field it
+/// describes).
+pub(super) fn sorted_record_fields<'t, W>(
+    def: &Node<'t>,
+    input: &str,
+    writer: &mut Renderer<'_, W>,
+) -> Option<Vec<Node<'t>>>
+where
+    W: std::io::Write,
+{
+    if !matches!(def.kind(), "record_literal" | "set_of_records")
+        || !writer.options().sort_record_fields()
+    {
+        return None;
+    }
+
+    let mut c = def.walk();
+    let children = def.children(&mut c).collect::<Vec<_>>();
+
+    // Children are laid out as "[" (name, separator, value) ("," name,
+    // separator, value)* "]".
+    let (open, rest) = children.split_first()?;
+    let (close, fields) = rest.split_last()?;
+    if open.kind() != "[" || close.kind() != "]" || (fields.len() + 1) % 4 != 0 {
+        return None;
+    }
+    let fields = fields.chunks(4).map(|v| &v[..3]).collect::<Vec<_>>();
+    if fields.iter().any(|v| v[0].kind() != "identifier")
+        || children.iter().any(|v| v.kind().ends_with("comment"))
+    {
+        return None;
+    }
+
+    let name = |field: &[Node<'_>]| get_str(&field[0], input);
+    let position = |field: &[Node<'_>]| {
+        writer
+            .options()
+            .record_field_order()
+            .position(|v| v == name(field))
+            .unwrap_or(usize::MAX)
+    };
+
+    let mut sorted = fields.clone();
+    sorted.sort_by(|a, b| {
+        position(a)
+            .cmp(&position(b))
+            .then_with(|| name(a).cmp(name(b)))
+    });
+    if sorted == fields {
+        return None;
+    }
+
+    writer.record(Rule::RecordFieldOrder);
+
+    // Replace each field in the input with the sorted field at the same
+    // position, leaving the delimiters in place.
+    let mut out = children.clone();
+    for (i, field) in sorted.iter().enumerate() {
+        out[1 + i * 4..][..3].copy_from_slice(field);
+    }
+
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{assert_rewrite, FormatOptions};
+
+    #[test]
+    fn test_sort_record_fields() {
+        assert_rewrite!(
+            r#"
+---- MODULE A ----
+Msg == [type |-> "req", b |-> 1, a |-> <<1, 2>>]
+Msgs == [type : {"req"}, b : Nat, a : Nat]
+Multi ==
+    [type |-> "req",
+     b |-> 1,
+     a |-> 2]
+Nested == [type |-> "req", val |-> [z |-> 1, y |-> 2]]
+Sorted == [a |-> 1, b |-> 2]
+Commented == [b |-> 1, \* first
+              a |-> 2]
+===="#,
+            FormatOptions::default().with_sort_record_fields(true)
+        );
+    }
+
+    #[test]
+    fn test_record_field_order() {
+        assert_rewrite!(
+            r#"
+---- MODULE A ----
+Msg == [val |-> 42, dst |-> 2, src |-> 1, type |-> "req", ack |-> FALSE]
+===="#,
+            FormatOptions::default()
+                .with_sort_record_fields(true)
+                .with_record_field_order(["type", "src", "dst"])
+        );
+    }
+}
@@ -0,0 +1,353 @@
+use tree_sitter::Node;
+
+use crate::{
+    ast_format::call::has_layout,
+    get_str,
+    helpers::width,
+    token::{GroupBreak, Token},
+    QuantifierListLayout, Renderer,
+};
+
+/// The layout of an expression broken across lines when it exceeds the line
+/// width.
+#[derive(Debug, Clone, Copy)]
+pub(super) enum Wrapping {
+    /// A delimited list of elements (`{a, b}`), grouped between its delimiters
+    /// and broken after each comma.
+    Delimited {
+        /// The placement of the lines broken between the elements.
+        placement: GroupBreak,
+
+        /// The width of the longest field name of a record literal, to which
+        /// all field names are padded when broken one per line.
+        name_width: usize,
+    },
+
+    /// An infix operator application, broken after the operator.
+    ///
+    /// Applications of the same operator (`a \union b \union c`) are broken
+    /// together, grouped by the outermost application (the `root`).
+    Infix { root: bool },
+}
+
+/// Returns the layout of `def`, if `def` is an expression broken across lines
+/// when it exceeds the line width.
+pub(super) fn element_wrapping<W>(
+    def: &Node<'_>,
+    input: &str,
+    writer: &Renderer<'_, W>,
+) -> Option<Wrapping>
+where
+    W: std::io::Write,
+{
+    if !writer.options().line_wrapping() || in_quantifier_list(def, writer) {
+        return None;
+    }
+
+    let placement = match def.kind() {
+        "finite_set_literal" | "tuple_literal" => GroupBreak::Fill,
+        "record_literal" => GroupBreak::Align,
+        "bound_infix_op" => {
+            // The outermost operator has the lowest precedence, and is broken
+            // before any operators nested within its operands.
+            let root = chain_root(*def);
+            if has_layout(&root) {
+                return None;
+            }
+            return Some(Wrapping::Infix { root: root == *def });
+        }
+        _ => return None,
+    };
+
+    // Breaking the line around a comment would comment out the code that
+    // follows it, and lists and CASE arms are aligned by their line breaks.
+    if has_layout(def) {
+        return None;
+    }
+
+    let mut c = def.walk();
+    let name_width = def
+        .children(&mut c)
+        .filter(is_field_name)
+        .map(|v| field_name_width(&v, input, writer))
+        .max()
+        .unwrap_or_default();
+
+    Some(Wrapping::Delimited {
+        placement,
+        name_width,
+    })
+}
+
+/// Push the layout tokens preceding the child `n` of an expression laid out as
+/// `wrapping`.
+pub(super) fn wrap_before<W>(
+    n: &Node<'_>,
+    wrapping: Wrapping,
+    writer: &mut Renderer<'_, W>,
+) -> std::io::Result<()>
+where
+    W: std::io::Write,
+{
+    match wrapping {
+        // The closing delimiter ends the group.
+        Wrapping::Delimited { .. } if n.next_sibling().is_none() => {
+            writer.push(Token::GroupEnd)?;
+        }
+        // The group of an operator chain spans both operands, with the
+        // continuation lines indented.
+        Wrapping::Infix { root: true } if n.prev_sibling().is_none() => {
+            writer.push(Token::Group(GroupBreak::Indent))?;
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Push the layout tokens following the child `n` of an expression laid out as
+/// `wrapping`.
+///
+/// For records with sorted fields, `n` is the node rendered in place of the
+/// child.
+pub(super) fn wrap_after<W>(
+    n: &Node<'_>,
+    wrapping: Wrapping,
+    input: &str,
+    writer: &mut Renderer<'_, W>,
+) -> std::io::Result<()>
+where
+    W: std::io::Write,
+{
+    match wrapping {
+        // The elements following the opening delimiter form the group, which
+        // may be broken after each comma.
+        Wrapping::Delimited { placement, .. } if n.prev_sibling().is_none() => {
+            writer.push(Token::Group(placement))?;
+        }
+        Wrapping::Delimited { .. } if n.kind() == "," => {
+            writer.push(Token::SoftLine)?;
+        }
+        Wrapping::Delimited { name_width, .. } if is_field_name(n) => {
+            // Align the `|->` of each field once broken.
+            let pad = name_width - field_name_width(n, input, writer);
+            if pad > 0 {
+                writer.push(Token::GroupPad(pad))?;
+            }
+        }
+
+        // Breaking after the operator (rather than before) never places a
+        // `/\` or `\/` at the start of a line, where it would begin a
+        // junction list, and keeps operators configured with
+        // `OperatorSpacing::NeverBreakBefore` on the line of their left
+        // operand.
+        //
+        // A delimited list following the operator is kept on the same line,
+        // and broken between its own elements instead.
+        Wrapping::Infix { .. } if is_infix_symbol(n) => {
+            if !n.next_sibling().is_some_and(|v| is_delimited(&v)) {
+                writer.push(Token::SoftLine)?;
+            }
+        }
+        Wrapping::Infix { root: true } if n.next_sibling().is_none() => {
+            writer.push(Token::GroupEnd)?;
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Returns true if `def` is within a junction list that may be placed on the
+/// line of its quantifier, which is only possible while each item is rendered
+/// on a single line.
+fn in_quantifier_list<W>(def: &Node<'_>, writer: &Renderer<'_, W>) -> bool
+where
+    W: std::io::Write,
+{
+    if writer.options().quantifier_list_layout() != QuantifierListLayout::SameLine {
+        return false;
+    }
+
+    let mut ptr = def.parent();
+    while let Some(p) = ptr {
+        if matches!(p.kind(), "conj_list" | "disj_list")
+            && p.parent()
+                .is_some_and(|v| v.kind() == "bounded_quantification")
+        {
+            return true;
+        }
+        ptr = p.parent();
+    }
+
+    false
+}
+
+/// Returns true if `n` is a delimited list of elements, wrapped between its
+/// delimiters.
+fn is_delimited(n: &Node<'_>) -> bool {
+    matches!(
+        n.kind(),
+        "finite_set_literal" | "tuple_literal" | "record_literal"
+    )
+}
+
+/// Returns the outermost infix operator application of the same operator as
+/// the application `def`, of which `def` is an operand (or `def` itself).
+fn chain_root(def: Node<'_>) -> Node<'_> {
+    let symbol = |v: &Node<'_>| v.child_by_field_name("symbol").map(|v| v.kind_id());
+
+    let mut root = def;
+    while let Some(p) = root.parent() {
+        if p.kind() != "bound_infix_op" || symbol(&p) != symbol(&def) {
+            break;
+        }
+        root = p;
+    }
+
+    root
+}
+
+/// Returns true if `n` is the operator symbol of an infix operator
+/// application.
+fn is_infix_symbol(n: &Node<'_>) -> bool {
+    n.parent()
+        .filter(|p| p.kind() == "bound_infix_op")
+        .and_then(|p| p.child_by_field_name("symbol"))
+        .is_some_and(|v| v == *n)
+}
+
+/// Returns true if `n` is the name of a record literal field.
+fn is_field_name(n: &Node<'_>) -> bool {
+    n.kind() == "identifier" && n.next_sibling().is_some_and(|v| v.kind() == "all_map_to")
+}
+
+/// Returns the rendered width of the field name `n`.
+fn field_name_width<W>(n: &Node<'_>, input: &str, writer: &Renderer<'_, W>) -> usize
+where
+    W: std::io::Write,
+{
+    width(writer.renamed(n).unwrap_or(get_str(n, input)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{assert_rewrite, FormatOptions};
+
+    #[test]
+    fn test_set_literal_wrapping() {
+        assert_rewrite!(
+            r"
+---- MODULE Bananas ----
+Short == {a, b, c}
+Servers == {bananas, platanos, mangos, apples, pears, plums, cherries, grapes, melons}
+Nested == /\ x \in {bananas, platanos, mangos, apples, pears, plums, cherries, grapes, melons}
+          /\ y = 1
+Broken == {bananas, platanos,
+   mangos}
+====",
+            FormatOptions::default().with_line_wrapping(true)
+        );
+    }
+
+    #[test]
+    fn test_tuple_literal_wrapping() {
+        assert_rewrite!(
+            r"
+---- MODULE Bananas ----
+vars == <<bananas, platanos, mangos, apples, pears, plums, cherries, grapes, melons>>
+Short == <<a, b>>
+Nested == << <<bananas, platanos, mangos, apples>>, <<pears, plums, cherries, grapes, melons>> >>
+====",
+            FormatOptions::default().with_line_wrapping(true)
+        );
+    }
+
+    #[test]
+    fn test_record_literal_wrapping() {
+        assert_rewrite!(
+            r#"
+---- MODULE Bananas ----
+Msg == [mtype |-> RequestVoteRequest, mterm |-> currentTerm[i], mlastLogTerm |-> LastTerm(log[i]), mlastLogIndex |-> Len(log[i])]
+Short == [a |-> 1, bread |-> 2]
+Broken == [a |-> 1,
+           bread |-> 2]
+Nested == [type |-> "req", body |-> [src |-> source, dst |-> destination, payload |-> data]]
+===="#,
+            FormatOptions::default().with_line_wrapping(true)
+        );
+    }
+
+    #[test]
+    fn test_record_literal_wrapping_sorted() {
+        assert_rewrite!(
+            r#"
+---- MODULE Bananas ----
+Sorted == [type |-> "req", src |-> source, dst |-> destination, payload |-> bananas]
+===="#,
+            FormatOptions::default()
+                .with_line_wrapping(true)
+                .with_sort_record_fields(true)
+        );
+    }
+
+    #[test]
+    fn test_infix_wrapping() {
+        assert_rewrite!(
+            r"
+---- MODULE Bananas ----
+Nodes == Servers \union Clients \union Proxies \union Observers \union Coordinators \union Witnesses
+Total == price * quantity + shipping * weight + handling * parcels + insurance * declaredValue + taxRate * subtotal
+Short == a \union b
+Inv == /\ Nodes \subseteq Servers \union Clients \union Proxies \union Observers \union Witnesses
+       /\ y = 1
+Broken == Servers \union
+    Clients \union
+    Proxies
+====",
+            FormatOptions::default().with_line_wrapping(true)
+        );
+    }
+
+    #[test]
+    fn test_temporal_wrapping() {
+        assert_rewrite!(
+            r"
+---- MODULE Bananas ----
+Spec == Init /\ [][Next]_vars /\ WF_vars(Next) /\ WF_vars(Recover) /\ SF_vars(Release)
+THEOREM Spec => []TypeOK
+THEOREM Spec => [](TypeOK /\ MutualExclusion /\ DeadlockFreedom /\ StarvationFreedom)
+THEOREM Spec => [](TypeOK /\ MutualExclusion /\ DeadlockFreedom /\ StarvationFreedom /\ BoundedWaiting /\ Fairness)
+====",
+            FormatOptions::default().with_line_wrapping(true)
+        );
+    }
+
+    #[test]
+    fn test_unchanged_wrapping() {
+        assert_rewrite!(
+            r"
+---- MODULE Bananas ----
+Next == /\ x' = x + 1
+        /\ UNCHANGED <<bananas, platanos, mangos, apples, pears, plums, cherries, grapes, melons>>
+Stutter == UNCHANGED <<bananas, platanos, mangos, apples, pears, plums, cherries, grapes, melons>>
+Short == UNCHANGED <<a, b>>
+====",
+            FormatOptions::default().with_line_wrapping(true)
+        );
+    }
+
+    #[test]
+    fn test_assignment_width() {
+        assert_rewrite!(
+            r"
+---- MODULE Bananas ----
+Next == /\ fruit' = <<bananas, platanos, mangos, apples, pears, plums, cherries, grapes>>
+        /\ UNCHANGED <<bananas, platanos, mangos, apples, pears, plums, cherries, grapes>>
+====",
+            FormatOptions::default()
+                .with_line_wrapping(true)
+                .with_assignment_width(100)
+        );
+    }
+}
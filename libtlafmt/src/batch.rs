@@ -0,0 +1,352 @@
+//! Formatting of many in-memory specs, spread across all available cores.
+
+use std::{
+    collections::BTreeSet,
+    fmt::Display,
+    num::NonZeroUsize,
+    path::PathBuf,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use tree_sitter::Parser;
+
+use crate::{parser, Error, FormatOptions, Location, ParsedFile, Rule, SyntaxError};
+
+/// A non-fatal problem observed when formatting a spec.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Diagnostic {
+    /// The input contains a syntax error starting on the 1-indexed `line`.
+    ///
+    /// Specs containing syntax errors are formatted on a best-effort basis,
+    /// with the erroneous source emitted as-is (see
+    /// [`Diagnostic::VerbatimRegion`]).
+    SyntaxError {
+        /// The 1-indexed line the error starts on.
+        line: usize,
+
+        /// The 1-indexed character column the error starts at.
+        column: usize,
+
+        /// The source from `column` to the end of the line, truncated to a
+        /// short excerpt.
+        excerpt: String,
+
+        /// The grammar kind of the node the parser expected, if the error is
+        /// a missing node rather than unexpected input.
+        missing: Option<&'static str>,
+    },
+
+    /// A top-level definition or statement contains a syntax error, and the
+    /// lines it spans were emitted as they appear in the input.
+    VerbatimRegion {
+        /// The 1-indexed line the region starts on.
+        first_line: usize,
+
+        /// The 1-indexed line the region ends on.
+        last_line: usize,
+    },
+
+    /// The indentation of `lines` excessively indented lines was reduced to
+    /// one level deeper than the enclosing block.
+    IndentLimited {
+        /// The number of lines that had their indentation reduced.
+        lines: usize,
+    },
+
+    /// The spec nests deeper than the
+    /// [maximum indentation depth](FormatOptions::with_max_indent_depth()),
+    /// and the indentation of the deepest lines was clamped to it.
+    IndentClamped {
+        /// The maximum indentation depth, in levels.
+        depth: usize,
+    },
+
+    /// A node the formatter does not support was emitted as it appears in the
+    /// input.
+    Unformatted {
+        /// The grammar kind of the node.
+        kind: &'static str,
+
+        /// The 1-indexed line the node starts on.
+        line: usize,
+
+        /// The first line of the node source.
+        snippet: String,
+    },
+
+    /// A control character in the input was removed before parsing.
+    ControlCharacter {
+        /// The 1-indexed line the character appeared on.
+        line: usize,
+
+        /// The 1-indexed character column the character appeared at.
+        column: usize,
+
+        /// The removed character.
+        character: char,
+    },
+
+    /// A definition, declaration or bound name spells a builtin boolean name in
+    /// a different case, so references to it were not normalised.
+    ShadowedBuiltin {
+        /// The name as spelled in the input.
+        name: String,
+
+        /// The builtin the name would otherwise be normalised to.
+        builtin: &'static str,
+    },
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::SyntaxError { line, .. } => write!(f, "syntax error on line {line}"),
+            Self::VerbatimRegion {
+                first_line,
+                last_line,
+            } => write!(
+                f,
+                "lines {first_line}-{last_line} contain a syntax error and were not formatted"
+            ),
+            Self::IndentLimited { lines } => {
+                write!(
+                    f,
+                    "reduced the indentation of {lines} deeply indented lines"
+                )
+            }
+            Self::IndentClamped { depth } => write!(
+                f,
+                "indentation exceeds the maximum depth of {depth} levels and was clamped"
+            ),
+            Self::Unformatted {
+                kind,
+                line,
+                snippet,
+            } => write!(f, "unformatted {kind} on line {line}: {snippet}"),
+            Self::ControlCharacter {
+                line,
+                column,
+                character,
+            } => write!(
+                f,
+                "removed control character U+{:04X} at {line}:{column}",
+                *character as u32
+            ),
+            Self::ShadowedBuiltin { name, builtin } => write!(
+                f,
+                "identifier {name:?} shadows the builtin {builtin} and is not normalised"
+            ),
+        }
+    }
+}
+
+impl From<SyntaxError<'_>> for Diagnostic {
+    fn from(v: SyntaxError<'_>) -> Self {
+        let Location {
+            line,
+            column,
+            excerpt,
+        } = v.location;
+
+        Self::SyntaxError {
+            line,
+            column,
+            excerpt,
+            missing: v.missing,
+        }
+    }
+}
+
+/// The outcome of formatting a single spec with [`format_many()`].
+#[derive(Debug)]
+pub struct FileResult {
+    /// The path identifying the spec, as provided by the caller.
+    pub path: PathBuf,
+
+    /// The unmodified input spec.
+    pub input: String,
+
+    /// The formatted spec, or the error that prevented it being formatted.
+    pub output: Result<String, Error>,
+
+    /// The style rules that shaped the output (see
+    /// [`ParsedFile::format_with_rules()`]).
+    pub rules: BTreeSet<Rule>,
+
+    /// Non-fatal problems observed when formatting the spec.
+    pub diagnostics: Vec<Diagnostic>,
+
+    /// The time spent parsing and formatting the spec.
+    pub elapsed: Duration,
+}
+
+impl FileResult {
+    /// Returns true if the spec was formatted and the output differs from the
+    /// input.
+    pub fn is_changed(&self) -> bool {
+        self.output.as_ref().is_ok_and(|v| *v != self.input)
+    }
+}
+
+/// Format each `(path, content)` spec in `files` with `options`, returning a
+/// [`FileResult`] for each in the same order.
+///
+/// The `path` identifies the spec in the result only - it is never read or
+/// written. Specs are formatted concurrently across all available cores, and
+/// an error formatting one spec does not prevent the others from being
+/// formatted.
+pub fn format_many<I>(files: I, options: &FormatOptions) -> Vec<FileResult>
+where
+    I: IntoIterator<Item = (PathBuf, String)>,
+{
+    let files = files.into_iter().collect::<Vec<_>>();
+
+    let workers = std::thread::available_parallelism()
+        .map(NonZeroUsize::get)
+        .unwrap_or(1)
+        .min(files.len());
+    if workers <= 1 {
+        let mut parser = parser();
+        return files
+            .into_iter()
+            .map(|(path, input)| format_one(&mut parser, path, input, options))
+            .collect();
+    }
+
+    // Workers pull the next spec from the shared queue, placing the result at
+    // the same index it was read from, reusing the same parser for each.
+    let queue = Mutex::new(files.into_iter().enumerate());
+    let mut results = std::thread::scope(|s| {
+        let handles = (0..workers)
+            .map(|_| {
+                s.spawn(|| {
+                    let mut parser = parser();
+                    let mut out = vec![];
+                    loop {
+                        let next = queue.lock().unwrap().next();
+                        let Some((i, (path, input))) = next else {
+                            return out;
+                        };
+                        out.push((i, format_one(&mut parser, path, input, options)));
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        handles
+            .into_iter()
+            .flat_map(|v| v.join().expect("format worker panicked"))
+            .collect::<Vec<_>>()
+    });
+
+    results.sort_unstable_by_key(|(i, _)| *i);
+    results.into_iter().map(|(_, v)| v).collect()
+}
+
+/// Parse with `parser` and format `input` with `options`.
+fn format_one(
+    parser: &mut Parser,
+    path: PathBuf,
+    input: String,
+    options: &FormatOptions,
+) -> FileResult {
+    let start = Instant::now();
+
+    let mut rules = BTreeSet::new();
+    let mut diagnostics = vec![];
+    let output = ParsedFile::new_with_parser(parser, &input, options).and_then(|parsed| {
+        let mut buf = Vec::with_capacity(input.len());
+        let (applied, report) = parsed.format_report(options, &mut buf)?;
+        rules = applied;
+        diagnostics = report;
+        Ok(String::from_utf8_lossy(&buf).into_owned())
+    });
+
+    FileResult {
+        path,
+        input,
+        output,
+        rules,
+        diagnostics,
+        elapsed: start.elapsed(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_many() {
+        let files = (0..10).map(|i| {
+            let content = match i {
+                3 => "---- MODULE A ----\nX == 1\nY == )\n====".to_string(),
+                _ => format!("---- MODULE A ----\nX == {i}\n===="),
+            };
+            (PathBuf::from(format!("{i}.tla")), content)
+        });
+
+        let got = format_many(files, &FormatOptions::default());
+        assert_eq!(got.len(), 10);
+
+        for (i, v) in got.iter().enumerate() {
+            assert_eq!(v.path, PathBuf::from(format!("{i}.tla")));
+            assert!(v.output.is_ok());
+        }
+
+        assert_eq!(
+            got[3].diagnostics,
+            [
+                Diagnostic::SyntaxError {
+                    line: 3,
+                    column: 1,
+                    excerpt: "Y == )".into(),
+                    missing: None,
+                },
+                Diagnostic::VerbatimRegion {
+                    first_line: 3,
+                    last_line: 3
+                }
+            ]
+        );
+        assert!(got[7].diagnostics.is_empty());
+        assert!(got[7].is_changed());
+        assert!(got[7].rules.contains(&Rule::ModuleHeader));
+    }
+
+    #[test]
+    fn test_format_many_indent_diagnostics() {
+        let limited = "\
+---- MODULE A ----
+Integrate(D, a, b, InitVals) ==
+  LET n == Len(InitVals)
+      gg == CHOOSE g :
+              \\E e \\in PosReal :
+                 /\\ \\A r \\in OpenInterval(a-e, b+e) :
+                        D[r] = 0
+  IN  gg
+====";
+        let nested = format!(
+            "---- MODULE A ----\nOp == {}1{}\n====",
+            "[x \\in S |-> ".repeat(300),
+            "]".repeat(300)
+        );
+
+        let got = format_many(
+            [
+                (PathBuf::from("limited.tla"), limited.to_string()),
+                (PathBuf::from("nested.tla"), nested),
+            ],
+            &FormatOptions::default(),
+        );
+
+        assert_eq!(got[0].diagnostics, [Diagnostic::IndentLimited { lines: 1 }]);
+        assert_eq!(
+            got[1].diagnostics,
+            [Diagnostic::IndentClamped { depth: 255 }]
+        );
+        assert!(got[1].output.is_ok());
+    }
+}
@@ -0,0 +1,90 @@
+//! Normalisation of the casing of the builtin boolean constants and set.
+
+use std::collections::{BTreeSet, HashMap};
+
+use tree_sitter::Node;
+
+use crate::{
+    get_str,
+    rename::{declared_names, find_idents, first_child_of_kind, is_shadowed},
+    Diagnostic,
+};
+
+/// The builtin names normalised when spelled in a different case.
+const BUILTINS: &[&str] = &["TRUE", "FALSE", "BOOLEAN"];
+
+/// Return the canonical spelling of each identifier reference in `root` that
+/// spells a builtin boolean name in a different case (such as `True`), keyed by
+/// the start byte offset of the reference.
+///
+/// References to a definition, declaration, bound variable or parameter with
+/// the same spelling are not normalised, and a
+/// [`Diagnostic::ShadowedBuiltin`] is pushed to `diagnostics` for each such
+/// name.
+pub(crate) fn normalise(
+    root: Node<'_>,
+    input: &str,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> HashMap<usize, &'static str> {
+    let Some(module) = first_child_of_kind(root, "module") else {
+        return HashMap::new();
+    };
+
+    // Module-level definitions and declarations that shadow a builtin.
+    let mut c = module.walk();
+    let declared = module
+        .named_children(&mut c)
+        .map(|v| match v.kind() {
+            "local_definition" => v.named_child(0).unwrap_or(v),
+            _ => v,
+        })
+        .flat_map(|v| declared_names(v).into_iter())
+        .map(|v| get_str(&v, input))
+        .collect::<BTreeSet<_>>();
+
+    let mut shadowed = BTreeSet::new();
+    let mut out = HashMap::new();
+    for n in find_idents(module).filter(|v| v.kind() == "identifier_ref") {
+        let name = get_str(&n, input);
+        let Some(builtin) = BUILTINS.iter().find(|v| v.eq_ignore_ascii_case(name)) else {
+            continue;
+        };
+
+        if declared.contains(name) || is_shadowed(n, input, name) {
+            shadowed.insert((name, *builtin));
+            continue;
+        }
+
+        out.insert(n.start_byte(), *builtin);
+    }
+
+    diagnostics.extend(
+        shadowed
+            .into_iter()
+            .map(|(name, builtin)| Diagnostic::ShadowedBuiltin {
+                name: name.to_string(),
+                builtin,
+            }),
+    );
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{assert_rewrite, FormatOptions};
+
+    #[test]
+    fn test_normalise_booleans() {
+        assert_rewrite!(
+            r"
+---- MODULE Bananas ----
+CONSTANT False
+A == True /\ x \in Boolean /\ FALSE
+B == \A true \in BOOLEAN : true
+C == False
+====",
+            FormatOptions::default().with_normalise_booleans(true)
+        );
+    }
+}
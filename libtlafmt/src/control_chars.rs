@@ -0,0 +1,123 @@
+//! Removal of control characters from the input, which the parser does not
+//! handle consistently (such as a NUL byte ending a comment early).
+
+use std::borrow::Cow;
+
+use crate::Diagnostic;
+
+/// Returns true if `c` is a control character removed from the input.
+///
+/// Line endings and tabs are retained.
+fn is_removed(c: char) -> bool {
+    c.is_control() && !matches!(c, '\n' | '\r' | '\t')
+}
+
+/// Returns true if `input` contains any control character that would be
+/// removed by [`strip()`].
+pub(crate) fn contains(input: &str) -> bool {
+    input.chars().any(is_removed)
+}
+
+/// Remove the control characters from `input`, returning a
+/// [`Diagnostic::ControlCharacter`] for each, in the order they appear.
+pub(crate) fn strip(input: Cow<'_, str>) -> (Cow<'_, str>, Vec<Diagnostic>) {
+    if !contains(&input) {
+        return (input, vec![]);
+    }
+
+    let mut diagnostics = vec![];
+    for (i, line) in input.split('\n').enumerate() {
+        for (column, c) in line.chars().enumerate() {
+            if is_removed(c) {
+                diagnostics.push(Diagnostic::ControlCharacter {
+                    line: i + 1,
+                    column: column + 1,
+                    character: c,
+                });
+            }
+        }
+    }
+
+    let input = input.chars().filter(|c| !is_removed(*c)).collect();
+    (Cow::Owned(input), diagnostics)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FormatOptions, ParsedFile};
+
+    #[test]
+    fn test_strip() {
+        let (got, diagnostics) = strip(Cow::Borrowed("a\tb\r\nc\0d\n\u{7}e\u{85}"));
+        assert_eq!(got, "a\tb\r\ncd\ne");
+        assert_eq!(
+            diagnostics,
+            [
+                Diagnostic::ControlCharacter {
+                    line: 2,
+                    column: 2,
+                    character: '\0'
+                },
+                Diagnostic::ControlCharacter {
+                    line: 3,
+                    column: 1,
+                    character: '\u{7}'
+                },
+                Diagnostic::ControlCharacter {
+                    line: 3,
+                    column: 3,
+                    character: '\u{85}'
+                },
+            ]
+        );
+
+        let (got, diagnostics) = strip(Cow::Borrowed("bananas"));
+        assert!(matches!(got, Cow::Borrowed("bananas")));
+        assert!(diagnostics.is_empty());
+    }
+
+    /// A NUL byte within a comment no longer ends the comment.
+    #[test]
+    fn test_format_control_chars() {
+        let input = "---- MODULE A ----\nX == 1 \\* com\0ment\nY ==\u{7} 2\n====\n";
+        let parsed = ParsedFile::new(input).unwrap();
+        assert!(!parsed.has_syntax_errors());
+
+        let mut out = vec![];
+        let diagnostics = parsed
+            .format_with_diagnostics(
+                &FormatOptions::default().with_verify_idempotent(true),
+                &mut out,
+            )
+            .unwrap();
+
+        assert_eq!(
+            diagnostics,
+            [
+                Diagnostic::ControlCharacter {
+                    line: 2,
+                    column: 14,
+                    character: '\0'
+                },
+                Diagnostic::ControlCharacter {
+                    line: 3,
+                    column: 5,
+                    character: '\u{7}'
+                },
+            ]
+        );
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            format!(
+                "{}\nX == 1 \\* comment\nY == 2\n{}",
+                render_header(),
+                "=".repeat(80)
+            )
+        );
+    }
+
+    fn render_header() -> String {
+        format!("{} MODULE A {}", "-".repeat(35), "-".repeat(35))
+    }
+}
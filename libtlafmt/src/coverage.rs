@@ -0,0 +1,69 @@
+//! An inventory of the node kinds in the TLA+ grammar, used to measure how much
+//! of the grammar a set of specs exercises.
+
+use std::collections::BTreeSet;
+
+use tree_sitter::{Language, Node};
+
+use crate::ParsedFile;
+
+/// Returns the named node kinds the TLA+ grammar can produce in a parsed AST.
+///
+/// Hidden and supertype kinds that never appear in a tree are excluded, as
+/// are anonymous (literal token) kinds.
+pub fn grammar_node_kinds() -> BTreeSet<&'static str> {
+    let lang = Language::from(tree_sitter_tlaplus::LANGUAGE);
+
+    (0..lang.node_kind_count() as u16)
+        .filter(|&id| lang.node_kind_is_named(id) && lang.node_kind_is_visible(id))
+        .filter(|&id| !lang.node_kind_is_supertype(id))
+        .filter_map(|id| lang.node_kind_for_id(id))
+        .filter(|v| *v != "ERROR")
+        .collect()
+}
+
+impl ParsedFile<'_> {
+    /// Returns the named node kinds that appear in the parsed AST.
+    ///
+    /// Use with [`grammar_node_kinds()`] to find the kinds of the grammar a
+    /// set of specs never exercise.
+    pub fn node_kinds(&self) -> BTreeSet<&'static str> {
+        let mut out = BTreeSet::new();
+        collect(self.t.root_node(), &mut out);
+        out
+    }
+}
+
+/// Insert the kind of `node` and all its named descendants into `out`.
+fn collect(node: Node<'_>, out: &mut BTreeSet<&'static str>) {
+    if node.is_named() && !node.is_error() {
+        out.insert(node.kind());
+    }
+
+    let mut c = node.walk();
+    for child in node.children(&mut c) {
+        collect(child, out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_node_kinds() {
+        let all = grammar_node_kinds();
+        assert!(all.contains("source_file"));
+        assert!(all.contains("bound_op"));
+        assert!(!all.contains("ERROR"));
+        assert!(!all.contains("\\in"));
+
+        let parsed = ParsedFile::new("---- MODULE A ----\nOp(a) == a + 1\n====").unwrap();
+        let got = parsed.node_kinds();
+
+        assert!(got.contains("operator_definition"));
+        assert!(got.contains("bound_infix_op"));
+        assert!(!got.contains("set_literal"));
+        assert!(got.is_subset(&all), "{:?}", got.difference(&all));
+    }
+}
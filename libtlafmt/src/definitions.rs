@@ -0,0 +1,210 @@
+//! Enumeration of the top-level definitions and declarations of a module.
+
+use std::ops::Range;
+
+use tree_sitter::Node;
+
+use crate::{get_str, rename::first_child_of_kind, ParsedFile};
+
+/// A top-level definition, declaration or statement of a module.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Definition<'a> {
+    /// The name of the definition or declaration, or the keyword introducing
+    /// the statement if it is unnamed (such as `ASSUME`).
+    ///
+    /// Declarations of multiple constants or variables are named after the
+    /// first.
+    pub name: &'a str,
+
+    /// The kind of definition, declaration or statement.
+    pub kind: DefinitionKind,
+
+    /// The range of input bytes spanned.
+    ///
+    /// Offsets refer to [`ParsedFile::input()`], in which any tab characters
+    /// of the original input are replaced with spaces.
+    pub bytes: Range<usize>,
+
+    /// The 0-indexed range of input lines spanned.
+    pub lines: Range<usize>,
+}
+
+/// The kind of a top-level [`Definition`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DefinitionKind {
+    /// An `EXTENDS` statement.
+    Extends,
+
+    /// A `CONSTANT` declaration.
+    Constant,
+
+    /// A `VARIABLE` declaration.
+    Variable,
+
+    /// An `ASSUME` statement.
+    Assumption,
+
+    /// An operator definition, such as `Op(a) == a + 1`.
+    Operator,
+
+    /// A function definition, such as `f[x \in S] == x + 1`.
+    Function,
+
+    /// A `RECURSIVE` operator declaration.
+    Recursive,
+
+    /// A named (`I == INSTANCE M`) or unnamed module instance.
+    Instance,
+
+    /// A `THEOREM` (or `LEMMA`, `COROLLARY`, etc.) statement.
+    Theorem,
+
+    /// Any other statement, such as a nested module or `USE`.
+    Other,
+}
+
+impl DefinitionKind {
+    /// Returns the kind of the top-level node `n`.
+    fn of(n: Node<'_>) -> Self {
+        match n.kind() {
+            "extends" => Self::Extends,
+            "constant_declaration" => Self::Constant,
+            "variable_declaration" => Self::Variable,
+            "assumption" => Self::Assumption,
+            "operator_definition" => Self::Operator,
+            "function_definition" => Self::Function,
+            "recursive_declaration" => Self::Recursive,
+            "module_definition" | "instance" => Self::Instance,
+            "theorem" => Self::Theorem,
+            _ => Self::Other,
+        }
+    }
+}
+
+impl ParsedFile<'_> {
+    /// Return the top-level definitions, declarations and statements of the
+    /// module, in the order they appear in the input.
+    ///
+    /// Each [`Definition`] describes the name, [kind](DefinitionKind) and span
+    /// of operators, functions, constants, variables, instances, theorems and
+    /// other statements, allowing tools to build an outline of the module or
+    /// to target individual definitions.
+    pub fn definitions(&self) -> impl Iterator<Item = Definition<'_>> {
+        units(self.t.root_node()).map(|n| {
+            let (start, end) = (n.start_position(), n.end_position());
+
+            // Nodes may end at the start of the following line.
+            let end = match end.column {
+                0 if end.row > start.row => end.row,
+                _ => end.row + 1,
+            };
+
+            Definition {
+                name: unit_name(n, &self.input),
+                kind: DefinitionKind::of(n),
+                bytes: n.byte_range(),
+                lines: start.row..end,
+            }
+        })
+    }
+}
+
+/// Return the top-level definitions, declarations and statements of the module
+/// in `root`, unwrapping any `LOCAL` definitions.
+pub(crate) fn units(root: Node<'_>) -> impl Iterator<Item = Node<'_>> {
+    let mut nodes = vec![];
+    if let Some(module) = first_child_of_kind(root, "module") {
+        let mut c = module.walk();
+        nodes.extend(
+            module
+                .named_children(&mut c)
+                .filter_map(|n| match n.kind() {
+                    "local_definition" => n.named_child(0),
+                    "header_line" | "identifier" | "double_line" | "single_line" | "comment"
+                    | "block_comment" | "extramodular_text" => None,
+                    _ => Some(n),
+                }),
+        );
+    }
+    nodes.into_iter()
+}
+
+/// Return the name of the top-level node `n`.
+pub(crate) fn unit_name<'a>(n: Node<'_>, input: &'a str) -> &'a str {
+    let mut c = n.walk();
+    let name = n.child_by_field_name("name").or_else(|| match n.kind() {
+        "constant_declaration" | "variable_declaration" | "recursive_declaration" => n
+            .named_children(&mut c)
+            .find(|v| matches!(v.kind(), "identifier" | "operator_declaration")),
+        _ => None,
+    });
+
+    match name {
+        Some(v) if v.kind() == "operator_declaration" => v
+            .child_by_field_name("name")
+            .map(|v| get_str(&v, input))
+            .unwrap_or_else(|| get_str(&v, input)),
+        Some(v) => get_str(&v, input),
+        None => get_str(&n, input)
+            .split_whitespace()
+            .next()
+            .unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_definitions() {
+        let input = r"---- MODULE S ----
+EXTENDS Naturals
+CONSTANTS N, F(_)
+ASSUME N > 0
+VARIABLE x
+
+\* A comment.
+Init == x = 0
+LOCAL Next ==
+    x' = x + 1
+----
+RECURSIVE R(_)
+R(n) == R(n)
+f[i \in 1..N] == i
+I == INSTANCE Naturals
+THEOREM Next => x > 0
+====
+";
+        let parsed = ParsedFile::new(input).unwrap();
+        let got = parsed
+            .definitions()
+            .map(|v| (v.name, v.kind, v.lines))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            got,
+            [
+                ("EXTENDS", DefinitionKind::Extends, 1..2),
+                ("N", DefinitionKind::Constant, 2..3),
+                ("ASSUME", DefinitionKind::Assumption, 3..4),
+                ("x", DefinitionKind::Variable, 4..5),
+                ("Init", DefinitionKind::Operator, 7..8),
+                ("Next", DefinitionKind::Operator, 8..10),
+                ("R", DefinitionKind::Recursive, 11..12),
+                ("R", DefinitionKind::Operator, 12..13),
+                ("f", DefinitionKind::Function, 13..14),
+                ("I", DefinitionKind::Instance, 14..15),
+                ("THEOREM", DefinitionKind::Theorem, 15..16),
+            ]
+        );
+
+        let got = parsed
+            .definitions()
+            .map(|v| &input[v.bytes])
+            .filter(|v| v.contains('\n'))
+            .collect::<Vec<_>>();
+        assert_eq!(got, ["Next ==\n    x' = x + 1"]);
+    }
+}
@@ -0,0 +1,78 @@
+//! A limit on the nesting depth of the AST, protecting the recursive lowering
+//! from exhausting the stack on pathological input.
+
+use tree_sitter::Tree;
+
+use crate::{Error, Location};
+
+/// The maximum depth of nested nodes in the AST of a spec accepted for
+/// formatting.
+///
+/// Chosen to leave ample headroom on the 2 MiB stack of spawned threads.
+pub const MAX_NESTING_DEPTH: usize = 320;
+
+/// Returns [`Error::NestingTooDeep`] if the nodes of `tree` are nested deeper
+/// than [`MAX_NESTING_DEPTH`].
+///
+/// The tree is walked without recursion.
+pub(crate) fn check(tree: &Tree, input: &str) -> Result<(), Error> {
+    let mut c = tree.walk();
+    let mut depth = 0;
+
+    loop {
+        if depth > MAX_NESTING_DEPTH {
+            return Err(Error::NestingTooDeep(Location::of(&c.node(), input)));
+        }
+
+        if c.goto_first_child() {
+            depth += 1;
+            continue;
+        }
+
+        // Move to the next sibling of the nearest ancestor that has one.
+        while !c.goto_next_sibling() {
+            if !c.goto_parent() {
+                return Ok(());
+            }
+            depth -= 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FormatOptions, ParsedFile};
+
+    /// Nested parentheses, each adding a level of nesting to the AST.
+    fn nested(n: usize) -> String {
+        format!(
+            "---- MODULE A ----\nX == {}1{}\n====\n",
+            "(".repeat(n),
+            ")".repeat(n)
+        )
+    }
+
+    #[test]
+    fn test_nesting_within_limit() {
+        // Formatted on the (smaller) stack of the test thread.
+        let input = nested(MAX_NESTING_DEPTH - 10);
+        let parsed = ParsedFile::new(&input).unwrap();
+
+        let mut out = vec![];
+        parsed
+            .format_with(&FormatOptions::default(), &mut out)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_nesting_too_deep() {
+        let input = nested(MAX_NESTING_DEPTH * 2);
+
+        let got = ParsedFile::new(&input);
+        let Err(Error::NestingTooDeep(location)) = got else {
+            panic!("unexpected result {got:?}");
+        };
+        assert_eq!(location.line, 2);
+    }
+}
@@ -0,0 +1,140 @@
+//! A reusable formatter, amortising the cost of preparing the parser across
+//! many specs.
+
+use std::{io::Write, sync::Mutex};
+
+use tree_sitter::Parser;
+
+use crate::{parser, Error, FormatOptions, ParsedFile};
+
+/// A formatter configured with [`FormatOptions`], reusing the same TLA+ parser
+/// for every spec.
+///
+/// [`ParsedFile::new()`] prepares a new parser for each spec - when formatting
+/// many specs (such as in a long-running process) a [`Formatter`] avoids
+/// repeating this setup:
+///
+/// ```
+/// use libtlafmt::{FormatOptions, Formatter};
+///
+/// let formatter = Formatter::new(FormatOptions::default().with_line_width(100));
+///
+/// let mut out = vec![];
+/// formatter.format("---- MODULE A ----\nX   ==   1\n====", &mut out).unwrap();
+/// ```
+///
+/// The parser is used by one spec at a time - concurrent calls from multiple
+/// threads are serialised while parsing.
+pub struct Formatter {
+    parser: Mutex<Parser>,
+    options: FormatOptions,
+}
+
+impl std::fmt::Debug for Formatter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Formatter")
+            .field("options", &self.options)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for Formatter {
+    fn default() -> Self {
+        Self::new(FormatOptions::default())
+    }
+}
+
+impl Formatter {
+    /// Initialise a [`Formatter`] configured by `options`.
+    pub fn new(options: FormatOptions) -> Self {
+        Self {
+            parser: Mutex::new(parser()),
+            options,
+        }
+    }
+
+    /// Returns the formatting configuration.
+    pub fn options(&self) -> &FormatOptions {
+        &self.options
+    }
+
+    /// Parse the `input` TLA spec into an AST, applying any
+    /// [pre-parse hook](FormatOptions::with_pre_parse_hook()) configured in
+    /// the options.
+    ///
+    /// See [`ParsedFile::new_with()`].
+    pub fn parse<'a>(&self, input: &'a str) -> Result<ParsedFile<'a>, Error> {
+        // A panic while parsing leaves the parser in a reusable state.
+        let mut parser = self.parser.lock().unwrap_or_else(|e| e.into_inner());
+        ParsedFile::new_with_parser(&mut parser, input, &self.options)
+    }
+
+    /// Parse the `input` TLA spec into an AST, taking ownership of `input` and
+    /// applying any pre-parse hook configured in the options.
+    ///
+    /// See [`ParsedFile::new_owned()`].
+    pub fn parse_owned(&self, input: String) -> Result<ParsedFile<'static>, Error> {
+        let mut parser = self.parser.lock().unwrap_or_else(|e| e.into_inner());
+        ParsedFile::new_owned_with_parser(&mut parser, input, &self.options)
+    }
+
+    /// Parse and format the `input` TLA spec, rendering it into `out`.
+    ///
+    /// # Errors
+    ///
+    /// If formatting fails `out` may contain partial content.
+    pub fn format<W>(&self, input: &str, out: W) -> Result<(), Error>
+    where
+        W: Write,
+    {
+        self.parse(input)?.format_with(&self.options, out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_formatter() {
+        let formatter = Formatter::new(FormatOptions::default().with_line_width(40));
+
+        for input in [
+            "---- MODULE A ----\nX   ==   1\n====",
+            "---- MODULE B ----\nY   ==   /\\ a\n         /\\ b\n====",
+        ] {
+            let mut got = vec![];
+            formatter.format(input, &mut got).unwrap();
+
+            let mut want = vec![];
+            ParsedFile::new(input)
+                .unwrap()
+                .format_with(formatter.options(), &mut want)
+                .unwrap();
+
+            assert_eq!(String::from_utf8(got), String::from_utf8(want));
+        }
+    }
+
+    #[test]
+    fn test_parse_owned() {
+        let formatter = Formatter::new(
+            FormatOptions::default().with_pre_parse_hook(|v| v.replace("bananas", "platanos")),
+        );
+
+        let parsed = formatter
+            .parse_owned("---- MODULE A ----\nX == bananas\n====".to_string())
+            .unwrap();
+
+        let mut got = vec![];
+        parsed.format_with(formatter.options(), &mut got).unwrap();
+
+        let mut want = vec![];
+        ParsedFile::new("---- MODULE A ----\nX == platanos\n====")
+            .unwrap()
+            .format_with(formatter.options(), &mut want)
+            .unwrap();
+
+        assert_eq!(String::from_utf8(got), String::from_utf8(want));
+    }
+}
@@ -1,27 +1,40 @@
 use std::ops::{Add, Sub};
 
+/// The string that replaces tab characters in the input before parsing.
 pub(crate) const INDENT_STR: &str = "    ";
 
+/// Whitespace sliced to pad tokens to a column.
+pub(crate) const PADDING: &str = "                                                                                                                                ";
+
 /// A fixed indentation level.
+///
+/// Arithmetic saturates at the bounds of the representable depth rather than
+/// overflowing.
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Ord, Eq)]
-pub(crate) struct Indent(u8);
+pub(crate) struct Indent(u16);
 
 impl Indent {
     pub(crate) const ZERO: Self = Self::new(0);
 
-    pub(crate) const fn new(v: u8) -> Self {
+    pub(crate) const fn new(v: u16) -> Self {
         Self(v)
     }
-    pub(crate) const fn get(&self) -> u8 {
+    pub(crate) const fn get(&self) -> u16 {
         self.0
     }
+
+    /// Returns the indentation level `v`, saturating at the maximum
+    /// representable depth.
+    pub(crate) fn saturating_from(v: usize) -> Self {
+        Self(u16::try_from(v).unwrap_or(u16::MAX))
+    }
 }
 
-impl Add<u8> for Indent {
+impl Add<u16> for Indent {
     type Output = Self;
 
-    fn add(self, rhs: u8) -> Self::Output {
-        Self(self.0 + rhs)
+    fn add(self, rhs: u16) -> Self::Output {
+        Self(self.0.saturating_add(rhs))
     }
 }
 
@@ -29,7 +42,7 @@ impl Sub for Indent {
     type Output = Self;
 
     fn sub(self, rhs: Self) -> Self::Output {
-        Self(self.0 - rhs.0)
+        Self(self.0.saturating_sub(rhs.0))
     }
 }
 
@@ -38,29 +51,82 @@ impl Sub for Indent {
 ///
 /// NOTE: this includes after newlines embedded within a token, such as a Raw or
 /// Comment token.
+///
+/// This decorator guarantees the output never contains trailing whitespace -
+/// whitespace is buffered until a subsequent non-whitespace character is
+/// written, and dropped if a newline (or the end of the output) is observed
+/// first. Empty lines are never indented.
 #[derive(Debug, Default)]
 pub(crate) struct IndentDecorator<W> {
     out: W,
 
     /// The indentation depth to render for subsequent writes.
-    depth: u8,
+    depth: u16,
+
+    /// The string rendered once per level of indentation depth.
+    indent: String,
 
     /// True when the last byte wrote to `out` was a newline.
     last_char_newline: bool,
+
+    /// Whitespace wrote to this decorator that has not yet been wrote to
+    /// `out`.
+    pending_whitespace: Vec<u8>,
 }
 
 impl<W> IndentDecorator<W> {
-    pub(crate) fn new(out: W) -> Self {
+    /// Wrap `out`, rendering `indent` once per level of indentation depth.
+    pub(crate) fn new(out: W, indent: &str) -> Self {
         Self {
             depth: 0,
+            indent: indent.to_owned(),
             out,
             last_char_newline: false,
+            pending_whitespace: Vec::new(),
         }
     }
 
     pub(crate) fn set(&mut self, depth: Indent) {
         self.depth = depth.get();
     }
+
+    /// Borrow the underlying writer.
+    pub(crate) fn get_ref(&self) -> &W {
+        &self.out
+    }
+
+    /// Write `buf` to the underlying writer unmodified, following the
+    /// indentation (if starting a line) and any whitespace pending before it.
+    ///
+    /// Unlike [`std::io::Write::write()`], lines within `buf` are not indented
+    /// and retain any trailing whitespace.
+    pub(crate) fn write_verbatim(&mut self, buf: &[u8]) -> std::io::Result<()>
+    where
+        W: std::io::Write,
+    {
+        let Some(&last) = buf.last() else {
+            return Ok(());
+        };
+
+        if self.last_char_newline && buf[0] != b'\n' {
+            for _ in 0..self.depth {
+                self.out.write_all(self.indent.as_bytes())?;
+            }
+        }
+
+        self.out.write_all(&self.pending_whitespace)?;
+        self.pending_whitespace.clear();
+        self.out.write_all(buf)?;
+        self.last_char_newline = last == b'\n';
+
+        Ok(())
+    }
+
+    /// Returns true if the last byte wrote to the underlying writer was a
+    /// newline.
+    pub(crate) fn ends_with_newline(&self) -> bool {
+        self.last_char_newline
+    }
 }
 
 impl<W> std::io::Write for IndentDecorator<W>
@@ -69,14 +135,41 @@ where
 {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
         for chunk in buf.split_inclusive(|&v| v == b'\n') {
-            if self.last_char_newline {
-                for _ in 0..self.depth {
-                    self.out.write_all(INDENT_STR.as_bytes())?;
+            let (line, newline) = match chunk.split_last() {
+                Some((b'\n', line)) => (line, true),
+                _ => (chunk, false),
+            };
+
+            // Split the trailing whitespace from the line content.
+            let content_len = line
+                .iter()
+                .rposition(|v| !matches!(v, b' ' | b'\t'))
+                .map(|v| v + 1)
+                .unwrap_or(0);
+            let (content, whitespace) = line.split_at(content_len);
+
+            if !content.is_empty() {
+                // Indentation is only rendered for lines that have content.
+                if self.last_char_newline {
+                    for _ in 0..self.depth {
+                        self.out.write_all(self.indent.as_bytes())?;
+                    }
+                    self.last_char_newline = false;
                 }
+
+                self.out.write_all(&self.pending_whitespace)?;
+                self.pending_whitespace.clear();
+                self.out.write_all(content)?;
             }
 
-            self.out.write_all(chunk)?;
-            self.last_char_newline = chunk.last().is_some_and(|v| *v == b'\n');
+            self.pending_whitespace.extend_from_slice(whitespace);
+
+            if newline {
+                // Drop any whitespace that would precede the newline.
+                self.pending_whitespace.clear();
+                self.out.write_all(b"\n")?;
+                self.last_char_newline = true;
+            }
         }
 
         Ok(buf.len())
@@ -103,12 +196,57 @@ Yes.
 ";
 
         let mut buf = Vec::new();
-        let mut out = IndentDecorator::new(&mut buf);
+        let mut out = IndentDecorator::new(&mut buf, INDENT_STR);
 
         out.set(Indent::new(2));
         out.write_all(s.as_bytes()).unwrap();
 
         let got = String::from_utf8(buf).unwrap();
-        assert_eq!(got, "Bananas\n        Are good?\n        \n        Yes.\n");
+        assert_eq!(got, "Bananas\n        Are good?\n\n        Yes.\n");
+    }
+
+    #[test]
+    fn test_trailing_whitespace_dropped() {
+        let mut buf = Vec::new();
+        let mut out = IndentDecorator::new(&mut buf, INDENT_STR);
+
+        out.set(Indent::new(1));
+        out.write_all(b"Bananas ").unwrap();
+        out.write_all(b" ").unwrap();
+        out.write_all(b"\n").unwrap();
+        out.write_all(b" ").unwrap();
+        out.write_all(b"Platanos \t\nAre").unwrap();
+        out.write_all(b" ").unwrap();
+        out.write_all(b"good ").unwrap();
+
+        let got = String::from_utf8(buf).unwrap();
+        assert_eq!(got, "Bananas\n     Platanos\n    Are good");
+    }
+
+    #[test]
+    fn test_indent_str() {
+        let mut buf = Vec::new();
+        let mut out = IndentDecorator::new(&mut buf, "  ");
+
+        out.set(Indent::new(2));
+        out.write_all(b"Bananas\nPlatanos").unwrap();
+
+        let got = String::from_utf8(buf).unwrap();
+        assert_eq!(got, "Bananas\n    Platanos");
+    }
+
+    #[test]
+    fn test_write_verbatim() {
+        let mut buf = Vec::new();
+        let mut out = IndentDecorator::new(&mut buf, INDENT_STR);
+
+        out.set(Indent::new(1));
+        out.write_all(b"Bananas\n").unwrap();
+        out.write_verbatim(b"Platanos \n  Are\t\n\ngood ").unwrap();
+        out.write_all(b"\nYes").unwrap();
+
+        // Only the first line is indented, and no whitespace is removed.
+        let got = String::from_utf8(buf).unwrap();
+        assert_eq!(got, "Bananas\n    Platanos \n  Are\t\n\ngood \n    Yes");
     }
 }
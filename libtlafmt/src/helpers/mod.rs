@@ -1,5 +1,9 @@
 mod indent;
 mod newline;
+mod sink;
+mod width;
 
 pub(crate) use indent::*;
 pub(crate) use newline::*;
+pub(crate) use sink::*;
+pub(crate) use width::*;
@@ -2,12 +2,29 @@
 
 use tree_sitter::Node;
 
-use crate::{token::Token, Renderer};
+use crate::{token::Token, Renderer, Rule};
 
 #[derive(Debug, Default)]
-pub(crate) struct EmptyLines(usize);
+pub(crate) struct EmptyLines {
+    /// The row the last observed node ends on.
+    last: usize,
+
+    /// Nodes starting on or before this row never insert line breaks.
+    ///
+    /// See [`EmptyLines::suppress_within()`].
+    joined: Option<usize>,
+}
 
 impl EmptyLines {
+    /// Initialise an [`EmptyLines`] that treats `node` as the first node in
+    /// the output, irrespective of the row it appears on.
+    pub(crate) fn starting_at(node: &Node<'_>) -> Self {
+        Self {
+            last: node.start_position().row,
+            joined: None,
+        }
+    }
+
     /// Observe the position of this next `node` and emit empty lines if
     /// required.
     ///
@@ -23,16 +40,22 @@ impl EmptyLines {
     {
         // Calculate the number of lines between the last observed node, and
         // this one.
-        let existing = node.start_position().row.saturating_sub(self.0);
+        let existing = match self.joined {
+            Some(v) if node.start_position().row <= v => 0,
+            _ => node.start_position().row.saturating_sub(self.last),
+        };
 
         // Track the end position of this new node.
-        self.0 = node.end_position().row;
+        self.last = node.end_position().row;
 
         match existing {
             0 => return Ok(false),
             1 => out.push(Token::SourceNewline)?,
             _ => {
                 // Squash to at most 1 empty line.
+                if existing > 2 {
+                    out.record(Rule::EmptyLines);
+                }
                 out.push(Token::SourceNewline)?;
                 out.push(Token::SourceNewline)?
             }
@@ -45,6 +68,19 @@ impl EmptyLines {
     /// linebreak if when observed later a node would have caused a line break
     /// to be inserted.
     pub(crate) fn suppress(&mut self, node: &Node<'_>) {
-        self.0 = node.end_position().row;
+        self.last = node.end_position().row;
+    }
+
+    /// As [`EmptyLines::suppress()`], but suppressing all lines prior to the
+    /// start of `node`.
+    pub(crate) fn suppress_before(&mut self, node: &Node<'_>) {
+        self.last = node.start_position().row;
+    }
+
+    /// Do not allow `node`, or any node within it, to insert a line break,
+    /// rendering it on a single line.
+    pub(crate) fn suppress_within(&mut self, node: &Node<'_>) {
+        self.last = node.start_position().row;
+        self.joined = Some(node.end_position().row);
     }
 }
@@ -0,0 +1,54 @@
+/// A writer decorator that counts the lines wrote to `W`, giving context to any
+/// error returned by `W`.
+#[derive(Debug)]
+pub(crate) struct LineCounter<W> {
+    out: W,
+
+    /// The number of newlines successfully wrote to `out`.
+    newlines: usize,
+}
+
+impl<W> LineCounter<W> {
+    pub(crate) fn new(out: W) -> Self {
+        Self { out, newlines: 0 }
+    }
+
+    /// The 1-indexed output line that the next write appends to.
+    pub(crate) fn line(&self) -> usize {
+        self.newlines + 1
+    }
+}
+
+impl<W> std::io::Write for LineCounter<W>
+where
+    W: std::io::Write,
+{
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = self.out.write(buf)?;
+        self.newlines += buf[..n].iter().filter(|&&v| v == b'\n').count();
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.out.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    #[test]
+    fn test_line() {
+        let mut out = LineCounter::new(Vec::new());
+        assert_eq!(out.line(), 1);
+
+        out.write_all(b"Bananas\nAre\n").unwrap();
+        assert_eq!(out.line(), 3);
+
+        out.write_all(b"good").unwrap();
+        assert_eq!(out.line(), 3);
+    }
+}
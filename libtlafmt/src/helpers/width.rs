@@ -0,0 +1,31 @@
+//! Display widths of text, accounting for characters that occupy more (or
+//! fewer) than one column.
+
+use unicode_width::UnicodeWidthStr;
+
+/// Returns the number of columns `s` occupies when displayed.
+pub(crate) fn width(s: &str) -> usize {
+    s.width()
+}
+
+/// Returns the display column of the byte offset `at` within its line of
+/// `input`.
+pub(crate) fn column_at(input: &str, at: usize) -> usize {
+    let start = input[..at].rfind('\n').map_or(0, |v| v + 1);
+    width(&input[start..at])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_width() {
+        assert_eq!(width("bananas"), 7);
+        assert_eq!(width("✓ größer"), 8);
+        assert_eq!(width("香蕉"), 4);
+
+        let input = "X == 1\n\\* ✓ größer \\* aligned";
+        assert_eq!(column_at(input, input.rfind("\\*").unwrap()), 12);
+    }
+}
@@ -15,74 +15,344 @@
 //   limitations under the License.
 
 mod ast_format;
+mod batch;
+mod booleans;
+mod control_chars;
+mod coverage;
+mod definitions;
+mod depth;
+mod formatter;
 mod helpers;
+mod line_endings;
+mod line_indent;
+mod location;
+mod modeline;
+mod options;
+mod partial;
+mod post_process;
+mod rename;
 mod renderer;
+mod rules;
+mod sections;
+mod syntax_errors;
 mod token;
+#[cfg(feature = "tokens")]
+mod tokens;
+mod verify;
 
 #[cfg(test)]
 mod test_utils;
 
-use std::{borrow::Cow, io::Write};
+use std::{borrow::Cow, collections::BTreeSet, io::Write};
 
 use ast_format::format_node;
+pub use batch::{format_many, Diagnostic, FileResult};
+pub use coverage::grammar_node_kinds;
+pub use definitions::{Definition, DefinitionKind};
+pub use depth::MAX_NESTING_DEPTH;
+pub use formatter::Formatter;
 use helpers::{EmptyLines, INDENT_STR};
+pub use location::Location;
+pub use options::*;
 use renderer::Renderer;
+pub use rules::Rule;
+pub use sections::{Misplaced, Section};
+pub use syntax_errors::SyntaxError;
 use thiserror::Error;
-use tree_sitter::{Node, Parser, Tree};
-
-const LINE_WIDTH: usize = 80;
+pub use token::{token_table, TokenText};
+#[cfg(feature = "tokens")]
+pub use tokens::LoweredToken;
+use tree_sitter::Parser;
+pub use tree_sitter::{InputEdit, Node, Point, Tree};
 
 /// Errors during AST parsing, lowering or rendering.
 #[derive(Debug, Error)]
 pub enum Error {
     /// An I/O error writing to the output sink.
-    #[error("data store disconnected")]
+    #[error("error writing output: {0}")]
     IO(#[from] std::io::Error),
 
+    /// An I/O error writing a rendered token to the output sink.
+    #[error("error writing output line {line} (token {token}): {source}")]
+    Render {
+        /// The 0-indexed position of the token being rendered in the token
+        /// stream.
+        token: usize,
+
+        /// The 1-indexed output line being wrote.
+        line: usize,
+
+        /// The error returned by the output sink.
+        source: std::io::Error,
+    },
+
     /// The input cannot be parsed into an AST.
-    #[error("unknown parser error")]
-    Parse,
+    ///
+    /// The location is the start of the input, or of the edit being applied by
+    /// [`ParsedFile::edit()`].
+    #[error("unknown parser error at {0}")]
+    Parse(Location),
+
+    /// The module header starting at the location is malformed.
+    #[error("invalid module header at {0}")]
+    ModuleHeader(Location),
+
+    /// The `[Next]_vars` sequence starting at the location is malformed.
+    #[error("invalid step-or-stutter sequence at {0}")]
+    StepOrStutter(Location),
+
+    /// The input contains syntax errors at the locations, and
+    /// [strict mode](FormatOptions::with_strict()) is enabled.
+    #[error("input contains syntax errors at {}", join_locations(.0))]
+    SyntaxErrors(Vec<Location>),
+
+    /// The formatted output changed the token at the location in the input,
+    /// and [verification](FormatOptions::with_verify()) is enabled.
+    ///
+    /// This is a bug in the formatter - no output is wrote.
+    #[error("formatting changed the spec at {0}")]
+    Verification(Location),
+
+    /// Formatting the output a second time changed it at the location in the
+    /// first output, and [idempotency
+    /// verification](FormatOptions::with_verify_idempotent()) is enabled.
+    ///
+    /// This is a bug in the formatter - no output is wrote.
+    #[error("formatting is not idempotent at {0}")]
+    NotIdempotent(Location),
 
-    /// The module header is malformed.
-    #[error("invalid module header")]
-    ModuleHeader,
+    /// The input nests expressions deeper than [`MAX_NESTING_DEPTH`], starting
+    /// at the location.
+    #[error("input is nested more than {MAX_NESTING_DEPTH} levels deep at {0}")]
+    NestingTooDeep(Location),
 
-    /// A `[Next]_vars` sequence is malformed.
-    #[error("invalid step-or-stutter sequence")]
-    StepOrStutter,
+    /// No module-level definition or declaration exists for the identifier to
+    /// be renamed.
+    #[error("no definition or declaration of {0:?} found")]
+    UnknownIdent(String),
+
+    /// The new name of a renamed identifier is already in use.
+    #[error("identifier {0:?} is already in use")]
+    IdentInUse(String),
+
+    /// The new name of a renamed identifier is not a valid identifier.
+    #[error("invalid identifier {0:?}")]
+    InvalidIdent(String),
+
+    /// A `tlafmt:` modeline comment contains an unknown setting or invalid
+    /// value.
+    #[error("invalid modeline setting {0:?}")]
+    Modeline(String),
+}
+
+/// Render `locations` as a `; ` separated list.
+fn join_locations(locations: &[Location]) -> String {
+    locations
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Returns a parser for the TLA+ grammar.
+fn parser() -> Parser {
+    let mut parser = Parser::new();
+    parser
+        .set_language(&tree_sitter_tlaplus::LANGUAGE.into())
+        .expect("error loading TLA+ grammar");
+    parser
 }
 
 /// A parsed TLA file ready for formatting.
+///
+/// # Thread Safety
+///
+/// A [`ParsedFile`] is [`Send`] and [`Sync`], allowing it to be moved to, or
+/// concurrently formatted from, other threads. [`FormatOptions`] is likewise
+/// [`Send`] and [`Sync`].
 #[derive(Debug)]
 pub struct ParsedFile<'a> {
     t: Tree,
     input: Cow<'a, str>,
+
+    /// True if tab, control characters or `\r\n` line endings in the input
+    /// were replaced before parsing, invalidating any byte offsets into the
+    /// original input.
+    normalised: bool,
+
+    /// True if the majority of lines in the input ended with `\r\n`.
+    crlf: bool,
+
+    /// A [`Diagnostic::ControlCharacter`] for each control character removed
+    /// from the input before parsing.
+    control_chars: Vec<Diagnostic>,
+}
+
+// Compile-time assertions that the public types remain usable from
+// multi-threaded drivers.
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+
+    assert_send_sync::<ParsedFile<'_>>();
+    assert_send_sync::<Formatter>();
+    assert_send_sync::<FormatOptions>();
+    assert_send_sync::<Error>();
+};
+
+impl ParsedFile<'static> {
+    /// Parse the `input` TLA spec into an AST, taking ownership of `input`.
+    ///
+    /// Unlike [`ParsedFile::new()`], the returned [`ParsedFile`] does not
+    /// borrow from the caller, allowing it to be stored in long-lived
+    /// structures independently of the source buffer.
+    pub fn new_owned(input: String) -> Result<Self, Error> {
+        Self::parse(Cow::Owned(input))
+    }
+
+    /// As [`ParsedFile::new_owned()`], parsing with `parser` and first
+    /// applying the pre-parse hook configured in `options` (if any).
+    pub(crate) fn new_owned_with_parser(
+        parser: &mut Parser,
+        input: String,
+        options: &FormatOptions,
+    ) -> Result<Self, Error> {
+        match options.pre_parse_hook() {
+            Some(hook) => Self::parse_with(parser, Cow::Owned(hook.apply(&input))),
+            None => Self::parse_with(parser, Cow::Owned(input)),
+        }
+    }
 }
 
 impl<'a> ParsedFile<'a> {
     /// Parse the `input` TLA spec into an AST.
     pub fn new(input: &'a str) -> Result<Self, Error> {
-        let mut parser = Parser::new();
-        parser
-            .set_language(&tree_sitter_tlaplus::LANGUAGE.into())
-            .expect("error loading TLA+ grammar");
+        Self::parse(Cow::Borrowed(input))
+    }
+
+    /// Parse the `input` TLA spec into an AST, first applying the
+    /// [pre-parse hook](FormatOptions::with_pre_parse_hook()) configured in
+    /// `options` (if any).
+    pub fn new_with(input: &'a str, options: &FormatOptions) -> Result<Self, Error> {
+        Self::new_with_parser(&mut parser(), input, options)
+    }
+
+    /// As [`ParsedFile::new_with()`], parsing with `parser`.
+    pub(crate) fn new_with_parser(
+        parser: &mut Parser,
+        input: &'a str,
+        options: &FormatOptions,
+    ) -> Result<Self, Error> {
+        match options.pre_parse_hook() {
+            Some(hook) => Self::parse_with(parser, Cow::Owned(hook.apply(input))),
+            None => Self::parse_with(parser, Cow::Borrowed(input)),
+        }
+    }
+
+    /// Parse the (possibly transformed) `input` into an AST.
+    fn parse(input: Cow<'a, str>) -> Result<Self, Error> {
+        Self::parse_with(&mut parser(), input)
+    }
+
+    /// Parse the (possibly transformed) `input` into an AST with `parser`.
+    fn parse_with(parser: &mut Parser, input: Cow<'a, str>) -> Result<Self, Error> {
+        // Remove control characters, which the parser does not handle
+        // consistently (such as a NUL byte ending a comment).
+        let (input, control_chars) = control_chars::strip(input);
+
+        // Render `\r\n` line endings as `\n`, restoring them after rendering.
+        let crlf_input = input.contains("\r\n");
+        let (input, crlf) = line_endings::normalise(input);
 
         // Normalise tab characters - if a mixture of tab and space are used,
         // the AST may produce incorrect nodes. Specifically conj_items using
         // tabs can become a bound_infix_op instead of conj_list, see
         // `test_mixed_tabs_spaces`.
-        let input = match input.contains("\t") {
+        let tabs = input.contains("\t");
+        let input = match tabs {
             true => Cow::Owned(input.replace("\t", INDENT_STR)),
-            false => Cow::Borrowed(input),
+            false => input,
         };
 
+        let t = parser
+            .parse(input.as_bytes(), None)
+            .ok_or_else(|| Error::Parse(Location::at(&input, Point::default())))?;
+        depth::check(&t, &input)?;
+
         Ok(Self {
-            t: parser.parse(input.as_bytes(), None).ok_or(Error::Parse)?,
+            t,
             input,
+            normalised: tabs || crlf_input || !control_chars.is_empty(),
+            crlf,
+            control_chars,
         })
     }
 
-    /// Format and render the parsed spec into `out`.
+    /// Apply `edit`, describing the change from the previously parsed input to
+    /// `new_text`, and reparse `new_text` reusing the unchanged parts of the
+    /// existing AST.
+    ///
+    /// Incrementally reparsing a small change to a large spec is much faster
+    /// than parsing the whole of `new_text` with [`ParsedFile::new()`]. Any
+    /// [pre-parse hook](FormatOptions::with_pre_parse_hook()) is not applied.
+    ///
+    /// Input containing tab or control characters, or `\r\n` line endings, is
+    /// always parsed in full, as they are replaced before parsing (invalidating
+    /// the offsets in `edit`).
+    pub fn edit(
+        &mut self,
+        edit: &InputEdit,
+        new_text: impl Into<Cow<'a, str>>,
+    ) -> Result<(), Error> {
+        let new_text = new_text.into();
+        if self.normalised
+            || new_text.contains('\t')
+            || new_text.contains("\r\n")
+            || control_chars::contains(&new_text)
+        {
+            *self = Self::parse(new_text)?;
+            return Ok(());
+        }
+
+        self.t.edit(edit);
+        let t = parser()
+            .parse(new_text.as_bytes(), Some(&self.t))
+            .ok_or_else(|| Error::Parse(Location::at(&new_text, edit.start_position)))?;
+        depth::check(&t, &new_text)?;
+        self.t = t;
+        self.input = new_text;
+
+        Ok(())
+    }
+
+    /// Returns the tree-sitter AST of the parsed input.
+    ///
+    /// Byte offsets and positions of the nodes within the tree refer to
+    /// [`ParsedFile::input()`].
+    pub fn tree(&self) -> &Tree {
+        &self.t
+    }
+
+    /// Returns the parsed input.
+    ///
+    /// Any tab characters in the original input are replaced with spaces and
+    /// `\r\n` line endings with `\n` before parsing, and any
+    /// [pre-parse hook](FormatOptions::with_pre_parse_hook()) has been
+    /// applied.
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+
+    /// Returns true if the input contains syntax errors.
+    ///
+    /// Specs containing syntax errors are formatted on a best-effort basis,
+    /// with the top-level definitions and statements containing the errors
+    /// emitted as-is.
+    pub fn has_syntax_errors(&self) -> bool {
+        self.t.root_node().has_error()
+    }
+
+    /// Format and render the parsed spec into `out` using the default
+    /// [`FormatOptions`].
     ///
     /// # Errors
     ///
@@ -91,15 +361,165 @@ impl<'a> ParsedFile<'a> {
     where
         W: Write,
     {
-        let mut out = Renderer::new(out);
+        self.format_with(&FormatOptions::default(), out)
+    }
+
+    /// Format and render the parsed spec into `out`, configured by `options`.
+    ///
+    /// # Errors
+    ///
+    /// If formatting fails `out` may contain partial content.
+    pub fn format_with<W>(&self, options: &FormatOptions, out: W) -> Result<(), Error>
+    where
+        W: Write,
+    {
+        self.format_with_rules(options, out).map(|_| ())
+    }
+
+    /// As [`ParsedFile::format_with()`], returning the style [`Rule`]s that
+    /// shaped the output.
+    ///
+    /// Only rules with a distinct effect on the output are reported - rules
+    /// that lay out every spec (such as [`Rule::ListBullets`]) are never
+    /// included.
+    ///
+    /// # Errors
+    ///
+    /// If formatting fails `out` may contain partial content.
+    pub fn format_with_rules<W>(
+        &self,
+        options: &FormatOptions,
+        out: W,
+    ) -> Result<BTreeSet<Rule>, Error>
+    where
+        W: Write,
+    {
+        self.format_report(options, out).map(|(rules, _)| rules)
+    }
+
+    /// As [`ParsedFile::format_with()`], returning the non-fatal problems
+    /// observed while formatting, such as syntax errors or nodes emitted
+    /// unformatted.
+    ///
+    /// The library never prints diagnostics itself - it is up to the caller to
+    /// report them.
+    ///
+    /// # Errors
+    ///
+    /// If formatting fails `out` may contain partial content.
+    pub fn format_with_diagnostics<W>(
+        &self,
+        options: &FormatOptions,
+        out: W,
+    ) -> Result<Vec<Diagnostic>, Error>
+    where
+        W: Write,
+    {
+        self.format_report(options, out).map(|(_, v)| v)
+    }
+
+    /// As [`ParsedFile::format_with_rules()`], additionally returning any
+    /// [`Diagnostic`] raised while rendering.
+    pub(crate) fn format_report<W>(
+        &self,
+        options: &FormatOptions,
+        mut out: W,
+    ) -> Result<(BTreeSet<Rule>, Vec<Diagnostic>), Error>
+    where
+        W: Write,
+    {
+        // Apply any per-file overrides declared in the spec.
+        let options = modeline::apply(&self.input, options.clone())?;
+        self.check_strict(&options)?;
+
+        // Render into a buffer for verification or post-processing, if any.
+        let crlf = self.crlf(&options);
+        if options.verify()
+            || options.verify_idempotent()
+            || post_process::is_required(&options, crlf)
+        {
+            let mut buf = Vec::with_capacity(self.input.len());
+            let report = self.render(options.clone(), &mut buf)?;
+            let output = String::from_utf8_lossy(&buf);
+            if options.verify() {
+                verify::check(self, &output, &options)?;
+            }
+            if options.verify_idempotent() {
+                verify::check_idempotent(&output, &options)?;
+            }
+            out.write_all(post_process::apply(&output, &options, crlf).as_bytes())?;
+            return Ok(report);
+        }
+
+        self.render(options, out)
+    }
+
+    /// Returns true if the output is rendered with `\r\n` line endings, as
+    /// configured by `options`.
+    pub(crate) fn crlf(&self, options: &FormatOptions) -> bool {
+        match options.line_ending() {
+            LineEnding::Detect => self.crlf,
+            LineEnding::Lf => false,
+            LineEnding::Crlf => true,
+        }
+    }
+
+    /// Returns [`Error::SyntaxErrors`] if `options` enables strict mode and the
+    /// input contains syntax errors.
+    pub(crate) fn check_strict(&self, options: &FormatOptions) -> Result<(), Error> {
+        if !options.strict() || !self.has_syntax_errors() {
+            return Ok(());
+        }
+
+        Err(Error::SyntaxErrors(
+            self.syntax_errors()
+                .into_iter()
+                .map(|v| v.location)
+                .collect(),
+        ))
+    }
+
+    /// Lower the AST into a series of formatter tokens rendered to `out`,
+    /// returning the style rules applied and any diagnostics raised.
+    fn render<W>(
+        &self,
+        options: FormatOptions,
+        out: W,
+    ) -> Result<(BTreeSet<Rule>, Vec<Diagnostic>), Error>
+    where
+        W: Write,
+    {
+        let final_newline = match options.final_newline() {
+            FinalNewline::Always => true,
+            FinalNewline::Never => false,
+            FinalNewline::Preserve => self.input.ends_with('\n'),
+        };
+        let mut out = Renderer::new(out, options.clone()).with_final_newline(final_newline);
         let mut empty_lines = EmptyLines::default();
 
-        // Lower the AST into a series of formatter tokens wrote to `out`.
+        let mut diagnostics = self.control_chars.clone();
+        diagnostics.extend(self.syntax_errors().into_iter().map(Diagnostic::from));
+
+        // Builtin boolean names are normalised through the renaming machinery.
+        let mut normalised = false;
+        if options.normalise_booleans() {
+            let renames = booleans::normalise(self.t.root_node(), &self.input, &mut diagnostics);
+            normalised = !renames.is_empty();
+            out = out.with_renames(renames);
+        }
+
         format_node(self.t.root_node(), &self.input, &mut empty_lines, &mut out)?;
 
-        out.flush()?;
+        let (mut rules, report) = out.flush()?;
+        diagnostics.extend(report);
+        if normalised {
+            rules.insert(Rule::BooleanCasing);
+        }
+        if final_newline && !self.input.ends_with('\n') {
+            rules.insert(Rule::FinalNewline);
+        }
 
-        Ok(())
+        Ok((rules, diagnostics))
     }
 }
 
@@ -187,4 +607,296 @@ X == /\\ x = 4
 ====="
         );
     }
+
+    #[test]
+    fn test_indent_width() {
+        assert_rewrite!(
+            r"
+---- MODULE Bananas ----
+Op == /\ x = 1        \* one
+      /\ \E y \in S:  \* two
+            y = 2
+====",
+            FormatOptions::default().with_indent_width(2)
+        );
+    }
+
+    #[test]
+    fn test_hard_tabs() {
+        assert_rewrite!(
+            r"
+---- MODULE Bananas ----
+Op == /\ x = 1        \* one
+      /\ \E y \in S:  \* two
+            y = 2
+====",
+            FormatOptions::default().with_hard_tabs(true)
+        );
+    }
+
+    #[test]
+    fn test_final_newline() {
+        let format = |input: &str, v: FinalNewline| {
+            let mut buf = Vec::new();
+            ParsedFile::new(input)
+                .unwrap()
+                .format_with(&FormatOptions::default().with_final_newline(v), &mut buf)
+                .unwrap();
+            String::from_utf8(buf).unwrap()
+        };
+
+        let want = format!(
+            "{0} MODULE B {0}\nX == 42\n{1}",
+            "-".repeat(35),
+            "=".repeat(80)
+        );
+        for input in [
+            "---- MODULE B ----\nX == 42\n====",
+            "---- MODULE B ----\nX == 42\n====\n",
+            "---- MODULE B ----\nX == 42\n====\n\n\n",
+        ] {
+            assert_eq!(format(input, FinalNewline::Always), format!("{want}\n"));
+            assert_eq!(format(input, FinalNewline::Never), want);
+
+            let preserved = match input.ends_with('\n') {
+                true => format!("{want}\n"),
+                false => want.clone(),
+            };
+            assert_eq!(format(input, FinalNewline::Preserve), preserved);
+        }
+    }
+
+    /// Embedders can hide non-TLA+ text from the parser and restore it after
+    /// rendering.
+    #[test]
+    fn test_hooks() {
+        let options = FormatOptions::default()
+            .with_pre_parse_hook(|v| v.replace("#pragma", "\\* #pragma"))
+            .with_post_render_hook(|v| v.replace("\\* #pragma", "#pragma"));
+
+        let input = "---- MODULE B ----\n#pragma fast\nX   ==   42\n====\n";
+        let parsed = ParsedFile::new_with(input, &options).unwrap();
+
+        let mut buf = Vec::new();
+        parsed.format_with(&options, &mut buf).unwrap();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            format!(
+                "{0} MODULE B {0}\n#pragma fast\nX == 42\n{1}",
+                "-".repeat(35),
+                "=".repeat(80)
+            )
+        );
+    }
+
+    /// A [`ParsedFile`] can be parsed in one thread and formatted in another.
+    #[test]
+    fn test_send_parsed_file() {
+        let input = String::from("---- MODULE B ----\nX == 42\n====\n");
+        let parsed = ParsedFile::new(&input).unwrap();
+
+        let mut control = Vec::new();
+        parsed.format(&mut control).unwrap();
+
+        let got = std::thread::scope(|s| {
+            s.spawn(move || {
+                let mut buf = Vec::new();
+                parsed.format(&mut buf).unwrap();
+                buf
+            })
+            .join()
+            .unwrap()
+        });
+
+        assert_eq!(got, control);
+    }
+
+    /// Formatting configuration is per call, allowing a single spec to be
+    /// formatted concurrently with different options.
+    #[test]
+    fn test_concurrent_options() {
+        let parsed = ParsedFile::new("---- MODULE B ----\nX == /\\ a\n     /\\ b\n====\n").unwrap();
+
+        let render = |width, indent| {
+            let mut buf = Vec::new();
+            parsed
+                .format_with(
+                    &FormatOptions::default()
+                        .with_line_width(width)
+                        .with_indent_width(indent),
+                    &mut buf,
+                )
+                .unwrap();
+            String::from_utf8(buf).unwrap()
+        };
+
+        let (narrow, wide) = std::thread::scope(|s| {
+            let narrow = s.spawn(|| render(40, 2));
+            let wide = s.spawn(|| render(120, 4));
+            (narrow.join().unwrap(), wide.join().unwrap())
+        });
+
+        assert_eq!(
+            narrow,
+            format!(
+                "{0} MODULE B {0}\nX ==\n  /\\ a\n  /\\ b\n{1}",
+                "-".repeat(15),
+                "=".repeat(40)
+            )
+        );
+        assert_eq!(
+            wide,
+            format!(
+                "{0} MODULE B {0}\nX ==\n    /\\ a\n    /\\ b\n{1}",
+                "-".repeat(55),
+                "=".repeat(120)
+            )
+        );
+        assert_ne!(narrow, wide);
+    }
+
+    /// Incrementally reparsing an edit produces the same AST as parsing the
+    /// edited input from scratch.
+    #[test]
+    fn test_edit() {
+        let old = "---- MODULE B ----\nX == 42\nY == /\\ a\n     /\\ b\n====\n";
+        let new = "---- MODULE B ----\nX == 42\nY == /\\ a\n     /\\ bananas\n====\n";
+
+        // Replace "b" on line 3 with "bananas".
+        let start = old.find("/\\ b").unwrap() + 3;
+        let edit = InputEdit {
+            start_byte: start,
+            old_end_byte: start + 1,
+            new_end_byte: start + 7,
+            start_position: Point::new(3, 8),
+            old_end_position: Point::new(3, 9),
+            new_end_position: Point::new(3, 15),
+        };
+
+        let mut parsed = ParsedFile::new(old).unwrap();
+        parsed.edit(&edit, new).unwrap();
+
+        let control = ParsedFile::new(new).unwrap();
+        assert_eq!(
+            parsed.t.root_node().to_sexp(),
+            control.t.root_node().to_sexp()
+        );
+
+        let mut got = vec![];
+        parsed.format(&mut got).unwrap();
+        let mut want = vec![];
+        control.format(&mut want).unwrap();
+        assert_eq!(got, want);
+
+        // Input containing tabs is reparsed in full.
+        let tabbed = new.replace("     /\\", "\t /\\");
+        parsed.edit(&edit, tabbed.as_str()).unwrap();
+        assert_eq!(
+            parsed.t.root_node().to_sexp(),
+            ParsedFile::new(&tabbed).unwrap().t.root_node().to_sexp()
+        );
+    }
+
+    #[test]
+    fn test_new_owned() {
+        let input = "---- MODULE B ----\nX   ==   42\n====\n";
+
+        // The parsed file outlives the input buffer.
+        let parsed = {
+            let owned = input.to_string();
+            ParsedFile::new_owned(owned).unwrap()
+        };
+
+        let mut got = vec![];
+        parsed.format(&mut got).unwrap();
+        let mut want = vec![];
+        ParsedFile::new(input).unwrap().format(&mut want).unwrap();
+        assert_eq!(got, want);
+    }
+
+    #[test]
+    fn test_format_with_diagnostics() {
+        let input = "---- MODULE B ----\nVARIABLE True\nX == True\nY == )\n====\n";
+        let parsed = ParsedFile::new(input).unwrap();
+
+        let mut buf = vec![];
+        let got = parsed
+            .format_with_diagnostics(
+                &FormatOptions::default().with_normalise_booleans(true),
+                &mut buf,
+            )
+            .unwrap();
+
+        assert_eq!(
+            got,
+            [
+                Diagnostic::SyntaxError {
+                    line: 4,
+                    column: 1,
+                    excerpt: "Y == )".into(),
+                    missing: None,
+                },
+                Diagnostic::ShadowedBuiltin {
+                    name: "True".into(),
+                    builtin: "TRUE"
+                },
+                Diagnostic::VerbatimRegion {
+                    first_line: 4,
+                    last_line: 4
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_strict() {
+        let input = "---- MODULE B ----\nX == )\n====\n";
+        let parsed = ParsedFile::new(input).unwrap();
+        let strict = FormatOptions::default().with_strict(true);
+
+        let got = parsed.format_with(&strict, vec![]);
+        let Err(Error::SyntaxErrors(locations)) = got else {
+            panic!("unexpected result {got:?}");
+        };
+        assert_eq!(locations.len(), 1);
+        assert_eq!((locations[0].line, locations[0].column), (2, 1));
+        assert_eq!(
+            Error::SyntaxErrors(locations).to_string(),
+            "input contains syntax errors at 2:1: \"X == )\""
+        );
+
+        // Partial formatting is also refused.
+        assert!(matches!(
+            parsed.format_range(1..2, &strict, vec![]),
+            Err(Error::SyntaxErrors(_))
+        ));
+
+        // Otherwise the input is formatted on a best-effort basis.
+        assert!(parsed
+            .format_with(&FormatOptions::default(), vec![])
+            .is_ok());
+
+        // Valid input is unaffected.
+        let parsed = ParsedFile::new("---- MODULE B ----\nX == 1\n====\n").unwrap();
+        assert!(parsed.format_with(&strict, vec![]).is_ok());
+    }
+
+    #[test]
+    fn test_tree_and_input() {
+        let input = "---- MODULE B ----\nX ==\t1\n====\n";
+        let parsed = ParsedFile::new(input).unwrap();
+
+        // Tabs are replaced in the parsed input.
+        assert_eq!(parsed.input(), input.replace('\t', INDENT_STR));
+
+        let root = parsed.tree().root_node();
+        assert_eq!(root.kind(), "source_file");
+
+        let def = root.named_child(0).and_then(|v| v.named_child(3)).unwrap();
+        assert_eq!(def.kind(), "operator_definition");
+        assert_eq!(
+            def.utf8_text(parsed.input().as_bytes()).unwrap(),
+            "X ==    1"
+        );
+    }
 }
@@ -0,0 +1,82 @@
+//! Detection of `\r\n` line endings, which are replaced with `\n` before
+//! parsing and restored in the rendered output.
+
+use std::borrow::Cow;
+
+/// Replace each `\r\n` in `input` with `\n`, returning true if the majority of
+/// lines in `input` ended with `\r\n`.
+pub(crate) fn normalise(input: Cow<'_, str>) -> (Cow<'_, str>, bool) {
+    let crlf = input.matches("\r\n").count();
+    if crlf == 0 {
+        return (input, false);
+    }
+
+    let lf = input.matches('\n').count() - crlf;
+    (Cow::Owned(input.replace("\r\n", "\n")), crlf > lf)
+}
+
+/// Replace each `\n` in the rendered `output` with `\r\n`.
+pub(crate) fn restore(output: Cow<'_, str>) -> Cow<'_, str> {
+    match output.contains('\n') {
+        true => Cow::Owned(output.replace('\n', "\r\n")),
+        false => output,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FormatOptions, LineEnding, ParsedFile};
+
+    #[test]
+    fn test_normalise() {
+        let (got, crlf) = normalise(Cow::Borrowed("a\r\nb\r\nc\n"));
+        assert_eq!(got, "a\nb\nc\n");
+        assert!(crlf);
+
+        // A minority of CRLF line endings is normalised, but not dominant.
+        let (got, crlf) = normalise(Cow::Borrowed("a\r\nb\nc\n"));
+        assert_eq!(got, "a\nb\nc\n");
+        assert!(!crlf);
+
+        let (got, crlf) = normalise(Cow::Borrowed("a\nb\n"));
+        assert!(matches!(got, Cow::Borrowed("a\nb\n")));
+        assert!(!crlf);
+    }
+
+    #[test]
+    fn test_format_crlf() {
+        let input = "---- MODULE A ----\r\n(* a\r\n   b *)\r\nX   ==   1\r\n====\r\n";
+        let parsed = ParsedFile::new(input).unwrap();
+        let format = |v: LineEnding| {
+            let mut buf = vec![];
+            parsed
+                .format_with(&FormatOptions::default().with_line_ending(v), &mut buf)
+                .unwrap();
+            String::from_utf8(buf).unwrap()
+        };
+
+        let want = format!(
+            "{} MODULE A {}\n(* a\n   b *)\nX == 1\n{}",
+            "-".repeat(35),
+            "-".repeat(35),
+            "=".repeat(80)
+        );
+
+        // The dominant line ending is used, including within comments.
+        assert_eq!(format(LineEnding::Detect), want.replace('\n', "\r\n"));
+        assert_eq!(format(LineEnding::Crlf), want.replace('\n', "\r\n"));
+        assert_eq!(format(LineEnding::Lf), want);
+
+        // LF input can be converted to CRLF.
+        let parsed = ParsedFile::new(&want).unwrap();
+        let mut buf = vec![];
+        parsed
+            .format_with(
+                &FormatOptions::default().with_line_ending(LineEnding::Crlf),
+                &mut buf,
+            )
+            .unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), want.replace('\n', "\r\n"));
+    }
+}
@@ -0,0 +1,136 @@
+//! Indentation of a single line as it is typed, for editor integrations.
+
+use tree_sitter::{Node, Point};
+
+use crate::ParsedFile;
+
+impl ParsedFile<'_> {
+    /// Returns the column the content of the 0-indexed `line` should be
+    /// indented to, if it starts an item of a conjunction / disjunction list or
+    /// a definition of a `LET`.
+    ///
+    /// A blank line is assumed to start a new item of the innermost list (or
+    /// `LET`) enclosing the end of the previous non-blank line, such as when a
+    /// newline has just been typed. Returns [`None`] for all other lines.
+    pub fn line_indent(&self, line: usize) -> Option<usize> {
+        let lines = self.input.split('\n').collect::<Vec<_>>();
+        let text = lines.get(line)?;
+        let root = self.t.root_node();
+
+        let trimmed = text.trim_start();
+        if !trimmed.is_empty() {
+            // The line must begin a list item or LET definition.
+            let point = Point::new(line, text.len() - trimmed.len());
+            let mut node = root.descendant_for_point_range(point, point)?;
+            loop {
+                if let Some(column) = item_column(node, point, &lines) {
+                    return Some(column);
+                }
+                node = node.parent().filter(|v| v.start_position() == point)?;
+            }
+        }
+
+        // Otherwise continue the innermost list or LET enclosing the last
+        // character of the previous non-blank line.
+        let (row, prev) = lines[..line]
+            .iter()
+            .enumerate()
+            .rev()
+            .find(|(_, v)| !v.trim().is_empty())?;
+        let last = prev.trim_end().char_indices().last()?.0;
+        let point = Point::new(row, last);
+
+        let mut node = root.descendant_for_point_range(point, point)?;
+        while node.kind() != "module" {
+            if let Some(column) = enclosing_column(node, point, &lines) {
+                return Some(column);
+            }
+            node = node.parent()?;
+        }
+
+        None
+    }
+}
+
+/// Returns the column of the items of the list (or `LET` definitions) that
+/// `n` is an item of, if `n` starts at `point`.
+fn item_column(n: Node<'_>, point: Point, lines: &[&str]) -> Option<usize> {
+    if n.start_position() != point {
+        return None;
+    }
+
+    let parent = n.parent()?;
+    match (n.kind(), parent.kind()) {
+        ("conj_item", "conj_list") | ("disj_item", "disj_list") => Some(column(parent, lines)),
+        (_, "let_in") if n.is_named() => enclosing_column(parent, point, lines),
+        _ => None,
+    }
+}
+
+/// Returns the column of the items of `n` if it is a list, or of the
+/// definitions of `n` if it is a `LET` and `point` lies before the `IN`.
+fn enclosing_column(n: Node<'_>, point: Point, lines: &[&str]) -> Option<usize> {
+    match n.kind() {
+        "conj_list" | "disj_list" => Some(column(n, lines)),
+        "let_in" => {
+            let mut c = n.walk();
+            let body = n.children(&mut c).find(|v| v.kind() == "IN");
+            if body.is_some_and(|v| v.start_position() <= point) {
+                return None;
+            }
+            n.named_child(0).map(|v| column(v, lines))
+        }
+        _ => None,
+    }
+}
+
+/// Returns the 0-indexed character column `n` starts at.
+fn column(n: Node<'_>, lines: &[&str]) -> usize {
+    let start = n.start_position();
+    lines[start.row][..start.column].chars().count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_indent() {
+        let input = r"---- MODULE A ----
+Op == LET x == 1
+          y == /\ a
+               /\ \/ b
+
+               /\ c
+          z == 2
+      IN x
+
+Next == x' = 1
+====
+";
+        let parsed = ParsedFile::new(input).unwrap();
+        let got = (0..input.lines().count())
+            .map(|v| parsed.line_indent(v))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            got,
+            [
+                None,
+                // The line starting the LET is not an item.
+                None,
+                Some(10),
+                Some(15),
+                // A blank line continues the innermost list.
+                Some(18),
+                Some(15),
+                Some(10),
+                // Neither the IN, nor the lines after it are items.
+                None,
+                None,
+                None,
+                None,
+            ]
+        );
+    }
+}
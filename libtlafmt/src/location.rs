@@ -0,0 +1,80 @@
+//! Locations within the input spec, used to point at the source of an error.
+
+use std::fmt::Display;
+
+use tree_sitter::{Node, Point};
+
+/// The maximum number of characters of source included in an excerpt.
+const EXCERPT_LEN: usize = 40;
+
+/// A position in the input spec, and an excerpt of the source found there.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    /// The 1-indexed line.
+    pub line: usize,
+
+    /// The 1-indexed character column.
+    pub column: usize,
+
+    /// The source from `column` to the end of the line, truncated to a short
+    /// excerpt.
+    pub excerpt: String,
+}
+
+impl Location {
+    /// Returns the location of the 0-indexed row and byte column of `point`
+    /// within `input`.
+    pub(crate) fn at(input: &str, point: Point) -> Self {
+        let text = input.split('\n').nth(point.row).unwrap_or_default();
+        let (prefix, rest) = text.split_at(point.column.min(text.len()));
+
+        let rest = rest.trim_end();
+        let mut excerpt = rest.chars().take(EXCERPT_LEN).collect::<String>();
+        if excerpt.len() < rest.len() {
+            excerpt.push_str("...");
+        }
+
+        Self {
+            line: point.row + 1,
+            column: prefix.chars().count() + 1,
+            excerpt,
+        }
+    }
+
+    /// Returns the location of the start of `node` within `input`.
+    pub(crate) fn of(node: &Node<'_>, input: &str) -> Self {
+        Self::at(input, node.start_position())
+    }
+}
+
+impl Display for Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}: {:?}", self.line, self.column, self.excerpt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_location() {
+        let input = "---- MODULE A ----\nX == \"λ\" + 1\n====";
+
+        let got = Location::at(input, Point::new(1, 10));
+        assert_eq!(
+            got,
+            Location {
+                line: 2,
+                column: 10,
+                excerpt: "+ 1".into()
+            }
+        );
+        assert_eq!(got.to_string(), "2:10: \"+ 1\"");
+
+        // Long lines are truncated.
+        let long = format!("X == {}", "a".repeat(100));
+        let got = Location::at(&long, Point::new(0, 0));
+        assert_eq!(got.excerpt, format!("X == {}...", "a".repeat(35)));
+    }
+}
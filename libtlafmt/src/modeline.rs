@@ -0,0 +1,160 @@
+//! Per-file [`FormatOptions`] overrides, declared in a comment within the first
+//! lines of a spec:
+//!
+//! ```text
+//! \* tlafmt: line-width=100 indent=2
+//! ---- MODULE Bananas ----
+//! ```
+
+use crate::{Error, FinalNewline, FormatOptions};
+
+/// The marker that identifies a modeline comment.
+const MODELINE_PREFIX: &str = "tlafmt:";
+
+/// The number of lines at the start of the input searched for modelines.
+const MODELINE_SEARCH_LINES: usize = 5;
+
+/// Apply the overrides declared by any modelines in the first
+/// [`MODELINE_SEARCH_LINES`] lines of `input` to `options`.
+///
+/// Comments starting with [`MODELINE_PREFIX`] that do not contain `key=value`
+/// settings (such as `\* tlafmt: off`) are not modelines and are ignored.
+///
+/// # Errors
+///
+/// Returns [`Error::Modeline`] if a modeline contains an unknown setting or an
+/// invalid value.
+pub(crate) fn apply(input: &str, mut options: FormatOptions) -> Result<FormatOptions, Error> {
+    for settings in input
+        .lines()
+        .take(MODELINE_SEARCH_LINES)
+        .filter_map(modeline)
+    {
+        for setting in settings.split_ascii_whitespace() {
+            let (key, value) = setting
+                .split_once('=')
+                .ok_or_else(|| Error::Modeline(setting.to_string()))?;
+
+            let invalid = || Error::Modeline(setting.to_string());
+            let number = || value.parse::<usize>().map_err(|_| invalid());
+            let boolean = || value.parse::<bool>().map_err(|_| invalid());
+
+            options = match key {
+                "line-width" => options.with_line_width(number()?),
+                "indent" | "indent-width" => options.with_indent_width(number()?),
+                "max-indent-depth" => options.with_max_indent_depth(number()?),
+                "hard-tabs" => options.with_hard_tabs(boolean()?),
+                "collapse-single-item-lists" => options.with_collapse_single_item_lists(boolean()?),
+                "align-comments" => options.with_align_comments(boolean()?),
+                "insert-final-newline" => options.with_final_newline(match boolean()? {
+                    true => FinalNewline::Always,
+                    false => FinalNewline::Never,
+                }),
+                "metadata-comment" => options.with_metadata_comment(boolean()?),
+                "align-assumptions" => options.with_align_assumptions(boolean()?),
+                "normalise-booleans" => options.with_normalise_booleans(boolean()?),
+                "standard-call-wrapping" => options.with_standard_call_wrapping(boolean()?),
+                "sort-record-fields" => options.with_sort_record_fields(boolean()?),
+                "line-wrapping" => options.with_line_wrapping(boolean()?),
+                "assignment-width" => options.with_assignment_width(number()?),
+                "strict" => options.with_strict(boolean()?),
+                "divider-length" => {
+                    options.with_divider_length(value.parse().map_err(|_| invalid())?)
+                }
+                "quantifier-list-layout" => {
+                    options.with_quantifier_list_layout(value.parse().map_err(|_| invalid())?)
+                }
+                "final-newline" => {
+                    options.with_final_newline(value.parse().map_err(|_| invalid())?)
+                }
+                "line-ending" => options.with_line_ending(value.parse().map_err(|_| invalid())?),
+                _ => return Err(invalid()),
+            };
+        }
+    }
+
+    Ok(options)
+}
+
+/// Return the settings declared by `line` if it is a modeline comment.
+fn modeline(line: &str) -> Option<&str> {
+    let settings = directive(line)?;
+
+    // Other directives sharing the prefix do not contain settings.
+    settings
+        .split_ascii_whitespace()
+        .next()
+        .is_some_and(|v| v.contains('='))
+        .then_some(settings)
+}
+
+/// Return the (trimmed) text following [`MODELINE_PREFIX`] if `comment` is a
+/// `\* tlafmt: ...` or `(* tlafmt: ... *)` comment.
+pub(crate) fn directive(comment: &str) -> Option<&str> {
+    let comment = comment.trim();
+    let text = match comment.strip_prefix("(*") {
+        Some(v) => v.strip_suffix("*)").unwrap_or(v),
+        None => comment.strip_prefix("\\*")?,
+    };
+
+    Some(text.trim().strip_prefix(MODELINE_PREFIX)?.trim())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assert_rewrite, ParsedFile};
+
+    #[test]
+    fn test_modeline() {
+        assert_rewrite!(
+            r"
+\* tlafmt: line-width=40 indent=2
+(* tlafmt: collapse-single-item-lists=true *)
+---- MODULE Bananas ----
+Op == /\ LET x == 1 IN x
+Op2 ==
+    /\ 42
+    /\ 24
+===="
+        );
+    }
+
+    #[test]
+    fn test_modeline_overrides_options() {
+        let input = "\\* tlafmt: line-width=20\n---- MODULE B ----\n====\n";
+        let got = apply(input, FormatOptions::default().with_line_width(100)).unwrap();
+        assert_eq!(got.line_width(), 20);
+
+        // Modelines are only read from the start of the input.
+        let input = "---- MODULE B ----\n\n\n\n\n\\* tlafmt: line-width=20\n====\n";
+        let got = apply(input, FormatOptions::default()).unwrap();
+        assert_eq!(got.line_width(), 80);
+
+        let input = "\\* tlafmt: assignment-width=120\n---- MODULE B ----\n====\n";
+        let got = apply(input, FormatOptions::default()).unwrap();
+        assert_eq!(got.assignment_width(), Some(120));
+    }
+
+    #[test]
+    fn test_modeline_errors() {
+        for input in [
+            "\\* tlafmt: bananas=42",
+            "\\* tlafmt: line-width=wide",
+            "\\* tlafmt: hard-tabs=yes",
+            "\\* tlafmt: indent=2 hard-tabs",
+        ] {
+            assert!(
+                matches!(
+                    ParsedFile::new(input).unwrap().format(Vec::new()),
+                    Err(Error::Modeline(_))
+                ),
+                "{input}"
+            );
+        }
+
+        // Directives without settings are not modelines.
+        assert!(apply("\\* tlafmt: off", FormatOptions::default()).is_ok());
+        assert!(apply("\\* tlafmt:", FormatOptions::default()).is_ok());
+    }
+}
@@ -0,0 +1,1004 @@
+//! User-configurable formatting behaviour.
+
+use std::{fmt::Display, str::FromStr, sync::Arc};
+
+/// The default maximum width of a rendered line.
+pub const DEFAULT_LINE_WIDTH: usize = 80;
+
+/// The default number of columns rendered per level of indentation.
+pub const DEFAULT_INDENT_WIDTH: usize = 4;
+
+/// The default maximum number of indentation levels rendered.
+pub const DEFAULT_MAX_INDENT_DEPTH: usize = 255;
+
+/// The layout of an infix operator relative to its operands.
+///
+/// See [`FormatOptions::with_operator_spacing()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OperatorSpacing {
+    /// The operator is never delimited from its operands by whitespace
+    /// (`a++b`).
+    Tight,
+
+    /// The operator is delimited from its operands by a single space
+    /// (`a ++ b`).
+    Spaced,
+
+    /// As [`OperatorSpacing::Spaced`], but the operator is always placed on the
+    /// same line as the preceding operand, removing any line break before it.
+    NeverBreakBefore,
+}
+
+impl FromStr for OperatorSpacing {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "tight" => Self::Tight,
+            "spaced" => Self::Spaced,
+            "never-break-before" => Self::NeverBreakBefore,
+            _ => {
+                return Err(format!(
+                    "unknown operator spacing {s:?} (expected one of tight, spaced, never-break-before)"
+                ))
+            }
+        })
+    }
+}
+
+impl Display for OperatorSpacing {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Tight => "tight",
+            Self::Spaced => "spaced",
+            Self::NeverBreakBefore => "never-break-before",
+        })
+    }
+}
+
+/// The line breaks permitted within a call to an operator (such as
+/// `Append(s, e)`).
+///
+/// See [`FormatOptions::with_call_wrapping()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallWrapping {
+    /// The call is always rendered on a single line, removing any line breaks
+    /// within it.
+    Unsplit,
+
+    /// Line breaks are only permitted after the comma between two arguments,
+    /// never between the operator name and `(`, nor inside the parentheses.
+    BetweenArguments,
+}
+
+impl FromStr for CallWrapping {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "unsplit" => Self::Unsplit,
+            "between-arguments" => Self::BetweenArguments,
+            _ => {
+                return Err(format!(
+                    "unknown call wrapping {s:?} (expected one of unsplit, between-arguments)"
+                ))
+            }
+        })
+    }
+}
+
+impl Display for CallWrapping {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Unsplit => "unsplit",
+            Self::BetweenArguments => "between-arguments",
+        })
+    }
+}
+
+/// The wrapping hints for calls to the operators of the `Sequences` and
+/// `FiniteSets` standard modules.
+///
+/// See [`FormatOptions::with_standard_call_wrapping()`].
+const STANDARD_CALL_WRAPPING: &[(&str, CallWrapping)] = &[
+    ("Seq", CallWrapping::Unsplit),
+    ("Len", CallWrapping::Unsplit),
+    ("Head", CallWrapping::Unsplit),
+    ("Tail", CallWrapping::Unsplit),
+    ("Append", CallWrapping::Unsplit),
+    ("SubSeq", CallWrapping::BetweenArguments),
+    ("SelectSeq", CallWrapping::BetweenArguments),
+    ("IsFiniteSet", CallWrapping::Unsplit),
+    ("Cardinality", CallWrapping::Unsplit),
+];
+
+/// The length of the module header, footer and dividing lines.
+///
+/// See [`FormatOptions::with_divider_length()`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DividerLength {
+    /// Dividing lines and the module footer span the configured line width,
+    /// and the module header is centred within it.
+    #[default]
+    Fill,
+
+    /// Dividing lines and the module footer retain the length they have in the
+    /// input, while the module header is sized as [`DividerLength::Fill`].
+    Preserve,
+
+    /// As [`DividerLength::Preserve`], but the dashes either side of the name in
+    /// the module header also retain the length they have in the input.
+    PreserveWithHeader,
+}
+
+impl FromStr for DividerLength {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "fill" => Self::Fill,
+            "preserve" => Self::Preserve,
+            "preserve-with-header" => Self::PreserveWithHeader,
+            _ => {
+                return Err(format!(
+                    "unknown divider length {s:?} (expected one of fill, preserve, preserve-with-header)"
+                ))
+            }
+        })
+    }
+}
+
+impl Display for DividerLength {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Fill => "fill",
+            Self::Preserve => "preserve",
+            Self::PreserveWithHeader => "preserve-with-header",
+        })
+    }
+}
+
+/// The placement of a conjunction or disjunction list that forms the body of a
+/// quantifier (such as `\E t \in Threads: \/ A \/ B`).
+///
+/// See [`FormatOptions::with_quantifier_list_layout()`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum QuantifierListLayout {
+    /// The list starts on a new line after the quantifier, indented one level
+    /// deeper than the line containing the quantifier.
+    #[default]
+    NewLine,
+
+    /// The first item of the list is placed on the same line as the
+    /// quantifier, with the remaining items aligned beneath it.
+    ///
+    /// Lists containing an item that spans multiple lines, or comments between
+    /// items, are laid out as [`QuantifierListLayout::NewLine`].
+    SameLine,
+}
+
+impl FromStr for QuantifierListLayout {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "new-line" => Self::NewLine,
+            "same-line" => Self::SameLine,
+            _ => {
+                return Err(format!(
+                    "unknown quantifier list layout {s:?} (expected one of new-line, same-line)"
+                ))
+            }
+        })
+    }
+}
+
+impl Display for QuantifierListLayout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::NewLine => "new-line",
+            Self::SameLine => "same-line",
+        })
+    }
+}
+
+/// Whether the output ends with a newline.
+///
+/// See [`FormatOptions::with_final_newline()`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FinalNewline {
+    /// The output ends with exactly one newline.
+    Always,
+
+    /// The output never ends with a newline.
+    #[default]
+    Never,
+
+    /// The output ends with exactly one newline if the input ends with a
+    /// newline, otherwise it ends without one.
+    Preserve,
+}
+
+impl FromStr for FinalNewline {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "always" => Self::Always,
+            "never" => Self::Never,
+            "preserve" => Self::Preserve,
+            _ => {
+                return Err(format!(
+                    "unknown final newline {s:?} (expected one of always, never, preserve)"
+                ))
+            }
+        })
+    }
+}
+
+impl Display for FinalNewline {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Always => "always",
+            Self::Never => "never",
+            Self::Preserve => "preserve",
+        })
+    }
+}
+
+/// The line ending rendered at the end of each line of the output.
+///
+/// See [`FormatOptions::with_line_ending()`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LineEnding {
+    /// Lines end with the line ending used by the majority of lines in the
+    /// input (`\n` unless most lines end with `\r\n`).
+    #[default]
+    Detect,
+
+    /// Lines end with `\n`.
+    Lf,
+
+    /// Lines end with `\r\n`.
+    Crlf,
+}
+
+impl FromStr for LineEnding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "detect" => Self::Detect,
+            "lf" => Self::Lf,
+            "crlf" => Self::Crlf,
+            _ => {
+                return Err(format!(
+                    "unknown line ending {s:?} (expected one of detect, lf, crlf)"
+                ))
+            }
+        })
+    }
+}
+
+impl Display for LineEnding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Detect => "detect",
+            Self::Lf => "lf",
+            Self::Crlf => "crlf",
+        })
+    }
+}
+
+/// A user-provided transformation of the text of a spec, applied before parsing
+/// or after rendering.
+///
+/// See [`FormatOptions::with_pre_parse_hook()`] and
+/// [`FormatOptions::with_post_render_hook()`].
+#[derive(Clone)]
+pub struct TextHook(Arc<dyn Fn(&str) -> String + Send + Sync>);
+
+impl TextHook {
+    /// Wrap the transformation `f`.
+    pub fn new<F>(f: F) -> Self
+    where
+        F: Fn(&str) -> String + Send + Sync + 'static,
+    {
+        Self(Arc::new(f))
+    }
+
+    /// Apply the transformation to `input`.
+    pub fn apply(&self, input: &str) -> String {
+        (self.0)(input)
+    }
+}
+
+impl std::fmt::Debug for TextHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("TextHook(..)")
+    }
+}
+
+/// Two hooks are equal if they wrap the same transformation.
+impl PartialEq for TextHook {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for TextHook {}
+
+/// Configuration of the formatter, passed to [`ParsedFile::format_with()`].
+///
+/// Construct an instance with [`FormatOptions::default()`] and use the `with_`
+/// methods to change the desired settings:
+///
+/// ```
+/// use libtlafmt::{FormatOptions, OperatorSpacing};
+///
+/// let opts = FormatOptions::default()
+///     .with_line_width(100)
+///     .with_collapse_single_item_lists(true)
+///     .with_operator_spacing("++", OperatorSpacing::Tight);
+/// ```
+///
+/// [`ParsedFile::format_with()`]: crate::ParsedFile::format_with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatOptions {
+    line_width: usize,
+    indent_width: usize,
+    hard_tabs: bool,
+    collapse_single_item_lists: bool,
+    align_comments: bool,
+    final_newline: FinalNewline,
+    metadata_comment: bool,
+    align_assumptions: bool,
+    normalise_booleans: bool,
+    standard_call_wrapping: bool,
+    sort_record_fields: bool,
+    line_wrapping: bool,
+    assignment_width: Option<usize>,
+    strict: bool,
+    verify: bool,
+    verify_idempotent: bool,
+    max_indent_depth: usize,
+    divider_length: DividerLength,
+    quantifier_list_layout: QuantifierListLayout,
+    line_ending: LineEnding,
+    operator_spacing: Vec<(String, OperatorSpacing)>,
+    call_wrapping: Vec<(String, CallWrapping)>,
+    record_field_order: Vec<String>,
+    protected_comment_prefixes: Vec<String>,
+    license_header: Option<String>,
+    pre_parse_hook: Option<TextHook>,
+    post_render_hook: Option<TextHook>,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            line_width: DEFAULT_LINE_WIDTH,
+            indent_width: DEFAULT_INDENT_WIDTH,
+            hard_tabs: false,
+            collapse_single_item_lists: false,
+            align_comments: true,
+            final_newline: FinalNewline::Never,
+            metadata_comment: false,
+            align_assumptions: false,
+            normalise_booleans: false,
+            standard_call_wrapping: false,
+            sort_record_fields: false,
+            line_wrapping: false,
+            assignment_width: None,
+            strict: false,
+            verify: true,
+            verify_idempotent: false,
+            max_indent_depth: DEFAULT_MAX_INDENT_DEPTH,
+            divider_length: DividerLength::Fill,
+            quantifier_list_layout: QuantifierListLayout::NewLine,
+            line_ending: LineEnding::Detect,
+            operator_spacing: Vec::new(),
+            call_wrapping: Vec::new(),
+            record_field_order: Vec::new(),
+            protected_comment_prefixes: Vec::new(),
+            license_header: None,
+            pre_parse_hook: None,
+            post_render_hook: None,
+        }
+    }
+}
+
+impl FormatOptions {
+    /// Set the maximum width of a rendered line, used to size module headers
+    /// and dividing lines.
+    ///
+    /// Defaults to [`DEFAULT_LINE_WIDTH`].
+    pub fn with_line_width(mut self, v: usize) -> Self {
+        self.line_width = v;
+        self
+    }
+
+    /// Returns the configured maximum line width.
+    ///
+    /// See [`FormatOptions::with_line_width()`].
+    pub fn line_width(&self) -> usize {
+        self.line_width
+    }
+
+    /// Set the number of columns rendered per level of indentation.
+    ///
+    /// Ignored when hard tabs are enabled. Defaults to
+    /// [`DEFAULT_INDENT_WIDTH`].
+    pub fn with_indent_width(mut self, v: usize) -> Self {
+        self.indent_width = v;
+        self
+    }
+
+    /// Returns the configured number of columns per level of indentation.
+    ///
+    /// See [`FormatOptions::with_indent_width()`].
+    pub fn indent_width(&self) -> usize {
+        self.indent_width
+    }
+
+    /// Set the maximum number of indentation levels rendered.
+    ///
+    /// Lines nested deeper are rendered at this depth, and a
+    /// [`Diagnostic::IndentClamped`] is raised. Defaults to
+    /// [`DEFAULT_MAX_INDENT_DEPTH`].
+    ///
+    /// [`Diagnostic::IndentClamped`]: crate::Diagnostic::IndentClamped
+    pub fn with_max_indent_depth(mut self, v: usize) -> Self {
+        self.max_indent_depth = v;
+        self
+    }
+
+    /// Returns the maximum number of indentation levels rendered.
+    ///
+    /// See [`FormatOptions::with_max_indent_depth()`].
+    pub fn max_indent_depth(&self) -> usize {
+        self.max_indent_depth
+    }
+
+    /// When true, each level of indentation is rendered as a single tab
+    /// character instead of spaces. Defaults to false.
+    ///
+    /// Tab characters are assumed to be 4 columns wide when aligning comments,
+    /// matching the treatment of tabs in input specs.
+    pub fn with_hard_tabs(mut self, v: bool) -> Self {
+        self.hard_tabs = v;
+        self
+    }
+
+    /// Returns true if indentation is rendered using tab characters.
+    ///
+    /// See [`FormatOptions::with_hard_tabs()`].
+    pub fn hard_tabs(&self) -> bool {
+        self.hard_tabs
+    }
+
+    /// Returns the string rendered for a single level of indentation.
+    pub(crate) fn indent_str(&self) -> String {
+        match self.hard_tabs {
+            true => "\t".to_string(),
+            false => " ".repeat(self.indent_width),
+        }
+    }
+
+    /// When true, conjunction and disjunction lists containing a single item
+    /// have their redundant bullet removed:
+    ///
+    /// ```text
+    /// Op == /\ x = 1
+    /// ```
+    ///
+    /// Is rendered as:
+    ///
+    /// ```text
+    /// Op == x = 1
+    /// ```
+    ///
+    /// Lists with comments between the bullet and the item body are always
+    /// preserved. Defaults to false.
+    pub fn with_collapse_single_item_lists(mut self, v: bool) -> Self {
+        self.collapse_single_item_lists = v;
+        self
+    }
+
+    /// Returns true if single item conjunction / disjunction lists are
+    /// collapsed.
+    ///
+    /// See [`FormatOptions::with_collapse_single_item_lists()`].
+    pub fn collapse_single_item_lists(&self) -> bool {
+        self.collapse_single_item_lists
+    }
+
+    /// When true, end-of-line comments on consecutive lines that are vertically
+    /// aligned in the input remain aligned after formatting.
+    ///
+    /// When false, end-of-line comments are always delimited from the preceding
+    /// token by a single space. Defaults to true.
+    pub fn with_align_comments(mut self, v: bool) -> Self {
+        self.align_comments = v;
+        self
+    }
+
+    /// Returns true if vertically aligned end-of-line comments are realigned
+    /// after formatting.
+    ///
+    /// See [`FormatOptions::with_align_comments()`].
+    pub fn align_comments(&self) -> bool {
+        self.align_comments
+    }
+
+    /// Configure whether the rendered output ends with exactly one newline,
+    /// irrespective of any trailing newlines in the input.
+    ///
+    /// Defaults to [`FinalNewline::Never`].
+    pub fn with_final_newline(mut self, v: FinalNewline) -> Self {
+        self.final_newline = v;
+        self
+    }
+
+    /// Returns the configured [`FinalNewline`].
+    ///
+    /// See [`FormatOptions::with_final_newline()`].
+    pub fn final_newline(&self) -> FinalNewline {
+        self.final_newline
+    }
+
+    /// When true, a comment recording the formatter version and style edition
+    /// that produced the output is placed before the module footer:
+    ///
+    /// ```text
+    /// \* formatted with tlafmt v0.4.1 (style 2025)
+    /// ====
+    /// ```
+    ///
+    /// An existing metadata comment is replaced only if it records a different
+    /// style edition, allowing tooling to detect specs formatted with a
+    /// different style without a new formatter version changing every spec.
+    /// Defaults to false.
+    pub fn with_metadata_comment(mut self, v: bool) -> Self {
+        self.metadata_comment = v;
+        self
+    }
+
+    /// Returns true if a formatting metadata comment is rendered.
+    ///
+    /// See [`FormatOptions::with_metadata_comment()`].
+    pub fn metadata_comment(&self) -> bool {
+        self.metadata_comment
+    }
+
+    /// When true, consecutive module-level `ASSUME` statements are formatted
+    /// as a block: the `==` of named assumptions is aligned to the same column,
+    /// and each assumption is separated by exactly one empty line.
+    ///
+    /// ```text
+    /// ASSUME NumNodes   == N \in Nat
+    ///
+    /// ASSUMPTION Quorum == Q \in 1..N
+    /// ```
+    ///
+    /// Defaults to false.
+    pub fn with_align_assumptions(mut self, v: bool) -> Self {
+        self.align_assumptions = v;
+        self
+    }
+
+    /// Returns true if consecutive `ASSUME` statements are aligned.
+    ///
+    /// See [`FormatOptions::with_align_assumptions()`].
+    pub fn align_assumptions(&self) -> bool {
+        self.align_assumptions
+    }
+
+    /// When true, references to `TRUE`, `FALSE` and `BOOLEAN` spelled in a
+    /// different case (such as `True` in older specs) are rendered with the
+    /// canonical spelling.
+    ///
+    /// Names with a definition, declaration or binder in scope are left
+    /// unchanged, and a [`Diagnostic::ShadowedBuiltin`] is raised. Defaults to
+    /// false.
+    ///
+    /// [`Diagnostic::ShadowedBuiltin`]: crate::Diagnostic::ShadowedBuiltin
+    pub fn with_normalise_booleans(mut self, v: bool) -> Self {
+        self.normalise_booleans = v;
+        self
+    }
+
+    /// Returns true if the casing of builtin boolean names is normalised.
+    ///
+    /// See [`FormatOptions::with_normalise_booleans()`].
+    pub fn normalise_booleans(&self) -> bool {
+        self.normalise_booleans
+    }
+
+    /// When true, calls to the operators of the `Sequences` and `FiniteSets`
+    /// standard modules are wrapped according to a built-in table of
+    /// [`CallWrapping`] hints: short calls (such as `Len(s)`) are kept on one
+    /// line, and `SubSeq` / `SelectSeq` only break between arguments.
+    ///
+    /// Hints configured with [`FormatOptions::with_call_wrapping()`] take
+    /// precedence. Defaults to false.
+    pub fn with_standard_call_wrapping(mut self, v: bool) -> Self {
+        self.standard_call_wrapping = v;
+        self
+    }
+
+    /// Returns true if the built-in standard library call wrapping hints are
+    /// applied.
+    ///
+    /// See [`FormatOptions::with_standard_call_wrapping()`].
+    pub fn standard_call_wrapping(&self) -> bool {
+        self.standard_call_wrapping
+    }
+
+    /// When true, the fields of record literals (`[a |-> 1, b |-> 2]`) and
+    /// sets of records (`[a : S, b : T]`) are sorted alphabetically, or by the
+    /// order configured with [`FormatOptions::with_record_field_order()`].
+    ///
+    /// Records containing comments are never changed. Defaults to false.
+    pub fn with_sort_record_fields(mut self, v: bool) -> Self {
+        self.sort_record_fields = v;
+        self
+    }
+
+    /// Returns true if record fields are sorted.
+    ///
+    /// See [`FormatOptions::with_sort_record_fields()`].
+    pub fn sort_record_fields(&self) -> bool {
+        self.sort_record_fields
+    }
+
+    /// When true, expressions exceeding the line width are broken across
+    /// lines: the elements of set literals (`{a, b, c}`) and tuples (`<<a, b,
+    /// c>>`, including those of `UNCHANGED`) are wrapped after the comma,
+    /// aligned with the first element, and record literals (`[a |-> 1]`) are
+    /// placed one field per line with the `|->` of each field aligned. Chains
+    /// of infix operators (`a \union b \union c`) are broken after each
+    /// application of the outermost (lowest precedence) operator, with the
+    /// continuation lines indented, placing the temporal formula of a long
+    /// `THEOREM Spec => [](A /\ B)` on its own line.
+    ///
+    /// Line breaks in the input are retained, and a record literal containing
+    /// a line break between its fields is always placed one field per line.
+    /// Expressions containing comments, or conjunction, disjunction or `CASE`
+    /// expressions, are left unchanged. Defaults to false.
+    pub fn with_line_wrapping(mut self, v: bool) -> Self {
+        self.line_wrapping = v;
+        self
+    }
+
+    /// Returns true if expressions exceeding the line width are broken across
+    /// lines.
+    ///
+    /// See [`FormatOptions::with_line_wrapping()`].
+    pub fn line_wrapping(&self) -> bool {
+        self.line_wrapping
+    }
+
+    /// Set the width a conjunction list item assigning a value to a variable
+    /// (such as `/\ x' = x + 1`) may extend to before it is broken across lines
+    /// by [line wrapping](FormatOptions::with_line_wrapping()), instead of the
+    /// line width.
+    ///
+    /// Breaking simple assignments harms their readability, so this is usually
+    /// set wider than the line width. Defaults to the line width.
+    pub fn with_assignment_width(mut self, v: usize) -> Self {
+        self.assignment_width = Some(v);
+        self
+    }
+
+    /// Returns the configured width of an assignment, if set.
+    ///
+    /// See [`FormatOptions::with_assignment_width()`].
+    pub fn assignment_width(&self) -> Option<usize> {
+        self.assignment_width
+    }
+
+    /// When true, input containing syntax errors is not formatted, and
+    /// [`Error::SyntaxErrors`] is returned listing the location of each error.
+    ///
+    /// Otherwise specs containing syntax errors are formatted on a best-effort
+    /// basis, with the erroneous source emitted as-is. Defaults to false.
+    ///
+    /// [`Error::SyntaxErrors`]: crate::Error::SyntaxErrors
+    pub fn with_strict(mut self, v: bool) -> Self {
+        self.strict = v;
+        self
+    }
+
+    /// Returns true if input containing syntax errors is not formatted.
+    ///
+    /// See [`FormatOptions::with_strict()`].
+    pub fn strict(&self) -> bool {
+        self.strict
+    }
+
+    /// When true, the formatted output is re-parsed and its tokens compared
+    /// against those of the input, returning [`Error::Verification`] instead
+    /// of writing output that changes the meaning of the spec.
+    ///
+    /// Comments, whitespace and the length of dividing lines are not compared,
+    /// and input containing syntax errors is not verified. Partial formatting
+    /// (such as [`ParsedFile::format_lines()`]) is not verified. Defaults to
+    /// true.
+    ///
+    /// [`ParsedFile::format_lines()`]: crate::ParsedFile::format_lines
+    ///
+    /// [`Error::Verification`]: crate::Error::Verification
+    pub fn with_verify(mut self, v: bool) -> Self {
+        self.verify = v;
+        self
+    }
+
+    /// Returns true if the formatted output is verified before it is wrote.
+    ///
+    /// See [`FormatOptions::with_verify()`].
+    pub fn verify(&self) -> bool {
+        self.verify
+    }
+
+    /// When true, the formatted output is formatted a second time, returning
+    /// [`Error::NotIdempotent`] instead of writing it if the two differ.
+    ///
+    /// This doubles the cost of formatting, and is intended to surface
+    /// formatter bugs. Defaults to false.
+    ///
+    /// [`Error::NotIdempotent`]: crate::Error::NotIdempotent
+    pub fn with_verify_idempotent(mut self, v: bool) -> Self {
+        self.verify_idempotent = v;
+        self
+    }
+
+    /// Returns true if the formatted output is checked to be unchanged when
+    /// formatted again.
+    ///
+    /// See [`FormatOptions::with_verify_idempotent()`].
+    pub fn verify_idempotent(&self) -> bool {
+        self.verify_idempotent
+    }
+
+    /// Configure whether the module header, footer and dividing lines span the
+    /// line width, or retain their length from the input.
+    ///
+    /// Defaults to [`DividerLength::Fill`].
+    pub fn with_divider_length(mut self, v: DividerLength) -> Self {
+        self.divider_length = v;
+        self
+    }
+
+    /// Returns the configured [`DividerLength`].
+    ///
+    /// See [`FormatOptions::with_divider_length()`].
+    pub fn divider_length(&self) -> DividerLength {
+        self.divider_length
+    }
+
+    /// Configure whether a conjunction or disjunction list forming the body of
+    /// a quantifier starts on a new line, or on the same line as the
+    /// quantifier, irrespective of the layout in the input.
+    ///
+    /// Defaults to [`QuantifierListLayout::NewLine`].
+    pub fn with_quantifier_list_layout(mut self, v: QuantifierListLayout) -> Self {
+        self.quantifier_list_layout = v;
+        self
+    }
+
+    /// Returns the configured [`QuantifierListLayout`].
+    ///
+    /// See [`FormatOptions::with_quantifier_list_layout()`].
+    pub fn quantifier_list_layout(&self) -> QuantifierListLayout {
+        self.quantifier_list_layout
+    }
+
+    /// Configure the line ending rendered at the end of each line, including
+    /// the lines of multi-line comments and unformatted source.
+    ///
+    /// Defaults to [`LineEnding::Detect`].
+    pub fn with_line_ending(mut self, v: LineEnding) -> Self {
+        self.line_ending = v;
+        self
+    }
+
+    /// Returns the configured [`LineEnding`].
+    ///
+    /// See [`FormatOptions::with_line_ending()`].
+    pub fn line_ending(&self) -> LineEnding {
+        self.line_ending
+    }
+
+    /// Override the spacing of the infix operator `op` with `spacing`.
+    ///
+    /// The operator is matched against the exact symbol used in the spec, so
+    /// operators with multiple spellings (such as `\oplus` and `(+)`) must be
+    /// configured once per spelling used. Only operators that are applied to
+    /// operands in an expression are affected.
+    ///
+    /// Configuring the same operator more than once replaces the previous
+    /// value.
+    pub fn with_operator_spacing(
+        mut self,
+        op: impl Into<String>,
+        spacing: OperatorSpacing,
+    ) -> Self {
+        let op = op.into();
+        self.operator_spacing.retain(|(v, _)| *v != op);
+        self.operator_spacing.push((op, spacing));
+        self
+    }
+
+    /// Returns the user-configured [`OperatorSpacing`] for the infix operator
+    /// `op`, if any.
+    ///
+    /// See [`FormatOptions::with_operator_spacing()`].
+    pub fn operator_spacing(&self, op: &str) -> Option<OperatorSpacing> {
+        self.operator_spacing
+            .iter()
+            .find(|(v, _)| v == op)
+            .map(|(_, spacing)| *spacing)
+    }
+
+    /// Returns all user-configured operator spacing overrides.
+    ///
+    /// See [`FormatOptions::with_operator_spacing()`].
+    pub fn operator_spacings(&self) -> impl Iterator<Item = (&str, OperatorSpacing)> {
+        self.operator_spacing
+            .iter()
+            .map(|(op, spacing)| (op.as_str(), *spacing))
+    }
+
+    /// Wrap calls to the operator named `op` (such as `Append`) according to
+    /// `wrapping`.
+    ///
+    /// Calls containing comments, or conjunction, disjunction or `CASE`
+    /// expressions (which rely on their line breaks) are never changed.
+    ///
+    /// Configuring the same operator more than once replaces the previous
+    /// value.
+    pub fn with_call_wrapping(mut self, op: impl Into<String>, wrapping: CallWrapping) -> Self {
+        let op = op.into();
+        self.call_wrapping.retain(|(v, _)| *v != op);
+        self.call_wrapping.push((op, wrapping));
+        self
+    }
+
+    /// Returns the [`CallWrapping`] applied to calls to the operator `op`, if
+    /// any.
+    ///
+    /// See [`FormatOptions::with_call_wrapping()`] and
+    /// [`FormatOptions::with_standard_call_wrapping()`].
+    pub fn call_wrapping(&self, op: &str) -> Option<CallWrapping> {
+        let standard = STANDARD_CALL_WRAPPING
+            .iter()
+            .filter(|_| self.standard_call_wrapping)
+            .map(|(op, v)| (*op, *v));
+
+        self.call_wrappings()
+            .chain(standard)
+            .find(|(v, _)| *v == op)
+            .map(|(_, v)| v)
+    }
+
+    /// Returns all user-configured call wrapping hints.
+    ///
+    /// See [`FormatOptions::with_call_wrapping()`].
+    pub fn call_wrappings(&self) -> impl Iterator<Item = (&str, CallWrapping)> {
+        self.call_wrapping.iter().map(|(op, v)| (op.as_str(), *v))
+    }
+
+    /// Sort the record fields named in `fields` before all others, in the
+    /// given order, when [`FormatOptions::with_sort_record_fields()`] is
+    /// enabled.
+    ///
+    /// Fields not named in `fields` follow in alphabetical order. Replaces any
+    /// previously configured order.
+    pub fn with_record_field_order<I, T>(mut self, fields: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.record_field_order = fields.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Returns the configured record field order.
+    ///
+    /// See [`FormatOptions::with_record_field_order()`].
+    pub fn record_field_order(&self) -> impl Iterator<Item = &str> {
+        self.record_field_order.iter().map(String::as_str)
+    }
+
+    /// Protect module-level comments starting with `prefix` (such as
+    /// `\* INCLUDE`) from formatting.
+    ///
+    /// Protected comments are rendered verbatim, and the line breaks between
+    /// a protected comment and the following definition are preserved, for
+    /// comments that are directives to external tooling.
+    ///
+    /// May be called multiple times to protect multiple prefixes.
+    pub fn with_protected_comment_prefix(mut self, prefix: impl Into<String>) -> Self {
+        let prefix = prefix.into();
+        if !self.protected_comment_prefixes.contains(&prefix) {
+            self.protected_comment_prefixes.push(prefix);
+        }
+        self
+    }
+
+    /// Returns the configured protected comment prefixes.
+    ///
+    /// See [`FormatOptions::with_protected_comment_prefix()`].
+    pub fn protected_comment_prefixes(&self) -> impl Iterator<Item = &str> {
+        self.protected_comment_prefixes.iter().map(String::as_str)
+    }
+
+    /// Returns true if the comment text `comment` starts with a protected
+    /// prefix.
+    pub(crate) fn is_protected_comment(&self, comment: &str) -> bool {
+        self.protected_comment_prefixes
+            .iter()
+            .any(|v| comment.starts_with(v.as_str()))
+    }
+
+    /// Place the comment block `header` (such as a copyright or license notice)
+    /// above the module header of the output, unless the text before the
+    /// module header already contains it.
+    ///
+    /// If the text before the module header contains a comment block that is
+    /// recognisably an outdated notice (mentioning a copyright or SPDX license
+    /// identifier, or starting with the first line of `header`), it is replaced
+    /// by `header`. Other comments, such as modelines, are kept.
+    ///
+    /// The header is placed outside of the module, and is never formatted.
+    pub fn with_license_header(mut self, header: impl Into<String>) -> Self {
+        self.license_header = Some(header.into());
+        self
+    }
+
+    /// Returns the configured license header, if any.
+    ///
+    /// See [`FormatOptions::with_license_header()`].
+    pub fn license_header(&self) -> Option<&str> {
+        self.license_header.as_deref()
+    }
+
+    /// Transform the input text with `f` before it is parsed by
+    /// [`ParsedFile::new_with()`], such as to hide preprocessor directives
+    /// that are not valid TLA+ from the parser.
+    ///
+    /// [`ParsedFile::new_with()`]: crate::ParsedFile::new_with
+    pub fn with_pre_parse_hook<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&str) -> String + Send + Sync + 'static,
+    {
+        self.pre_parse_hook = Some(TextHook::new(f));
+        self
+    }
+
+    /// Returns the configured pre-parse [`TextHook`], if any.
+    ///
+    /// See [`FormatOptions::with_pre_parse_hook()`].
+    pub fn pre_parse_hook(&self) -> Option<&TextHook> {
+        self.pre_parse_hook.as_ref()
+    }
+
+    /// Transform the rendered output with `f` before it is wrote to the output
+    /// sink, such as to restore text hidden by a
+    /// [pre-parse hook](FormatOptions::with_pre_parse_hook()).
+    pub fn with_post_render_hook<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&str) -> String + Send + Sync + 'static,
+    {
+        self.post_render_hook = Some(TextHook::new(f));
+        self
+    }
+
+    /// Returns the configured post-render [`TextHook`], if any.
+    ///
+    /// See [`FormatOptions::with_post_render_hook()`].
+    pub fn post_render_hook(&self) -> Option<&TextHook> {
+        self.post_render_hook.as_ref()
+    }
+}
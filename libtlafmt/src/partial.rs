@@ -0,0 +1,334 @@
+//! Reformatting of a subset of the top-level nodes of a module, leaving all
+//! other lines unchanged.
+
+use std::{collections::HashMap, io::Write, ops::Range};
+
+use tree_sitter::Node;
+
+use crate::{
+    ast_format::format_node, booleans, definitions::DefinitionKind, helpers::EmptyLines, modeline,
+    post_process, rename::first_child_of_kind, renderer::Renderer, Error, FinalNewline,
+    FormatOptions, ParsedFile,
+};
+
+impl<'a> ParsedFile<'a> {
+    /// Format only the top-level definitions, declarations and comments that
+    /// overlap any of the 0-indexed input `lines` using `options`, returning
+    /// all other lines unchanged.
+    ///
+    /// This allows the formatter to be adopted incrementally, reformatting
+    /// only the code touched by a change.
+    pub fn format_lines(
+        &self,
+        lines: &[Range<usize>],
+        options: &FormatOptions,
+    ) -> Result<String, Error> {
+        self.reformat(options, HashMap::new(), |group| {
+            let (Some(first), Some(last)) = (group.first(), group.last()) else {
+                return false;
+            };
+
+            // The module header is formatted as a whole with the module.
+            if first.kind() == "header_line" {
+                return false;
+            }
+
+            let spans = first.start_position().row..last_row(last) + 1;
+            lines
+                .iter()
+                .any(|v| v.start < spans.end && spans.start < v.end)
+        })
+    }
+
+    /// Format only the top-level definitions, declarations and comments that
+    /// overlap the 0-indexed input `lines` using `options`, writing the result
+    /// (including all other lines, unchanged) to `out`.
+    ///
+    /// See [`ParsedFile::format_lines()`].
+    ///
+    /// # Errors
+    ///
+    /// If formatting fails `out` is left unchanged.
+    pub fn format_range<W>(
+        &self,
+        lines: Range<usize>,
+        options: &FormatOptions,
+        mut out: W,
+    ) -> Result<(), Error>
+    where
+        W: Write,
+    {
+        let formatted = self.format_lines(&[lines], options)?;
+        out.write_all(formatted.as_bytes())?;
+        Ok(())
+    }
+
+    /// Format only the operator and function definitions named in `names`
+    /// using `options`, returning all other lines unchanged.
+    ///
+    /// This allows the formatter to be adopted incrementally, one definition at
+    /// a time.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::UnknownIdent`] if no operator or function definition
+    /// exists for one of `names`.
+    pub fn format_definitions(
+        &self,
+        names: &[&str],
+        options: &FormatOptions,
+    ) -> Result<String, Error> {
+        let mut lines = Vec::with_capacity(names.len());
+        for name in names {
+            let len = lines.len();
+            lines.extend(
+                self.definitions()
+                    .filter(|v| v.name == *name)
+                    .filter(|v| {
+                        matches!(v.kind, DefinitionKind::Operator | DefinitionKind::Function)
+                    })
+                    .map(|v| v.lines),
+            );
+            if lines.len() == len {
+                return Err(Error::UnknownIdent(name.to_string()));
+            }
+        }
+
+        self.format_lines(&lines, options)
+    }
+
+    /// Format only the operator or function definition `name` using
+    /// `options`, writing the result (including all other lines, unchanged) to
+    /// `out`.
+    ///
+    /// See [`ParsedFile::format_definitions()`].
+    ///
+    /// # Errors
+    ///
+    /// If formatting fails `out` is left unchanged.
+    pub fn format_definition<W>(
+        &self,
+        name: &str,
+        options: &FormatOptions,
+        mut out: W,
+    ) -> Result<(), Error>
+    where
+        W: Write,
+    {
+        let formatted = self.format_definitions(&[name], options)?;
+        out.write_all(formatted.as_bytes())?;
+        Ok(())
+    }
+
+    /// Reformat each group of top-level nodes (as returned by [`line_groups()`])
+    /// for which `select` returns true, replacing the source lines it spans and
+    /// rendering the identifiers in `renames` with their new names.
+    ///
+    /// Any post-render hook is applied to the whole of the output.
+    pub(crate) fn reformat<F>(
+        &'a self,
+        options: &FormatOptions,
+        renames: HashMap<usize, &'a str>,
+        mut select: F,
+    ) -> Result<String, Error>
+    where
+        F: FnMut(&[Node<'_>]) -> bool,
+    {
+        // Line endings of the replaced lines are preserved from the input.
+        let options =
+            modeline::apply(&self.input, options.clone())?.with_final_newline(FinalNewline::Never);
+        self.check_strict(&options)?;
+
+        let mut renames = renames;
+        if options.normalise_booleans() {
+            for (offset, name) in booleans::normalise(self.t.root_node(), &self.input, &mut vec![])
+            {
+                renames.entry(offset).or_insert(name);
+            }
+        }
+
+        let lines = self.input.split_inclusive('\n').collect::<Vec<_>>();
+        let mut out = String::with_capacity(self.input.len());
+        let mut next_row = 0;
+
+        for group in line_groups(self.t.root_node()) {
+            let (Some(first), Some(last)) = (group.first(), group.last()) else {
+                continue;
+            };
+
+            if !select(&group) {
+                continue;
+            }
+
+            let start_row = first.start_position().row;
+            let end_row = last_row(last);
+
+            let mut buf = Vec::new();
+            let mut renderer =
+                Renderer::new(&mut buf, options.clone()).with_renames(renames.clone());
+            let mut empty_lines = EmptyLines::starting_at(first);
+            for n in &group {
+                format_node(*n, &self.input, &mut empty_lines, &mut renderer)?;
+            }
+            renderer.flush()?;
+
+            lines[next_row..start_row]
+                .iter()
+                .for_each(|v| out.push_str(v));
+            out.push_str(&String::from_utf8_lossy(&buf));
+            if lines.get(end_row).is_some_and(|v| v.ends_with('\n')) {
+                out.push('\n');
+            }
+
+            next_row = end_row + 1;
+        }
+
+        lines
+            .get(next_row..)
+            .unwrap_or_default()
+            .iter()
+            .for_each(|v| out.push_str(v));
+
+        let crlf = self.crlf(&options);
+        Ok(post_process::apply(&out, &options, crlf).into_owned())
+    }
+}
+
+/// Return the children of the module in `root`, grouped such that no two
+/// groups contain nodes on the same line.
+fn line_groups(root: Node<'_>) -> Vec<Vec<Node<'_>>> {
+    let mut groups: Vec<Vec<Node<'_>>> = vec![];
+
+    let Some(module) = first_child_of_kind(root, "module") else {
+        return groups;
+    };
+
+    let mut c = module.walk();
+    for n in module.named_children(&mut c) {
+        match groups.last_mut() {
+            Some(g)
+                if g.last()
+                    .is_some_and(|v| last_row(v) >= n.start_position().row) =>
+            {
+                g.push(n)
+            }
+            _ => groups.push(vec![n]),
+        }
+    }
+
+    groups
+}
+
+/// Return the last row spanned by `n`, which may end at the start of the
+/// following line.
+fn last_row(n: &Node<'_>) -> usize {
+    let (start, end) = (n.start_position(), n.end_position());
+    match end.column {
+        0 if end.row > start.row => end.row - 1,
+        _ => end.row,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_lines() {
+        let input = r"
+---- MODULE B ----
+VARIABLE   x
+Init   ==   x = 0
+Next == /\ x' = x + 1
+        /\ x < 10
+Spec   ==   Init /\ [][Next]_x
+====
+";
+        let parsed = ParsedFile::new(input).unwrap();
+        let got = parsed
+            .format_lines(&[0..1, 4..5], &FormatOptions::default())
+            .unwrap();
+        insta::assert_snapshot!(got);
+
+        // Lines outside of any definition, and the module header, change
+        // nothing.
+        let got = parsed
+            .format_lines(&[0..1, 1..2], &FormatOptions::default())
+            .unwrap();
+        assert_eq!(got, input);
+    }
+
+    #[test]
+    fn test_format_range() {
+        let input = r"---- MODULE B ----
+Init   ==   x = 0
+Next   ==   x' = x + 1
+Spec   ==   Init /\ [][Next]_x
+====
+";
+        let parsed = ParsedFile::new(input).unwrap();
+
+        let mut got = vec![];
+        parsed
+            .format_range(2..3, &FormatOptions::default(), &mut got)
+            .unwrap();
+
+        assert_eq!(
+            String::from_utf8(got).unwrap(),
+            r"---- MODULE B ----
+Init   ==   x = 0
+Next == x' = x + 1
+Spec   ==   Init /\ [][Next]_x
+====
+"
+        );
+    }
+
+    #[test]
+    fn test_format_definition() {
+        let input = r"---- MODULE B ----
+Init   ==   x = 0
+Next   ==   /\ x' = x + 1
+            /\ x < 10
+f[i \in 1..2]   ==   i
+====
+";
+        let parsed = ParsedFile::new(input).unwrap();
+
+        let mut got = vec![];
+        parsed
+            .format_definition("Next", &FormatOptions::default(), &mut got)
+            .unwrap();
+
+        assert_eq!(
+            String::from_utf8(got).unwrap(),
+            r"---- MODULE B ----
+Init   ==   x = 0
+Next ==
+    /\ x' = x + 1
+    /\ x < 10
+f[i \in 1..2]   ==   i
+====
+"
+        );
+
+        let got = parsed
+            .format_definitions(&["Init", "f"], &FormatOptions::default())
+            .unwrap();
+        assert_eq!(
+            got,
+            r"---- MODULE B ----
+Init == x = 0
+Next   ==   /\ x' = x + 1
+            /\ x < 10
+f[i \in 1..2] == i
+====
+"
+        );
+
+        assert!(matches!(
+            parsed.format_definition("Spec", &FormatOptions::default(), vec![]),
+            Err(Error::UnknownIdent(v)) if v == "Spec"
+        ));
+    }
+}
@@ -0,0 +1,232 @@
+//! Transformations of the rendered output, applied before it is wrote to the
+//! output sink.
+
+use std::{borrow::Cow, ops::Range};
+
+use crate::{line_endings, modeline, FormatOptions};
+
+/// Returns true if `options` configures any post-processing of the rendered
+/// output, or the output is rendered with `\r\n` line endings (`crlf`).
+pub(crate) fn is_required(options: &FormatOptions, crlf: bool) -> bool {
+    crlf || options.license_header().is_some() || options.post_render_hook().is_some()
+}
+
+/// Apply the post-processing configured in `options` to the rendered `output`.
+///
+/// The built-in post-processors run first, followed by the user-provided
+/// [post-render hook](FormatOptions::with_post_render_hook()) (if any). Line
+/// endings are rewritten to `\r\n` last if `crlf` is true.
+pub(crate) fn apply<'a>(output: &'a str, options: &FormatOptions, crlf: bool) -> Cow<'a, str> {
+    let mut output = Cow::Borrowed(output);
+
+    if let Some(header) = options.license_header() {
+        output = insert_license_header(output, header);
+    }
+
+    if let Some(hook) = options.post_render_hook() {
+        output = Cow::Owned(hook.apply(&output));
+    }
+
+    if crlf {
+        output = line_endings::restore(output);
+    }
+
+    output
+}
+
+/// Place `header` above the module header in `output`, unless the text before
+/// the module header already contains it.
+///
+/// If the text before the module header contains a comment block that is
+/// recognisably a (stale) license header, it is replaced by `header`. Any other
+/// text is kept below `header`.
+fn insert_license_header<'a>(output: Cow<'a, str>, header: &str) -> Cow<'a, str> {
+    let header = header.trim_end();
+    if header.trim().is_empty() {
+        return output;
+    }
+
+    // Find the start of the module header line.
+    let mut offset = 0;
+    let module = output.split_inclusive('\n').find_map(|line| {
+        let start = offset;
+        offset += line.len();
+        (line.starts_with("----") && line.contains("MODULE")).then_some(start)
+    });
+
+    let Some(module) = module else {
+        return output;
+    };
+
+    let before = &output[..module];
+    if before.contains(header.trim_start()) {
+        return output;
+    }
+
+    match license_block(before, header) {
+        Some(r) => Cow::Owned(format!(
+            "{}{header}\n{}",
+            &output[..r.start],
+            &output[r.end..]
+        )),
+        None => Cow::Owned(format!("{header}\n{output}")),
+    }
+}
+
+/// Returns the byte range of the first block of consecutive comment lines in
+/// `s` that is recognisably a license header, including any empty lines that
+/// follow it.
+///
+/// A block is a license header if it mentions a copyright or an SPDX license
+/// identifier, or starts with the first line of `header`. `tlafmt:` directives
+/// (such as modelines) are never part of a block.
+fn license_block(s: &str, header: &str) -> Option<Range<usize>> {
+    let first_line = header.lines().next().unwrap_or_default().trim();
+    let is_license = |block: &str| {
+        block.contains("Copyright")
+            || block.contains("SPDX-License-Identifier")
+            || block.lines().next().map(str::trim) == Some(first_line)
+    };
+
+    let mut blocks: Vec<Range<usize>> = vec![];
+    let mut in_block = false;
+    let mut in_comment = false;
+    let mut offset = 0;
+    for line in s.split_inclusive('\n') {
+        let start = offset;
+        offset += line.len();
+
+        let line = line.trim();
+        let is_comment = if in_comment {
+            in_comment = !line.contains("*)");
+            true
+        } else if modeline::directive(line).is_some() {
+            false
+        } else if let Some(rest) = line.strip_prefix("(*") {
+            in_comment = !rest.contains("*)");
+            true
+        } else {
+            line.starts_with("\\*")
+        };
+
+        match (is_comment, in_block) {
+            (true, true) => blocks.last_mut().expect("open block").end = offset,
+            (true, false) => blocks.push(start..offset),
+            (false, _) => {}
+        }
+        in_block = is_comment;
+    }
+
+    let block = blocks.into_iter().find(|v| is_license(&s[v.clone()]))?;
+
+    // Drop the empty lines separating the block from the text that follows.
+    let empty = s[block.end..]
+        .split_inclusive('\n')
+        .take_while(|v| v.trim().is_empty())
+        .map(str::len)
+        .sum::<usize>();
+
+    Some(block.start..block.end + empty)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_license_header() {
+        const HEADER: &str = "\\* Copyright 2025 Bananas Inc.\n\\* SPDX-License-Identifier: MIT\n";
+        let options = FormatOptions::default().with_license_header(HEADER);
+
+        // The header is inserted above the module when missing.
+        let input = "---- MODULE A ----\n====\n";
+        let got = apply(input, &options, false);
+        assert_eq!(got, format!("{HEADER}{input}"));
+
+        // But is not inserted again.
+        assert_eq!(apply(&got, &options, false), got);
+
+        // Nor when it appears alongside other text before the module.
+        let input = format!("Some notes\n\n{HEADER}\n---- MODULE A ----\n====\n");
+        assert_eq!(apply(&input, &options, false), input);
+
+        // A header in the module body does not count.
+        let input = format!("---- MODULE A ----\n{HEADER}====\n");
+        assert_eq!(apply(&input, &options, false), format!("{HEADER}{input}"));
+
+        // A stale header is replaced.
+        let input = "\
+\\* Copyright 2024 Bananas Inc.
+\\* SPDX-License-Identifier: MIT
+
+---- MODULE A ----
+====
+";
+        assert_eq!(
+            apply(input, &options, false),
+            format!("{HEADER}---- MODULE A ----\n====\n")
+        );
+
+        // As is a stale block comment header.
+        let input = "(* Copyright 2024\n   Bananas Inc. *)\n---- MODULE A ----\n====\n";
+        assert_eq!(
+            apply(input, &options, false),
+            format!("{HEADER}---- MODULE A ----\n====\n")
+        );
+
+        // But other text before the module is kept.
+        let input = "Some notes\n---- MODULE A ----\n====\n";
+        assert_eq!(apply(input, &options, false), format!("{HEADER}{input}"));
+
+        // Output without a module header is never changed.
+        assert_eq!(apply("bananas\n", &options, false), "bananas\n");
+    }
+
+    #[test]
+    fn test_license_header_modeline() {
+        const HEADER: &str = "\\* Copyright 2025 Bananas Inc.\n";
+        let options = FormatOptions::default().with_license_header(HEADER);
+
+        // A stale header is replaced, keeping the modeline above it.
+        let input =
+            "\\* tlafmt: indent=2\n\\* Copyright 2024 Bananas Inc.\n\n---- MODULE A ----\n====\n";
+        assert_eq!(
+            apply(input, &options, false),
+            format!("\\* tlafmt: indent=2\n{HEADER}---- MODULE A ----\n====\n")
+        );
+
+        // And below it.
+        let input =
+            "\\* Copyright 2024 Bananas Inc.\n\\* tlafmt: indent=2\n---- MODULE A ----\n====\n";
+        assert_eq!(
+            apply(input, &options, false),
+            format!("{HEADER}\\* tlafmt: indent=2\n---- MODULE A ----\n====\n")
+        );
+
+        // A modeline alone is never replaced.
+        let input = "\\* tlafmt: indent=2\n---- MODULE A ----\n====\n";
+        assert_eq!(apply(input, &options, false), format!("{HEADER}{input}"));
+
+        // Nor are descriptive comments.
+        let input = "(* A spec of\n   bananas. *)\n\\* More notes.\n---- MODULE A ----\n====\n";
+        assert_eq!(apply(input, &options, false), format!("{HEADER}{input}"));
+
+        // A block starting with the first line of the header is replaced.
+        let options = FormatOptions::default().with_license_header("\\* Bananas Inc.\n\\* 2025\n");
+        let input = "\\* tlafmt: indent=2\n\\* Bananas Inc.\n\\* 2024\n---- MODULE A ----\n====\n";
+        assert_eq!(
+            apply(input, &options, false),
+            "\\* tlafmt: indent=2\n\\* Bananas Inc.\n\\* 2025\n---- MODULE A ----\n====\n"
+        );
+    }
+
+    #[test]
+    fn test_license_header_then_hook() {
+        let options = FormatOptions::default()
+            .with_license_header("\\* header")
+            .with_post_render_hook(|v| v.to_uppercase());
+
+        let got = apply("---- MODULE a ----\n====", &options, false);
+        assert_eq!(got, "\\* HEADER\n---- MODULE A ----\n====");
+    }
+}
@@ -0,0 +1,366 @@
+//! Parse tree driven renaming of module-level definitions and declarations.
+
+use std::collections::HashMap;
+
+use tree_sitter::Node;
+
+use crate::{get_str, verify, Error, FormatOptions, ParsedFile};
+
+impl ParsedFile<'_> {
+    /// Rename the module-level operator, function, constant or variable named
+    /// `from` to `to`, including all references to it within the module.
+    ///
+    /// References to bound variables, parameters and `LET` definitions that
+    /// shadow `from` are not renamed. Each renamed identifier is replaced in
+    /// place, leaving all other text unchanged, unless the output parses
+    /// differently to the input (such as when a longer name misaligns the
+    /// bullets of a conjunction list) in which case the top-level definitions
+    /// and declarations containing a renamed identifier are reformatted (using
+    /// `options`) instead.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `to` is not a valid identifier or is a reserved
+    /// word, if no module-level definition or declaration of `from` exists, or
+    /// if `to` is already used within the module (which would change the
+    /// meaning of the spec).
+    pub fn rename(&self, from: &str, to: &str, options: &FormatOptions) -> Result<String, Error> {
+        if !is_identifier(to) {
+            return Err(Error::InvalidIdent(to.to_string()));
+        }
+
+        let root = self.t.root_node();
+        if find_idents(root).any(|v| get_str(&v, &self.input) == to) {
+            return Err(Error::IdentInUse(to.to_string()));
+        }
+
+        let mut targets = resolve(root, &self.input, from);
+        if targets.is_empty() {
+            return Err(Error::UnknownIdent(from.to_string()));
+        }
+        targets.sort_by_key(|v| v.start_byte());
+
+        let mut out = String::with_capacity(self.input.len());
+        let mut next = 0;
+        for v in &targets {
+            out.push_str(&self.input[next..v.start_byte()]);
+            out.push_str(to);
+            next = v.end_byte();
+        }
+        out.push_str(&self.input[next..]);
+
+        // Renaming must not change the structure of the spec.
+        if ParsedFile::new(&out)?.t.root_node().to_sexp() == root.to_sexp() {
+            return Ok(out);
+        }
+
+        let renames = targets
+            .iter()
+            .map(|v| (v.start_byte(), to))
+            .collect::<HashMap<_, _>>();
+
+        // Otherwise reformat each top-level group of nodes that contains a
+        // renamed identifier.
+        let out = self.reformat(options, renames.clone(), |group| {
+            let (Some(first), Some(last)) = (group.first(), group.last()) else {
+                return false;
+            };
+
+            let range = first.start_byte()..last.end_byte();
+            targets.iter().any(|v| range.contains(&v.start_byte()))
+        })?;
+
+        verify::check_renamed(self, &out, options, renames)?;
+        Ok(out)
+    }
+}
+
+/// Return the identifier nodes that declare or reference the module-level
+/// definition of `name`.
+fn resolve<'a>(root: Node<'a>, input: &str, name: &str) -> Vec<Node<'a>> {
+    let Some(module) = first_child_of_kind(root, "module") else {
+        return vec![];
+    };
+
+    // Find the module-level declaration identifiers.
+    let mut c = module.walk();
+    let mut out = module
+        .named_children(&mut c)
+        .map(|v| match v.kind() {
+            "local_definition" => v.named_child(0).unwrap_or(v),
+            _ => v,
+        })
+        .flat_map(|v| declared_names(v).into_iter())
+        .filter(|v| get_str(v, input) == name)
+        .collect::<Vec<_>>();
+
+    if out.is_empty() {
+        return out;
+    }
+
+    // And all references to it that are not shadowed.
+    out.extend(
+        find_idents(module)
+            .filter(|v| v.kind() == "identifier_ref" && get_str(v, input) == name)
+            .filter(|v| !is_shadowed(*v, input, name)),
+    );
+
+    out
+}
+
+/// Return the identifiers declared by the module-level node `n`.
+pub(crate) fn declared_names(n: Node<'_>) -> Vec<Node<'_>> {
+    let mut c = n.walk();
+    match n.kind() {
+        "operator_definition" | "function_definition" => {
+            n.child_by_field_name("name").into_iter().collect()
+        }
+        "constant_declaration" | "variable_declaration" => n
+            .named_children(&mut c)
+            .filter_map(|v| match v.kind() {
+                "identifier" => Some(v),
+                "operator_declaration" => v.child_by_field_name("name"),
+                _ => None,
+            })
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// Returns true if the identifier reference `n` refers to a bound variable,
+/// parameter or `LET` definition named `name` instead of the module-level
+/// definition.
+pub(crate) fn is_shadowed(n: Node<'_>, input: &str, name: &str) -> bool {
+    let mut child = n;
+    while let Some(parent) = child.parent() {
+        if parent.kind() == "module" {
+            return false;
+        }
+
+        let mut c = parent.walk();
+        for (i, v) in parent.named_children(&mut c).enumerate() {
+            // A binder never applies to the node it is bound within, such as
+            // the set in a quantifier bound.
+            if v == child {
+                continue;
+            }
+
+            let field = parent.field_name_for_named_child(i as u32);
+            let bound = match v.kind() {
+                // The name of a definition is not a binder within its body.
+                "identifier" if field != Some("name") => vec![v],
+                "operator_declaration" => v.child_by_field_name("name").into_iter().collect(),
+                "quantifier_bound" => bound_idents(v),
+                "operator_definition" | "function_definition" if parent.kind() == "let_in" => {
+                    v.child_by_field_name("name").into_iter().collect()
+                }
+                _ => continue,
+            };
+
+            if bound.iter().any(|v| get_str(v, input) == name) {
+                return true;
+            }
+        }
+
+        child = parent;
+    }
+
+    false
+}
+
+/// Return the identifiers introduced by a `quantifier_bound` node.
+fn bound_idents(n: Node<'_>) -> Vec<Node<'_>> {
+    let mut c = n.walk();
+    n.named_children(&mut c)
+        .flat_map(|v| match v.kind() {
+            "identifier" => vec![v],
+            "tuple_of_identifiers" => {
+                let mut c = v.walk();
+                v.named_children(&mut c)
+                    .filter(|v| v.kind() == "identifier")
+                    .collect()
+            }
+            _ => vec![],
+        })
+        .collect()
+}
+
+/// Return all `identifier` and `identifier_ref` nodes within `n`.
+pub(crate) fn find_idents(n: Node<'_>) -> impl Iterator<Item = Node<'_>> {
+    let mut stack = vec![n];
+    std::iter::from_fn(move || loop {
+        let n = stack.pop()?;
+
+        let mut c = n.walk();
+        stack.extend(n.named_children(&mut c));
+
+        if matches!(n.kind(), "identifier" | "identifier_ref") {
+            return Some(n);
+        }
+    })
+}
+
+/// Return the first named child of `n` of kind `kind`.
+pub(crate) fn first_child_of_kind<'a>(n: Node<'a>, kind: &str) -> Option<Node<'a>> {
+    let mut c = n.walk();
+    let v = n.named_children(&mut c).find(|v| v.kind() == kind);
+    v
+}
+
+/// Words that may not be used as identifiers.
+const RESERVED: &[&str] = &[
+    "ACTION",
+    "ASSUME",
+    "ASSUMPTION",
+    "AXIOM",
+    "BOOLEAN",
+    "BY",
+    "CASE",
+    "CHOOSE",
+    "CONSTANT",
+    "CONSTANTS",
+    "COROLLARY",
+    "DEF",
+    "DEFINE",
+    "DEFS",
+    "DOMAIN",
+    "ELSE",
+    "ENABLED",
+    "EXCEPT",
+    "EXTENDS",
+    "FALSE",
+    "HAVE",
+    "HIDE",
+    "IF",
+    "IN",
+    "INSTANCE",
+    "LAMBDA",
+    "LEMMA",
+    "LET",
+    "LOCAL",
+    "MODULE",
+    "NEW",
+    "OBVIOUS",
+    "OMITTED",
+    "ONLY",
+    "OTHER",
+    "PICK",
+    "PROOF",
+    "PROPOSITION",
+    "PROVE",
+    "QED",
+    "RECURSIVE",
+    "STATE",
+    "STRING",
+    "SUBSET",
+    "SUFFICES",
+    "TAKE",
+    "TEMPORAL",
+    "THEN",
+    "THEOREM",
+    "TRUE",
+    "UNCHANGED",
+    "UNION",
+    "USE",
+    "VARIABLE",
+    "VARIABLES",
+    "WITH",
+    "WITNESS",
+];
+
+/// Returns true if `s` is a valid TLA+ identifier that is not a reserved word.
+fn is_identifier(s: &str) -> bool {
+    s.chars().all(|v| v.is_ascii_alphanumeric() || v == '_')
+        && s.chars().any(|v| v.is_ascii_alphabetic())
+        && !RESERVED.contains(&s)
+        // Fairness operators are lexed as a prefix of the subscript.
+        && !s.starts_with("WF_")
+        && !s.starts_with("SF_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rename(input: &str, from: &str, to: &str) -> Result<String, Error> {
+        ParsedFile::new(input)
+            .unwrap()
+            .rename(from, to, &FormatOptions::default())
+    }
+
+    #[test]
+    fn test_rename() {
+        let input = r"
+---- MODULE B ----
+CONSTANT N, F(_)
+VARIABLE x
+Op(N, G(_)) == N
+f[N \in S] == N + F(N)
+Q == \A N \in S : N
+Q2 == \E y \in N : y
+Q3 == {N : y \in S} \union {y \in S : N}
+Q4 == LET N == 1 IN N
+Q5 == \A <<N, y>> \in S : N
+Next == x' = N   \* N is left in comments
+Spec == [][Next]_x
+====
+";
+        insta::assert_snapshot!(rename(input, "N", "Size").unwrap());
+    }
+
+    #[test]
+    fn test_rename_only_affected_lines() {
+        let input = r"
+---- MODULE B ----
+VARIABLE x
+Init   ==   x = 0
+Next == /\ x' = x + 1
+        /\ x < 10
+Spec == Init /\ [][Next]_x
+====
+";
+        insta::assert_snapshot!(rename(input, "Next", "Step").unwrap());
+    }
+
+    #[test]
+    fn test_rename_realigns_lists() {
+        let input = r"
+---- MODULE B ----
+VARIABLE x
+Init   ==   x = 0
+Next == /\ x' = x + 1
+        /\ x < 10
+Spec == Init /\ [][Next]_x
+====
+";
+        insta::assert_snapshot!(rename(input, "Next", "Go").unwrap());
+    }
+
+    #[test]
+    fn test_rename_errors() {
+        let input = r"
+---- MODULE B ----
+VARIABLE x
+Op == \A y \in S : x = y
+====
+";
+        assert!(matches!(
+            rename(input, "y", "z"),
+            Err(Error::UnknownIdent(_))
+        ));
+        assert!(matches!(rename(input, "x", "y"), Err(Error::IdentInUse(_))));
+        assert!(matches!(
+            rename(input, "x", "a b"),
+            Err(Error::InvalidIdent(_))
+        ));
+        assert!(matches!(
+            rename(input, "x", "CHOOSE"),
+            Err(Error::InvalidIdent(_))
+        ));
+        assert!(matches!(
+            rename(input, "x", "WF_x"),
+            Err(Error::InvalidIdent(_))
+        ));
+        assert!(rename(input, "x", "z").is_ok());
+    }
+}
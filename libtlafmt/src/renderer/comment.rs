@@ -1,7 +1,7 @@
 use std::cmp::max;
 
 use crate::{
-    helpers::{Indent, INDENT_STR},
+    helpers::Indent,
     renderer::token_len,
     token::{Position, Token},
 };
@@ -18,7 +18,14 @@ use super::is_newline;
 /// The [`Token::Comment`] that are aligned have their [`Position`] updated to
 /// specify relative padding used during rendering in order to maintain the
 /// above.
-pub(super) fn align_comments(buf: &mut Vec<(Token<'_>, Indent)>) {
+///
+/// Line lengths are computed as rendered with a maximum width of `line_width`,
+/// and `indent_width` columns per level of indentation.
+pub(super) fn align_comments(
+    buf: &mut Vec<(Token<'_>, Indent)>,
+    line_width: usize,
+    indent_width: usize,
+) {
     let mut candidates = vec![];
 
     // Look for end-of-line comments in consecutive rows.
@@ -28,8 +35,11 @@ pub(super) fn align_comments(buf: &mut Vec<(Token<'_>, Indent)>) {
     while i < buf.len() {
         // If this token is a comment, extract the source position and indent
         // level for it.
+        //
+        // Comments already positioned relative to the preceding token are
+        // never realigned.
         let (pos, _) = match &buf[i] {
-            (Token::Comment(_, v), indent) => (*v, indent),
+            (Token::Comment(_, v @ Position::Source { .. }), indent) => (*v, indent),
             (Token::Newline, _) => {
                 newline_count += 1;
                 i += 1;
@@ -66,7 +76,7 @@ pub(super) fn align_comments(buf: &mut Vec<(Token<'_>, Indent)>) {
                     || (newline_count - last_newline_count) > 1
             })
         {
-            process_candidates(buf, &mut candidates);
+            process_candidates(buf, &mut candidates, line_width, indent_width);
             candidates.truncate(0);
         }
 
@@ -82,13 +92,18 @@ pub(super) fn align_comments(buf: &mut Vec<(Token<'_>, Indent)>) {
         i += 1;
     }
 
-    process_candidates(buf, &mut candidates);
+    process_candidates(buf, &mut candidates, line_width, indent_width);
 }
 
 /// Process a set of comments that are vertically aligned in the source and
 /// appear in `buf` to set the appropriate amount of padding on their comments
 /// in order to maintain vertical alignment after their lines are formatted.
-fn process_candidates(buf: &mut [(Token<'_>, Indent)], candidates: &mut [(usize, Position)]) {
+fn process_candidates(
+    buf: &mut [(Token<'_>, Indent)],
+    candidates: &mut [(usize, Position)],
+    line_width: usize,
+    indent_width: usize,
+) {
     if candidates.len() < 2 {
         return;
     }
@@ -126,7 +141,7 @@ fn process_candidates(buf: &mut [(Token<'_>, Indent)], candidates: &mut [(usize,
     let mut max_line = 0; // Maximum observed line length.
 
     let iter = buf[start..=end].iter();
-    let lines = line_len(iter).fold(lines, |mut acc, v| {
+    let lines = line_len(iter, line_width, indent_width).fold(lines, |mut acc, v| {
         max_line = max(max_line, v);
         acc.push(v);
         acc
@@ -164,7 +179,11 @@ fn process_candidates(buf: &mut [(Token<'_>, Indent)], candidates: &mut [(usize,
 
 // Consume one line from newline to line-ending comment from `iter` and return
 // the line length up to, but not including the comment or its preceding space.
-fn line_len<'a, T>(iter: T) -> impl Iterator<Item = usize> + use<'a, T>
+fn line_len<'a, T>(
+    iter: T,
+    line_width: usize,
+    indent_width: usize,
+) -> impl Iterator<Item = usize> + use<'a, T>
 where
     T: Iterator<Item = &'a (Token<'a>, Indent)>,
 {
@@ -184,7 +203,7 @@ where
             // the indentation level from the first token on the next line (only the
             // first can set the line indentation).
             if is_newline(t) {
-                len = iter.peek().unwrap().1.get() as usize * INDENT_STR.len();
+                len = iter.peek().unwrap().1.get() as usize * indent_width;
                 line_tokens = 0;
                 continue;
             }
@@ -211,7 +230,7 @@ where
                 }
             }
 
-            len += token_len(t);
+            len += token_len(t, line_width);
             line_tokens += 1;
 
             // Account for any whitespace.
@@ -224,7 +243,7 @@ where
 
 #[cfg(test)]
 mod tests {
-    use crate::assert_rewrite;
+    use crate::{assert_rewrite, FormatOptions, DEFAULT_INDENT_WIDTH, DEFAULT_LINE_WIDTH};
 
     use super::*;
 
@@ -242,7 +261,9 @@ mod tests {
 
         let iter = tokens.iter().peekable();
 
-        let got = line_len(iter).next().unwrap();
+        let got = line_len(iter, DEFAULT_LINE_WIDTH, DEFAULT_INDENT_WIDTH)
+            .next()
+            .unwrap();
         assert_eq!(got, 16);
     }
 
@@ -263,7 +284,9 @@ mod tests {
 
         let iter = tokens.iter().peekable();
 
-        let got = line_len(iter).next().unwrap();
+        let got = line_len(iter, DEFAULT_LINE_WIDTH, DEFAULT_INDENT_WIDTH)
+            .next()
+            .unwrap();
         assert_eq!(got, 18);
     }
 
@@ -280,7 +303,9 @@ mod tests {
 
         let iter = tokens.iter().peekable();
 
-        let got = line_len(iter).next().unwrap();
+        let got = line_len(iter, DEFAULT_LINE_WIDTH, DEFAULT_INDENT_WIDTH)
+            .next()
+            .unwrap();
         assert_eq!(got, 4);
     }
 
@@ -359,6 +384,35 @@ Op == /\ bananas=42         \* This is an important number.
         );
     }
 
+    /// Alignment is by display column, irrespective of the byte length of the
+    /// preceding characters.
+    #[test]
+    fn test_comment_aligned_unicode() {
+        assert_rewrite!(
+            r#"
+---- MODULE bananas ----
+Op == /\ bananas = "größer ✓"    \* Wide characters.
+      /\ platanos' = "smaller"   \* Narrow characters.
+====
+"#
+        );
+    }
+
+    #[test]
+    fn test_comment_align_disabled() {
+        assert_rewrite!(
+            r"
+---- MODULE bananas ----
+Op == /\ bananas = 42       \* This is an important number.
+      /\ platanos' = 42     \* That should be assigned here.
+      \* A comment-only line.
+      /\ x = 1              \* Short.
+====
+",
+            FormatOptions::default().with_align_comments(false)
+        );
+    }
+
     /// Spec fragments with consecutive comment lines, some of which are the
     /// only token on the line (comment only lines).
     mod comment_only_lines {
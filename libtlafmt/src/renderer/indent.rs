@@ -24,8 +24,15 @@ use crate::{helpers::Indent, renderer::is_newline, token::Token};
 ///                     D[X |-> 42] = 0
 /// ```
 ///
-pub(super) fn limit_indents(buf: &mut [(Token<'_>, Indent)]) {
+/// Returns the number of lines that had their indentation reduced.
+pub(super) fn limit_indents(buf: &mut [(Token<'_>, Indent)]) -> usize {
+    let before = buf.iter().map(|(_, v)| *v).collect::<Vec<_>>();
     recurse(buf, true, None);
+
+    buf.iter()
+        .zip(before)
+        .filter(|((_, after), before)| after != before)
+        .count()
 }
 
 /// The indentation rewriter state.
@@ -264,7 +271,7 @@ mod tests {
         ];
 
         let mut got = tokens.clone();
-        limit_indents(&mut got);
+        assert_eq!(limit_indents(&mut got), 0);
         assert_eq!(got, tokens);
     }
 
@@ -283,7 +290,7 @@ mod tests {
             (Token::Bang, Indent::new(1)),
         ];
 
-        limit_indents(&mut tokens);
+        assert_eq!(limit_indents(&mut tokens), 3);
         assert_eq!(
             tokens,
             [
@@ -0,0 +1,691 @@
+use crate::{
+    helpers::{width, Indent, PADDING},
+    token::{GroupBreak, Token},
+};
+
+use super::renderer::{is_newline, token_len};
+
+/// Process the token buffer, resolving each [`Token::Group`] into the line
+/// breaks needed to render it within `line_width` columns.
+///
+/// Groups are laid out outermost first. A group that fits on the current line
+/// is rendered on it in full, while a group that does not is broken at each of
+/// its own [`Token::SoftLine`], with any nested groups then laid out from the
+/// resulting lines:
+///
+/// ```text
+///    vars == << bananas, platanos, ..., mangos >>
+/// ```
+///
+/// Becomes, for a group with [`GroupBreak::Align`] placement:
+///
+/// ```text
+///    vars == << bananas,
+///               platanos,
+///               ...,
+///               mangos >>
+/// ```
+///
+/// A group fits if the tokens from the start of the group up to the first line
+/// break within or following it (or the first soft line break following it)
+/// fit within `line_width`. Line breaks already present in the buffer are
+/// never removed, and those following a [`Token::SoftLine`] continue at the
+/// same column as a broken soft line break, ensuring the output is laid out
+/// identically when formatted again.
+///
+/// Groups on a line holding a conjunction list item that assigns a value to a
+/// variable (such as `/\ x' = << ... >>`) fit within `assignment_width`
+/// instead, if set.
+///
+/// All layout tokens are removed from `buf`. Returns true if any line breaks
+/// were inserted.
+pub(super) fn break_groups(
+    buf: &mut Vec<(Token<'_>, Indent)>,
+    line_width: usize,
+    assignment_width: Option<usize>,
+    indent_width: usize,
+) -> bool {
+    if !buf.iter().any(|(t, _)| t.is_layout()) {
+        return false;
+    }
+
+    let input = std::mem::take(buf);
+    let mut line = Line::new(line_width, indent_width);
+    let mut groups: Vec<Group> = vec![];
+    let mut wrapped = false;
+
+    // The indentation depth applied to the next token, when it starts a line
+    // continuing at the column of a group.
+    let mut line_indent = None;
+
+    // True when no tokens have been rendered since the last soft line break.
+    let mut after_soft_line = false;
+
+    for (i, (t, indent)) in input.iter().enumerate() {
+        match t {
+            Token::Group(placement) => {
+                // A group starting a line is indented by its first token.
+                let next =
+                    next_token(&input, i).map(|(t, indent)| (t, line_indent.unwrap_or(*indent)));
+                let indent = match next {
+                    Some((_, v)) if line.is_empty() => v,
+                    _ => line.indent,
+                };
+
+                let col = match (placement, next) {
+                    (GroupBreak::Align | GroupBreak::Fill, Some((next, next_indent))) => {
+                        line.start_col(next, next_indent)
+                    }
+                    // Continuation lines are indented from the start of the
+                    // line, which may itself be a continuation line.
+                    _ => {
+                        let start = match (line.start, next) {
+                            (Some(v), _) if !line.is_empty() => v,
+                            (_, Some((next, next_indent))) => line.start_col(next, next_indent),
+                            _ => line.indent_col(indent),
+                        };
+                        start + indent_width
+                    }
+                };
+
+                let rest = &input[i + 1..];
+                let width = width_of(buf, line_width, assignment_width);
+                let broken = !line.clone().with_line_width(width).fits(rest, 1)
+                    || (*placement == GroupBreak::Align && has_line_break(rest));
+
+                groups.push(Group {
+                    placement: *placement,
+                    broken,
+                    indent,
+                    col,
+                });
+            }
+            Token::GroupEnd => {
+                groups.pop();
+            }
+            Token::GroupPad(n) => {
+                // The padding is delimited from the surrounding tokens by a
+                // space either side.
+                if *n == 0 || !groups.last().is_some_and(|v| v.broken) {
+                    continue;
+                }
+                let t = Token::Raw(&PADDING[..(n - 1).min(PADDING.len())]);
+                line.push(&t, *indent);
+                buf.push((t, *indent));
+            }
+            Token::SoftLine => {
+                after_soft_line = true;
+
+                let Some(group) = groups.last().filter(|v| v.broken) else {
+                    continue;
+                };
+
+                // Soft line breaks adjacent to an existing line break are
+                // redundant.
+                let Some((next, _)) = next_token(&input, i) else {
+                    continue;
+                };
+                if line.is_empty() || is_newline(next) {
+                    continue;
+                }
+
+                // Filled groups only break before content that does not fit
+                // on the current line.
+                let width = width_of(buf, line_width, assignment_width);
+                if group.placement == GroupBreak::Fill
+                    && line.clone().with_line_width(width).fits(&input[i + 1..], 0)
+                {
+                    continue;
+                }
+
+                buf.push((Token::Newline, group.indent));
+                line.push(&Token::Newline, group.indent);
+                line_indent = Some(group.pad(next, &mut line, buf));
+                wrapped = true;
+            }
+            t if is_newline(t) => {
+                line.push(t, *indent);
+                buf.push((t.clone(), *indent));
+
+                // A line break between the elements of a group continues at the
+                // column of the group.
+                let next = next_token(&input, i).filter(|(v, _)| !is_newline(v));
+                if let (true, Some(group), Some((next, _))) = (after_soft_line, groups.last(), next)
+                {
+                    line_indent = Some(group.pad(next, &mut line, buf));
+                }
+            }
+            t => {
+                after_soft_line = false;
+
+                let indent = line_indent.take().unwrap_or(*indent);
+                line.push(t, indent);
+                buf.push((t.clone(), indent));
+            }
+        }
+    }
+
+    wrapped
+}
+
+/// A [`Token::Group`] being laid out.
+#[derive(Debug)]
+struct Group {
+    /// The placement of the lines broken within the group.
+    placement: GroupBreak,
+
+    /// True if the group does not fit on the line it starts on.
+    broken: bool,
+
+    /// The indentation depth of the line the group starts on.
+    indent: Indent,
+
+    /// The column at which lines broken within the group continue.
+    col: usize,
+}
+
+impl Group {
+    /// Pad the empty `line` to the continuation column of the group, followed
+    /// by `next`, returning the indentation depth to render `next` with.
+    fn pad<'a>(
+        &self,
+        next: &Token<'_>,
+        line: &mut Line<'a>,
+        buf: &mut Vec<(Token<'a>, Indent)>,
+    ) -> Indent {
+        // Account for the space delimiting the padding from the next token.
+        let indent_col = line.indent_col(self.indent);
+        if self.col > indent_col {
+            let pad = Token::Raw("").delimiting_space_len(next);
+            let n = self.col.saturating_sub(indent_col + pad);
+            let t = Token::Raw(&PADDING[..n.min(PADDING.len())]);
+            line.push(&t, self.indent);
+            buf.push((t, self.indent));
+        }
+
+        // The padding is whitespace, so the line is indented by the depth of
+        // the next token.
+        self.indent
+    }
+}
+
+/// The position within the line being laid out.
+#[derive(Debug, Clone)]
+struct Line<'a> {
+    /// The last token on the line, or [`None`] if the line is empty.
+    prev: Option<Token<'a>>,
+
+    /// The column following the last token on the line.
+    col: usize,
+
+    /// The indentation depth of the line, set by the first token on it.
+    indent: Indent,
+
+    /// The column of the first non-whitespace token on the line.
+    start: Option<usize>,
+
+    line_width: usize,
+    indent_width: usize,
+}
+
+impl<'a> Line<'a> {
+    fn new(line_width: usize, indent_width: usize) -> Self {
+        Self {
+            prev: None,
+            col: 0,
+            indent: Indent::ZERO,
+            start: None,
+            line_width,
+            indent_width,
+        }
+    }
+
+    /// Measure the line against `line_width`.
+    fn with_line_width(mut self, line_width: usize) -> Self {
+        self.line_width = line_width;
+        self
+    }
+
+    /// Returns true if no tokens have been rendered on the line.
+    fn is_empty(&self) -> bool {
+        self.prev.as_ref().is_none_or(|v| {
+            is_newline(v) || matches!(v, Token::Raw(s) | Token::Verbatim(s) if s.ends_with('\n'))
+        })
+    }
+
+    /// Returns the column of the indentation depth `indent`.
+    fn indent_col(&self, indent: Indent) -> usize {
+        indent.get() as usize * self.indent_width
+    }
+
+    /// Returns the column at which `t` starts when pushed with `indent`.
+    fn start_col(&self, t: &Token<'_>, indent: Indent) -> usize {
+        match &self.prev {
+            Some(prev) if !self.is_empty() => self.col + prev.delimiting_space_len(t),
+            _ => self.indent_col(indent),
+        }
+    }
+
+    /// Advance the line past `t`, rendered with `indent`.
+    fn push(&mut self, t: &Token<'a>, indent: Indent) {
+        if !is_newline(t) {
+            if self.is_empty() {
+                self.indent = indent;
+                self.start = None;
+            }
+            if self.start.is_none() && !matches!(t, Token::Raw(s) if s.trim().is_empty()) {
+                self.start = Some(self.start_col(t, indent));
+            }
+
+            self.col = match t {
+                // Only the last line of a multi-line token shares the line
+                // with the tokens that follow it.
+                Token::Raw(s) | Token::Verbatim(s) if s.contains('\n') => {
+                    self.indent_col(indent) + width(s.rsplit('\n').next().unwrap_or_default())
+                }
+                Token::Comment(s, _) if s.contains('\n') => {
+                    width(s.rsplit('\n').next().unwrap_or_default())
+                }
+                _ => self.start_col(t, indent) + token_len(t, self.line_width),
+            };
+        }
+
+        self.prev = Some(t.clone());
+    }
+
+    /// Returns true if the tokens in `rest` fit within the line width when
+    /// rendered on this line, up to the end of the line or the first soft line
+    /// break outside of the `depth` groups enclosing the start of `rest`.
+    fn fits(mut self, rest: &[(Token<'a>, Indent)], mut depth: usize) -> bool {
+        for (t, indent) in rest {
+            match t {
+                Token::Group(_) => depth += 1,
+                Token::GroupEnd => depth = depth.saturating_sub(1),
+
+                // Soft line breaks within the groups are measured unbroken,
+                // while those following them may end the line.
+                Token::SoftLine if depth == 0 => return true,
+                Token::SoftLine => {}
+
+                // The line ends at a line break, or a comment (which never
+                // causes the code it follows to be broken).
+                t if is_newline(t) || matches!(t, Token::Comment(..)) => return true,
+                Token::Raw(s) | Token::Verbatim(s) if s.contains('\n') => {
+                    let first = s.split('\n').next().unwrap_or_default();
+                    return self.start_col(t, *indent) + width(first) <= self.line_width;
+                }
+
+                t => {
+                    self.push(t, *indent);
+                    if self.col > self.line_width {
+                        return false;
+                    }
+                }
+            }
+        }
+
+        true
+    }
+}
+
+/// Returns true if the group started immediately before `rest` contains a line
+/// break following one of its own soft line breaks.
+fn has_line_break(rest: &[(Token<'_>, Indent)]) -> bool {
+    let mut depth = 1_usize;
+    let mut after_soft_line = false;
+
+    for (t, _) in rest {
+        match t {
+            Token::Group(_) => depth += 1,
+            Token::GroupEnd if depth == 1 => return false,
+            Token::GroupEnd => depth -= 1,
+            Token::SoftLine => after_soft_line = depth == 1,
+            Token::GroupPad(_) => {}
+            t if is_newline(t) => {
+                if after_soft_line {
+                    return true;
+                }
+            }
+            _ => after_soft_line = false,
+        }
+    }
+
+    false
+}
+
+/// Returns the width the last line of `buf` is laid out within:
+/// `assignment_width` if set and the line is an assignment, otherwise
+/// `line_width`.
+fn width_of(
+    buf: &[(Token<'_>, Indent)],
+    line_width: usize,
+    assignment_width: Option<usize>,
+) -> usize {
+    match assignment_width {
+        Some(v) if is_assignment(buf) => v,
+        _ => line_width,
+    }
+}
+
+/// Returns true if the last line of `buf` is a conjunction list item assigning
+/// a value to a variable (`/\ x = ...` or `/\ x' = ...`), optionally following
+/// the head of the definition it belongs to (such as `Init ==`).
+fn is_assignment(buf: &[(Token<'_>, Indent)]) -> bool {
+    let start = buf
+        .iter()
+        .rposition(|(t, _)| is_newline(t))
+        .map_or(0, |v| v + 1);
+    let line = &buf[start..];
+    let item = line
+        .iter()
+        .position(|(t, _)| matches!(t, Token::Eq2))
+        .map_or(line, |v| &line[v + 1..]);
+
+    // Padding aligning the item is ignored.
+    let mut tokens = item
+        .iter()
+        .map(|(t, _)| t)
+        .filter(|t| !matches!(t, Token::Raw(s) if s.trim().is_empty()));
+
+    matches!(
+        (tokens.next(), tokens.next()),
+        (Some(Token::And), Some(Token::Ident(_)))
+    ) && match tokens.next() {
+        Some(Token::Prime) => matches!(tokens.next(), Some(Token::Eq)),
+        t => matches!(t, Some(Token::Eq)),
+    }
+}
+
+/// Returns the first token following the token at index `i` in `buf` that is
+/// not a layout token.
+fn next_token<'a>(buf: &[(Token<'a>, Indent)], i: usize) -> Option<&(Token<'a>, Indent)> {
+    buf[i + 1..].iter().find(|(t, _)| !t.is_layout())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{renderer::Renderer, FormatOptions, Rule};
+
+    fn format<'a>(width: usize, tokens: impl IntoIterator<Item = (Token<'a>, Indent)>) -> String {
+        format_with(FormatOptions::default().with_line_width(width), tokens)
+    }
+
+    fn format_with<'a>(
+        options: FormatOptions,
+        tokens: impl IntoIterator<Item = (Token<'a>, Indent)>,
+    ) -> String {
+        let mut buf = Vec::new();
+        let mut w = Renderer::new(&mut buf, options);
+
+        for (t, indent) in tokens {
+            w.indent_set(indent);
+            w.push(t).unwrap();
+        }
+
+        w.flush().unwrap();
+
+        String::from_utf8(buf).expect("valid utf8 output")
+    }
+
+    /// The tokens of `vars == << a, b, c >>` with a group around the tuple
+    /// elements.
+    fn tuple(placement: GroupBreak) -> Vec<(Token<'static>, Indent)> {
+        let mut tokens = vec![
+            (Token::Ident("vars"), Indent::ZERO),
+            (Token::Eq2, Indent::ZERO),
+            (Token::AngleOpen, Indent::new(1)),
+            (Token::Group(placement), Indent::new(2)),
+        ];
+        for (i, v) in ["bananas", "platanos", "mangos"].into_iter().enumerate() {
+            if i > 0 {
+                tokens.push((Token::Comma, Indent::new(2)));
+                tokens.push((Token::SoftLine, Indent::new(2)));
+            }
+            tokens.push((Token::Ident(v), Indent::new(2)));
+        }
+        tokens.push((Token::GroupEnd, Indent::new(2)));
+        tokens.push((Token::AngleClose, Indent::new(1)));
+        tokens
+    }
+
+    #[test]
+    fn test_group_fits() {
+        let output = format(80, tuple(GroupBreak::Align));
+        assert_eq!(output, "vars == << bananas, platanos, mangos >>");
+
+        // Exactly filling the line width fits.
+        let output = format(39, tuple(GroupBreak::Align));
+        assert_eq!(output, "vars == << bananas, platanos, mangos >>");
+    }
+
+    #[test]
+    fn test_group_align() {
+        let output = format(38, tuple(GroupBreak::Align));
+        assert_eq!(
+            output,
+            "vars == << bananas,\n           platanos,\n           mangos >>"
+        );
+    }
+
+    #[test]
+    fn test_group_indent() {
+        let output = format(38, tuple(GroupBreak::Indent));
+        assert_eq!(output, "vars == << bananas,\n    platanos,\n    mangos >>");
+
+        // The continuation is indented relative to the line the group starts
+        // on.
+        let tokens = tuple(GroupBreak::Indent)
+            .into_iter()
+            .map(|(t, indent)| (t, indent + 1));
+        let output = format(
+            42,
+            [(Token::Newline, Indent::new(1))].into_iter().chain(tokens),
+        );
+        assert_eq!(
+            output,
+            "\n    vars == << bananas,\n        platanos,\n        mangos >>"
+        );
+
+        // Including when that line is itself a continuation line.
+        let tokens = [
+            Token::Group(GroupBreak::Indent),
+            Token::Ident("Spec"),
+            Token::Implies,
+            Token::SoftLine,
+            Token::Group(GroupBreak::Indent),
+            Token::Ident("bananas"),
+            Token::And,
+            Token::SoftLine,
+            Token::Ident("platanos"),
+            Token::GroupEnd,
+            Token::GroupEnd,
+        ];
+        let output = format(20, tokens.map(|v| (v, Indent::ZERO)));
+        assert_eq!(output, "Spec =>\n    bananas /\\\n        platanos");
+    }
+
+    /// Nested groups are only broken if they do not fit once the enclosing
+    /// group is broken.
+    #[test]
+    fn test_group_nested() {
+        let mut tokens = vec![
+            (Token::CurlyOpen, Indent::ZERO),
+            (Token::Group(GroupBreak::Align), Indent::ZERO),
+        ];
+        tokens.extend(tuple(GroupBreak::Align));
+        tokens.extend([
+            (Token::Comma, Indent::ZERO),
+            (Token::SoftLine, Indent::ZERO),
+            (Token::Ident("x"), Indent::ZERO),
+            (Token::GroupEnd, Indent::ZERO),
+            (Token::CurlyClose, Indent::ZERO),
+        ]);
+
+        let output = format(80, tokens.clone());
+        assert_eq!(output, "{vars == << bananas, platanos, mangos >>, x}");
+
+        let output = format(42, tokens.clone());
+        assert_eq!(output, "{vars == << bananas, platanos, mangos >>,\n x}");
+
+        let output = format(30, tokens);
+        assert_eq!(
+            output,
+            "{vars == << bananas,\n            platanos,\n            mangos >>,\n x}"
+        );
+    }
+
+    /// Existing line breaks are preserved, and continue at the column of the
+    /// group if between its elements.
+    #[test]
+    fn test_group_existing_newline() {
+        let tokens = |placement| {
+            let mut tokens = tuple(placement);
+            tokens.insert(10, (Token::SourceNewline, Indent::new(2)));
+            tokens
+        };
+
+        let output = format(80, tokens(GroupBreak::Fill));
+        assert_eq!(
+            output,
+            "vars == << bananas, platanos,\n           mangos >>"
+        );
+
+        let output = format(20, tokens(GroupBreak::Fill));
+        assert_eq!(
+            output,
+            "vars == << bananas,\n           platanos,\n           mangos >>"
+        );
+
+        // An aligned group containing a line break between its elements is
+        // always broken.
+        let output = format(80, tokens(GroupBreak::Align));
+        assert_eq!(
+            output,
+            "vars == << bananas,\n           platanos,\n           mangos >>"
+        );
+    }
+
+    /// Padding is only rendered when the group is broken.
+    #[test]
+    fn test_group_pad() {
+        let tokens = vec![
+            (Token::SquareOpen, Indent::ZERO),
+            (Token::Group(GroupBreak::Align), Indent::ZERO),
+            (Token::Ident("a"), Indent::ZERO),
+            (Token::GroupPad(5), Indent::ZERO),
+            (Token::AllMapsTo, Indent::ZERO),
+            (Token::Lit("1"), Indent::ZERO),
+            (Token::Comma, Indent::ZERO),
+            (Token::SoftLine, Indent::ZERO),
+            (Token::Ident("bread"), Indent::ZERO),
+            (Token::GroupPad(1), Indent::ZERO),
+            (Token::AllMapsTo, Indent::ZERO),
+            (Token::Lit("2"), Indent::ZERO),
+            (Token::Comma, Indent::ZERO),
+            (Token::SoftLine, Indent::ZERO),
+            (Token::Ident("banana"), Indent::ZERO),
+            (Token::GroupPad(0), Indent::ZERO),
+            (Token::AllMapsTo, Indent::ZERO),
+            (Token::Lit("3"), Indent::ZERO),
+            (Token::GroupEnd, Indent::ZERO),
+            (Token::SquareClose, Indent::ZERO),
+        ];
+
+        let output = format(80, tokens.clone());
+        assert_eq!(output, "[a |-> 1, bread |-> 2, banana |-> 3]");
+
+        let output = format(20, tokens);
+        assert_eq!(output, "[a      |-> 1,\n bread  |-> 2,\n banana |-> 3]");
+    }
+
+    /// Filled groups only break before elements that do not fit on the
+    /// current line.
+    #[test]
+    fn test_group_fill() {
+        let output = format(39, tuple(GroupBreak::Fill));
+        assert_eq!(output, "vars == << bananas, platanos, mangos >>");
+
+        let output = format(30, tuple(GroupBreak::Fill));
+        assert_eq!(
+            output,
+            "vars == << bananas, platanos,\n           mangos >>"
+        );
+
+        let output = format(20, tuple(GroupBreak::Fill));
+        assert_eq!(
+            output,
+            "vars == << bananas,\n           platanos,\n           mangos >>"
+        );
+    }
+
+    /// Comments following a group do not cause it to be broken.
+    #[test]
+    fn test_group_trailing_comment() {
+        let mut tokens = tuple(GroupBreak::Align);
+        tokens.push((
+            Token::Comment(r"\* a comment", crate::token::Position::Relative(1)),
+            Indent::ZERO,
+        ));
+
+        let output = format(40, tokens);
+        assert_eq!(
+            output,
+            r"vars == << bananas, platanos, mangos >> \* a comment"
+        );
+    }
+
+    /// Breaking a group is recorded as a rule shaping the output.
+    #[test]
+    fn test_group_rule() {
+        let flush = |width| {
+            let mut w = Renderer::new(
+                std::io::sink(),
+                FormatOptions::default().with_line_width(width),
+            );
+            for (t, indent) in tuple(GroupBreak::Align) {
+                w.indent_set(indent);
+                w.push(t).unwrap();
+            }
+            w.flush().unwrap().0
+        };
+
+        assert!(!flush(80).contains(&Rule::LineWrapping));
+        assert!(flush(20).contains(&Rule::LineWrapping));
+    }
+
+    #[test]
+    fn test_group_assignment_width() {
+        // The tokens of `/\ x' = << bananas, platanos, mangos >>`.
+        let assignment = || {
+            [
+                (Token::And, Indent::ZERO),
+                (Token::Ident("x"), Indent::ZERO),
+                (Token::Prime, Indent::ZERO),
+                (Token::Eq, Indent::ZERO),
+            ]
+            .into_iter()
+            .chain(tuple(GroupBreak::Align).into_iter().skip(2))
+        };
+        let options = FormatOptions::default().with_line_width(38);
+
+        // Assignments are broken at the line width by default.
+        let output = format_with(options.clone(), assignment());
+        assert_eq!(
+            output,
+            "/\\ x' = << bananas,\n           platanos,\n           mangos >>"
+        );
+
+        // But may extend to the assignment width when set.
+        let options = options.with_assignment_width(39);
+        let output = format_with(options.clone(), assignment());
+        assert_eq!(output, "/\\ x' = << bananas, platanos, mangos >>");
+
+        // While other lines may not.
+        let output = format_with(options, tuple(GroupBreak::Align));
+        assert_eq!(
+            output,
+            "vars == << bananas,\n           platanos,\n           mangos >>"
+        );
+    }
+}
@@ -1,5 +1,6 @@
 mod comment;
 mod indent;
+mod layout;
 
 #[allow(clippy::module_inception)]
 mod renderer;
@@ -1,56 +1,142 @@
-use std::io::Write;
+use std::{
+    collections::{BTreeSet, HashMap},
+    io::Write,
+};
 
 use crate::{
-    helpers::{Indent, IndentDecorator},
+    helpers::{width, Indent, IndentDecorator, LineCounter, INDENT_STR},
     token::Token,
-    LINE_WIDTH,
+    Diagnostic, Error, FinalNewline, FormatOptions, Rule,
 };
 
-use super::{comment::align_comments, indent::limit_indents};
+use super::{comment::align_comments, indent::limit_indents, layout::break_groups};
 
 /// A renderer of [`Token`] instances, writing the resulting output to `W`.
 pub(crate) struct Renderer<'a, W> {
     /// The current indentation depth.
     indent_depth: Indent,
 
+    /// The maximum indentation depth rendered, from
+    /// [`FormatOptions::max_indent_depth()`].
+    max_indent_depth: Indent,
+
+    /// The number of indentation increases that were discarded because the
+    /// depth is already at `max_indent_depth`.
+    indent_clamped: usize,
+
+    /// True if the indentation depth was clamped at any point.
+    was_clamped: bool,
+
     /// A decorator over `W` that emits indentation after every rendered
-    /// newline.
-    indent: IndentDecorator<W>,
+    /// newline, and counts the lines rendered to give context to write errors.
+    indent: IndentDecorator<LineCounter<W>>,
 
     /// A buffer containing the lowered formatter [`Token`] to write to `W`.
     buf: Vec<(Token<'a>, Indent)>,
 
     /// True when the last token wrote to `ident` was a newline.
     last_token_was_newline: bool,
+
+    /// The user-provided formatting configuration.
+    options: FormatOptions,
+
+    /// When [`Some`], any newlines ending the output are replaced with exactly
+    /// one newline if true, or removed if false.
+    final_newline: Option<bool>,
+
+    /// Identifiers to be rendered with a new name, keyed by the byte offset of
+    /// the identifier in the input.
+    renames: HashMap<usize, &'a str>,
+
+    /// The style rules that shaped the output.
+    rules: BTreeSet<Rule>,
+
+    /// Non-fatal problems observed while formatting.
+    diagnostics: Vec<Diagnostic>,
 }
 
 impl<'a, W> Renderer<'a, W>
 where
     W: std::io::Write,
 {
-    /// Initialise a [`Renderer`] to write to `out`.
-    pub(crate) fn new(out: W) -> Self {
+    /// Initialise a [`Renderer`] to write to `out`, configured by `options`.
+    ///
+    /// All layout configuration is held by the [`Renderer`], allowing
+    /// concurrent formatting with different configurations.
+    pub(crate) fn new(out: W, options: FormatOptions) -> Self {
         Self {
             indent_depth: Indent::ZERO,
-            indent: IndentDecorator::new(out),
+            max_indent_depth: Indent::saturating_from(options.max_indent_depth()),
+            indent_clamped: 0,
+            was_clamped: false,
+            indent: IndentDecorator::new(LineCounter::new(out), &options.indent_str()),
             buf: Default::default(),
             last_token_was_newline: false,
+            final_newline: None,
+            options,
+            renames: HashMap::new(),
+            rules: BTreeSet::new(),
+            diagnostics: vec![],
         }
     }
 
+    /// Render the identifiers starting at the byte offsets in `renames` with
+    /// the mapped name instead of their name in the input.
+    pub(crate) fn with_renames(mut self, renames: HashMap<usize, &'a str>) -> Self {
+        self.renames = renames;
+        self
+    }
+
+    /// End the output with exactly one newline if `v` is true, otherwise end
+    /// it without one, irrespective of the newlines in the input.
+    pub(crate) fn with_final_newline(mut self, v: bool) -> Self {
+        self.final_newline = Some(v);
+        self
+    }
+
+    /// Returns the new name of the identifier `node`, if it is being renamed.
+    pub(crate) fn renamed(&self, node: &tree_sitter::Node<'_>) -> Option<&'a str> {
+        self.renames.get(&node.start_byte()).copied()
+    }
+
+    /// Read the formatting configuration.
+    pub(crate) fn options(&self) -> &FormatOptions {
+        &self.options
+    }
+
+    /// Record that `rule` shaped the output.
+    pub(crate) fn record(&mut self, rule: Rule) {
+        self.rules.insert(rule);
+    }
+
+    /// Record a non-fatal problem observed while formatting.
+    pub(crate) fn warn(&mut self, diagnostic: Diagnostic) {
+        self.diagnostics.push(diagnostic);
+    }
+
     /// Read the current indentation depth.
     pub(crate) fn indent_get(&self) -> Indent {
         self.indent_depth
     }
 
     /// Increase the indentation depth.
+    ///
+    /// The depth is clamped to the configured
+    /// [maximum](FormatOptions::max_indent_depth()), with the excess increases
+    /// discarded by matching calls to [`Renderer::indent_dec()`].
     pub(crate) fn indent_inc(&mut self) {
+        if self.indent_depth >= self.max_indent_depth {
+            self.indent_clamped += 1;
+            self.was_clamped = true;
+            return;
+        }
         self.indent_depth = self.indent_depth + 1;
     }
 
     /// Decrease the indentation depth.
     pub(crate) fn indent_set(&mut self, v: Indent) {
         self.indent_depth = v;
+        self.indent_clamped = 0;
     }
 
     /// Decrement the indentation depth.
@@ -59,11 +145,56 @@ where
     ///
     /// Panics if the indentation depth is 0.
     pub(crate) fn indent_dec(&mut self) {
+        if self.indent_clamped > 0 {
+            self.indent_clamped -= 1;
+            return;
+        }
+
         debug_assert_ne!(self.indent_depth.get(), 0);
 
         self.indent_depth = self.indent_depth - Indent::new(1);
     }
 
+    /// Returns the indentation depth of the line currently being rendered, and
+    /// the number of columns rendered after the indentation when `next` is
+    /// pushed (including any space delimiting it from the preceding token).
+    ///
+    /// Returns [`None`] if `next` would start a new line, or if the width of
+    /// the line cannot be determined because it contains a multi-line token.
+    pub(crate) fn line_offset(&self, next: &Token<'_>) -> Option<(Indent, usize)> {
+        let start = self
+            .buf
+            .iter()
+            .rposition(|(t, _)| {
+                is_newline(t)
+                    || matches!(t, Token::Raw(s) | Token::Verbatim(s) if s.ends_with('\n'))
+            })
+            .map(|v| v + 1)
+            .unwrap_or(0);
+
+        // Layout tokens are never rendered.
+        let line = self.buf[start..]
+            .iter()
+            .filter(|(t, _)| !t.is_layout())
+            .collect::<Vec<_>>();
+        let (_, indent) = line.first()?;
+
+        let line_width = self.options.line_width();
+        let mut width = 0;
+        for (i, (t, _)) in line.iter().enumerate() {
+            if let Token::Raw(s) | Token::Verbatim(s) | Token::Comment(s, _) = t {
+                if s.contains('\n') {
+                    return None;
+                }
+            }
+
+            let next = line.get(i + 1).map(|(v, _)| v).unwrap_or(next);
+            width += token_len(t, line_width) + t.delimiting_space_len(next);
+        }
+
+        Some((*indent, width))
+    }
+
     /// Add `t` to the render queue.
     pub(crate) fn push(&mut self, t: Token<'a>) -> Result<(), std::io::Error> {
         self.buf.push((t, self.indent_depth));
@@ -71,25 +202,104 @@ where
         Ok(())
     }
 
-    /// Flush the queue of [`Token`], rendering them to the output sink.
-    pub(crate) fn flush(mut self) -> Result<(), std::io::Error> {
+    /// Consume the renderer, returning the queue of [`Token`] and the
+    /// indentation depth of each without rendering them.
+    ///
+    /// Lines are broken to fit within the line width as they would be when
+    /// rendered.
+    #[cfg(feature = "tokens")]
+    pub(crate) fn into_tokens(mut self) -> Vec<(Token<'a>, Indent)> {
+        let indent_width = self.indent_width();
+        break_groups(
+            &mut self.buf,
+            self.options.line_width(),
+            self.options.assignment_width(),
+            indent_width,
+        );
+        self.buf
+    }
+
+    /// Returns the number of columns in one level of indentation.
+    fn indent_width(&self) -> usize {
+        // Tabs in the input are expanded to INDENT_STR when parsing, so compute
+        // columns using the same width.
+        match self.options.hard_tabs() {
+            true => INDENT_STR.len(),
+            false => self.options.indent_width(),
+        }
+    }
+
+    /// Flush the queue of [`Token`], rendering them to the output sink and
+    /// returning the style rules recorded while formatting, and any
+    /// [`Diagnostic`] describing structural changes made to keep the output
+    /// renderable.
+    ///
+    /// Errors writing to the output sink identify the token and output line
+    /// being rendered when the error occurred.
+    pub(crate) fn flush(mut self) -> Result<(BTreeSet<Rule>, Vec<Diagnostic>), Error> {
+        let mut diagnostics = std::mem::take(&mut self.diagnostics);
+        if self.was_clamped {
+            diagnostics.push(Diagnostic::IndentClamped {
+                depth: self.max_indent_depth.get() as usize,
+            });
+        }
+
+        let indent_width = self.indent_width();
+
+        // Break the lines of groups that do not fit within the line width.
+        if break_groups(
+            &mut self.buf,
+            self.options.line_width(),
+            self.options.assignment_width(),
+            indent_width,
+        ) {
+            self.rules.insert(Rule::LineWrapping);
+        }
+
         // Rewrite indentation levels if necessary, to prevent blocks from being
         // excessively indented.
-        limit_indents(&mut self.buf);
+        let lines = limit_indents(&mut self.buf);
+        if lines > 0 {
+            diagnostics.push(Diagnostic::IndentLimited { lines });
+        }
 
         // Find consecutive lines that contain end-of-line comments that are
         // aligned vertically and rewrite them to preserve their alignment after
         // their respective lines are formatted.
-        align_comments(&mut self.buf);
+        if self.options.align_comments() {
+            align_comments(&mut self.buf, self.options.line_width(), indent_width);
+        }
 
-        let mut iter = self.buf.drain(..).peekable();
+        // The final newline (if any) is rendered after the tokens.
+        if self.final_newline.is_some() {
+            trim_trailing_newlines(&mut self.buf);
+        }
 
-        while let Some((t, indent_depth)) = iter.next() {
+        let buf = std::mem::take(&mut self.buf);
+        let mut token = 0;
+        self.render(buf, &mut token)
+            .map_err(|source| Error::Render {
+                token,
+                line: self.indent.get_ref().line(),
+                source,
+            })?;
+
+        Ok((self.rules, diagnostics))
+    }
+
+    /// Render the tokens in `buf` to the output sink, setting `token` to the
+    /// index of the token being rendered.
+    fn render(&mut self, buf: Vec<(Token<'a>, Indent)>, token: &mut usize) -> std::io::Result<()> {
+        let line_width = self.options.line_width();
+        let mut iter = buf.into_iter().enumerate().peekable();
+
+        while let Some((i, (t, indent_depth))) = iter.next() {
+            *token = i;
             self.indent.set(indent_depth);
 
             // If this token cannot appear before the next token, skip rendering
             // this one.
-            if let Some((next, _)) = iter.peek() {
+            if let Some((_, (next, _))) = iter.peek() {
                 if !t.can_precede(next) {
                     continue;
                 }
@@ -98,7 +308,7 @@ where
             let s = match &t {
                 Token::StepOrStutter(ident) => {
                     let s = format!("[{ident}]_");
-                    debug_assert_eq!(s.len(), token_len(&t));
+                    debug_assert_eq!(width(&s), token_len(&t, line_width));
 
                     self.indent.write_all(s.as_bytes())?;
                     continue;
@@ -109,17 +319,17 @@ where
                     // source spec.
                     continue;
                 }
-                Token::ModuleHeader(name) => {
-                    let s = render_module_header(name);
-                    debug_assert_eq!(s.len(), token_len(&t));
+                Token::ModuleHeader(name, dashes) => {
+                    let s = render_module_header(name, *dashes, line_width);
+                    debug_assert_eq!(width(&s), token_len(&t, line_width));
 
                     self.indent.write_all(s.as_bytes())?;
 
                     continue;
                 }
-                Token::LineDivider(c) => {
-                    let s = std::iter::repeat_n(c, LINE_WIDTH).collect::<String>();
-                    debug_assert_eq!(s.len(), token_len(&t));
+                Token::LineDivider(c, len) => {
+                    let s = std::iter::repeat_n(c, len.unwrap_or(line_width)).collect::<String>();
+                    debug_assert_eq!(width(&s), token_len(&t, line_width));
 
                     self.indent.write_all(s.as_bytes())?;
                     continue;
@@ -149,95 +359,39 @@ where
                     self.indent.set(orig);
 
                     // Optionally add a space delimiter.
-                    if let Some(n) = iter.peek().map(|(v, _)| t.delimiting_space_len(v)) {
+                    if let Some(n) = iter.peek().map(|(_, (v, _))| t.delimiting_space_len(v)) {
                         self.indent.write_all(&b" ".repeat(n))?;
                     }
                     continue;
                 }
-                Token::Raw(s) => s,
-                Token::Prime => "'",
-                Token::Always => "[]",
-                Token::Eventually => "<>",
+                Token::Verbatim(s) => {
+                    self.last_token_was_newline = false;
+
+                    // Written without indentation or whitespace removal.
+                    self.indent.write_verbatim(s.as_bytes())?;
+
+                    if let Some((n, next_indent)) = iter
+                        .peek()
+                        .map(|(_, (v, next_indent))| (t.delimiting_space_len(v), next_indent))
+                    {
+                        self.indent.set(*next_indent);
+                        self.indent.write_all(&b" ".repeat(n))?;
+                    }
+                    continue;
+                }
+                Token::Raw(s) | Token::Ident(s) | Token::Lit(s) | Token::InfixOp(s, _) => s,
                 Token::Newline | Token::SourceNewline => "\n",
-                Token::KeywordChoose => "CHOOSE",
-                Token::KeywordLet => "LET",
-                Token::KeywordIn => "IN",
-                Token::KeywordLocal => "LOCAL",
-                Token::KeywordInstance => "INSTANCE",
-                Token::KeywordDomain => "DOMAIN",
-                Token::KeywordIf => "IF",
-                Token::KeywordThen => "THEN",
-                Token::KeywordElse => "ELSE",
-                Token::KeywordCase => "CASE",
-                Token::KeywordExtends => "EXTENDS",
-                Token::KeywordConstant => "CONSTANT",
-                Token::KeywordConstants => "CONSTANTS",
-                Token::KeywordVariable => "VARIABLE",
-                Token::KeywordVariables => "VARIABLES",
-                Token::KeywordExcept => "EXCEPT",
-                Token::KeywordUnchanged => "UNCHANGED",
-                Token::KeywordEnabled => "ENABLED",
-                Token::KeywordSubset => "SUBSET",
-                Token::KeywordUnion => "UNION",
-                Token::CaseBox => "[]",
-                Token::MapTo => ":>",
-                Token::MapsTo => "->",
-                Token::AllMapsTo => "|->",
-                Token::Compose => "@@",
-                Token::Exists => r"\E",
-                Token::All => r"\A",
-                Token::SetIn => r"\in",
-                Token::SetNotIn => r"\notin",
-                Token::And => r"/\",
-                Token::Or => r"\/",
-                Token::Not => "~",
-                Token::Ident(s) => s,
-                Token::Lit(s) => s,
-                Token::ParenOpen => "(",
-                Token::ParenClose => ")",
-                Token::SquareOpen => "[",
-                Token::SquareClose => "]",
-                Token::CurlyOpen => "{",
-                Token::CurlyClose => "}",
-                Token::AngleOpen => "<<",
-                Token::AngleClose => ">>",
-                Token::Comma => ",",
-                Token::SemiColon => ":",
-                Token::Bang => "!",
-                Token::At => "@",
-                Token::Eq => "=",
-                Token::Eq2 => "==",
-                Token::NotEq => "/=",
-                Token::SubsetEq => r"\subseteq",
-                Token::Dot => ".",
-                Token::Dots2 => "..",
-                Token::Plus => "+",
-                Token::Minus => "-",
-                Token::Multiply => "*",
-                Token::AppendShort => r"\o",
-                Token::Real => r"Real",
-                Token::Int => r"Int",
-                Token::Nat => r"Nat",
-                Token::GreaterThan => ">",
-                Token::GreaterThanEqual => ">=",
-                Token::LessThan => "<",
-                Token::LessThanEqual => "<=",
-                Token::SetMinus => r"\",
-                Token::Divide => r"/",
-                Token::True => "TRUE",
-                Token::False => "FALSE",
-                Token::Union => r"\union",
-                Token::Intersect => r"\intersect",
-                Token::WeakFairness => "WF_",
-                Token::StrongFairness => "SF_",
-                Token::Implies => "=>",
-                Token::KeywordTheorem => "THEOREM",
-                Token::CaseArrow => "->",
+                t => t
+                    .fixed_text()
+                    .expect("all other tokens render as fixed text"),
             };
 
             // Invariant: the rendered text must match the reported token
             // length.
-            debug_assert!(s.len() == token_len(&t) || is_newline(&t), "{s:?}");
+            debug_assert!(
+                width(s) == token_len(&t, line_width) || is_newline(&t),
+                "{s:?}"
+            );
 
             // Write the rendered token.
             self.indent.write_all(s.as_bytes())?;
@@ -246,7 +400,7 @@ where
             // delimited.
             if let Some((n, next_indent)) = iter
                 .peek()
-                .map(|(v, next_indent)| (t.delimiting_space_len(v), next_indent))
+                .map(|(_, (v, next_indent))| (t.delimiting_space_len(v), next_indent))
             {
                 self.indent.set(*next_indent);
                 self.indent.write_all(&b" ".repeat(n))?;
@@ -255,6 +409,13 @@ where
             self.last_token_was_newline = is_newline(&t);
         }
 
+        let final_newline = self
+            .final_newline
+            .unwrap_or_else(|| self.options.final_newline() == FinalNewline::Always);
+        if final_newline && !self.indent.ends_with_newline() {
+            self.indent.write_all(b"\n")?;
+        }
+
         Ok(())
     }
 }
@@ -263,11 +424,34 @@ pub(super) fn is_newline(t: &Token) -> bool {
     matches!(t, Token::Newline | Token::SourceNewline)
 }
 
-/// Render a module header line for `name`.
-fn render_module_header(name: &&str) -> String {
+/// Remove the newlines (and any whitespace in unformatted source) that end the
+/// output in `buf`.
+fn trim_trailing_newlines(buf: &mut Vec<(Token<'_>, Indent)>) {
+    loop {
+        match buf.last_mut() {
+            Some((t, _)) if is_newline(t) => {}
+            Some((Token::Raw(s) | Token::Verbatim(s), _)) => {
+                let v = *s;
+                *s = v.trim_end();
+                if !s.is_empty() {
+                    return;
+                }
+            }
+            _ => return,
+        }
+        buf.pop();
+    }
+}
+
+/// Render a module header line for `name`, filling `line_width` columns.
+fn render_module_header(name: &&str, dashes: Option<(usize, usize)>, line_width: usize) -> String {
     const MODULE: &str = " MODULE ";
-    let line_len = LINE_WIDTH
-        .checked_sub(name.len())
+
+    if let Some((left, right)) = dashes {
+        return format!("{}{MODULE}{name} {}", "-".repeat(left), "-".repeat(right));
+    }
+    let line_len = line_width
+        .checked_sub(width(name))
         .and_then(|v| v.checked_sub(MODULE.len() + 1))
         .and_then(|v| v.checked_div(2))
         .unwrap_or(1);
@@ -277,7 +461,7 @@ fn render_module_header(name: &&str) -> String {
     //
     // When this happens, pad the right-side line with an extra dash.
     let mut right_extra = 0;
-    if (line_len * 2) + MODULE.len() + 1 + name.len() == LINE_WIDTH - 1 {
+    if (line_len * 2) + MODULE.len() + 1 + width(name) == line_width.saturating_sub(1) {
         right_extra = 1;
     }
 
@@ -288,98 +472,33 @@ fn render_module_header(name: &&str) -> String {
     )
 }
 
-/// Return the size of a rendered [`Token`], exclusive of whitespace delimiters.
-pub(super) fn token_len(t: &Token<'_>) -> usize {
+/// Return the display width of a rendered [`Token`], exclusive of whitespace
+/// delimiters, when rendered with a maximum line width of `line_width`.
+pub(super) fn token_len(t: &Token<'_>, line_width: usize) -> usize {
     match t {
-        Token::Raw(s) => s.len(),
-        Token::ModuleHeader(name) => render_module_header(name).len(),
-        Token::Comment(s, _) => s.len(),
+        Token::Raw(s) | Token::Verbatim(s) => width(s),
+        Token::InfixOp(s, _) => width(s),
+        Token::ModuleHeader(name, dashes) => {
+            width(&render_module_header(name, *dashes, line_width))
+        }
+        Token::Comment(s, _) => width(s),
         Token::Newline | Token::SourceNewline => 0,
-        Token::KeywordChoose => 6,
-        Token::KeywordLet => 3,
-        Token::KeywordIn => 2,
-        Token::KeywordUnchanged => 9,
-        Token::KeywordExtends => 7,
-        Token::KeywordConstant => 8,
-        Token::KeywordConstants => 9,
-        Token::KeywordVariable => 8,
-        Token::KeywordVariables => 9,
-        Token::KeywordExcept => 6,
-        Token::KeywordEnabled => 7,
-        Token::KeywordTheorem => 7,
-        Token::KeywordLocal => 5,
-        Token::KeywordInstance => 8,
-        Token::KeywordDomain => 6,
-        Token::KeywordSubset => 6,
-        Token::KeywordIf => 2,
-        Token::KeywordThen => 4,
-        Token::KeywordElse => 4,
-        Token::KeywordCase => 4,
-        Token::KeywordUnion => 5,
-        Token::Exists => 2,
-        Token::All => 2,
-        Token::SetIn => 3,
-        Token::SetNotIn => 6,
-        Token::And => 2,
-        Token::Or => 2,
-        Token::MapsTo => 2,
-        Token::MapTo => 2,
-        Token::AllMapsTo => 3,
-        Token::Ident(s) => s.len(),
-        Token::Lit(s) => s.len(),
-        Token::ParenOpen => 1,
-        Token::ParenClose => 1,
-        Token::Comma => 1,
-        Token::SemiColon => 1,
-        Token::Plus => 1,
-        Token::Minus => 1,
-        Token::Multiply => 1,
-        Token::Eq => 1,
-        Token::Eq2 => 2,
-        Token::NotEq => 2,
-        Token::SubsetEq => 9,
-        Token::Dot => 1,
-        Token::Dots2 => 2,
-        Token::At => 1,
-        Token::SquareOpen => 1,
-        Token::SquareClose => 1,
-        Token::CurlyOpen => 1,
-        Token::CurlyClose => 1,
-        Token::AngleOpen => 2,
-        Token::AngleClose => 2,
-        Token::AppendShort => 2,
-        Token::Real => 4,
-        Token::GreaterThan => 1,
-        Token::GreaterThanEqual => 2,
-        Token::LessThan => 1,
-        Token::LessThanEqual => 2,
-        Token::Not => 1,
-        Token::SetMinus => 1,
-        Token::Divide => 1,
-        Token::LineDivider(_) => LINE_WIDTH,
-        Token::Prime => 1,
-        Token::Always => 2,
-        Token::Eventually => 2,
-        Token::Implies => 2,
-        Token::Bang => 1,
-        Token::True => 4,
-        Token::False => 5,
-        Token::WeakFairness => 3,
-        Token::StrongFairness => 3,
-        Token::Union => 6,
-        Token::Intersect => 10,
-        Token::Compose => 2,
-        Token::StepOrStutter(s) => s.len() + 3,
-        Token::CaseBox => 2,
-        Token::CaseArrow => 2,
-        Token::Int => 3,
-        Token::Nat => 3,
+        Token::Group(_) | Token::GroupEnd | Token::SoftLine | Token::GroupPad(_) => 0,
+        Token::Ident(s) => width(s),
+        Token::Lit(s) => width(s),
+        Token::LineDivider(_, len) => len.unwrap_or(line_width),
+        Token::StepOrStutter(s) => width(s) + 3,
+        t => width(
+            t.fixed_text()
+                .expect("all other tokens render as fixed text"),
+        ),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{token::FIXED_TOKENS, OperatorSpacing};
 
     fn format<'a>(tokens: impl IntoIterator<Item = Token<'a>>) -> String {
         format_indented(tokens.into_iter().map(|v| (v, Indent::ZERO)))
@@ -387,7 +506,7 @@ mod tests {
 
     fn format_indented<'a>(tokens: impl IntoIterator<Item = (Token<'a>, Indent)>) -> String {
         let mut buf = Vec::new();
-        let mut w = Renderer::new(&mut buf);
+        let mut w = Renderer::new(&mut buf, FormatOptions::default());
 
         for (t, indent) in tokens {
             w.indent_set(indent);
@@ -399,6 +518,61 @@ mod tests {
         String::from_utf8(buf).expect("valid utf8 output")
     }
 
+    /// The public token table matches the rendered output.
+    #[test]
+    fn test_token_table() {
+        let table = crate::token_table().collect::<Vec<_>>();
+        assert_eq!(table.len(), FIXED_TOKENS.len());
+
+        for (t, v) in FIXED_TOKENS.iter().zip(table) {
+            assert_eq!(format([t.clone()]), v.text, "{}", v.name);
+            assert_eq!(token_len(t, 80), v.width, "{}", v.name);
+        }
+    }
+
+    #[test]
+    fn test_indent_clamped() {
+        let mut buf = Vec::new();
+        let mut w = Renderer::new(&mut buf, FormatOptions::default().with_max_indent_depth(2));
+
+        for _ in 0..5 {
+            w.indent_inc();
+        }
+        assert_eq!(w.indent_get(), Indent::new(2));
+
+        // The discarded increases are balanced by the decreases.
+        for _ in 0..4 {
+            w.indent_dec();
+        }
+        assert_eq!(w.indent_get(), Indent::new(1));
+        w.indent_dec();
+
+        let (_, diagnostics) = w.flush().unwrap();
+        assert_eq!(diagnostics, [Diagnostic::IndentClamped { depth: 2 }]);
+    }
+
+    /// Trailing newlines are replaced with exactly one newline, or removed.
+    #[test]
+    fn test_final_newline() {
+        let format = |v| {
+            let mut buf = Vec::new();
+            let mut w = Renderer::new(&mut buf, FormatOptions::default()).with_final_newline(v);
+            for t in [
+                Token::Ident("bananas"),
+                Token::Raw("!!!\n \n"),
+                Token::SourceNewline,
+                Token::Newline,
+            ] {
+                w.push(t).unwrap();
+            }
+            w.flush().unwrap();
+            String::from_utf8(buf).unwrap()
+        };
+
+        assert_eq!(format(false), "bananas !!!");
+        assert_eq!(format(true), "bananas !!!\n");
+    }
+
     #[test]
     fn test_write_line() {
         let output = format([Token::Raw("testing")]);
@@ -674,19 +848,59 @@ mod tests {
 
         let output: String = format([Token::Raw("!!!\n"), Token::Ident("bananas")]);
         assert_eq!(output, "!!!\nbananas");
+
+        let output: String = format([
+            Token::Raw("!!!\n"),
+            Token::InfixOp("++", OperatorSpacing::Spaced),
+            Token::Ident("bananas"),
+        ]);
+        assert_eq!(output, "!!!\n++ bananas");
+    }
+
+    /// Space delimiters, indentation and trailing whitespace within tokens must
+    /// never be rendered immediately before a newline.
+    #[test]
+    fn test_no_trailing_whitespace() {
+        let output: String = format([Token::Raw("!!!"), Token::Raw("\nbananas")]);
+        assert_eq!(output, "!!!\nbananas");
+
+        let output: String = format([
+            Token::Ident("bananas"),
+            Token::Comment(r"\* platanos  ", crate::token::Position::Relative(2)),
+            Token::Newline,
+            Token::Ident("bananas"),
+        ]);
+        assert_eq!(output, "bananas  \\* platanos\nbananas");
+
+        let output: String = format_indented([
+            (Token::Ident("bananas"), Indent::new(1)),
+            (Token::SourceNewline, Indent::new(1)),
+            (Token::SourceNewline, Indent::new(1)),
+            (Token::Ident("platanos"), Indent::new(1)),
+            (Token::Raw(" "), Indent::new(1)),
+        ]);
+        assert_eq!(output, "bananas\n\n    platanos");
     }
 
     /// An unhandled node that follows a newline should not be space delimited -
     /// it leads to an unexpected space before the content of a line.
     #[test]
     fn test_newline_line_divider() {
-        let output: String = format([Token::Raw("!!!"), Token::Newline, Token::LineDivider('-')]);
+        let output: String = format([
+            Token::Raw("!!!"),
+            Token::Newline,
+            Token::LineDivider('-', None),
+        ]);
         assert_eq!(
             output,
             "!!!\n--------------------------------------------------------------------------------"
         );
 
-        let output: String = format([Token::Raw("!!!"), Token::Newline, Token::LineDivider('=')]);
+        let output: String = format([
+            Token::Raw("!!!"),
+            Token::Newline,
+            Token::LineDivider('=', None),
+        ]);
         assert_eq!(
             output,
             "!!!\n================================================================================"
@@ -740,4 +954,72 @@ mod tests {
         ]);
         assert_eq!(output, "bananas\n");
     }
+
+    #[test]
+    fn test_line_width() {
+        let mut buf = Vec::new();
+        let mut w = Renderer::new(&mut buf, FormatOptions::default().with_line_width(40));
+
+        w.push(Token::ModuleHeader("Bananas", None)).unwrap();
+        w.push(Token::Newline).unwrap();
+        w.push(Token::LineDivider('=', None)).unwrap();
+        w.flush().unwrap();
+
+        let output = String::from_utf8(buf).expect("valid utf8 output");
+        assert_eq!(
+            output,
+            "------------ MODULE Bananas ------------\n\
+             ========================================"
+        );
+    }
+
+    /// Errors writing to the output sink identify the position of the failed
+    /// write.
+    #[test]
+    fn test_write_error_context() {
+        /// A sink that accepts the given number of bytes before failing.
+        struct Closed(usize);
+
+        impl Write for Closed {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                if self.0 == 0 {
+                    return Err(std::io::ErrorKind::BrokenPipe.into());
+                }
+                let n = buf.len().min(self.0);
+                self.0 -= n;
+                Ok(n)
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut w = Renderer::new(Closed(10), FormatOptions::default());
+        for t in [
+            Token::Ident("bananas"),
+            Token::Newline,
+            Token::Ident("platanos"),
+            Token::Newline,
+        ] {
+            w.push(t).unwrap();
+        }
+
+        let err = w.flush().unwrap_err();
+        assert!(
+            matches!(
+                err,
+                Error::Render {
+                    token: 2,
+                    line: 2,
+                    ..
+                }
+            ),
+            "{err:?}"
+        );
+        assert_eq!(
+            err.to_string(),
+            "error writing output line 2 (token 2): broken pipe"
+        );
+    }
 }
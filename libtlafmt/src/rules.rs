@@ -0,0 +1,233 @@
+//! A registry of the style rules applied by the formatter.
+
+use std::fmt::Display;
+
+use crate::{DividerLength, FinalNewline, FormatOptions, QuantifierListLayout};
+
+/// A style rule applied by the formatter, identified by a stable ID.
+///
+/// Rule IDs are never reused, allowing changes to the formatting output
+/// between releases to be communicated in terms of the rules that changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
+pub enum Rule {
+    /// Module headers centre the module name within the line width.
+    ModuleHeader,
+
+    /// Dividing lines and the module footer span the line width.
+    Dividers,
+
+    /// Conjunction and disjunction items start on their own line.
+    ListBullets,
+
+    /// The bullet of a conjunction or disjunction list with a single item is
+    /// removed.
+    CollapseSingleItemLists,
+
+    /// A list forming the body of a quantifier starts on the quantifier line.
+    QuantifierListSameLine,
+
+    /// Consecutive empty lines are squashed into one.
+    EmptyLines,
+
+    /// Vertically aligned end-of-line comments remain aligned.
+    CommentAlignment,
+
+    /// Infix operators are spaced as configured by the user.
+    OperatorSpacing,
+
+    /// Protected module-level comments are rendered verbatim.
+    ProtectedComments,
+
+    /// A newline is inserted at the end of the output.
+    FinalNewline,
+
+    /// A comment recording the formatter version is placed before the module
+    /// footer.
+    MetadataComment,
+
+    /// Consecutive `ASSUME` statements are aligned and separated by one empty
+    /// line.
+    AssumptionBlocks,
+
+    /// Builtin boolean names spelled in a different case use the canonical
+    /// spelling.
+    BooleanCasing,
+
+    /// Line breaks within operator calls follow the configured wrapping hints.
+    CallWrapping,
+
+    /// The fields of record literals and sets of records are sorted.
+    RecordFieldOrder,
+
+    /// Expressions exceeding the line width are broken across lines.
+    LineWrapping,
+}
+
+impl Rule {
+    /// All rules, in ID order.
+    pub const ALL: &[Self] = &[
+        Self::ModuleHeader,
+        Self::Dividers,
+        Self::ListBullets,
+        Self::CollapseSingleItemLists,
+        Self::QuantifierListSameLine,
+        Self::EmptyLines,
+        Self::CommentAlignment,
+        Self::OperatorSpacing,
+        Self::ProtectedComments,
+        Self::FinalNewline,
+        Self::MetadataComment,
+        Self::AssumptionBlocks,
+        Self::BooleanCasing,
+        Self::CallWrapping,
+        Self::RecordFieldOrder,
+        Self::LineWrapping,
+    ];
+
+    /// The stable ID of this rule (such as `TF001`).
+    pub fn id(&self) -> &'static str {
+        match self {
+            Self::ModuleHeader => "TF001",
+            Self::Dividers => "TF002",
+            Self::ListBullets => "TF003",
+            Self::CollapseSingleItemLists => "TF004",
+            Self::QuantifierListSameLine => "TF005",
+            Self::EmptyLines => "TF006",
+            Self::CommentAlignment => "TF007",
+            Self::OperatorSpacing => "TF008",
+            Self::ProtectedComments => "TF009",
+            Self::FinalNewline => "TF010",
+            Self::MetadataComment => "TF011",
+            Self::AssumptionBlocks => "TF012",
+            Self::BooleanCasing => "TF013",
+            Self::CallWrapping => "TF014",
+            Self::RecordFieldOrder => "TF015",
+            Self::LineWrapping => "TF016",
+        }
+    }
+
+    /// The human-readable name of this rule.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::ModuleHeader => "module-header",
+            Self::Dividers => "dividers",
+            Self::ListBullets => "list-bullets",
+            Self::CollapseSingleItemLists => "collapse-single-item-lists",
+            Self::QuantifierListSameLine => "quantifier-list-same-line",
+            Self::EmptyLines => "empty-lines",
+            Self::CommentAlignment => "comment-alignment",
+            Self::OperatorSpacing => "operator-spacing",
+            Self::ProtectedComments => "protected-comments",
+            Self::FinalNewline => "final-newline",
+            Self::MetadataComment => "metadata-comment",
+            Self::AssumptionBlocks => "assumption-blocks",
+            Self::BooleanCasing => "boolean-casing",
+            Self::CallWrapping => "call-wrapping",
+            Self::RecordFieldOrder => "record-field-order",
+            Self::LineWrapping => "line-wrapping",
+        }
+    }
+
+    /// A one-line description of the formatting applied by this rule.
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::ModuleHeader => "Centre the module name within the line width",
+            Self::Dividers => "Extend dividing lines and the module footer to the line width",
+            Self::ListBullets => "Start each conjunction and disjunction item on its own line",
+            Self::CollapseSingleItemLists => "Remove the bullet from single item lists",
+            Self::QuantifierListSameLine => "Start lists under a quantifier on the quantifier line",
+            Self::EmptyLines => "Squash consecutive empty lines into one",
+            Self::CommentAlignment => "Keep vertically aligned end-of-line comments aligned",
+            Self::OperatorSpacing => "Space infix operators as configured",
+            Self::ProtectedComments => "Render protected module-level comments verbatim",
+            Self::FinalNewline => "Insert a newline at the end of the output",
+            Self::MetadataComment => "Place a formatter metadata comment before the footer",
+            Self::AssumptionBlocks => "Align consecutive ASSUME statements as a block",
+            Self::BooleanCasing => "Spell TRUE, FALSE and BOOLEAN in upper case",
+            Self::CallWrapping => "Break operator calls only where permitted by wrapping hints",
+            Self::RecordFieldOrder => "Sort the fields of records by name or a configured order",
+            Self::LineWrapping => "Break expressions that exceed the line width",
+        }
+    }
+
+    /// Returns true if this rule is applied when formatting with `options`.
+    pub fn is_active(&self, options: &FormatOptions) -> bool {
+        match self {
+            Self::ModuleHeader => options.divider_length() != DividerLength::PreserveWithHeader,
+            Self::Dividers => options.divider_length() == DividerLength::Fill,
+            Self::ListBullets | Self::EmptyLines => true,
+            Self::CollapseSingleItemLists => options.collapse_single_item_lists(),
+            Self::QuantifierListSameLine => {
+                options.quantifier_list_layout() == QuantifierListLayout::SameLine
+            }
+            Self::CommentAlignment => options.align_comments(),
+            Self::OperatorSpacing => options.operator_spacings().next().is_some(),
+            Self::ProtectedComments => options.protected_comment_prefixes().next().is_some(),
+            Self::FinalNewline => options.final_newline() != FinalNewline::Never,
+            Self::MetadataComment => options.metadata_comment(),
+            Self::AssumptionBlocks => options.align_assumptions(),
+            Self::BooleanCasing => options.normalise_booleans(),
+            Self::CallWrapping => {
+                options.standard_call_wrapping() || options.call_wrappings().next().is_some()
+            }
+            Self::RecordFieldOrder => options.sort_record_fields(),
+            Self::LineWrapping => options.line_wrapping(),
+        }
+    }
+
+    /// Returns the rules applied when formatting with `options`, in ID order.
+    pub fn active(options: &FormatOptions) -> impl Iterator<Item = Self> + '_ {
+        Self::ALL.iter().copied().filter(|v| v.is_active(options))
+    }
+}
+
+impl Display for Rule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.id(), self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ids_unique_and_ordered() {
+        let ids = Rule::ALL.iter().map(Rule::id).collect::<Vec<_>>();
+
+        let mut sorted = ids.clone();
+        sorted.sort();
+        sorted.dedup();
+        assert_eq!(ids, sorted);
+    }
+
+    #[test]
+    fn test_active() {
+        let got = Rule::active(&FormatOptions::default()).collect::<Vec<_>>();
+        assert_eq!(
+            got,
+            [
+                Rule::ModuleHeader,
+                Rule::Dividers,
+                Rule::ListBullets,
+                Rule::EmptyLines,
+                Rule::CommentAlignment,
+            ]
+        );
+
+        let options = FormatOptions::default()
+            .with_collapse_single_item_lists(true)
+            .with_divider_length(DividerLength::PreserveWithHeader);
+        let got = Rule::active(&options).collect::<Vec<_>>();
+        assert_eq!(
+            got,
+            [
+                Rule::ListBullets,
+                Rule::CollapseSingleItemLists,
+                Rule::EmptyLines,
+                Rule::CommentAlignment,
+            ]
+        );
+    }
+}
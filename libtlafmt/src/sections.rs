@@ -0,0 +1,237 @@
+//! Linting of the order of the top-level sections of a module.
+//!
+//! Specs conventionally follow the layout:
+//!
+//! ```text
+//! EXTENDS ...
+//! CONSTANTS ... / ASSUME ...
+//! VARIABLES ...
+//! <helper definitions>
+//! Init / <actions> / Next / Spec
+//! <properties and theorems>
+//! ```
+
+use std::fmt::Display;
+
+use tree_sitter::Node;
+
+use crate::{
+    definitions::{unit_name, units},
+    get_str, ParsedFile,
+};
+
+/// Definitions that describe the behaviour of a spec by name, regardless of
+/// their content.
+const BEHAVIOUR_NAMES: &[&str] = &["Init", "Next", "Spec"];
+
+/// Definition name suffixes that conventionally denote a property.
+const PROPERTY_SUFFIXES: &[&str] = &["Inv", "Invariant", "Property"];
+
+/// A conventional top-level section of a module, ordered by the position it
+/// appears in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Section {
+    /// `EXTENDS` statements.
+    Extends,
+
+    /// `CONSTANT` declarations and `ASSUME` statements.
+    Constants,
+
+    /// `VARIABLE` declarations.
+    Variables,
+
+    /// Helper definitions, instances and recursive declarations.
+    Definitions,
+
+    /// `Init`, `Next` and `Spec`, along with any actions (definitions
+    /// containing primed variables) and fairness conditions.
+    Behaviour,
+
+    /// Temporal properties, invariants and theorems.
+    Properties,
+}
+
+impl Display for Section {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Extends => "extends",
+            Self::Constants => "constants",
+            Self::Variables => "variables",
+            Self::Definitions => "definitions",
+            Self::Behaviour => "behaviour",
+            Self::Properties => "properties",
+        };
+        f.write_str(s)
+    }
+}
+
+/// A top-level unit of a module that appears after a unit belonging to a later
+/// [`Section`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Misplaced {
+    /// The 1-indexed line the unit starts on.
+    pub line: usize,
+
+    /// The name of the definition or declaration, or the keyword introducing
+    /// the unit if it is unnamed.
+    pub name: String,
+
+    /// The section the unit belongs to.
+    pub section: Section,
+
+    /// The (later) section it appears after.
+    pub after: Section,
+}
+
+impl ParsedFile<'_> {
+    /// Return the top-level units of the module that appear out of the
+    /// conventional [`Section`] order.
+    ///
+    /// Units are classified using the node kind, name and content of each
+    /// definition, so the result is a best-effort lint rather than a guarantee
+    /// of structure.
+    pub fn misplaced_sections(&self) -> Vec<Misplaced> {
+        let mut latest = Section::Extends;
+        let mut out = vec![];
+
+        for n in units(self.t.root_node()) {
+            let Some(section) = classify(n, &self.input) else {
+                continue;
+            };
+
+            if section < latest {
+                out.push(Misplaced {
+                    line: n.start_position().row + 1,
+                    name: unit_name(n, &self.input).to_string(),
+                    section,
+                    after: latest,
+                });
+            }
+
+            latest = latest.max(section);
+        }
+
+        out
+    }
+}
+
+/// Return the [`Section`] the top-level node `n` belongs to, or [`None`] if it
+/// is not a section member (such as comments and the module header).
+fn classify(n: Node<'_>, input: &str) -> Option<Section> {
+    let section = match n.kind() {
+        "extends" => Section::Extends,
+        "constant_declaration" | "assumption" => Section::Constants,
+        "variable_declaration" => Section::Variables,
+        "module_definition" | "instance" | "recursive_declaration" => Section::Definitions,
+        "theorem" => Section::Properties,
+        "operator_definition" | "function_definition" => {
+            let name = n
+                .child_by_field_name("name")
+                .map(|v| get_str(&v, input))
+                .unwrap_or_default();
+
+            if BEHAVIOUR_NAMES.contains(&name) || contains_kind(n, &["prime", "fairness"]) {
+                Section::Behaviour
+            } else if PROPERTY_SUFFIXES.iter().any(|v| name.ends_with(v))
+                || contains_kind(n, &["always", "eventually", "leads_to"])
+            {
+                Section::Properties
+            } else {
+                Section::Definitions
+            }
+        }
+        _ => return None,
+    };
+
+    Some(section)
+}
+
+/// Returns true if `n` or any of its descendants is of one of `kinds`.
+fn contains_kind(n: Node<'_>, kinds: &[&str]) -> bool {
+    if kinds.contains(&n.kind()) {
+        return true;
+    }
+
+    let mut c = n.walk();
+    let found = n.named_children(&mut c).any(|v| contains_kind(v, kinds));
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn misplaced(input: &str) -> Vec<(usize, String, Section, Section)> {
+        ParsedFile::new(input)
+            .unwrap()
+            .misplaced_sections()
+            .into_iter()
+            .map(|v| (v.line, v.name, v.section, v.after))
+            .collect()
+    }
+
+    #[test]
+    fn test_conventional_order() {
+        let input = r"---- MODULE S ----
+EXTENDS Naturals
+CONSTANT N
+ASSUME N > 0
+VARIABLE x
+vars == <<x>>
+F[i \in 1..2] == i
+RECURSIVE R(_)
+R(n) == R(n)
+Init == x = 0
+Inc == x' = x + 1
+Next == Inc
+Fairness == WF_vars(Next)
+Spec == Init /\ [][Next]_vars /\ Fairness
+TypeInv == x \in Nat
+Live == <>(x = 3)
+THEOREM Spec => []Live
+====
+";
+        assert_eq!(misplaced(input), []);
+    }
+
+    #[test]
+    fn test_misplaced() {
+        let input = r"---- MODULE S ----
+VARIABLE x
+CONSTANT N, F(_)
+Init == x = 0
+Helper == x + 1
+LOCAL Other == 42
+Next == x' = Helper
+THEOREM Next => x > 0
+Spec == Init /\ [][Next]_x
+VARIABLE y
+====
+";
+        assert_eq!(
+            misplaced(input),
+            [
+                (3, "N".to_string(), Section::Constants, Section::Variables),
+                (
+                    5,
+                    "Helper".to_string(),
+                    Section::Definitions,
+                    Section::Behaviour
+                ),
+                (
+                    6,
+                    "Other".to_string(),
+                    Section::Definitions,
+                    Section::Behaviour
+                ),
+                (
+                    9,
+                    "Spec".to_string(),
+                    Section::Behaviour,
+                    Section::Properties
+                ),
+                (10, "y".to_string(), Section::Variables, Section::Properties),
+            ]
+        );
+    }
+}
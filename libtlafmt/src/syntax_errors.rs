@@ -0,0 +1,93 @@
+//! Inspection of the syntax errors in a parsed spec.
+
+use std::ops::Range;
+
+use tree_sitter::Node;
+
+use crate::{get_str, Location, ParsedFile};
+
+/// A syntax error in the input spec.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxError<'a> {
+    /// The location the error starts at.
+    pub location: Location,
+
+    /// The range of input bytes spanned by the erroneous source.
+    ///
+    /// Offsets refer to [`ParsedFile::input()`], in which any tab characters
+    /// of the original input are replaced with spaces.
+    pub bytes: Range<usize>,
+
+    /// The erroneous source, emitted as-is when formatting.
+    ///
+    /// Empty if the error is a missing token.
+    pub text: &'a str,
+
+    /// The kind of the token the parser expected but did not find, if the
+    /// error is a missing token.
+    pub missing: Option<&'static str>,
+}
+
+impl ParsedFile<'_> {
+    /// Returns the syntax errors in the input, in the order they appear.
+    ///
+    /// Each unparseable span of source and each missing token is reported
+    /// once - errors nested within an unparseable span are not reported
+    /// separately.
+    pub fn syntax_errors(&self) -> Vec<SyntaxError<'_>> {
+        let mut out = vec![];
+        collect(self.t.root_node(), &self.input, &mut out);
+        out
+    }
+}
+
+/// Push a [`SyntaxError`] to `out` for each outermost error or missing node
+/// within `node`.
+fn collect<'a>(node: Node<'_>, input: &'a str, out: &mut Vec<SyntaxError<'a>>) {
+    if !node.has_error() {
+        return;
+    }
+    if node.is_error() || node.is_missing() {
+        out.push(SyntaxError {
+            location: Location::of(&node, input),
+            bytes: node.byte_range(),
+            text: get_str(&node, input),
+            missing: node.is_missing().then(|| node.kind()),
+        });
+        return;
+    }
+
+    let mut c = node.walk();
+    for child in node.children(&mut c) {
+        collect(child, input, out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_syntax_errors() {
+        let input = "---- MODULE A ----\nX == (1 + )\nY == 1\n\nZ == )\n====\n";
+        let parsed = ParsedFile::new(input).unwrap();
+
+        let got = parsed.syntax_errors();
+        assert_eq!(got.len(), 2, "{got:#?}");
+
+        // The right operand is missing.
+        assert_eq!(got[0].location.line, 2);
+        assert_eq!(got[0].location.column, 10);
+        assert_eq!(got[0].text, "");
+        assert_eq!(got[0].missing, Some("identifier_ref"));
+
+        assert_eq!(got[1].location.line, 5);
+        assert_eq!(got[1].location.column, 1);
+        assert_eq!(got[1].text, "Z == )");
+        assert_eq!(got[1].missing, None);
+        assert_eq!(&input[got[1].bytes.clone()], "Z == )");
+
+        let parsed = ParsedFile::new("---- MODULE A ----\nX == 1\n====\n").unwrap();
+        assert!(parsed.syntax_errors().is_empty());
+    }
+}
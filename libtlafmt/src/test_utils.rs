@@ -1,22 +1,42 @@
 /// Parse and format the macro string argument and generate a insta snapshot
 /// assertion against it in the name of the caller.
+///
+/// If the `TLAFMT_SNAPSHOT_RULES` environment variable is set, the style rules
+/// that shaped the output are recorded in the snapshot description.
 #[macro_export]
 macro_rules! assert_rewrite {
     ($input:expr) => {{
+        assert_rewrite!($input, $crate::FormatOptions::default())
+    }};
+    ($input:expr, $options:expr) => {{
+        let options = $options;
+
         let mut buf = Vec::new();
-        $crate::ParsedFile::new($input)
+        let rules = $crate::ParsedFile::new($input)
             .expect("parse AST")
-            .format(&mut buf)
+            .format_with_rules(&options, &mut buf)
             .expect("format AST");
 
         let output = String::from_utf8(buf).expect("valid utf8");
-        ::insta::assert_snapshot!(output);
+        match ::std::env::var_os("TLAFMT_SNAPSHOT_RULES") {
+            Some(_) => {
+                let description = rules
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                ::insta::with_settings!({ description => format!("rules: {description}") }, {
+                    ::insta::assert_snapshot!(output);
+                });
+            }
+            None => ::insta::assert_snapshot!(output),
+        }
 
         // Output must be idempotent.
         let mut buf = Vec::new();
         $crate::ParsedFile::new(&output)
             .expect("parse AST")
-            .format(&mut buf)
+            .format_with(&options, &mut buf)
             .expect("format AST");
         let output2 = String::from_utf8(buf).expect("valid utf8");
         ::pretty_assertions::assert_eq!(output, output2, "non-idempotent formatting");
@@ -1,5 +1,10 @@
 use tree_sitter::Node;
 
+use crate::{
+    helpers::{column_at, width},
+    OperatorSpacing,
+};
+
 /// Positional metadata for a token.
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub(crate) enum Position {
@@ -28,12 +33,13 @@ impl Position {
     }
 }
 
-impl From<&Node<'_>> for Position {
-    fn from(n: &Node<'_>) -> Self {
-        let pos = n.start_position();
+impl Position {
+    /// Returns the source position of `n`, with the display column of its
+    /// start within `input`.
+    pub(crate) fn of(n: &Node<'_>, input: &str) -> Self {
         Self::Source {
-            row: pos.row,
-            col: pos.column,
+            row: n.start_position().row,
+            col: column_at(input, n.start_byte()),
         }
     }
 }
@@ -46,8 +52,15 @@ pub(crate) enum Token<'a> {
     /// NOTE: this str may contain newlines.
     Raw(&'a str),
 
-    /// A module header (`--- MODULE name ---`).
-    ModuleHeader(&'a str),
+    /// Source text rendered byte-for-byte, without indentation or the removal
+    /// of trailing whitespace.
+    ///
+    /// NOTE: this str may contain newlines.
+    Verbatim(&'a str),
+
+    /// A module header (`--- MODULE name ---`), optionally with the number of
+    /// dashes to render either side of the name instead of filling the line.
+    ModuleHeader(&'a str, Option<(usize, usize)>),
 
     /// A comment (inline or box).
     ///
@@ -60,6 +73,22 @@ pub(crate) enum Token<'a> {
     /// An explicit newline inserted by the formatter.
     Newline,
 
+    /// The start of a group of tokens, ended by [`Token::GroupEnd`], that is
+    /// rendered on one line when it fits within the line width, or broken at
+    /// each of its [`Token::SoftLine`] otherwise.
+    Group(GroupBreak),
+
+    /// The end of the innermost [`Token::Group`].
+    GroupEnd,
+
+    /// A line break rendered only when the innermost [`Token::Group`] does not
+    /// fit within the line width.
+    SoftLine,
+
+    /// The given number of columns of padding, rendered only when the
+    /// innermost [`Token::Group`] is broken.
+    GroupPad(usize),
+
     /// A `CHOOSE` operator.
     KeywordChoose,
 
@@ -255,8 +284,9 @@ pub(crate) enum Token<'a> {
     /// A `/`.
     Divide,
 
-    /// A dividing line composed of `-----` or `=====`.
-    LineDivider(char),
+    /// A dividing line composed of `-----` or `=====`, optionally of a fixed
+    /// length instead of filling the line.
+    LineDivider(char, Option<usize>),
 
     /// A prime var marker `'`.
     Prime,
@@ -296,9 +326,251 @@ pub(crate) enum Token<'a> {
 
     /// A `[Next]_` sequence.
     StepOrStutter(&'a str),
+
+    /// An infix operator with user-configured spacing.
+    InfixOp(&'a str, OperatorSpacing),
+}
+
+/// The placement of the lines broken within a [`Token::Group`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum GroupBreak {
+    /// Broken lines start at the column of the first token in the group.
+    ///
+    /// A group containing a line break after one of its [`Token::SoftLine`] is
+    /// always broken.
+    Align,
+
+    /// As [`GroupBreak::Align`], but only breaking the line before content
+    /// that does not fit on the current line, packing as much of the group on
+    /// each line as fits.
+    Fill,
+
+    /// Broken lines are indented by one level from the line the group starts
+    /// on.
+    Indent,
+}
+
+/// The tokens that are always rendered as the same source text.
+pub(crate) const FIXED_TOKENS: &[Token<'static>] = &[
+    Token::KeywordChoose,
+    Token::KeywordLet,
+    Token::KeywordIn,
+    Token::KeywordUnchanged,
+    Token::KeywordLocal,
+    Token::KeywordInstance,
+    Token::KeywordDomain,
+    Token::KeywordSubset,
+    Token::KeywordIf,
+    Token::KeywordThen,
+    Token::KeywordElse,
+    Token::KeywordCase,
+    Token::KeywordExtends,
+    Token::KeywordConstant,
+    Token::KeywordConstants,
+    Token::KeywordVariable,
+    Token::KeywordVariables,
+    Token::KeywordExcept,
+    Token::KeywordEnabled,
+    Token::KeywordTheorem,
+    Token::KeywordUnion,
+    Token::Exists,
+    Token::CaseBox,
+    Token::CaseArrow,
+    Token::All,
+    Token::SetIn,
+    Token::SetNotIn,
+    Token::And,
+    Token::Or,
+    Token::MapsTo,
+    Token::MapTo,
+    Token::AllMapsTo,
+    Token::ParenOpen,
+    Token::ParenClose,
+    Token::Comma,
+    Token::SemiColon,
+    Token::Plus,
+    Token::Minus,
+    Token::Multiply,
+    Token::Eq,
+    Token::Eq2,
+    Token::NotEq,
+    Token::SubsetEq,
+    Token::Dot,
+    Token::Dots2,
+    Token::At,
+    Token::SquareOpen,
+    Token::SquareClose,
+    Token::CurlyOpen,
+    Token::CurlyClose,
+    Token::AngleOpen,
+    Token::AngleClose,
+    Token::AppendShort,
+    Token::Real,
+    Token::Int,
+    Token::Nat,
+    Token::GreaterThan,
+    Token::GreaterThanEqual,
+    Token::LessThan,
+    Token::LessThanEqual,
+    Token::Not,
+    Token::SetMinus,
+    Token::Divide,
+    Token::Prime,
+    Token::Always,
+    Token::Eventually,
+    Token::Implies,
+    Token::Bang,
+    Token::True,
+    Token::False,
+    Token::WeakFairness,
+    Token::StrongFairness,
+    Token::Union,
+    Token::Intersect,
+    Token::Compose,
+];
+
+/// The canonical source text of a formatter token.
+///
+/// See [`token_table()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenText {
+    /// The name of the token (such as `KeywordChoose`).
+    pub name: String,
+
+    /// The source text rendered for the token (such as `CHOOSE`).
+    pub text: &'static str,
+
+    /// The display width of the rendered text, in columns.
+    pub width: usize,
+}
+
+/// Return the canonical source text and display width of each token that is
+/// always rendered as the same text, such as keywords and operators.
+///
+/// This is the same table used by the formatter when rendering, allowing
+/// external tools (such as syntax highlighters) to remain consistent with the
+/// formatter output.
+///
+/// Tokens whose text depends on the input (identifiers, literals, comments)
+/// or configuration (module headers and dividing lines) are not included.
+pub fn token_table() -> impl Iterator<Item = TokenText> {
+    FIXED_TOKENS.iter().map(|t| {
+        let text = t.fixed_text().expect("fixed tokens have fixed text");
+        TokenText {
+            name: format!("{t:?}"),
+            text,
+            width: width(text),
+        }
+    })
 }
 
 impl Token<'_> {
+    /// Returns the source text always rendered for `self`, or [`None`] if the
+    /// rendered text depends on the token content or configuration.
+    pub(crate) fn fixed_text(&self) -> Option<&'static str> {
+        Some(match self {
+            Self::KeywordChoose => "CHOOSE",
+            Self::KeywordLet => "LET",
+            Self::KeywordIn => "IN",
+            Self::KeywordUnchanged => "UNCHANGED",
+            Self::KeywordLocal => "LOCAL",
+            Self::KeywordInstance => "INSTANCE",
+            Self::KeywordDomain => "DOMAIN",
+            Self::KeywordSubset => "SUBSET",
+            Self::KeywordIf => "IF",
+            Self::KeywordThen => "THEN",
+            Self::KeywordElse => "ELSE",
+            Self::KeywordCase => "CASE",
+            Self::KeywordExtends => "EXTENDS",
+            Self::KeywordConstant => "CONSTANT",
+            Self::KeywordConstants => "CONSTANTS",
+            Self::KeywordVariable => "VARIABLE",
+            Self::KeywordVariables => "VARIABLES",
+            Self::KeywordExcept => "EXCEPT",
+            Self::KeywordEnabled => "ENABLED",
+            Self::KeywordTheorem => "THEOREM",
+            Self::KeywordUnion => "UNION",
+            Self::Exists => r"\E",
+            Self::CaseBox => "[]",
+            Self::CaseArrow => "->",
+            Self::All => r"\A",
+            Self::SetIn => r"\in",
+            Self::SetNotIn => r"\notin",
+            Self::And => r"/\",
+            Self::Or => r"\/",
+            Self::MapsTo => "->",
+            Self::MapTo => ":>",
+            Self::AllMapsTo => "|->",
+            Self::ParenOpen => "(",
+            Self::ParenClose => ")",
+            Self::Comma => ",",
+            Self::SemiColon => ":",
+            Self::Plus => "+",
+            Self::Minus => "-",
+            Self::Multiply => "*",
+            Self::Eq => "=",
+            Self::Eq2 => "==",
+            Self::NotEq => "/=",
+            Self::SubsetEq => r"\subseteq",
+            Self::Dot => ".",
+            Self::Dots2 => "..",
+            Self::At => "@",
+            Self::SquareOpen => "[",
+            Self::SquareClose => "]",
+            Self::CurlyOpen => "{",
+            Self::CurlyClose => "}",
+            Self::AngleOpen => "<<",
+            Self::AngleClose => ">>",
+            Self::AppendShort => r"\o",
+            Self::Real => r"Real",
+            Self::Int => r"Int",
+            Self::Nat => r"Nat",
+            Self::GreaterThan => ">",
+            Self::GreaterThanEqual => ">=",
+            Self::LessThan => "<",
+            Self::LessThanEqual => "<=",
+            Self::Not => "~",
+            Self::SetMinus => r"\",
+            Self::Divide => r"/",
+            Self::Prime => "'",
+            Self::Always => "[]",
+            Self::Eventually => "<>",
+            Self::Implies => "=>",
+            Self::Bang => "!",
+            Self::True => "TRUE",
+            Self::False => "FALSE",
+            Self::WeakFairness => "WF_",
+            Self::StrongFairness => "SF_",
+            Self::Union => r"\union",
+            Self::Intersect => r"\intersect",
+            Self::Compose => "@@",
+            Self::Raw(_)
+            | Self::Verbatim(_)
+            | Self::ModuleHeader(..)
+            | Self::Comment(..)
+            | Self::SourceNewline
+            | Self::Newline
+            | Self::Group(_)
+            | Self::GroupEnd
+            | Self::SoftLine
+            | Self::GroupPad(_)
+            | Self::Ident(_)
+            | Self::Lit(_)
+            | Self::LineDivider(..)
+            | Self::StepOrStutter(_)
+            | Self::InfixOp(..) => return None,
+        })
+    }
+
+    /// Returns true if `self` only directs the layout of other tokens, and is
+    /// never rendered itself.
+    pub(crate) fn is_layout(&self) -> bool {
+        matches!(
+            self,
+            Self::Group(_) | Self::GroupEnd | Self::SoftLine | Self::GroupPad(_)
+        )
+    }
+
     /// Returns true when `self` and `next` are allowed to appear in sequence.
     ///
     /// If false, the caller is expected to drop `self` when rendering.
@@ -313,7 +585,7 @@ impl Token<'_> {
     /// character when rendered.
     pub(crate) fn delimiting_space_len(&self, next: &Self) -> usize {
         match (self, next) {
-            (_, Token::ModuleHeader(_)) => 0,
+            (_, Token::ModuleHeader(..)) => 0,
 
             // Comments with explicit whitespace padding render the provided
             // amount of space.
@@ -323,9 +595,19 @@ impl Token<'_> {
             (Token::Newline | Token::SourceNewline, _) => 0,
             (_, Token::Newline | Token::SourceNewline) => 0,
 
-            (Token::Raw(s), _) if s.ends_with("\n") => 0,
-            (Token::Raw(_), _) => 1,
-            (_, Token::Raw(_)) => 1,
+            (Token::Raw(s) | Token::Verbatim(s), _) if s.ends_with("\n") => 0,
+
+            // Infix operators with user-configured spacing.
+            (Token::InfixOp(_, OperatorSpacing::Tight), _) => 0,
+            (_, Token::InfixOp(_, OperatorSpacing::Tight)) => 0,
+            (Token::InfixOp(..), _) | (_, Token::InfixOp(..)) => 1,
+
+            // The name following the `!` of a qualified `Inst!Op` reference,
+            // or the `!.field` / `![x]` of an EXCEPT.
+            (Token::Bang, _) => 0,
+
+            (Token::Raw(_) | Token::Verbatim(_), _) => 1,
+            (_, Token::Raw(_) | Token::Verbatim(_)) => 1,
 
             // These tokens can never be followed by a space, irrespective of
             // the next token.
@@ -337,14 +619,16 @@ impl Token<'_> {
                 | Token::SquareClose
                 | Token::CurlyClose
                 | Token::AngleClose
-                | Token::At
-                | Token::Bang,
+                | Token::At,
                 Token::Dot | Token::SquareOpen,
             ) => 0,
 
             // No space between Fn[application].
             (Token::Ident(_), Token::SquareOpen) => 0,
 
+            // The prefix of a qualified `Inst!Op` reference.
+            (Token::Ident(_) | Token::ParenClose, Token::Bang) => 0,
+
             (Token::AngleOpen, Token::AngleClose) => 0,
 
             // A `record.field` sequence.
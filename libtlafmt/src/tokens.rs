@@ -0,0 +1,100 @@
+//! A read-only view of the formatter tokens lowered from the AST, for tools
+//! building on the formatter's own lowering (such as syntax highlighters).
+
+use crate::{
+    ast_format::format_node, helpers::EmptyLines, modeline, renderer::Renderer, token::Token,
+    Error, FormatOptions, ParsedFile,
+};
+
+/// A formatter token lowered from the AST, see [`ParsedFile::tokens()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoweredToken<'a> {
+    /// The name of the token (such as `KeywordChoose` or `Ident`), as reported
+    /// by [`token_table()`](crate::token_table()) for fixed tokens.
+    pub name: String,
+
+    /// The source text of the token, or [`None`] for layout tokens (such as
+    /// newlines) and tokens rendered according to the configuration (module
+    /// headers and dividing lines).
+    pub text: Option<&'a str>,
+
+    /// The depth of indentation in effect when the token is emitted, in
+    /// levels, applied to any line started after the token.
+    pub indent: usize,
+}
+
+impl ParsedFile<'_> {
+    /// Lower the AST into the formatter tokens that would be rendered with
+    /// `options`, in output order.
+    ///
+    /// The stream reflects the formatter's layout decisions (such as inserted
+    /// newlines and indentation) before rendering - adjustments made while
+    /// rendering, such as aligning end-of-line comments, are not included.
+    pub fn tokens(
+        &self,
+        options: &FormatOptions,
+    ) -> Result<impl Iterator<Item = LoweredToken<'_>>, Error> {
+        let options = modeline::apply(&self.input, options.clone())?;
+
+        let mut renderer = Renderer::new(std::io::sink(), options);
+        let mut empty_lines = EmptyLines::default();
+        format_node(
+            self.t.root_node(),
+            &self.input,
+            &mut empty_lines,
+            &mut renderer,
+        )?;
+
+        Ok(renderer
+            .into_tokens()
+            .into_iter()
+            .map(|(t, indent)| LoweredToken {
+                name: name(&t),
+                text: text(&t),
+                indent: indent.get() as usize,
+            }))
+    }
+}
+
+/// Returns the name of the `Token` variant.
+fn name(t: &Token<'_>) -> String {
+    let debug = format!("{t:?}");
+    match debug.split_once('(') {
+        Some((name, _)) => name.to_string(),
+        None => debug,
+    }
+}
+
+/// Returns the source text of `t`, if any.
+fn text<'a>(t: &Token<'a>) -> Option<&'a str> {
+    match t {
+        Token::Raw(v)
+        | Token::Verbatim(v)
+        | Token::Comment(v, _)
+        | Token::Ident(v)
+        | Token::Lit(v)
+        | Token::StepOrStutter(v)
+        | Token::InfixOp(v, _) => Some(v),
+        _ => t.fixed_text(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokens() {
+        let input = "---- MODULE A ----\nOp == /\\ x \\* bananas\n      /\\ CHOOSE y : TRUE\n====";
+        let parsed = ParsedFile::new(input).unwrap();
+
+        let got = parsed
+            .tokens(&FormatOptions::default())
+            .unwrap()
+            .filter(|v| v.text.is_some())
+            .map(|v| (v.name, v.text.unwrap(), v.indent))
+            .collect::<Vec<_>>();
+
+        insta::assert_debug_snapshot!(got);
+    }
+}
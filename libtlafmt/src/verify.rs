@@ -0,0 +1,324 @@
+//! Verification that formatting preserved the content of the spec.
+
+use std::collections::HashMap;
+
+use tree_sitter::{Node, Point};
+
+use crate::{
+    ast_format::{into_output_token, is_collapsible},
+    booleans, parser, Error, FormatOptions, Location, ParsedFile,
+};
+
+/// Node kinds that carry no meaning in the spec, and whose content is not
+/// compared.
+const TRIVIA: &[&str] = &["comment", "block_comment", "extramodular_text"];
+
+/// Re-parse the rendered `output` of `parsed` and compare its tokens against
+/// those of the input, returning [`Error::Verification`] at the first token
+/// that differs.
+///
+/// Comments, whitespace, the length of dividing lines and the spelling of
+/// synonymous operators (such as `#` and `/=`) are ignored, and the changes
+/// requested by `options` (such as normalising the casing of booleans) are
+/// expected. When record fields are sorted, the fields of each record are
+/// compared regardless of their order.
+///
+/// Input containing syntax errors is not verified, as the erroneous source has
+/// no token structure to compare.
+pub(crate) fn check(
+    parsed: &ParsedFile<'_>,
+    output: &str,
+    options: &FormatOptions,
+) -> Result<(), Error> {
+    check_renamed(parsed, output, options, HashMap::new())
+}
+
+/// As [`check()`], expecting the identifiers starting at the byte offsets in
+/// `renames` to be rendered with their new names.
+pub(crate) fn check_renamed(
+    parsed: &ParsedFile<'_>,
+    output: &str,
+    options: &FormatOptions,
+    mut renames: HashMap<usize, &str>,
+) -> Result<(), Error> {
+    if parsed.has_syntax_errors() {
+        return Ok(());
+    }
+
+    if options.normalise_booleans() {
+        for (offset, name) in booleans::normalise(parsed.t.root_node(), &parsed.input, &mut vec![])
+        {
+            renames.entry(offset).or_insert(name);
+        }
+    }
+
+    let mut want = vec![];
+    let collapse = options.collapse_single_item_lists();
+    let sort_fields = options.sort_record_fields();
+    collect(
+        parsed.t.root_node(),
+        &parsed.input,
+        &renames,
+        collapse,
+        sort_fields,
+        &mut want,
+    );
+
+    let tree = parser()
+        .parse(output, None)
+        .ok_or_else(|| Error::Parse(Location::at(output, Point::default())))?;
+    let mut got = vec![];
+    collect(
+        tree.root_node(),
+        output,
+        &HashMap::new(),
+        collapse,
+        sort_fields,
+        &mut got,
+    );
+
+    let end = Point::new(parsed.input.split('\n').count() - 1, usize::MAX);
+    for i in 0..want.len().max(got.len()) {
+        match (want.get(i), got.get(i)) {
+            (Some(a), Some(b)) if a.0 == b.0 => continue,
+            (Some((_, at)), _) => {
+                return Err(Error::Verification(Location::at(&parsed.input, *at)))
+            }
+            (None, _) => return Err(Error::Verification(Location::at(&parsed.input, end))),
+        }
+    }
+
+    Ok(())
+}
+
+/// Format `output` a second time with `options`, returning
+/// [`Error::NotIdempotent`] at the first character of `output` that changes.
+pub(crate) fn check_idempotent(output: &str, options: &FormatOptions) -> Result<(), Error> {
+    let mut buf = Vec::with_capacity(output.len());
+    ParsedFile::new(output)?.render(options.clone(), &mut buf)?;
+    let again = String::from_utf8_lossy(&buf);
+
+    let Some(at) = first_difference(output, &again) else {
+        return Ok(());
+    };
+
+    let row = output[..at].matches('\n').count();
+    let column = at - output[..at].rfind('\n').map_or(0, |v| v + 1);
+    Err(Error::NotIdempotent(Location::at(
+        output,
+        Point::new(row, column),
+    )))
+}
+
+/// Returns the byte offset of the first character in `a` that differs from
+/// `b`, or [`None`] if they are equal.
+fn first_difference(a: &str, b: &str) -> Option<usize> {
+    if a == b {
+        return None;
+    }
+
+    Some(
+        a.char_indices()
+            .zip(b.chars())
+            .find(|((_, a), b)| a != b)
+            .map_or(a.len().min(b.len()), |((i, _), _)| i),
+    )
+}
+
+/// Push the normalised text and start position of each non-trivia leaf within
+/// `node` to `out`, replacing the text of any leaf starting at a byte offset in
+/// `renames`.
+///
+/// If `collapse` is true, the bullets of single item lists are skipped. If
+/// `sort_fields` is true, the fields of each record literal and set of records
+/// are pushed in a canonical order, as the formatter may reorder them.
+fn collect(
+    node: Node<'_>,
+    input: &str,
+    renames: &HashMap<usize, &str>,
+    collapse: bool,
+    sort_fields: bool,
+    out: &mut Vec<(String, Point)>,
+) {
+    if TRIVIA.contains(&node.kind()) || node.is_missing() {
+        return;
+    }
+    if collapse
+        && matches!(node.kind(), "bullet_conj" | "bullet_disj")
+        && node.parent().is_some_and(|v| is_collapsible(&v))
+    {
+        return;
+    }
+
+    if node.child_count() == 0 {
+        let text = match renames.get(&node.start_byte()) {
+            Some(v) => v.to_string(),
+            None => normalise(&node, input),
+        };
+        out.push((text, node.start_position()));
+        return;
+    }
+
+    let mut c = node.walk();
+    if !sort_fields || !matches!(node.kind(), "record_literal" | "set_of_records") {
+        for child in node.children(&mut c) {
+            collect(child, input, renames, collapse, sort_fields, out);
+        }
+        return;
+    }
+
+    // Split the tokens of the record at its delimiters, leaving an (empty)
+    // group before "[" and after "]", with a group for each field between.
+    let mut groups = vec![vec![]];
+    let mut delimiters = vec![];
+    for child in node.children(&mut c) {
+        match child.kind() {
+            "[" | "," | "]" => {
+                collect(
+                    child,
+                    input,
+                    renames,
+                    collapse,
+                    sort_fields,
+                    &mut delimiters,
+                );
+                groups.push(vec![]);
+            }
+            _ => {
+                let group = groups.last_mut().expect("group");
+                collect(child, input, renames, collapse, sort_fields, group);
+            }
+        }
+    }
+
+    let n = groups.len();
+    if n > 2 {
+        groups[1..n - 1].sort_by(|a, b| a.iter().map(|v| &v.0).cmp(b.iter().map(|v| &v.0)));
+    }
+
+    let mut delimiters = delimiters.into_iter();
+    for group in groups {
+        out.extend(group);
+        out.extend(delimiters.next());
+    }
+}
+
+/// Returns the text of the leaf `node`, in the spelling rendered by the
+/// formatter for fixed tokens, with runs of whitespace collapsed to a single
+/// space and dividing lines truncated to a fixed length.
+fn normalise(node: &Node<'_>, input: &str) -> String {
+    // Operators are wrapped in a named node of the operator kind.
+    let fixed = node
+        .parent()
+        .filter(|v| v.child_count() == 1)
+        .and_then(|v| into_output_token(&v, input))
+        .or_else(|| into_output_token(node, input))
+        .and_then(|v| v.fixed_text());
+    if let Some(v) = fixed {
+        return v.to_string();
+    }
+
+    let text = &input[node.byte_range()];
+    if text.len() >= 4 && (text.bytes().all(|b| b == b'-') || text.bytes().all(|b| b == b'=')) {
+        return text[..4].to_string();
+    }
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check() {
+        let input = "---- MODULE A ----\n\\* bananas\nX == /\\ a\n     /\\ b\n========\n";
+        let parsed = ParsedFile::new(input).unwrap();
+        let options = FormatOptions::default();
+
+        // Layout, comments and divider lengths may change.
+        check(
+            &parsed,
+            "---- MODULE A ----\nX == /\\ a /\\ b\n====",
+            &options,
+        )
+        .unwrap();
+
+        // Tokens may not.
+        let got = check(&parsed, "---- MODULE A ----\nX == /\\ a\n====\n", &options);
+        let Err(Error::Verification(location)) = got else {
+            panic!("unexpected result {got:?}");
+        };
+        assert_eq!(location.line, 4);
+        assert_eq!(location.excerpt, "/\\ b");
+    }
+
+    #[test]
+    fn test_check_normalised_booleans() {
+        let input = "---- MODULE A ----\nX == True\n====\n";
+        let parsed = ParsedFile::new(input).unwrap();
+
+        let output = "---- MODULE A ----\nX == TRUE\n====\n";
+        let options = FormatOptions::default().with_normalise_booleans(true);
+        check(&parsed, output, &options).unwrap();
+
+        assert!(matches!(
+            check(&parsed, output, &FormatOptions::default()),
+            Err(Error::Verification(_))
+        ));
+    }
+
+    #[test]
+    fn test_check_sorted_record_fields() {
+        let input = "---- MODULE A ----\nX == [b |-> 1, a |-> 2]\nY == 3\n====\n";
+        let parsed = ParsedFile::new(input).unwrap();
+        let options = FormatOptions::default().with_sort_record_fields(true);
+
+        // The fields of a record may be reordered.
+        check(
+            &parsed,
+            "---- MODULE A ----\nX == [a |-> 2, b |-> 1]\nY == 3\n====\n",
+            &options,
+        )
+        .unwrap();
+
+        // But tokens outside of it may not.
+        let got = check(
+            &parsed,
+            "---- MODULE A ----\nY == 3\nX == [a |-> 2, b |-> 1]\n====\n",
+            &options,
+        );
+        let Err(Error::Verification(location)) = got else {
+            panic!("unexpected result {got:?}");
+        };
+        assert_eq!(location.line, 2);
+        assert_eq!(location.excerpt, "X == [b |-> 1, a |-> 2]");
+
+        // Nor may a value move to another field.
+        let got = check(
+            &parsed,
+            "---- MODULE A ----\nX == [a |-> 1, b |-> 2]\nY == 3\n====\n",
+            &options,
+        );
+        assert!(matches!(got, Err(Error::Verification(_))), "{got:?}");
+    }
+
+    #[test]
+    fn test_check_idempotent() {
+        let options = FormatOptions::default();
+        let input = "---- MODULE A ----\nX ==   1\n====";
+
+        let mut formatted = vec![];
+        ParsedFile::new(input)
+            .unwrap()
+            .format_with(&options, &mut formatted)
+            .unwrap();
+        check_idempotent(&String::from_utf8(formatted).unwrap(), &options).unwrap();
+
+        let got = check_idempotent(input, &options);
+        let Err(Error::NotIdempotent(location)) = got else {
+            panic!("unexpected result {got:?}");
+        };
+        assert_eq!(location.line, 1);
+        assert_eq!(location.column, 5);
+    }
+}
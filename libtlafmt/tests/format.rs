@@ -5,9 +5,21 @@ include!("../src/test_utils.rs"); // Pull in private assert_rewrite!
 use thiserror as _;
 use tree_sitter as _;
 use tree_sitter_tlaplus as _;
+use unicode_width as _;
+
+use std::{collections::BTreeSet, path::Path};
 
 use insta::glob;
-use libtlafmt::ParsedFile;
+use libtlafmt::{grammar_node_kinds, ParsedFile};
+
+/// Only corpus files whose name contains one of these comma-separated
+/// substrings are formatted, if set.
+const FILTER_ENV: &str = "TLAFMT_CORPUS_FILTER";
+
+/// An `INDEX/COUNT` pair (such as `0/4`) selecting the 0-indexed shard of the
+/// corpus to format, allowing the corpus to be split across multiple
+/// concurrent test runs.
+const SHARD_ENV: &str = "TLAFMT_CORPUS_SHARD";
 
 /// Execute the formatter against each file in the TLA spec corpus, and compare
 /// the output against a reference copy.
@@ -15,13 +27,80 @@ use libtlafmt::ParsedFile;
 /// Additionally re-run the formatter against the output of the first run to
 /// ensure stable output between repeated runs.
 ///
+/// The corpus files formatted can be limited with [`FILTER_ENV`] and
+/// [`SHARD_ENV`].
+///
 /// If this test fails, use "cargo insta review" to inspect any output changes.
 #[test]
 fn test_corpus() {
     use std::fs;
 
+    let filter = std::env::var(FILTER_ENV).ok();
+    let shard = std::env::var(SHARD_ENV).ok().map(|v| parse_shard(&v));
+
+    let mut index = 0;
     glob!("../", "tests/corpus/*.tla", |path| {
+        index += 1;
+        if !is_selected(path, index - 1, filter.as_deref(), shard) {
+            return;
+        }
+
         let input = fs::read_to_string(path).expect("read test corpus file");
         assert_rewrite!(&input);
     });
 }
+
+/// Record the node kinds of the TLA+ grammar that never appear in the corpus.
+///
+/// Kinds missing from the corpus are never exercised by [`test_corpus()`] - a
+/// change to this snapshot shows the coverage of the grammar changed. Use
+/// "tlafmt snapshots coverage --stubs DIR" to generate inputs for them.
+#[test]
+fn test_corpus_coverage() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/corpus");
+
+    let mut seen = BTreeSet::new();
+    for entry in std::fs::read_dir(dir).expect("read test corpus dir") {
+        let path = entry.expect("read test corpus dir").path();
+        if path.extension().is_some_and(|v| v == "tla") {
+            let input = std::fs::read_to_string(&path).expect("read test corpus file");
+            seen.extend(ParsedFile::new(&input).unwrap().node_kinds());
+        }
+    }
+
+    let missing = grammar_node_kinds()
+        .difference(&seen)
+        .copied()
+        .collect::<Vec<_>>();
+    insta::assert_snapshot!(missing.join("\n"));
+}
+
+/// Returns true if the corpus file at `path` (the `index`-th file) matches
+/// `filter` and falls within `shard`.
+fn is_selected(
+    path: &Path,
+    index: usize,
+    filter: Option<&str>,
+    shard: Option<(usize, usize)>,
+) -> bool {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+
+    let matches = filter.is_none_or(|v| {
+        v.split(',')
+            .map(str::trim)
+            .any(|v| !v.is_empty() && name.contains(v))
+    });
+
+    matches && shard.is_none_or(|(shard, count)| index % count == shard)
+}
+
+/// Parse an `INDEX/COUNT` shard specification.
+fn parse_shard(v: &str) -> (usize, usize) {
+    let parse = || {
+        let (index, count) = v.split_once('/')?;
+        let (index, count) = (index.parse().ok()?, count.parse().ok()?);
+        (index < count).then_some((index, count))
+    };
+
+    parse().unwrap_or_else(|| panic!("invalid {SHARD_ENV} {v:?} (expected INDEX/COUNT)"))
+}
@@ -0,0 +1,118 @@
+//! A JSON-lines protocol for formatting many in-memory specs in one process.
+//!
+//! Each line read from the input is a JSON object describing a single spec:
+//!
+//! ```text
+//! {"path": "specs/bananas.tla", "content": "---- MODULE bananas ----\n..."}
+//! ```
+//!
+//! The `path` is used only to discover the applicable `tlafmt.toml` and to
+//! identify the response - the file is never read or written.
+//!
+//! For each request, a single line is wrote to the output containing either
+//! the formatted `content`, or an `error` describing why it could not be
+//! formatted:
+//!
+//! ```text
+//! {"path": "specs/bananas.tla", "content": "---- MODULE bananas ----\n..."}
+//! {"path": "specs/platanos.tla", "error": "formatting error: ..."}
+//! ```
+//!
+//! Responses are flushed as each request is processed, allowing editor plugins
+//! to keep a single process running (as `tlafmt --daemon`) and send a request
+//! each time a spec is saved, rather than starting a new process each time.
+
+use std::{
+    io::{BufRead, Write},
+    path::Path,
+};
+
+use libtlafmt::Formatter;
+use serde::{Deserialize, Serialize};
+
+use crate::{discover_config, Error, FormatArgs};
+
+/// A single spec to be formatted.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Request {
+    path: String,
+    content: String,
+}
+
+/// The result of formatting a single [`Request`].
+#[derive(Debug, Serialize)]
+struct Response<'a> {
+    /// The path of the request, or [`None`] if the request was malformed.
+    path: Option<&'a str>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Read requests from `input` until EOF, writing a response line for each to
+/// `out`.
+///
+/// Errors formatting an individual request are reported in the response for
+/// that request - only I/O errors reading `input` or writing `out` are
+/// returned.
+pub(crate) fn run<R, W>(input: R, mut out: W, format: &FormatArgs) -> Result<(), Error>
+where
+    R: BufRead,
+    W: Write,
+{
+    // Reuse the same parser for every request.
+    let formatter = Formatter::default();
+
+    for line in input.lines() {
+        let line = line.map_err(Error::ReadFile)?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let resp = match serde_json::from_str::<Request>(&line) {
+            Ok(req) => {
+                let (content, error) = match format_request(&formatter, &req, format) {
+                    Ok(v) => (Some(v), None),
+                    Err(e) => (None, Some(e.to_string())),
+                };
+                serde_json::to_string(&Response {
+                    path: Some(&req.path),
+                    content,
+                    error,
+                })
+            }
+            Err(e) => serde_json::to_string(&Response {
+                path: None,
+                content: None,
+                error: Some(format!("invalid request: {e}")),
+            }),
+        }
+        .expect("responses always serialise");
+
+        // Flush each response so that callers can stream requests.
+        writeln!(out, "{resp}").map_err(Error::Write)?;
+        out.flush().map_err(Error::Write)?;
+    }
+
+    Ok(())
+}
+
+/// Format the spec in `req`, parsing it with `formatter`.
+fn format_request(
+    formatter: &Formatter,
+    req: &Request,
+    format: &FormatArgs,
+) -> Result<String, Error> {
+    let options = format.format_options(&discover_config(Some(Path::new(&req.path)))?);
+
+    let mut buf = Vec::with_capacity(req.content.len());
+    formatter
+        .parse(&req.content)?
+        .format_with(&options, &mut buf)?;
+
+    Ok(String::from_utf8(buf)?)
+}
@@ -0,0 +1,173 @@
+//! Helpers for excluding repository-wide formatting commits from `git blame`.
+
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use libtlafmt::ParsedFile;
+use serde::Serialize;
+
+use crate::{discover_config, Error, FormatArgs};
+
+/// The file extension of TLA+ specs.
+pub(crate) const SPEC_EXTENSION: &str = "tla";
+
+/// The metadata of a single commit.
+#[derive(Debug)]
+struct Commit {
+    hash: String,
+    author: String,
+    date: String,
+    subject: String,
+}
+
+/// A machine-readable summary of the specs changed by a commit.
+#[derive(Debug, Serialize)]
+struct Manifest<'a> {
+    commit: &'a str,
+    files: Vec<ManifestFile>,
+}
+
+/// A single spec changed by a commit.
+#[derive(Debug, Serialize)]
+struct ManifestFile {
+    path: String,
+
+    /// True if formatting the spec before the commit produces the spec after
+    /// the commit.
+    formatting_only: bool,
+}
+
+/// Print a `.git-blame-ignore-revs` entry for the commit `rev` to stdout.
+///
+/// If `annotate` is provided, a JSON manifest of the specs changed by `rev`
+/// (and whether each was changed purely by formatting) is wrote to it.
+pub(crate) fn ignore_revs(
+    rev: &str,
+    annotate: Option<&Path>,
+    format: &FormatArgs,
+) -> Result<(), Error> {
+    let commit = Commit::load(rev)?;
+
+    if let Some(path) = annotate {
+        let root = PathBuf::from(git(&["rev-parse", "--show-toplevel"])?.trim());
+        let manifest = Manifest {
+            commit: &commit.hash,
+            files: changed_specs(&commit.hash)?
+                .into_iter()
+                .map(|path| {
+                    Ok(ManifestFile {
+                        formatting_only: is_formatting_only(&commit.hash, &root, &path, format)?,
+                        path,
+                    })
+                })
+                .collect::<Result<_, Error>>()?,
+        };
+
+        let json = serde_json::to_string_pretty(&manifest).expect("manifest always serialises");
+        std::fs::write(path, json + "\n").map_err(Error::Write)?;
+    }
+
+    let mut out = std::io::stdout().lock();
+    writeln!(
+        out,
+        "# {} ({}, {})\n{}",
+        commit.subject, commit.author, commit.date, commit.hash
+    )
+    .map_err(Error::Write)
+}
+
+impl Commit {
+    /// Resolve and read the metadata of the commit `rev`.
+    fn load(rev: &str) -> Result<Self, Error> {
+        let out = git(&[
+            "log",
+            "-1",
+            "--date=short",
+            "--format=%H%n%an%n%ad%n%s",
+            rev,
+            "--",
+        ])?;
+
+        let mut lines = out.lines().map(str::to_string);
+        let mut next = || {
+            lines
+                .next()
+                .ok_or_else(|| Error::Git(format!("cannot read commit {rev}")))
+        };
+
+        Ok(Self {
+            hash: next()?,
+            author: next()?,
+            date: next()?,
+            subject: next()?,
+        })
+    }
+}
+
+/// Return the paths of the specs modified by the commit `hash`, relative to
+/// the repository root.
+fn changed_specs(hash: &str) -> Result<Vec<String>, Error> {
+    let out = git(&[
+        "diff-tree",
+        "--no-commit-id",
+        "--name-only",
+        "--diff-filter=M",
+        "-r",
+        hash,
+    ])?;
+
+    Ok(out
+        .lines()
+        .filter(|v| {
+            Path::new(v)
+                .extension()
+                .is_some_and(|v| v == SPEC_EXTENSION)
+        })
+        .map(str::to_string)
+        .collect())
+}
+
+/// Returns true if formatting the spec at `path` (relative to the repository
+/// `root`) as of the parent of `hash` produces the content of the spec as of
+/// `hash`.
+fn is_formatting_only(
+    hash: &str,
+    root: &Path,
+    path: &str,
+    format: &FormatArgs,
+) -> Result<bool, Error> {
+    let before = git(&["show", &format!("{hash}^:{path}")])?;
+    let after = git(&["show", &format!("{hash}:{path}")])?;
+
+    let options = format.format_options(&discover_config(Some(&root.join(path)))?);
+
+    // Specs that cannot be formatted were not changed by the formatter.
+    let Ok(parsed) = ParsedFile::new(&before) else {
+        return Ok(false);
+    };
+    let mut buf = Vec::with_capacity(before.len());
+    if parsed.format_with(&options, &mut buf).is_err() {
+        return Ok(false);
+    }
+
+    Ok(buf.trim_ascii() == after.trim_ascii().as_bytes())
+}
+
+/// Run `git` with `args`, returning the stdout.
+pub(crate) fn git(args: &[&str]) -> Result<String, Error> {
+    let out = Command::new("git")
+        .args(args)
+        .output()
+        .map_err(|e| Error::Git(e.to_string()))?;
+
+    if !out.status.success() {
+        return Err(Error::Git(
+            String::from_utf8_lossy(&out.stderr).trim().to_string(),
+        ));
+    }
+
+    Ok(String::from_utf8(out.stdout)?)
+}
@@ -0,0 +1,107 @@
+//! An on-disk cache of the specs known to be formatted, allowing repeat runs to
+//! skip unchanged specs.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use libtlafmt::FormatOptions;
+use serde::{Deserialize, Serialize};
+
+use crate::Error;
+
+/// The default path of the cache file, relative to the current directory.
+pub(crate) const DEFAULT_CACHE_PATH: &str = ".tlafmt-cache";
+
+/// The specs known to be formatted, keyed by absolute path.
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct Entries {
+    /// The version of tlafmt that wrote the cache, as a different version may
+    /// format specs differently.
+    version: String,
+
+    /// The hash of the formatted content and options of each spec.
+    specs: BTreeMap<PathBuf, String>,
+}
+
+/// An on-disk cache of formatted specs.
+#[derive(Debug)]
+pub(crate) struct Cache {
+    path: PathBuf,
+    entries: Entries,
+    dirty: bool,
+}
+
+impl Cache {
+    /// Read the cache file at `path`, starting from an empty cache if it does
+    /// not exist, cannot be read, or was wrote by a different version.
+    pub(crate) fn load(path: &Path) -> Self {
+        let entries = std::fs::read(path)
+            .ok()
+            .and_then(|v| serde_json::from_slice::<Entries>(&v).ok())
+            .filter(|v| v.version == env!("CARGO_PKG_VERSION"))
+            .unwrap_or_else(|| Entries {
+                version: env!("CARGO_PKG_VERSION").to_string(),
+                specs: BTreeMap::new(),
+            });
+
+        Self {
+            path: path.to_path_buf(),
+            entries,
+            dirty: false,
+        }
+    }
+
+    /// Returns true if `content` of the spec at `path` is known to be
+    /// formatted with `options`.
+    pub(crate) fn is_formatted(
+        &self,
+        path: &Path,
+        content: &[u8],
+        options: &FormatOptions,
+    ) -> bool {
+        std::path::absolute(path).is_ok_and(|path| {
+            self.entries
+                .specs
+                .get(&path)
+                .is_some_and(|v| *v == hash(content, options))
+        })
+    }
+
+    /// Record `content` of the spec at `path` as formatted with `options`.
+    pub(crate) fn insert(&mut self, path: &Path, content: &[u8], options: &FormatOptions) {
+        if let Ok(path) = std::path::absolute(path) {
+            self.entries.specs.insert(path, hash(content, options));
+            self.dirty = true;
+        }
+    }
+
+    /// Write the cache back to disk, if it has changed.
+    pub(crate) fn save(&self) -> Result<(), Error> {
+        if !self.dirty {
+            return Ok(());
+        }
+
+        let json = serde_json::to_vec(&self.entries).expect("cache always serialises");
+        std::fs::write(&self.path, json).map_err(|e| Error::Cache(self.path.clone(), e))
+    }
+}
+
+/// Return the 64-bit FNV-1a hash of `content` and `options` as a hex string.
+///
+/// Unlike the std hashers, FNV-1a produces the same value across builds,
+/// allowing it to be persisted.
+fn hash(content: &[u8], options: &FormatOptions) -> String {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    let options = format!("{options:?}");
+    let v = content
+        .iter()
+        .chain([0].iter())
+        .chain(options.as_bytes())
+        .fold(OFFSET, |h, b| (h ^ u64::from(*b)).wrapping_mul(PRIME));
+
+    format!("{v:016x}")
+}
@@ -0,0 +1,128 @@
+//! Discovery of the lines changed in a spec, from a unified diff or git.
+
+use std::{
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use crate::{blame::git, Error};
+
+/// Return the 0-indexed lines of the spec at `path` changed since the git
+/// revision `rev`, including uncommitted changes.
+///
+/// All lines of untracked specs are considered changed.
+pub(crate) fn from_git(path: &Path, rev: &str) -> Result<Vec<Range<usize>>, Error> {
+    let dir = match path.parent() {
+        Some(v) if !v.as_os_str().is_empty() => v,
+        _ => Path::new("."),
+    };
+    let dir = dir.to_string_lossy();
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+
+    let untracked = git(&[
+        "-C",
+        &dir,
+        "ls-files",
+        "--others",
+        "--exclude-standard",
+        "--",
+        &name,
+    ])?;
+    if !untracked.trim().is_empty() {
+        return Ok(std::iter::once(0..usize::MAX).collect());
+    }
+
+    let diff = git(&[
+        "-C",
+        &dir,
+        "diff",
+        "-U0",
+        "--no-color",
+        "--no-ext-diff",
+        rev,
+        "--",
+        &name,
+    ])?;
+
+    Ok(parse(&diff).into_iter().flat_map(|(_, v)| v).collect())
+}
+
+/// Return the 0-indexed lines of the spec at `path` changed by the unified
+/// diff read from `diff`.
+///
+/// Paths in the diff are matched against the trailing components of `path`,
+/// allowing diffs relative to the repository root to be used from any
+/// directory.
+pub(crate) fn from_diff(diff: &Path, path: &Path) -> Result<Vec<Range<usize>>, Error> {
+    let diff = std::fs::read_to_string(diff).map_err(Error::ReadFile)?;
+    let path = std::path::absolute(path).map_err(Error::ReadFile)?;
+
+    Ok(parse(&diff)
+        .into_iter()
+        .filter(|(v, _)| path.ends_with(v))
+        .flat_map(|(_, v)| v)
+        .collect())
+}
+
+/// Parse the unified diff `diff` into the 0-indexed lines changed in each new
+/// file.
+///
+/// Lines removed without replacement mark the lines either side of the
+/// removal as changed.
+fn parse(diff: &str) -> Vec<(PathBuf, Vec<Range<usize>>)> {
+    let mut files: Vec<(PathBuf, Vec<Range<usize>>)> = vec![];
+
+    // The number of old and new lines remaining in the current hunk, within
+    // which lines are content rather than headers.
+    let mut remaining: (usize, usize) = (0, 0);
+
+    for line in diff.lines() {
+        if remaining != (0, 0) {
+            match line.as_bytes().first() {
+                Some(b'+') => remaining.1 = remaining.1.saturating_sub(1),
+                Some(b'-') => remaining.0 = remaining.0.saturating_sub(1),
+                Some(b'\\') => {}
+                _ => remaining = (remaining.0.saturating_sub(1), remaining.1.saturating_sub(1)),
+            }
+            continue;
+        }
+
+        if let Some(v) = line.strip_prefix("+++ ") {
+            let v = v.split('\t').next().unwrap_or_default();
+            let v = v.strip_prefix("b/").unwrap_or(v);
+            files.push((PathBuf::from(v), vec![]));
+            continue;
+        }
+
+        let Some(v) = line.strip_prefix("@@ ") else {
+            continue;
+        };
+        let mut ranges = v.split_ascii_whitespace();
+        let (Some(old), Some(new)) = (ranges.next(), ranges.next()) else {
+            continue;
+        };
+        let (Some((_, old_len)), Some((start, new_len))) = (
+            old.strip_prefix('-').map(hunk_range),
+            new.strip_prefix('+').map(hunk_range),
+        ) else {
+            continue;
+        };
+        remaining = (old_len, new_len);
+
+        let lines = match new_len {
+            0 => start.saturating_sub(1)..start + 1,
+            n => start - 1..start - 1 + n,
+        };
+        if let Some((_, v)) = files.last_mut() {
+            v.push(lines);
+        }
+    }
+
+    files
+}
+
+/// Parse a 1-indexed `START[,LEN]` hunk range.
+fn hunk_range(s: &str) -> (usize, usize) {
+    let (start, len) = s.split_once(',').unwrap_or((s, "1"));
+    (start.parse().unwrap_or(1), len.parse().unwrap_or(0))
+}
@@ -0,0 +1,85 @@
+//! Rendering of check results as a checkstyle XML report.
+
+use std::io::Write;
+
+/// The name reported as the source of each checkstyle error.
+const SOURCE: &str = "tlafmt";
+
+/// Write the start of a checkstyle report to `out`.
+pub(crate) fn begin(out: &mut impl Write) -> std::io::Result<()> {
+    writeln!(out, r#"<?xml version="1.0" encoding="utf-8"?>"#)?;
+    writeln!(out, r#"<checkstyle version="4.3">"#)
+}
+
+/// Write the end of a checkstyle report to `out`.
+pub(crate) fn end(out: &mut impl Write) -> std::io::Result<()> {
+    writeln!(out, "</checkstyle>")
+}
+
+/// Write a checkstyle `file` element for `path` to `out`, containing an error
+/// for each run of lines that differ between `old` and `new`.
+pub(crate) fn write_file(
+    out: &mut impl Write,
+    path: &str,
+    old: &str,
+    new: &str,
+) -> std::io::Result<()> {
+    writeln!(out, r#"  <file name="{}">"#, escape(path))?;
+
+    // The 1-indexed input line the next removed line is on.
+    let mut line = 1;
+    let mut change: Option<(usize, Vec<&str>)> = None;
+
+    for diff in diff::lines(old.trim_end(), new.trim_end()) {
+        match diff {
+            diff::Result::Both(..) => {
+                if let Some((line, added)) = change.take() {
+                    write_error(out, line, &added)?;
+                }
+                line += 1;
+            }
+            diff::Result::Left(_) => {
+                change.get_or_insert_with(|| (line, vec![]));
+                line += 1;
+            }
+            diff::Result::Right(v) => change.get_or_insert_with(|| (line, vec![])).1.push(v),
+        }
+    }
+    if let Some((line, added)) = change {
+        write_error(out, line, &added)?;
+    }
+
+    writeln!(out, "  </file>")
+}
+
+/// Write a checkstyle `error` element for a change at `line` that replaces the
+/// input lines with `added`.
+fn write_error(out: &mut impl Write, line: usize, added: &[&str]) -> std::io::Result<()> {
+    let message = match added {
+        [] => "Should be removed".to_string(),
+        v => format!("Should be `{}`", v.join("\n")),
+    };
+
+    writeln!(
+        out,
+        r#"    <error line="{line}" severity="warning" message="{}" source="{SOURCE}" />"#,
+        escape(&message)
+    )
+}
+
+/// Escape `s` for use as an XML attribute value.
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            '\n' => out.push_str("&#10;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
@@ -0,0 +1,446 @@
+//! Discovery and parsing of `tlafmt.toml` project configuration files.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use libtlafmt::{
+    CallWrapping, DividerLength, FinalNewline, FormatOptions, LineEnding, OperatorSpacing,
+    QuantifierListLayout,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::Error;
+
+pub(crate) mod editorconfig;
+
+/// The name of the configuration file searched for.
+pub(crate) const CONFIG_FILE_NAME: &str = "tlafmt.toml";
+
+/// The formatting configuration read from a `tlafmt.toml` file.
+///
+/// All fields are optional, and unset fields retain the default value (or the
+/// value provided by a CLI flag).
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Config {
+    line_width: Option<usize>,
+    indent_width: Option<usize>,
+    max_indent_depth: Option<usize>,
+    hard_tabs: Option<bool>,
+    collapse_single_item_lists: Option<bool>,
+    align_comments: Option<bool>,
+
+    /// Equivalent to a `final_newline` of "always" or "never".
+    #[serde(skip_serializing_if = "Option::is_none")]
+    insert_final_newline: Option<bool>,
+
+    metadata_comment: Option<bool>,
+    align_assumptions: Option<bool>,
+    normalise_booleans: Option<bool>,
+    standard_call_wrapping: Option<bool>,
+    sort_record_fields: Option<bool>,
+    line_wrapping: Option<bool>,
+
+    /// Unset when it defaults to the line width.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    assignment_width: Option<usize>,
+
+    strict: Option<bool>,
+
+    /// The [`DividerLength`] name.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_divider_length",
+        serialize_with = "serialize_divider_length",
+        skip_serializing_if = "Option::is_none"
+    )]
+    divider_length: Option<DividerLength>,
+
+    /// The [`QuantifierListLayout`] name.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_quantifier_list_layout",
+        serialize_with = "serialize_quantifier_list_layout",
+        skip_serializing_if = "Option::is_none"
+    )]
+    quantifier_list_layout: Option<QuantifierListLayout>,
+
+    /// The [`LineEnding`] name.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_line_ending",
+        serialize_with = "serialize_line_ending",
+        skip_serializing_if = "Option::is_none"
+    )]
+    line_ending: Option<LineEnding>,
+
+    /// The [`FinalNewline`] name, taking precedence over
+    /// `insert_final_newline`.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_final_newline",
+        serialize_with = "serialize_final_newline",
+        skip_serializing_if = "Option::is_none"
+    )]
+    final_newline: Option<FinalNewline>,
+
+    /// Prefixes of module-level comments that are never formatted.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    protected_comment_prefixes: Vec<String>,
+
+    /// The record field names sorted before all others, in order.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    record_field_order: Option<Vec<String>>,
+
+    /// A comment block placed above the module header, if missing.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    license_header: Option<String>,
+
+    /// A map of operator symbol to spacing class.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_operator_spacing",
+        serialize_with = "serialize_operator_spacing",
+        skip_serializing_if = "BTreeMap::is_empty"
+    )]
+    operator_spacing: BTreeMap<String, OperatorSpacing>,
+
+    /// A map of operator name to call wrapping hint.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_call_wrapping",
+        serialize_with = "serialize_call_wrapping",
+        skip_serializing_if = "BTreeMap::is_empty"
+    )]
+    call_wrapping: BTreeMap<String, CallWrapping>,
+}
+
+impl Config {
+    /// Construct a [`Config`] with every field set to the value in `opts`.
+    pub(crate) fn from_options(opts: &FormatOptions) -> Self {
+        Self {
+            line_width: Some(opts.line_width()),
+            indent_width: Some(opts.indent_width()),
+            max_indent_depth: Some(opts.max_indent_depth()),
+            hard_tabs: Some(opts.hard_tabs()),
+            collapse_single_item_lists: Some(opts.collapse_single_item_lists()),
+            align_comments: Some(opts.align_comments()),
+            insert_final_newline: None,
+            metadata_comment: Some(opts.metadata_comment()),
+            align_assumptions: Some(opts.align_assumptions()),
+            normalise_booleans: Some(opts.normalise_booleans()),
+            standard_call_wrapping: Some(opts.standard_call_wrapping()),
+            sort_record_fields: Some(opts.sort_record_fields()),
+            line_wrapping: Some(opts.line_wrapping()),
+            assignment_width: opts.assignment_width(),
+            strict: Some(opts.strict()),
+            divider_length: Some(opts.divider_length()),
+            quantifier_list_layout: Some(opts.quantifier_list_layout()),
+            line_ending: Some(opts.line_ending()),
+            final_newline: Some(opts.final_newline()),
+            protected_comment_prefixes: opts
+                .protected_comment_prefixes()
+                .map(str::to_string)
+                .collect(),
+            record_field_order: Some(opts.record_field_order().map(str::to_string).collect())
+                .filter(|v: &Vec<_>| !v.is_empty()),
+            license_header: opts.license_header().map(str::to_string),
+            operator_spacing: opts
+                .operator_spacings()
+                .map(|(op, spacing)| (op.to_string(), spacing))
+                .collect(),
+            call_wrapping: opts
+                .call_wrappings()
+                .map(|(op, v)| (op.to_string(), v))
+                .collect(),
+        }
+    }
+
+    /// Search `dir` and each of its ancestors for a [`CONFIG_FILE_NAME`] file,
+    /// returning the parsed contents and path of the first one found.
+    pub(crate) fn discover(dir: &Path) -> Result<Option<(PathBuf, Self)>, Error> {
+        let dir = std::path::absolute(dir).map_err(|e| Error::ReadConfig(dir.to_path_buf(), e))?;
+
+        for dir in dir.ancestors() {
+            let path = dir.join(CONFIG_FILE_NAME);
+            if path.is_file() {
+                let config = Self::load(&path)?;
+                return Ok(Some((path, config)));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Read and parse the configuration file at `path`.
+    pub(crate) fn load(path: &Path) -> Result<Self, Error> {
+        let s =
+            std::fs::read_to_string(path).map_err(|e| Error::ReadConfig(path.to_path_buf(), e))?;
+
+        toml::from_str(&s).map_err(|e| Error::ParseConfig(path.to_path_buf(), e.to_string()))
+    }
+
+    /// Merge `self` with `fallback`, using the values in `fallback` for any
+    /// fields left unset in `self`.
+    pub(crate) fn or(self, fallback: Self) -> Self {
+        let mut operator_spacing = fallback.operator_spacing;
+        operator_spacing.extend(self.operator_spacing);
+
+        let mut call_wrapping = fallback.call_wrapping;
+        call_wrapping.extend(self.call_wrapping);
+
+        let mut protected_comment_prefixes = fallback.protected_comment_prefixes;
+        protected_comment_prefixes.extend(self.protected_comment_prefixes);
+
+        Self {
+            line_width: self.line_width.or(fallback.line_width),
+            indent_width: self.indent_width.or(fallback.indent_width),
+            max_indent_depth: self.max_indent_depth.or(fallback.max_indent_depth),
+            hard_tabs: self.hard_tabs.or(fallback.hard_tabs),
+            collapse_single_item_lists: self
+                .collapse_single_item_lists
+                .or(fallback.collapse_single_item_lists),
+            align_comments: self.align_comments.or(fallback.align_comments),
+            insert_final_newline: None,
+            metadata_comment: self.metadata_comment.or(fallback.metadata_comment),
+            align_assumptions: self.align_assumptions.or(fallback.align_assumptions),
+            normalise_booleans: self.normalise_booleans.or(fallback.normalise_booleans),
+            standard_call_wrapping: self
+                .standard_call_wrapping
+                .or(fallback.standard_call_wrapping),
+            sort_record_fields: self.sort_record_fields.or(fallback.sort_record_fields),
+            line_wrapping: self.line_wrapping.or(fallback.line_wrapping),
+            assignment_width: self.assignment_width.or(fallback.assignment_width),
+            strict: self.strict.or(fallback.strict),
+            divider_length: self.divider_length.or(fallback.divider_length),
+            quantifier_list_layout: self
+                .quantifier_list_layout
+                .or(fallback.quantifier_list_layout),
+            line_ending: self.line_ending.or(fallback.line_ending),
+            final_newline: self.final_newline().or(fallback.final_newline()),
+            protected_comment_prefixes,
+            record_field_order: self.record_field_order.or(fallback.record_field_order),
+            license_header: self.license_header.or(fallback.license_header),
+            operator_spacing,
+            call_wrapping,
+        }
+    }
+
+    /// The configured [`FinalNewline`], from either `final_newline` or the
+    /// equivalent `insert_final_newline`.
+    fn final_newline(&self) -> Option<FinalNewline> {
+        self.final_newline
+            .or(self.insert_final_newline.map(|v| match v {
+                true => FinalNewline::Always,
+                false => FinalNewline::Never,
+            }))
+    }
+
+    /// Apply the configured values to `opts`.
+    pub(crate) fn apply(&self, mut opts: FormatOptions) -> FormatOptions {
+        if let Some(v) = self.line_width {
+            opts = opts.with_line_width(v);
+        }
+        if let Some(v) = self.indent_width {
+            opts = opts.with_indent_width(v);
+        }
+        if let Some(v) = self.max_indent_depth {
+            opts = opts.with_max_indent_depth(v);
+        }
+        if let Some(v) = self.hard_tabs {
+            opts = opts.with_hard_tabs(v);
+        }
+        if let Some(v) = self.collapse_single_item_lists {
+            opts = opts.with_collapse_single_item_lists(v);
+        }
+        if let Some(v) = self.align_comments {
+            opts = opts.with_align_comments(v);
+        }
+        if let Some(v) = self.metadata_comment {
+            opts = opts.with_metadata_comment(v);
+        }
+        if let Some(v) = self.align_assumptions {
+            opts = opts.with_align_assumptions(v);
+        }
+        if let Some(v) = self.normalise_booleans {
+            opts = opts.with_normalise_booleans(v);
+        }
+        if let Some(v) = self.standard_call_wrapping {
+            opts = opts.with_standard_call_wrapping(v);
+        }
+        if let Some(v) = self.sort_record_fields {
+            opts = opts.with_sort_record_fields(v);
+        }
+        if let Some(v) = self.line_wrapping {
+            opts = opts.with_line_wrapping(v);
+        }
+        if let Some(v) = self.assignment_width {
+            opts = opts.with_assignment_width(v);
+        }
+        if let Some(v) = self.strict {
+            opts = opts.with_strict(v);
+        }
+        if let Some(v) = self.divider_length {
+            opts = opts.with_divider_length(v);
+        }
+        if let Some(v) = self.quantifier_list_layout {
+            opts = opts.with_quantifier_list_layout(v);
+        }
+        if let Some(v) = self.line_ending {
+            opts = opts.with_line_ending(v);
+        }
+        if let Some(v) = self.final_newline() {
+            opts = opts.with_final_newline(v);
+        }
+        if let Some(v) = &self.record_field_order {
+            opts = opts.with_record_field_order(v);
+        }
+        if let Some(v) = &self.license_header {
+            opts = opts.with_license_header(v);
+        }
+
+        let opts = self
+            .protected_comment_prefixes
+            .iter()
+            .fold(opts, |opts, v| opts.with_protected_comment_prefix(v));
+
+        let opts = self
+            .operator_spacing
+            .iter()
+            .fold(opts, |opts, (op, spacing)| {
+                opts.with_operator_spacing(op, *spacing)
+            });
+
+        self.call_wrapping
+            .iter()
+            .fold(opts, |opts, (op, v)| opts.with_call_wrapping(op, *v))
+    }
+}
+
+/// Deserialise a [`DividerLength`] name.
+fn deserialize_divider_length<'de, D>(d: D) -> Result<Option<DividerLength>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let v = String::deserialize(d)?;
+    v.parse().map(Some).map_err(serde::de::Error::custom)
+}
+
+/// Serialise a [`DividerLength`] name.
+fn serialize_divider_length<S>(v: &Option<DividerLength>, s: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    match v {
+        Some(v) => s.serialize_str(&v.to_string()),
+        None => s.serialize_none(),
+    }
+}
+
+/// Deserialise a [`QuantifierListLayout`] name.
+fn deserialize_quantifier_list_layout<'de, D>(
+    d: D,
+) -> Result<Option<QuantifierListLayout>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let v = String::deserialize(d)?;
+    v.parse().map(Some).map_err(serde::de::Error::custom)
+}
+
+/// Serialise a [`QuantifierListLayout`] name.
+fn serialize_quantifier_list_layout<S>(
+    v: &Option<QuantifierListLayout>,
+    s: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    match v {
+        Some(v) => s.serialize_str(&v.to_string()),
+        None => s.serialize_none(),
+    }
+}
+
+/// Deserialise a [`LineEnding`] name.
+fn deserialize_line_ending<'de, D>(d: D) -> Result<Option<LineEnding>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let v = String::deserialize(d)?;
+    v.parse().map(Some).map_err(serde::de::Error::custom)
+}
+
+/// Serialise a [`LineEnding`] name.
+fn serialize_line_ending<S>(v: &Option<LineEnding>, s: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    match v {
+        Some(v) => s.serialize_str(&v.to_string()),
+        None => s.serialize_none(),
+    }
+}
+
+/// Deserialise a [`FinalNewline`] name.
+fn deserialize_final_newline<'de, D>(d: D) -> Result<Option<FinalNewline>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let v = String::deserialize(d)?;
+    v.parse().map(Some).map_err(serde::de::Error::custom)
+}
+
+/// Serialise a [`FinalNewline`] name.
+fn serialize_final_newline<S>(v: &Option<FinalNewline>, s: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    match v {
+        Some(v) => s.serialize_str(&v.to_string()),
+        None => s.serialize_none(),
+    }
+}
+
+/// Deserialise a map of operator symbol to [`OperatorSpacing`] name.
+fn deserialize_operator_spacing<'de, D>(d: D) -> Result<BTreeMap<String, OperatorSpacing>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    BTreeMap::<String, String>::deserialize(d)?
+        .into_iter()
+        .map(|(op, v)| Ok((op, v.parse().map_err(serde::de::Error::custom)?)))
+        .collect()
+}
+
+/// Serialise a map of operator symbol to [`OperatorSpacing`] name.
+fn serialize_operator_spacing<S>(
+    v: &BTreeMap<String, OperatorSpacing>,
+    s: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    s.collect_map(v.iter().map(|(op, spacing)| (op, spacing.to_string())))
+}
+
+/// Deserialise a map of operator name to [`CallWrapping`] name.
+fn deserialize_call_wrapping<'de, D>(d: D) -> Result<BTreeMap<String, CallWrapping>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    BTreeMap::<String, String>::deserialize(d)?
+        .into_iter()
+        .map(|(op, v)| Ok((op, v.parse().map_err(serde::de::Error::custom)?)))
+        .collect()
+}
+
+/// Serialise a map of operator name to [`CallWrapping`] name.
+fn serialize_call_wrapping<S>(v: &BTreeMap<String, CallWrapping>, s: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    s.collect_map(v.iter().map(|(op, v)| (op, v.to_string())))
+}
@@ -0,0 +1,183 @@
+//! Discovery and parsing of [EditorConfig] files.
+//!
+//! Only the subset of properties that map to formatter options is read:
+//!
+//!   * `indent_style`: `tab` enables hard tabs.
+//!   * `indent_size` (or `tab_width` when set to `tab`): the indent width.
+//!   * `max_line_length`: the line width (`off` is ignored).
+//!   * `insert_final_newline`: a final newline policy of always (or never).
+//!   * `end_of_line`: `lf` or `crlf` line endings (`cr` is ignored).
+//!
+//! [EditorConfig]: https://editorconfig.org/
+
+use std::{collections::HashMap, path::Path};
+
+use globset::{GlobBuilder, GlobMatcher};
+use libtlafmt::{FinalNewline, LineEnding};
+
+use super::Config;
+use crate::Error;
+
+/// The name of the EditorConfig file searched for.
+pub(crate) const EDITORCONFIG_FILE_NAME: &str = ".editorconfig";
+
+/// A parsed `.editorconfig` file.
+#[derive(Debug, Default)]
+struct EditorConfig {
+    /// True if the search for files should stop at this file.
+    root: bool,
+    sections: Vec<Section>,
+}
+
+/// A `[glob]` section and the properties it sets.
+#[derive(Debug)]
+struct Section {
+    /// The matcher for the section glob, or [`None`] if the glob is invalid
+    /// (in which case the section never matches).
+    glob: Option<GlobMatcher>,
+    properties: Vec<(String, String)>,
+}
+
+/// Resolve the EditorConfig properties that apply to the file at `path`,
+/// searching the directory of `path` and each of its parents until a file
+/// marked `root = true` is found.
+///
+/// Properties in files closer to `path` take precedence, as do properties in
+/// later sections of the same file.
+pub(crate) fn discover(path: &Path) -> Result<Config, Error> {
+    let path = std::path::absolute(path).map_err(|e| Error::ReadConfig(path.to_path_buf(), e))?;
+
+    let mut files = vec![];
+    for dir in path.ancestors().skip(1) {
+        let file = dir.join(EDITORCONFIG_FILE_NAME);
+        if !file.is_file() {
+            continue;
+        }
+
+        let config = EditorConfig::load(&file)?;
+        let root = config.root;
+        files.push((dir, config));
+        if root {
+            break;
+        }
+    }
+
+    // Apply the files furthest from `path` first, allowing closer files to
+    // override their properties.
+    let mut properties = HashMap::new();
+    for (dir, config) in files.iter().rev() {
+        let Ok(relative) = path.strip_prefix(dir) else {
+            continue;
+        };
+
+        config
+            .sections
+            .iter()
+            .filter(|v| v.glob.as_ref().is_some_and(|g| g.is_match(relative)))
+            .flat_map(|v| v.properties.iter().cloned())
+            .for_each(|(k, v)| {
+                properties.insert(k, v);
+            });
+    }
+
+    Ok(to_config(&properties))
+}
+
+impl EditorConfig {
+    /// Read and parse the file at `path`.
+    fn load(path: &Path) -> Result<Self, Error> {
+        std::fs::read_to_string(path)
+            .map(|v| Self::parse(&v))
+            .map_err(|e| Error::ReadConfig(path.to_path_buf(), e))
+    }
+
+    /// Parse the INI-style contents of an `.editorconfig` file.
+    ///
+    /// EditorConfig files are parsed leniently - malformed lines and unknown
+    /// properties are ignored.
+    fn parse(s: &str) -> Self {
+        let mut config = Self::default();
+
+        for line in s.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with(['#', ';']) {
+                continue;
+            }
+
+            if let Some(glob) = line.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+                config.sections.push(Section {
+                    glob: compile_glob(glob),
+                    properties: vec![],
+                });
+                continue;
+            }
+
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let key = key.trim().to_ascii_lowercase();
+            let value = value.trim().to_ascii_lowercase();
+
+            match config.sections.last_mut() {
+                Some(section) => section.properties.push((key, value)),
+                None if key == "root" => config.root = value == "true",
+                None => {}
+            }
+        }
+
+        config
+    }
+}
+
+/// Compile a section glob, relative to the directory containing the
+/// `.editorconfig` file.
+///
+/// Globs containing a `/` match paths relative to the file's directory, all
+/// others match the file name in any subdirectory.
+fn compile_glob(glob: &str) -> Option<GlobMatcher> {
+    let glob = match glob.contains('/') {
+        true => glob.trim_start_matches('/').to_string(),
+        false => format!("**/{glob}"),
+    };
+
+    GlobBuilder::new(&glob)
+        .literal_separator(true)
+        .build()
+        .ok()
+        .map(|v| v.compile_matcher())
+}
+
+/// Map the resolved EditorConfig `properties` into a [`Config`].
+///
+/// Properties with unrecognised values (including `unset`) are ignored.
+fn to_config(properties: &HashMap<String, String>) -> Config {
+    let get = |k: &str| properties.get(k).map(String::as_str);
+    let number = |k: &str| get(k).and_then(|v| v.parse::<usize>().ok());
+    let boolean = |k: &str| match get(k) {
+        Some("true") => Some(true),
+        Some("false") => Some(false),
+        _ => None,
+    };
+
+    Config {
+        line_width: number("max_line_length"),
+        indent_width: match get("indent_size") {
+            Some("tab") => number("tab_width"),
+            _ => number("indent_size"),
+        },
+        hard_tabs: match get("indent_style") {
+            Some("tab") => Some(true),
+            Some("space") => Some(false),
+            _ => None,
+        },
+        final_newline: boolean("insert_final_newline").map(|v| match v {
+            true => FinalNewline::Always,
+            false => FinalNewline::Never,
+        }),
+        line_ending: match get("end_of_line") {
+            Some("lf") => Some(LineEnding::Lf),
+            Some("crlf") => Some(LineEnding::Crlf),
+            _ => None,
+        },
+        ..Default::default()
+    }
+}
@@ -0,0 +1,68 @@
+//! A report of the TLA+ grammar node kinds exercised by a set of specs, used to
+//! find gaps in the formatter test corpus.
+
+use std::{
+    collections::BTreeSet,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use libtlafmt::{grammar_node_kinds, ParsedFile};
+
+use crate::{blame::SPEC_EXTENSION, paths, Error};
+
+/// Print the grammar node kinds that appear in the specs within `dirs`, and
+/// those that never do.
+///
+/// If `stubs` is provided, a stub spec is written into it for each kind never
+/// seen, to be completed with an example of the kind and added to the corpus.
+/// Existing stubs are left unchanged.
+pub(crate) fn report(dirs: &[PathBuf], stubs: Option<&Path>) -> Result<(), Error> {
+    let mut seen = BTreeSet::new();
+    for path in paths::expand(dirs)? {
+        let input = std::fs::read_to_string(&path).map_err(Error::ReadFile)?;
+        seen.extend(ParsedFile::new(&input)?.node_kinds());
+    }
+
+    let all = grammar_node_kinds();
+    let missing = all.difference(&seen).copied().collect::<Vec<_>>();
+
+    let mut out = std::io::stdout().lock();
+    writeln!(
+        out,
+        "covered {} of {} node kinds",
+        all.len() - missing.len(),
+        all.len()
+    )
+    .map_err(Error::Write)?;
+    for kind in &missing {
+        writeln!(out, "missing: {kind}").map_err(Error::Write)?;
+    }
+
+    let Some(dir) = stubs else {
+        return Ok(());
+    };
+
+    std::fs::create_dir_all(dir).map_err(Error::Write)?;
+    for kind in missing {
+        let path = dir.join(format!("Coverage_{kind}.{SPEC_EXTENSION}"));
+        if path.exists() {
+            continue;
+        }
+
+        std::fs::write(&path, stub(kind)).map_err(Error::Write)?;
+        writeln!(out, "wrote {}", path.display()).map_err(Error::Write)?;
+    }
+
+    Ok(())
+}
+
+/// A placeholder spec to be completed with an example of the `kind` node.
+fn stub(kind: &str) -> String {
+    format!(
+        "---- MODULE Coverage_{kind} ----\n\
+         \\* TODO: exercise the \"{kind}\" grammar node, then move this spec\n\
+         \\* into libtlafmt/tests/corpus.\n\
+         ====\n"
+    )
+}
@@ -0,0 +1,84 @@
+//! Format-on-save configuration snippets for common editors.
+//!
+//! Each snippet pipes the buffer through `tlafmt --stdin`, naming the file with
+//! `--stdin-filepath` where the editor supports it so that config files are
+//! discovered relative to the spec. Editors replace the buffer only when
+//! `tlafmt` exits successfully - specs containing syntax errors exit with code
+//! 4 and are left unchanged.
+
+use clap::ValueEnum;
+
+/// An editor with a known format-on-save configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum Editor {
+    /// Visual Studio Code, using the "Custom Local Formatters" extension.
+    Vscode,
+
+    /// Neovim, using conform.nvim.
+    Nvim,
+
+    /// Emacs, using reformatter.el.
+    Emacs,
+
+    /// Helix.
+    Helix,
+}
+
+impl Editor {
+    /// The configuration snippet for this editor.
+    pub(crate) fn snippet(&self) -> &'static str {
+        match self {
+            Self::Vscode => VSCODE,
+            Self::Nvim => NVIM,
+            Self::Emacs => EMACS,
+            Self::Helix => HELIX,
+        }
+    }
+}
+
+const VSCODE: &str = r#"// settings.json - requires the jkillian.custom-local-formatters extension.
+{
+  "customLocalFormatters.formatters": [
+    {
+      "command": "tlafmt --stdin --stdin-filepath \"${file}\"",
+      "languages": ["tlaplus"]
+    }
+  ],
+  "[tlaplus]": {
+    "editor.defaultFormatter": "jkillian.custom-local-formatters",
+    "editor.formatOnSave": true
+  }
+}
+"#;
+
+const NVIM: &str = r#"-- init.lua - requires stevearc/conform.nvim.
+require("conform").setup({
+  formatters = {
+    tlafmt = {
+      command = "tlafmt",
+      args = { "--stdin", "--stdin-filepath", "$FILENAME" },
+      stdin = true,
+    },
+  },
+  formatters_by_ft = {
+    tla = { "tlafmt" },
+  },
+  format_on_save = {},
+})
+"#;
+
+const EMACS: &str = r#";; init.el - requires reformatter.el.
+(reformatter-define tlafmt-format
+  :program "tlafmt"
+  :args (list "--stdin" "--stdin-filepath" (or buffer-file-name "spec.tla"))
+  :lighter " tlafmt")
+
+(add-hook 'tla-mode-hook #'tlafmt-format-on-save-mode)
+"#;
+
+const HELIX: &str = r#"# languages.toml
+[[language]]
+name = "tlaplus"
+formatter = { command = "tlafmt", args = ["--stdin", "--stdin-filepath", "%{buffer_name}"] }
+auto-format = true
+"#;
@@ -0,0 +1,54 @@
+//! Decoding of input specs that are not UTF-8 encoded.
+
+use std::string::FromUtf8Error;
+
+use clap::ValueEnum;
+
+use crate::Error;
+
+/// The character encoding of an input spec.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum Encoding {
+    // UTF-8 if the input is valid UTF-8, otherwise Latin-1.
+    #[default]
+    Auto,
+
+    #[value(name = "utf-8", alias = "utf8")]
+    Utf8,
+
+    // Latin-1 (ISO 8859-1).
+    #[value(name = "latin-1", alias = "latin1", alias = "iso-8859-1")]
+    Latin1,
+}
+
+impl Encoding {
+    /// Decode `bytes` into a string, returning it alongside the concrete
+    /// encoding it was decoded from (never [`Encoding::Auto`]).
+    pub(crate) fn decode(self, bytes: Vec<u8>) -> Result<(String, Self), FromUtf8Error> {
+        match self {
+            Self::Utf8 => Ok((String::from_utf8(bytes)?, Self::Utf8)),
+            Self::Latin1 => Ok((decode_latin1(&bytes), Self::Latin1)),
+            Self::Auto => match String::from_utf8(bytes) {
+                Ok(v) => Ok((v, Self::Utf8)),
+                Err(e) => Ok((decode_latin1(e.as_bytes()), Self::Latin1)),
+            },
+        }
+    }
+
+    /// Encode the UTF-8 `bytes` using this encoding.
+    pub(crate) fn encode(self, bytes: Vec<u8>) -> Result<Vec<u8>, Error> {
+        match self {
+            Self::Auto | Self::Utf8 => Ok(bytes),
+            Self::Latin1 => String::from_utf8(bytes)?
+                .chars()
+                .map(|c| u8::try_from(c).map_err(|_| Error::Encode(c)))
+                .collect(),
+        }
+    }
+}
+
+/// Decode the Latin-1 `bytes`, each of which maps directly to the Unicode code
+/// point of the same value.
+fn decode_latin1(bytes: &[u8]) -> String {
+    bytes.iter().copied().map(char::from).collect()
+}
@@ -0,0 +1,536 @@
+//! A minimal language server, serving formatting (including indentation as
+//! newlines are typed), syntax diagnostics and a module outline to editors over
+//! stdio.
+//!
+//! Documents are synchronised in full on each change. Formatting a document
+//! containing syntax errors returns no edits, leaving the buffer unchanged.
+
+use std::{
+    collections::HashMap,
+    io::{BufRead, Write},
+    path::{Path, PathBuf},
+};
+
+use libtlafmt::{DefinitionKind, Diagnostic, FormatOptions, Formatter, ParsedFile};
+use serde_json::{json, Value};
+
+use crate::Error;
+
+/// The JSON-RPC error code for a request with an unknown method.
+const METHOD_NOT_FOUND: i64 = -32601;
+
+/// The JSON-RPC error code for a request with invalid parameters.
+const INVALID_PARAMS: i64 = -32602;
+
+/// The LSP error code for a request received before `initialize`.
+const SERVER_NOT_INITIALIZED: i64 = -32002;
+
+/// The `TextDocumentSyncKind` sending the full document content on change.
+const SYNC_FULL: u8 = 1;
+
+/// The `DiagnosticSeverity` of an error.
+const SEVERITY_ERROR: u8 = 1;
+
+/// The `SymbolKind` of an instantiated module.
+const SYMBOL_MODULE: u8 = 2;
+
+/// The `SymbolKind` of an operator or function definition.
+const SYMBOL_FUNCTION: u8 = 12;
+
+/// The `SymbolKind` of a variable declaration.
+const SYMBOL_VARIABLE: u8 = 13;
+
+/// The `SymbolKind` of a constant declaration.
+const SYMBOL_CONSTANT: u8 = 14;
+
+/// The `SymbolKind` of a theorem or assumption.
+const SYMBOL_BOOLEAN: u8 = 17;
+
+/// The number of columns a tab character is expanded to when parsing, and
+/// rendered as when [hard tabs](FormatOptions::with_hard_tabs()) are enabled.
+const TAB_WIDTH: usize = 4;
+
+/// An open text document.
+#[derive(Debug)]
+struct Document {
+    /// The content of the document.
+    text: String,
+
+    /// The version of the document, increasing with each change.
+    version: i64,
+
+    /// The parsed content of the document.
+    parsed: Result<ParsedFile<'static>, libtlafmt::Error>,
+}
+
+impl Document {
+    /// Parse `text` with `formatter`.
+    fn new(formatter: &Formatter, text: &str, version: i64) -> Self {
+        Self {
+            text: text.to_string(),
+            version,
+            parsed: formatter.parse_owned(text.to_string()),
+        }
+    }
+
+    /// Returns the parsed content of the document.
+    fn parsed(&self) -> Result<&ParsedFile<'static>, (i64, String)> {
+        self.parsed
+            .as_ref()
+            .map_err(|e| (INVALID_PARAMS, e.to_string()))
+    }
+}
+
+/// The state of a language server session.
+struct Server<F> {
+    /// Resolves the formatting options for the spec at the provided path, if
+    /// the document is a file.
+    options: F,
+
+    /// Open documents, keyed by URI.
+    documents: HashMap<String, Document>,
+
+    /// Parses each document, reusing the same parser.
+    formatter: Formatter,
+
+    initialized: bool,
+    shutdown: bool,
+}
+
+/// Serve LSP requests read from `input`, writing responses and notifications
+/// to `output` until the client exits.
+///
+/// The formatting options used for a document are resolved by calling
+/// `options` with its path.
+pub(crate) fn serve<R, W, F>(mut input: R, mut output: W, options: F) -> Result<(), Error>
+where
+    R: BufRead,
+    W: Write,
+    F: Fn(Option<&Path>) -> Result<FormatOptions, Error>,
+{
+    let mut server = Server {
+        options,
+        documents: HashMap::new(),
+        formatter: Formatter::default(),
+        initialized: false,
+        shutdown: false,
+    };
+
+    while let Some(msg) = read_message(&mut input)? {
+        let method = msg["method"].as_str().unwrap_or_default();
+        if method == "exit" {
+            return match server.shutdown {
+                true => Ok(()),
+                false => Err(Error::Lsp("exit before shutdown request".into())),
+            };
+        }
+
+        for v in server.handle(method, &msg) {
+            write_message(&mut output, &v)?;
+        }
+    }
+
+    Ok(())
+}
+
+impl<F> Server<F>
+where
+    F: Fn(Option<&Path>) -> Result<FormatOptions, Error>,
+{
+    /// Handle the `method` message `msg`, returning the messages to send to
+    /// the client in response.
+    fn handle(&mut self, method: &str, msg: &Value) -> Vec<Value> {
+        let params = &msg["params"];
+
+        // Notifications carry no ID and are never responded to.
+        let Some(id) = msg.get("id") else {
+            return match self.initialized {
+                true => self.notify(method, params),
+                false => vec![],
+            };
+        };
+
+        let result = match method {
+            "initialize" => {
+                self.initialized = true;
+                Ok(json!({
+                    "capabilities": {
+                        "textDocumentSync": SYNC_FULL,
+                        "documentFormattingProvider": true,
+                        "documentSymbolProvider": true,
+                        "documentOnTypeFormattingProvider": {
+                            "firstTriggerCharacter": "\n",
+                        },
+                    },
+                    "serverInfo": {
+                        "name": env!("CARGO_PKG_NAME"),
+                        "version": env!("CARGO_PKG_VERSION"),
+                    },
+                }))
+            }
+            _ if !self.initialized => {
+                Err((SERVER_NOT_INITIALIZED, "server not initialized".into()))
+            }
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Value::Null)
+            }
+            "textDocument/formatting" => self.formatting(params),
+            "textDocument/documentSymbol" => self.document_symbols(params),
+            "textDocument/onTypeFormatting" => self.on_type_formatting(params),
+            _ => Err((METHOD_NOT_FOUND, format!("unsupported method {method:?}"))),
+        };
+
+        vec![match result {
+            Ok(v) => json!({ "jsonrpc": "2.0", "id": id, "result": v }),
+            Err((code, message)) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": code, "message": message },
+            }),
+        }]
+    }
+
+    /// Handle the `method` notification, returning any notifications to send
+    /// to the client.
+    fn notify(&mut self, method: &str, params: &Value) -> Vec<Value> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        let version = params["textDocument"]["version"]
+            .as_i64()
+            .unwrap_or_default();
+
+        match method {
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                self.documents.insert(
+                    uri.to_string(),
+                    Document::new(&self.formatter, text, version),
+                );
+            }
+            "textDocument/didChange" => {
+                // With full synchronisation the last change holds the entire
+                // document.
+                let Some(text) = params["contentChanges"]
+                    .as_array()
+                    .and_then(|v| v.last())
+                    .and_then(|v| v["text"].as_str())
+                else {
+                    return vec![];
+                };
+                self.documents.insert(
+                    uri.to_string(),
+                    Document::new(&self.formatter, text, version),
+                );
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(uri);
+                return vec![publish_diagnostics(uri, None, vec![])];
+            }
+            _ => return vec![],
+        }
+
+        vec![self.diagnostics(uri)]
+    }
+
+    /// Returns a `textDocument/publishDiagnostics` notification reporting the
+    /// syntax errors in the document at `uri`.
+    fn diagnostics(&self, uri: &str) -> Value {
+        let Some(doc) = self.documents.get(uri) else {
+            return publish_diagnostics(uri, None, vec![]);
+        };
+
+        let path = uri_to_path(uri);
+        let diagnostics = match (self.options)(path.as_deref()) {
+            Ok(options) => doc
+                .parsed
+                .as_ref()
+                .ok()
+                .and_then(|v| v.format_with_diagnostics(&options, std::io::sink()).ok())
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|v| match v {
+                    Diagnostic::SyntaxError { line, .. } => Some(json!({
+                        "range": {
+                            "start": { "line": line - 1, "character": 0 },
+                            "end": { "line": line, "character": 0 },
+                        },
+                        "severity": SEVERITY_ERROR,
+                        "source": env!("CARGO_PKG_NAME"),
+                        "message": "syntax error",
+                    })),
+                    _ => None,
+                })
+                .collect(),
+            Err(e) => vec![json!({
+                "range": {
+                    "start": { "line": 0, "character": 0 },
+                    "end": { "line": 0, "character": 0 },
+                },
+                "severity": SEVERITY_ERROR,
+                "source": env!("CARGO_PKG_NAME"),
+                "message": e.to_string(),
+            })],
+        };
+
+        publish_diagnostics(uri, Some(doc.version), diagnostics)
+    }
+
+    /// Returns the URI and open document identified by the `textDocument` of
+    /// the request `params`.
+    fn document<'a>(&'a self, params: &'a Value) -> Result<(&'a str, &'a Document), (i64, String)> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        self.documents
+            .get(uri)
+            .map(|v| (uri, v))
+            .ok_or_else(|| (INVALID_PARAMS, format!("unknown document {uri:?}")))
+    }
+
+    /// Handle a `textDocument/documentSymbol` request, returning the top-level
+    /// operators, functions, constants, variables, instances and theorems of
+    /// the document.
+    fn document_symbols(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let (_, doc) = self.document(params)?;
+        let parsed = doc.parsed()?;
+        let lines = doc.text.split('\n').collect::<Vec<_>>();
+
+        let symbols = parsed
+            .definitions()
+            .filter_map(|def| {
+                let kind = match def.kind {
+                    DefinitionKind::Operator | DefinitionKind::Function => SYMBOL_FUNCTION,
+                    DefinitionKind::Constant => SYMBOL_CONSTANT,
+                    DefinitionKind::Variable => SYMBOL_VARIABLE,
+                    DefinitionKind::Instance => SYMBOL_MODULE,
+                    DefinitionKind::Theorem | DefinitionKind::Assumption => SYMBOL_BOOLEAN,
+                    _ => return None,
+                };
+
+                // The definition ends at the end of its last line.
+                let last = def.lines.end.max(def.lines.start + 1) - 1;
+                let end = lines.get(last).map_or(0, |v| v.encode_utf16().count());
+
+                // Select the name where it first appears on the first line.
+                let first = lines.get(def.lines.start).copied().unwrap_or_default();
+                let column = find_word(first, def.name).unwrap_or_default();
+                let name_start = first[..column].encode_utf16().count();
+                let name_end = name_start + def.name.encode_utf16().count();
+
+                Some(json!({
+                    "name": def.name,
+                    "kind": kind,
+                    "range": {
+                        "start": { "line": def.lines.start, "character": 0 },
+                        "end": { "line": last, "character": end },
+                    },
+                    "selectionRange": {
+                        "start": { "line": def.lines.start, "character": name_start },
+                        "end": { "line": def.lines.start, "character": name_end },
+                    },
+                }))
+            })
+            .collect::<Vec<_>>();
+
+        Ok(symbols.into())
+    }
+
+    /// Handle a `textDocument/onTypeFormatting` request for a typed newline,
+    /// returning an edit that indents the new line to continue any enclosing
+    /// conjunction / disjunction list or `LET` definitions.
+    fn on_type_formatting(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let (uri, doc) = self.document(params)?;
+        let Some(line) = params["position"]["line"].as_u64() else {
+            return Err((INVALID_PARAMS, "missing position".into()));
+        };
+        let line = line as usize;
+
+        let path = uri_to_path(uri);
+        let options =
+            (self.options)(path.as_deref()).map_err(|e| (INVALID_PARAMS, e.to_string()))?;
+
+        let parsed = doc.parsed()?;
+        let Some(indent) = parsed.line_indent(line) else {
+            return Ok(json!([]));
+        };
+
+        // Existing indentation reaching the same column is left as typed,
+        // whether it uses tabs or spaces.
+        let text = doc.text.split('\n').nth(line).unwrap_or_default();
+        let current = &text[..text.len() - text.trim_start().len()];
+        let width = current
+            .chars()
+            .map(|v| if v == '\t' { TAB_WIDTH } else { 1 })
+            .sum::<usize>();
+        if width == indent {
+            return Ok(json!([]));
+        }
+
+        let new_text = match options.hard_tabs() {
+            true => format!(
+                "{}{}",
+                "\t".repeat(indent / TAB_WIDTH),
+                " ".repeat(indent % TAB_WIDTH)
+            ),
+            false => " ".repeat(indent),
+        };
+
+        Ok(json!([{
+            "range": {
+                "start": { "line": line, "character": 0 },
+                "end": { "line": line, "character": current.encode_utf16().count() },
+            },
+            "newText": new_text,
+        }]))
+    }
+
+    /// Handle a `textDocument/formatting` request, returning the edits that
+    /// format the document.
+    fn formatting(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let (uri, doc) = self.document(params)?;
+
+        let path = uri_to_path(uri);
+        let options =
+            (self.options)(path.as_deref()).map_err(|e| (INVALID_PARAMS, e.to_string()))?;
+
+        let parsed = match &doc.parsed {
+            Ok(v) if !v.has_syntax_errors() => v,
+            _ => return Ok(json!([])),
+        };
+
+        let mut buf = Vec::with_capacity(doc.text.len());
+        let formatted = match parsed.format_with(&options, &mut buf) {
+            Ok(()) => String::from_utf8_lossy(&buf),
+            Err(_) => return Ok(json!([])),
+        };
+        if formatted == doc.text {
+            return Ok(json!([]));
+        }
+
+        // Replace the entire document.
+        Ok(json!([{
+            "range": {
+                "start": { "line": 0, "character": 0 },
+                "end": end_position(&doc.text),
+            },
+            "newText": formatted,
+        }]))
+    }
+}
+
+/// Returns a `textDocument/publishDiagnostics` notification for `uri`.
+fn publish_diagnostics(uri: &str, version: Option<i64>, diagnostics: Vec<Value>) -> Value {
+    let mut params = json!({ "uri": uri, "diagnostics": diagnostics });
+    if let Some(v) = version {
+        params["version"] = v.into();
+    }
+
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/publishDiagnostics",
+        "params": params,
+    })
+}
+
+/// Returns the LSP position of the end of `text`, measured in UTF-16 code
+/// units.
+fn end_position(text: &str) -> Value {
+    let (line, last) = text
+        .split('\n')
+        .enumerate()
+        .last()
+        .expect("split always yields at least one item");
+
+    json!({ "line": line, "character": last.encode_utf16().count() })
+}
+
+/// Returns the byte offset of the first occurrence of the identifier `word` in
+/// `line` that is not part of a longer identifier.
+fn find_word(line: &str, word: &str) -> Option<usize> {
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+
+    line.match_indices(word)
+        .map(|(i, _)| i)
+        .find(|&i| !line[..i].ends_with(is_ident) && !line[i + word.len()..].starts_with(is_ident))
+}
+
+/// Returns the path of a `file://` URI, or [`None`] for other schemes.
+fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let path = uri.strip_prefix("file://")?;
+
+    // Decode percent-encoded bytes, such as "%20".
+    let bytes = path.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let decoded = (bytes[i] == b'%')
+            .then(|| path.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|v| u8::from_str_radix(v, 16).ok());
+        match decoded {
+            Some(v) => {
+                out.push(v);
+                i += 3;
+            }
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+
+    let path = String::from_utf8(out).ok()?;
+
+    // Windows drive letters are prefixed with a slash ("/C:/foo").
+    let path = match path.as_bytes() {
+        [b'/', _, b':', ..] if cfg!(windows) => &path[1..],
+        _ => &path,
+    };
+
+    Some(PathBuf::from(path))
+}
+
+/// Read the next framed message from `input`, or [`None`] at the end of the
+/// input.
+fn read_message<R: BufRead>(input: &mut R) -> Result<Option<Value>, Error> {
+    let mut len = None;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if input.read_line(&mut line).map_err(Error::ReadFile)? == 0 {
+            return match len {
+                None => Ok(None),
+                Some(_) => Err(Error::Lsp("unexpected end of input in header".into())),
+            };
+        }
+
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                len = Some(
+                    value
+                        .trim()
+                        .parse::<usize>()
+                        .map_err(|e| Error::Lsp(format!("invalid content length: {e}")))?,
+                );
+            }
+        }
+    }
+
+    let len = len.ok_or_else(|| Error::Lsp("missing content length".into()))?;
+    let mut body = vec![0; len];
+    input.read_exact(&mut body).map_err(Error::ReadFile)?;
+
+    serde_json::from_slice(&body)
+        .map(Some)
+        .map_err(|e| Error::Lsp(format!("invalid message: {e}")))
+}
+
+/// Write `msg` to `output` as a framed message.
+fn write_message<W: Write>(output: &mut W, msg: &Value) -> Result<(), Error> {
+    let body = msg.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{body}", body.len()).map_err(Error::Write)?;
+    output.flush().map_err(Error::Write)
+}
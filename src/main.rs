@@ -13,39 +13,864 @@
 //   limitations under the License.
 
 use std::{
-    io::{BufWriter, IsTerminal, Write},
-    path::PathBuf,
+    ffi::OsString,
+    io::{BufWriter, IsTerminal, Read, Write},
+    ops::Range,
+    path::{Path, PathBuf},
     string::FromUtf8Error,
+    time::Instant,
 };
 
 use anstyle::Style;
 use clap::{
     builder::styling::{AnsiColor, Color},
-    Parser,
+    CommandFactory, Parser, Subcommand, ValueEnum,
+};
+use libtlafmt::{
+    format_many, CallWrapping, Diagnostic, DividerLength, FileResult, FinalNewline, FormatOptions,
+    LineEnding, OperatorSpacing, ParsedFile, QuantifierListLayout, Rule,
 };
-use libtlafmt::ParsedFile;
 use thiserror::Error;
 
+mod batch;
+mod blame;
+mod cache;
+mod changed_lines;
+mod checkstyle;
+mod config;
+mod coverage;
+mod editor;
+mod encoding;
+mod lsp;
+mod metadata;
+mod parse;
+mod paths;
+mod snapshots;
+mod stats;
+mod unified;
+mod word_diff;
+
+use cache::Cache;
+use config::{editorconfig, Config};
+use editor::Editor;
+use encoding::Encoding;
+use stats::Stats;
+
+/// The number of diff lines printed per file before truncating, unless
+/// `--full-diff` is specified.
+const DIFF_LINE_LIMIT: usize = 400;
+
+/// The exit code when a spec is not formatted in check (or list) mode.
+const EXIT_UNFORMATTED: i32 = 3;
+
+/// The exit code when a spec contains syntax errors, taking precedence over
+/// [`EXIT_UNFORMATTED`].
+const EXIT_INVALID: i32 = 4;
+
+/// The exit code when `sections` finds definitions out of the conventional
+/// section order.
+const EXIT_MISPLACED: i32 = 5;
+
+/// The exit codes documented in the `--help` text.
+const EXIT_CODES: &str = "\
+Exit codes:
+  0  Success
+  1  An error occurred reading, formatting or writing a spec
+  2  Invalid arguments
+  3  A spec is not formatted (check and list mode)
+  4  A spec contains syntax errors (and was formatted except for the definitions containing them)
+  5  A spec has definitions out of the conventional section order (sections)";
+
 /// Formatter of TLA+ specs.
 #[derive(Parser, Debug)]
-#[command(version, about)]
+#[command(
+    version,
+    about,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true,
+    after_help = EXIT_CODES
+)]
 struct Args {
-    /// Path to the TLA+ file to format.
-    #[arg(required_unless_present = "stdin", conflicts_with = "stdin")]
-    file: Option<PathBuf>,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Paths to the TLA+ files to format, or directories to search for them
+    /// (skipping any paths ignored by a .gitignore or .ignore file).
+    #[arg(
+        required_unless_present_any = ["stdin", "batch", "print_config", "changed", "since"],
+        conflicts_with_all = ["stdin", "batch", "changed", "since"]
+    )]
+    file: Vec<PathBuf>,
 
     /// Check the input file and print a diff of any changes that would be made.
-    #[arg(short, long)]
+    #[arg(short, long, group = "verify")]
     check: bool,
 
+    /// Print the changes in check mode as a unified diff to stdout, suitable
+    /// for applying with patch.
+    #[arg(long, requires = "check")]
+    diff: bool,
+
+    /// Print the changes in check mode as a machine-readable report to stdout
+    /// instead of a diff.
+    #[arg(
+        long,
+        value_enum,
+        value_name = "FORMAT",
+        requires = "check",
+        conflicts_with = "diff"
+    )]
+    emit: Option<Emit>,
+
+    /// Print only the paths of the input files that would be reformatted,
+    /// without a diff.
+    #[arg(short, long, group = "verify", conflicts_with_all = ["in_place", "batch"])]
+    list_different: bool,
+
     /// Overwrite the source file with the formatted output instead of printing
     /// it to stdout.
     #[arg(short, long, conflicts_with = "check", conflicts_with = "stdin")]
     in_place: bool,
 
-    /// Read the input file from stdin instead of the filesystem.
+    /// Write the formatted output to the file at PATH instead of printing it to
+    /// stdout, leaving the input file unchanged.
+    #[arg(
+        short,
+        long,
+        value_name = "PATH",
+        conflicts_with_all = ["verify", "in_place", "batch", "print_config"]
+    )]
+    output: Option<PathBuf>,
+
+    /// Create the temporary file used when writing a file in DIR, instead of
+    /// next to the file being written. DIR must be on the same filesystem as
+    /// the written file.
+    #[arg(long, value_name = "DIR")]
+    temp_dir: Option<PathBuf>,
+
+    /// Exit successfully in check (or list) mode even if files are not
+    /// formatted or contain syntax errors, still printing the diffs (or
+    /// paths).
+    #[arg(long, requires = "verify")]
+    exit_zero: bool,
+
+    /// Do not print the diff of unformatted files in check mode, nor any other
+    /// non-error output to stderr.
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Log the time taken to format each file, and the files written, to
+    /// stderr.
+    #[arg(short, long)]
+    verbose: bool,
+
+    /// Print the complete diff in check and interactive mode, instead of
+    /// truncating it after 400 lines.
+    #[arg(long)]
+    full_diff: bool,
+
+    /// Print the changes to each file and prompt whether to apply them, for use
+    /// with --in-place.
+    #[arg(long, requires = "in_place")]
+    interactive: bool,
+
+    /// Format the targets of symlinked specs when formatting multiple files or
+    /// directories, instead of skipping them.
+    #[arg(long)]
+    follow_symlinks: bool,
+
+    /// Read the input file from stdin instead of the filesystem (also enabled
+    /// by passing "-" as FILE).
     #[arg(long)]
     stdin: bool,
+
+    /// The path of the spec read from stdin, used to name it in the output and
+    /// error messages, and to discover the config files that apply to it.
+    #[arg(long, value_name = "PATH", requires = "stdin", conflicts_with = "file")]
+    stdin_filepath: Option<PathBuf>,
+
+    /// Format the specs added or modified in the current git repository
+    /// (including uncommitted and untracked specs) instead of FILE.
+    #[arg(long, conflicts_with_all = ["stdin", "batch"])]
+    changed: bool,
+
+    /// As --changed, but for the specs modified since the git revision REF
+    /// instead of HEAD.
+    #[arg(long, value_name = "REF", conflicts_with_all = ["stdin", "batch"])]
+    since: Option<String>,
+
+    /// Reformat only the top-level definitions that overlap lines changed
+    /// since HEAD (or the --since revision) according to git, leaving all other
+    /// lines unchanged.
+    #[arg(long, conflicts_with_all = ["stdin", "batch"])]
+    changed_lines: bool,
+
+    /// As --changed-lines, but reading the changed lines from the unified diff
+    /// at DIFF instead of asking git.
+    #[arg(
+        long,
+        value_name = "DIFF",
+        conflicts_with_all = ["stdin", "batch", "changed_lines"]
+    )]
+    changed_lines_from: Option<PathBuf>,
+
+    /// Reformat only the top-level definitions that overlap the 1-indexed,
+    /// inclusive range of lines START:END, leaving all other lines unchanged.
+    /// May be repeated.
+    #[arg(
+        long,
+        value_name = "START:END",
+        value_parser = parse_line_range,
+        conflicts_with_all = ["batch", "changed_lines", "changed_lines_from"]
+    )]
+    lines: Vec<Range<usize>>,
+
+    /// Reformat only the operator and function definitions named NAME, leaving
+    /// all other lines unchanged. Accepts a comma-separated list, and may be
+    /// repeated.
+    #[arg(
+        long,
+        value_name = "NAME",
+        value_delimiter = ',',
+        conflicts_with_all = ["batch", "changed_lines", "changed_lines_from", "lines"]
+    )]
+    only: Vec<String>,
+
+    /// Skip specs that are unchanged since they were last checked or formatted,
+    /// recording formatted specs in a .tlafmt-cache file.
+    #[arg(
+        long,
+        conflicts_with_all = ["stdin", "batch", "changed_lines", "changed_lines_from", "lines", "only"]
+    )]
+    cache: bool,
+
+    /// As --cache, but storing the cache in the file at PATH.
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = ["stdin", "batch", "changed_lines", "changed_lines_from", "lines", "only"]
+    )]
+    cache_location: Option<PathBuf>,
+
+    /// Read {"path": ..., "content": ...} JSON-lines formatting requests from
+    /// stdin, writing a JSON-lines response to stdout for each until stdin is
+    /// closed.
+    #[arg(long, visible_alias = "daemon", conflicts_with_all = ["check", "in_place", "stdin"])]
+    batch: bool,
+
+    /// The character encoding of the input file, with "auto" falling back to
+    /// Latin-1 for input that is not valid UTF-8. Output uses the same encoding.
+    #[arg(long, value_enum, default_value_t)]
+    encoding: Encoding,
+
+    /// Print the effective formatting configuration for FILE (or the current
+    /// directory) as TOML, resolved from the defaults, any config files and CLI
+    /// flags.
+    #[arg(long, conflicts_with_all = ["check", "in_place", "stdin", "batch"])]
+    print_config: bool,
+
+    /// Print a summary of the changes made (or that would be made) to stderr
+    /// after formatting.
+    #[arg(long, conflicts_with_all = ["batch", "print_config"])]
+    stats: bool,
+
+    #[command(flatten)]
+    format: FormatArgs,
+}
+
+/// A machine-readable report format for the results of check mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Emit {
+    // A checkstyle XML report.
+    Checkstyle,
+}
+
+impl Args {
+    /// The maximum number of diff lines to print per file, if any.
+    fn diff_limit(&self) -> Option<usize> {
+        (!self.full_diff).then_some(DIFF_LINE_LIMIT)
+    }
+
+    /// The path of the cache file, if caching is enabled.
+    fn cache_path(&self) -> Option<PathBuf> {
+        match (&self.cache_location, self.cache) {
+            (Some(v), _) => Some(v.clone()),
+            (None, true) => Some(PathBuf::from(cache::DEFAULT_CACHE_PATH)),
+            (None, false) => None,
+        }
+    }
+
+    /// The 0-indexed lines of the spec at `path` to reformat, or [`None`] if
+    /// the whole spec is formatted.
+    fn changed_lines(&self, path: Option<&Path>) -> Result<Option<Vec<Range<usize>>>, Error> {
+        if !self.lines.is_empty() {
+            return Ok(Some(self.lines.clone()));
+        }
+
+        let Some(path) = path else {
+            return Ok(None);
+        };
+
+        match (&self.changed_lines_from, self.changed_lines) {
+            (Some(diff), _) => changed_lines::from_diff(diff, path).map(Some),
+            (None, true) => {
+                changed_lines::from_git(path, self.since.as_deref().unwrap_or("HEAD")).map(Some)
+            }
+            (None, false) => Ok(None),
+        }
+    }
+}
+
+/// Rewrite a `-` FILE in the command line `args` to `--stdin`, as is
+/// conventional for unix tools.
+///
+/// A `-` given as the value of an option, after `--`, or to a subcommand is
+/// left unchanged.
+fn stdin_alias(args: impl IntoIterator<Item = OsString>) -> Vec<OsString> {
+    let cmd = Args::command();
+    let takes_value = |flag: &str| {
+        cmd.get_arguments().any(|a| {
+            a.get_action().takes_values()
+                && (flag
+                    .strip_prefix("--")
+                    .is_some_and(|v| a.get_long() == Some(v))
+                    || flag
+                        .strip_prefix('-')
+                        .is_some_and(|v| a.get_short().is_some_and(|c| v == c.to_string())))
+        })
+    };
+
+    let mut out = Vec::new();
+    let mut rewrite = true;
+    let mut is_value = false;
+    for (i, arg) in args.into_iter().enumerate() {
+        match arg.to_str() {
+            Some("-") if rewrite && !is_value => {
+                out.push("--stdin".into());
+                continue;
+            }
+            Some("--") => rewrite = false,
+            Some(v) if i == 1 && cmd.find_subcommand(v).is_some() => rewrite = false,
+            _ => {}
+        }
+
+        is_value = arg.to_str().is_some_and(takes_value);
+        out.push(arg);
+    }
+
+    out
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Rename a module-level operator, function, constant or variable and all
+    /// references to it, leaving all other text unchanged.
+    Rename {
+        /// Path to the TLA+ file containing the definition to rename.
+        file: PathBuf,
+
+        /// The current name of the definition.
+        from: String,
+
+        /// The new name of the definition.
+        to: String,
+
+        /// Overwrite the source file with the renamed output instead of
+        /// printing it to stdout.
+        #[arg(short, long)]
+        in_place: bool,
+
+        #[command(flatten)]
+        format: FormatArgs,
+    },
+
+    /// Print a .git-blame-ignore-revs entry for a repository-wide formatting
+    /// commit.
+    BlameIgnore {
+        /// The formatting commit.
+        #[arg(default_value = "HEAD")]
+        rev: String,
+
+        /// Write a JSON manifest of the specs changed by the commit, and whether
+        /// each was changed purely by formatting, to PATH.
+        #[arg(long, value_name = "PATH")]
+        annotate: Option<PathBuf>,
+
+        #[command(flatten)]
+        format: FormatArgs,
+    },
+
+    /// Report top-level definitions and declarations that are out of the
+    /// conventional section order (EXTENDS, constants, variables, definitions,
+    /// behaviour, properties).
+    Sections {
+        /// Paths to the TLA+ files to check.
+        #[arg(required = true)]
+        file: Vec<PathBuf>,
+    },
+
+    /// List the style rules applied with the formatting configuration.
+    Rules {
+        /// Also list the rules that are not applied with the configuration.
+        #[arg(long)]
+        all: bool,
+
+        #[command(flatten)]
+        format: FormatArgs,
+    },
+
+    /// Print a format-on-save configuration snippet for EDITOR, invoking
+    /// tlafmt on the editor buffer via stdin.
+    EditorSetup {
+        /// The editor to configure.
+        editor: Editor,
+
+        /// Write the snippet to the file at PATH instead of printing it to
+        /// stdout.
+        #[arg(short, long, value_name = "PATH")]
+        output: Option<PathBuf>,
+    },
+
+    /// Run a language server over stdio, serving document formatting and
+    /// syntax error diagnostics to editors.
+    Lsp {
+        #[command(flatten)]
+        format: FormatArgs,
+    },
+
+    /// Print the parsed AST, or the lowered formatter tokens, of a spec to
+    /// diagnose why it is formatted the way it is.
+    Parse {
+        /// Path to the TLA+ file to parse.
+        file: PathBuf,
+
+        /// The representation to print.
+        #[arg(long, value_enum, default_value_t)]
+        emit: parse::Emit,
+
+        #[command(flatten)]
+        format: FormatArgs,
+    },
+
+    /// Render a roff man page for tlafmt.
+    #[command(hide = true)]
+    Man {
+        /// Write the man page to the file at PATH instead of printing it to
+        /// stdout.
+        #[arg(short, long, value_name = "PATH")]
+        output: Option<PathBuf>,
+    },
+
+    /// Maintainer tooling for reviewing the formatter test corpus.
+    #[command(hide = true, subcommand)]
+    Snapshots(SnapshotsCommand),
+}
+
+#[derive(Subcommand, Debug)]
+enum SnapshotsCommand {
+    /// Render the before and after of all pending insta snapshot changes
+    /// side-by-side.
+    Diff {
+        /// The directory to search for pending snapshots.
+        #[arg(default_value = ".")]
+        dir: PathBuf,
+
+        /// The total width of the rendered output.
+        #[arg(long, value_name = "COLUMNS", default_value_t = 160)]
+        width: usize,
+    },
+
+    /// Report the TLA+ grammar node kinds that never appear in the specs
+    /// within DIR.
+    Coverage {
+        /// The directories (or specs) to search.
+        #[arg(default_value = "libtlafmt/tests/corpus")]
+        dir: Vec<PathBuf>,
+
+        /// Write a stub spec for each missing node kind into DIR.
+        #[arg(long, value_name = "DIR")]
+        stubs: Option<PathBuf>,
+    },
+}
+
+/// Formatting configuration.
+#[derive(clap::Args, Debug)]
+struct FormatArgs {
+    /// Remove the redundant bullet from conjunction and disjunction lists that
+    /// contain a single item.
+    #[arg(
+        long,
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true",
+        hide_possible_values = true
+    )]
+    collapse_single_item_lists: Option<bool>,
+
+    /// Do not realign vertically aligned end-of-line comments, instead placing
+    /// them a single space after the preceding token.
+    #[arg(
+        long,
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true",
+        hide_possible_values = true
+    )]
+    no_align_comments: Option<bool>,
+
+    /// The maximum width of a line, used to size module headers and dividing
+    /// lines [default: 80].
+    #[arg(long, value_name = "COLUMNS")]
+    line_width: Option<usize>,
+
+    /// The number of columns per level of indentation [default: 4].
+    #[arg(long, value_name = "COLUMNS")]
+    indent_width: Option<usize>,
+
+    /// The maximum number of indentation levels rendered, beyond which deeply
+    /// nested lines are clamped [default: 255].
+    #[arg(long, value_name = "LEVELS")]
+    max_indent_depth: Option<usize>,
+
+    /// Indent using tab characters instead of spaces.
+    #[arg(
+        long,
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true",
+        hide_possible_values = true
+    )]
+    hard_tabs: Option<bool>,
+
+    /// Place a comment recording the formatter version and style edition
+    /// before the module footer, replacing any existing one.
+    #[arg(
+        long,
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true",
+        hide_possible_values = true
+    )]
+    metadata_comment: Option<bool>,
+
+    /// Align the "==" of consecutive named ASSUME statements, separating each
+    /// with one empty line.
+    #[arg(
+        long,
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true",
+        hide_possible_values = true
+    )]
+    align_assumptions: Option<bool>,
+
+    /// Render "True", "False" and "Boolean" (in any case) as the builtin TRUE,
+    /// FALSE and BOOLEAN unless they are defined or bound within the spec.
+    #[arg(
+        long,
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true",
+        hide_possible_values = true
+    )]
+    normalise_booleans: Option<bool>,
+
+    /// Keep short calls to Sequences and FiniteSets operators (such as
+    /// "Len(s)") on one line, and only break others between arguments.
+    #[arg(
+        long,
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true",
+        hide_possible_values = true
+    )]
+    standard_call_wrapping: Option<bool>,
+
+    /// Sort the fields of record literals and sets of records alphabetically,
+    /// or by --record-field-order.
+    #[arg(
+        long,
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true",
+        hide_possible_values = true
+    )]
+    sort_record_fields: Option<bool>,
+
+    /// Break expressions exceeding the line width across lines, such as
+    /// wrapping the elements of long set literals, tuples and records, or
+    /// breaking long chains of infix operators.
+    #[arg(
+        long,
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true",
+        hide_possible_values = true
+    )]
+    line_wrapping: Option<bool>,
+
+    /// The width a conjunction list item assigning a value to a variable (such
+    /// as "/\ x' = x + 1") may extend to before --line-wrapping breaks it
+    /// [default: the line width].
+    #[arg(long, value_name = "COLUMNS")]
+    assignment_width: Option<usize>,
+
+    /// Refuse to format specs containing syntax errors, exiting with an error
+    /// listing their locations.
+    #[arg(
+        long,
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true",
+        hide_possible_values = true
+    )]
+    strict: Option<bool>,
+
+    /// Skip re-parsing the formatted output to verify the tokens of the spec
+    /// are unchanged before it is wrote.
+    #[arg(
+        long,
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true",
+        hide_possible_values = true
+    )]
+    skip_verify: Option<bool>,
+
+    /// Format the output a second time, failing if the two results differ.
+    ///
+    /// Use this to surface formatter bugs.
+    #[arg(
+        long,
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true",
+        hide_possible_values = true
+    )]
+    verify_idempotent: Option<bool>,
+
+    /// The record field names to sort before all others with
+    /// --sort-record-fields, as a comma-separated list.
+    #[arg(long, value_name = "FIELDS", value_delimiter = ',')]
+    record_field_order: Vec<String>,
+
+    /// Whether the module header, footer and dividing lines span the line
+    /// width ("fill"), or retain their length from the input ("preserve", or
+    /// "preserve-with-header" to also retain the header dashes) [default:
+    /// fill].
+    #[arg(long, value_name = "LENGTH")]
+    divider_length: Option<DividerLength>,
+
+    /// Whether a conjunction or disjunction list forming the body of a
+    /// quantifier starts on a new line ("new-line"), or on the same line as the
+    /// quantifier ("same-line") [default: new-line].
+    #[arg(long, value_name = "LAYOUT")]
+    quantifier_list_layout: Option<QuantifierListLayout>,
+
+    /// The line ending rendered at the end of each line, either "lf", "crlf",
+    /// or "detect" to use the line ending of the majority of lines in the input
+    /// [default: detect].
+    #[arg(long, value_name = "ENDING")]
+    line_ending: Option<LineEnding>,
+
+    /// Whether the output ends with exactly one newline ("always"), without a
+    /// newline ("never"), or only if the input ends with a newline
+    /// ("preserve") [default: never].
+    #[arg(long, value_name = "POLICY")]
+    final_newline: Option<FinalNewline>,
+
+    /// Never format module-level comments starting with PREFIX (such as
+    /// "\* INCLUDE"), nor the line breaks following them. May be specified
+    /// multiple times.
+    #[arg(long, value_name = "PREFIX")]
+    protect_comment: Vec<String>,
+
+    /// Place the comment block in the file at PATH (such as a license notice)
+    /// above the module header, unless it is already present.
+    #[arg(long, value_name = "PATH", value_parser = read_license_header)]
+    license_header: Option<String>,
+
+    /// Override the spacing of a user-defined infix operator, specified as
+    /// OP=SPACING where SPACING is one of "tight", "spaced" or
+    /// "never-break-before". May be specified multiple times.
+    #[arg(long, value_name = "OP=SPACING", value_parser = parse_operator_spacing)]
+    operator_spacing: Vec<(String, OperatorSpacing)>,
+
+    /// Set the line breaks permitted within calls to an operator, specified as
+    /// OP=WRAPPING where WRAPPING is one of "unsplit" or "between-arguments".
+    /// May be specified multiple times.
+    #[arg(long, value_name = "OP=WRAPPING", value_parser = parse_call_wrapping)]
+    call_wrapping: Vec<(String, CallWrapping)>,
+}
+
+impl FormatArgs {
+    /// Construct the [`FormatOptions`] specified by `config`, overridden by
+    /// any CLI arguments.
+    fn format_options(&self, config: &Config) -> FormatOptions {
+        let mut opts = config.apply(FormatOptions::default());
+
+        if let Some(v) = self.line_width {
+            opts = opts.with_line_width(v);
+        }
+        if let Some(v) = self.indent_width {
+            opts = opts.with_indent_width(v);
+        }
+        if let Some(v) = self.max_indent_depth {
+            opts = opts.with_max_indent_depth(v);
+        }
+        if let Some(v) = self.hard_tabs {
+            opts = opts.with_hard_tabs(v);
+        }
+        if let Some(v) = self.collapse_single_item_lists {
+            opts = opts.with_collapse_single_item_lists(v);
+        }
+        if let Some(v) = self.no_align_comments {
+            opts = opts.with_align_comments(!v);
+        }
+        if let Some(v) = self.metadata_comment {
+            opts = opts.with_metadata_comment(v);
+        }
+        if let Some(v) = self.align_assumptions {
+            opts = opts.with_align_assumptions(v);
+        }
+        if let Some(v) = self.normalise_booleans {
+            opts = opts.with_normalise_booleans(v);
+        }
+        if let Some(v) = self.standard_call_wrapping {
+            opts = opts.with_standard_call_wrapping(v);
+        }
+        if let Some(v) = self.sort_record_fields {
+            opts = opts.with_sort_record_fields(v);
+        }
+        if let Some(v) = self.line_wrapping {
+            opts = opts.with_line_wrapping(v);
+        }
+        if let Some(v) = self.assignment_width {
+            opts = opts.with_assignment_width(v);
+        }
+        if let Some(v) = self.strict {
+            opts = opts.with_strict(v);
+        }
+        if let Some(v) = self.skip_verify {
+            opts = opts.with_verify(!v);
+        }
+        if let Some(v) = self.verify_idempotent {
+            opts = opts.with_verify_idempotent(v);
+        }
+        if !self.record_field_order.is_empty() {
+            opts = opts.with_record_field_order(&self.record_field_order);
+        }
+        if let Some(v) = self.divider_length {
+            opts = opts.with_divider_length(v);
+        }
+        if let Some(v) = self.quantifier_list_layout {
+            opts = opts.with_quantifier_list_layout(v);
+        }
+        if let Some(v) = self.line_ending {
+            opts = opts.with_line_ending(v);
+        }
+        if let Some(v) = self.final_newline {
+            opts = opts.with_final_newline(v);
+        }
+        if let Some(v) = &self.license_header {
+            opts = opts.with_license_header(v);
+        }
+
+        let opts = self
+            .protect_comment
+            .iter()
+            .fold(opts, |opts, v| opts.with_protected_comment_prefix(v));
+
+        let opts = self
+            .operator_spacing
+            .iter()
+            .fold(opts, |opts, (op, spacing)| {
+                opts.with_operator_spacing(op, *spacing)
+            });
+
+        self.call_wrapping
+            .iter()
+            .fold(opts, |opts, (op, v)| opts.with_call_wrapping(op, *v))
+    }
+}
+
+/// Discover the `tlafmt.toml` configuration that applies to the spec at
+/// `path`, or the current directory if reading from stdin.
+///
+/// Settings in `tlafmt.toml` take precedence over any `.editorconfig`
+/// properties that apply to `path`.
+fn discover_config(path: Option<&Path>) -> Result<Config, Error> {
+    let dir = match path.and_then(Path::parent) {
+        Some(v) if !v.as_os_str().is_empty() => v,
+        _ => Path::new("."),
+    };
+
+    let config = Config::discover(dir)?.map(|(_, v)| v).unwrap_or_default();
+
+    Ok(match path {
+        Some(path) => config.or(editorconfig::discover(path)?),
+        None => config,
+    })
+}
+
+/// Read the license header comment block from the file at `path`.
+fn read_license_header(path: &str) -> Result<String, String> {
+    std::fs::read_to_string(path).map_err(|e| format!("cannot read license header {path:?}: {e}"))
+}
+
+/// Parse a 1-indexed, inclusive `START:END` line range into a 0-indexed range.
+fn parse_line_range(s: &str) -> Result<Range<usize>, String> {
+    let invalid = || format!("invalid line range {s:?} (expected START:END)");
+
+    let (start, end) = s.split_once(':').ok_or_else(invalid)?;
+    let start = start.parse::<usize>().map_err(|_| invalid())?;
+    let end = end.parse::<usize>().map_err(|_| invalid())?;
+
+    if start == 0 || end < start {
+        return Err(format!(
+            "invalid line range {s:?} (lines start at 1, and END must not be before START)"
+        ));
+    }
+
+    Ok(start - 1..end)
+}
+
+/// Parse an `OP=SPACING` operator spacing override.
+fn parse_operator_spacing(s: &str) -> Result<(String, OperatorSpacing), String> {
+    let (op, spacing) = s
+        .rsplit_once('=')
+        .ok_or_else(|| format!("invalid operator spacing {s:?} (expected OP=SPACING)"))?;
+
+    if op.is_empty() {
+        return Err(format!("invalid operator spacing {s:?} (missing operator)"));
+    }
+
+    Ok((op.to_string(), spacing.parse()?))
+}
+
+/// Parse an `OP=WRAPPING` call wrapping hint.
+fn parse_call_wrapping(s: &str) -> Result<(String, CallWrapping), String> {
+    let (op, wrapping) = s
+        .split_once('=')
+        .ok_or_else(|| format!("invalid call wrapping {s:?} (expected OP=WRAPPING)"))?;
+
+    if op.is_empty() {
+        return Err(format!("invalid call wrapping {s:?} (missing operator)"));
+    }
+
+    Ok((op.to_string(), wrapping.parse()?))
 }
 
 #[derive(Debug, Error)]
@@ -57,8 +882,8 @@ enum Error {
     Format(#[from] libtlafmt::Error),
 
     /// Creating a temporary file for --in-place output.
-    #[error("failed to create temporary file in current dir: {0}")]
-    CreateTempFile(std::io::Error),
+    #[error("failed to create temporary file in {0}: {1}")]
+    CreateTempFile(PathBuf, std::io::Error),
 
     /// Flushing the formatted output through the buffered writer for
     /// --in-place.
@@ -69,38 +894,883 @@ enum Error {
     #[error("failed to persist formatted output: {0}")]
     SaveTempFile(std::io::Error),
 
+    /// Copying the permissions of the original file to the formatted output
+    /// for --in-place.
+    #[error("failed to copy file permissions: {0}")]
+    CopyMetadata(std::io::Error),
+
+    /// Reading a configuration file failed.
+    #[error("failed to read config file {0}: {1}")]
+    ReadConfig(PathBuf, std::io::Error),
+
+    /// A configuration file contains invalid content.
+    #[error("invalid config file {0}: {1}")]
+    ParseConfig(PathBuf, String),
+
+    /// Searching a directory for specs failed.
+    #[error("failed to search directory {0}: {1}")]
+    Walk(PathBuf, std::io::Error),
+
+    /// Writing the cache file failed.
+    #[error("failed to write cache file {0}: {1}")]
+    Cache(PathBuf, std::io::Error),
+
+    /// Running a git command failed.
+    #[error("git error: {0}")]
+    Git(String),
+
+    /// A formatted spec contains a character that cannot be represented in
+    /// the input encoding.
+    #[error("cannot encode {0:?} in the input encoding")]
+    Encode(char),
+
+    /// Writing the output failed.
+    #[error("failed to write output: {0}")]
+    Write(std::io::Error),
+
+    /// A malformed language server message was received.
+    #[error("language server error: {0}")]
+    Lsp(String),
+
     /// A non-UTF8 string was generated (likely from non-UTF8 input).
     #[error("non-utf8 string found: {0}")]
     Utf8(#[from] FromUtf8Error),
 }
 
+impl Error {
+    /// Returns true if this is an error writing to stdout after the reader
+    /// closed it, such as when piping the output to `head`.
+    fn is_broken_pipe(&self) -> bool {
+        matches!(self, Self::Write(e) if e.kind() == std::io::ErrorKind::BrokenPipe)
+    }
+}
+
 fn main() -> Result<(), Error> {
-    let args = Args::parse();
+    match run() {
+        // Stop quietly once the output is no longer being read, like other Unix
+        // filters.
+        Err(e) if e.is_broken_pipe() => Ok(()),
+        v => v,
+    }
+}
+
+fn run() -> Result<(), Error> {
+    let args = Args::parse_from(stdin_alias(std::env::args_os()));
+
+    match &args.command {
+        Some(Command::Rename {
+            file,
+            from,
+            to,
+            in_place,
+            format,
+        }) => {
+            let options = format.format_options(&discover_config(Some(file))?);
+            return rename(file, from, to, *in_place, &options);
+        }
+        Some(Command::BlameIgnore {
+            rev,
+            annotate,
+            format,
+        }) => {
+            return blame::ignore_revs(rev, annotate.as_deref(), format);
+        }
+        Some(Command::Sections { file }) => {
+            return sections(file);
+        }
+        Some(Command::EditorSetup { editor, output }) => {
+            return editor_setup(*editor, output.as_deref());
+        }
+        Some(Command::Rules { all, format }) => {
+            let options = format.format_options(&discover_config(None)?);
+            return rules(*all, &options);
+        }
+        Some(Command::Lsp { format }) => {
+            return lsp::serve(std::io::stdin().lock(), std::io::stdout().lock(), |path| {
+                Ok(format.format_options(&discover_config(path)?))
+            });
+        }
+        Some(Command::Parse { file, emit, format }) => {
+            let options = format.format_options(&discover_config(Some(file))?);
+            return parse::dump(file, *emit, &options);
+        }
+        Some(Command::Man { output }) => {
+            return man(output.as_deref());
+        }
+        Some(Command::Snapshots(SnapshotsCommand::Diff { dir, width })) => {
+            return snapshots::diff(dir, *width);
+        }
+        Some(Command::Snapshots(SnapshotsCommand::Coverage { dir, stubs })) => {
+            return coverage::report(dir, stubs.as_deref());
+        }
+        None => {}
+    }
+
+    if args.print_config {
+        let options = args
+            .format
+            .format_options(&discover_config(args.file.first().map(PathBuf::as_path))?);
+        return print_config(&options);
+    }
+
+    if args.batch {
+        return batch::run(
+            std::io::stdin().lock(),
+            std::io::stdout().lock(),
+            &args.format,
+        );
+    }
+
+    let files = match (args.changed, &args.since) {
+        (_, Some(rev)) => paths::changed(rev)?,
+        (true, None) => paths::changed("HEAD")?,
+        (false, None) => paths::expand(&args.file)?,
+    };
+    let mut cache = args.cache_path().map(|v| Cache::load(&v));
+    let mut stats = args.stats.then(Stats::default);
 
-    let input = match args.file.as_ref() {
-        Some(v) => std::fs::read_to_string(v),
-        None => std::io::read_to_string(std::io::stdin().lock()),
+    if args.emit == Some(Emit::Checkstyle) {
+        checkstyle::begin(&mut std::io::stdout().lock()).map_err(Error::Write)?;
+    }
+
+    match files.as_slice() {
+        // Directories (or repositories) that contain no specs have nothing to
+        // format.
+        [] if !args.file.is_empty() || args.changed || args.since.is_some() => Ok(()),
+        // A single input (or stdin) is formatted and any error returned as-is.
+        [] | [_] => {
+            // Specs discovered from a directory or git are always named in the
+            // output.
+            let discovered =
+                args.changed || args.since.is_some() || args.file.iter().any(|v| v.is_dir());
+
+            let path = files.first().map(PathBuf::as_path);
+            let outcome = match format_file(path, &args, discovered, cache.as_mut(), stats.as_mut())
+            {
+                Ok(v) => v,
+                // Name the spec read from stdin in the error, if known.
+                Err(e) => match &args.stdin_filepath {
+                    Some(name) if !e.is_broken_pipe() => {
+                        eprintln!("{}: {e}", name.display());
+                        std::process::exit(1);
+                    }
+                    _ => return Err(e),
+                },
+            };
+            cache.as_ref().map(Cache::save).transpose()?;
+            end_report(&args)?;
+            print_stats(stats.as_ref());
+
+            match outcome {
+                _ if args.exit_zero => Ok(()),
+                Outcome::Unformatted => std::process::exit(EXIT_UNFORMATTED),
+                Outcome::Invalid => std::process::exit(EXIT_INVALID),
+                Outcome::Formatted | Outcome::Quit => Ok(()),
+            }
+        }
+        _ if args.output.is_some() => Args::command()
+            .error(
+                clap::error::ErrorKind::ArgumentConflict,
+                "the argument '--output <PATH>' cannot be used with multiple input files",
+            )
+            .exit(),
+        _ => format_files(&files, &args, cache, stats),
+    }
+}
+
+/// Format each of the input `files`, reporting per-file errors to stderr
+/// without aborting the run.
+///
+/// The specs formatted in full are formatted concurrently with
+/// [`format_many()`] before any output is wrote, after which the results are
+/// reported in the order of `files`.
+fn format_files(
+    files: &[PathBuf],
+    args: &Args,
+    mut cache: Option<Cache>,
+    mut stats: Option<Stats>,
+) -> Result<(), Error> {
+    let mut failed = false;
+    let mut unformatted = false;
+    let mut invalid = false;
+
+    let mut specs = vec![];
+    for path in files {
+        if let Some(reason) = paths::skip_reason(path, args.follow_symlinks) {
+            if !args.quiet {
+                eprintln!("{}: skipped {reason}", path.display());
+            }
+            continue;
+        }
+
+        specs.push((path, read_spec(Some(path), args, true, cache.as_ref())));
+    }
+
+    let results = format_batch(&specs, args);
+    for ((path, spec), result) in specs.into_iter().zip(results) {
+        let outcome =
+            spec.and_then(|v| write_spec(v, result, args, true, cache.as_mut(), stats.as_mut()));
+        match outcome {
+            Ok(Outcome::Formatted) => {}
+            Ok(Outcome::Unformatted) => unformatted = true,
+            Ok(Outcome::Invalid) => invalid = true,
+            Ok(Outcome::Quit) => break,
+            Err(e) if e.is_broken_pipe() => return Err(e),
+            Err(e) => {
+                eprintln!("{}: {e}", path.display());
+                failed = true;
+            }
+        }
+    }
+
+    cache.as_ref().map(Cache::save).transpose()?;
+    end_report(args)?;
+    print_stats(stats.as_ref());
+
+    if failed {
+        std::process::exit(1);
+    }
+    if invalid && !args.exit_zero {
+        std::process::exit(EXIT_INVALID);
+    }
+    if unformatted && !args.exit_zero {
+        std::process::exit(EXIT_UNFORMATTED);
+    }
+
+    Ok(())
+}
+
+/// Format each of the read `specs` that is formatted in full (rather than
+/// only selected definitions or lines, or skipped by the cache) with
+/// [`format_many()`], returning the result for each spec in order.
+///
+/// Specs are formatted in groups sharing the same options.
+fn format_batch(
+    specs: &[(&PathBuf, Result<Spec<'_>, Error>)],
+    args: &Args,
+) -> Vec<Option<FileResult>> {
+    let mut out = specs.iter().map(|_| None).collect::<Vec<_>>();
+    if !args.only.is_empty() {
+        return out;
+    }
+
+    let mut groups: Vec<(&FormatOptions, Vec<usize>)> = vec![];
+    for (i, (_, spec)) in specs.iter().enumerate() {
+        let Some(spec) = spec
+            .as_ref()
+            .ok()
+            .filter(|v| !v.cached && v.lines.is_none())
+        else {
+            continue;
+        };
+        match groups.iter_mut().find(|(v, _)| **v == spec.options) {
+            Some((_, v)) => v.push(i),
+            None => groups.push((&spec.options, vec![i])),
+        }
+    }
+
+    for (options, indexes) in groups {
+        let files = indexes.iter().filter_map(|&i| {
+            let (path, spec) = &specs[i];
+            let spec = spec.as_ref().ok()?;
+            Some((path.to_path_buf(), spec.input.clone()))
+        });
+        for (i, v) in indexes.iter().zip(format_many(files, options)) {
+            out[*i] = Some(v);
+        }
+    }
+
+    out
+}
+
+/// Write the end of the machine-readable report selected by `args`, if any.
+fn end_report(args: &Args) -> Result<(), Error> {
+    match args.emit {
+        Some(Emit::Checkstyle) => {
+            checkstyle::end(&mut std::io::stdout().lock()).map_err(Error::Write)
+        }
+        None => Ok(()),
+    }
+}
+
+/// Print the `--stats` summary to stderr, if enabled.
+fn print_stats(stats: Option<&Stats>) {
+    if let Some(v) = stats {
+        eprint!("{v}");
+    }
+}
+
+/// The result of formatting a single spec.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    /// The spec was formatted, or left unchanged at the user's request.
+    Formatted,
+
+    /// Running in check (or list) mode and the spec is not formatted.
+    Unformatted,
+
+    /// The spec contains syntax errors, and was formatted (or checked) except
+    /// for the definitions containing them.
+    Invalid,
+
+    /// The user asked to stop processing any further specs.
+    Quit,
+}
+
+/// A spec read from disk (or stdin), and how it is to be formatted.
+struct Spec<'a> {
+    /// The path the spec was read from, or [`None`] for stdin.
+    path: Option<&'a Path>,
+
+    /// The decoded content of the spec.
+    input: String,
+
+    /// The encoding `input` was decoded from.
+    encoding: Encoding,
+
+    /// The options the spec is formatted with.
+    options: FormatOptions,
+
+    /// The options the formatted spec is recorded in the cache with.
+    cache_options: FormatOptions,
+
+    /// The lines to reformat, or [`None`] if the whole spec is formatted.
+    lines: Option<Vec<Range<usize>>>,
+
+    /// True if the spec is recorded as formatted in the cache, and is skipped.
+    cached: bool,
+
+    /// The time the spec was read.
+    start: Instant,
+}
+
+/// Format the spec at `path` (or stdin if [`None`]) as configured by `args`,
+/// as one of `multiple` specs being formatted.
+///
+/// In check and in-place mode, specs recorded as formatted in `cache` are
+/// skipped, and newly formatted specs are recorded. The changes made are
+/// recorded in `stats`, if any.
+fn format_file(
+    path: Option<&Path>,
+    args: &Args,
+    multiple: bool,
+    cache: Option<&mut Cache>,
+    stats: Option<&mut Stats>,
+) -> Result<Outcome, Error> {
+    let spec = read_spec(path, args, multiple, cache.as_deref())?;
+    write_spec(spec, None, args, multiple, cache, stats)
+}
+
+/// Read the spec at `path` (or stdin if [`None`]), resolving the options it
+/// is formatted with as one of `multiple` specs.
+fn read_spec<'a>(
+    path: Option<&'a Path>,
+    args: &Args,
+    multiple: bool,
+    cache: Option<&Cache>,
+) -> Result<Spec<'a>, Error> {
+    let start = Instant::now();
+    let input = match path {
+        Some(v) => std::fs::read(v),
+        None => {
+            let mut buf = Vec::new();
+            std::io::stdin().lock().read_to_end(&mut buf).map(|_| buf)
+        }
     }
     .map_err(Error::ReadFile)?;
+    let (input, encoding) = args.encoding.decode(input)?;
+
+    // The path naming the spec in the output, which may be provided for stdin.
+    let name = path.or(args.stdin_filepath.as_deref());
 
-    let parsed = ParsedFile::new(input.as_str())?;
+    let options = args.format.format_options(&discover_config(name)?);
+    let cache_options = options.clone();
+
+    // Only check, list and in-place mode leave formatted specs unchanged.
+    let cached = path
+        .zip(cache.filter(|_| args.check || args.list_different || args.in_place))
+        .is_some_and(|(path, cache)| cache.is_formatted(path, input.as_bytes(), &cache_options));
+
+    // Output of multiple files concatenated to stdout is newline delimited.
+    let options = match multiple && !args.in_place && !args.check && !args.list_different {
+        true => options.with_final_newline(FinalNewline::Always),
+        false => options,
+    };
+
+    let lines = match cached {
+        true => None,
+        false => args.changed_lines(path)?,
+    };
+
+    Ok(Spec {
+        path,
+        input,
+        encoding,
+        options,
+        cache_options,
+        lines,
+        cached,
+        start,
+    })
+}
+
+/// Write the formatted `spec` as configured by `args`, as one of `multiple`
+/// specs being formatted.
+///
+/// If the spec was already formatted in full, the `result` is used instead of
+/// formatting it again.
+fn write_spec(
+    spec: Spec<'_>,
+    result: Option<FileResult>,
+    args: &Args,
+    multiple: bool,
+    cache: Option<&mut Cache>,
+    stats: Option<&mut Stats>,
+) -> Result<Outcome, Error> {
+    let Spec {
+        path,
+        input,
+        encoding,
+        options,
+        cache_options,
+        lines,
+        cached,
+        start,
+    } = spec;
+    let name = path.or(args.stdin_filepath.as_deref());
+
+    if cached {
+        if let Some(stats) = stats {
+            stats.skipped();
+        }
+        log(args, name, "skipped (unchanged since last run)", start);
+        return Ok(Outcome::Formatted);
+    }
+    let cache = path.zip(cache.filter(|_| args.check || args.list_different || args.in_place));
+
+    // Specs formatted by format_batch() carry their syntax errors back in the
+    // result's diagnostics, and are only parsed again to render a diff.
+    let only = args.only.iter().map(String::as_str).collect::<Vec<_>>();
+    let (parsed, diagnostics, formatted) = match (lines, result) {
+        (None, Some(result)) if only.is_empty() => {
+            (None, result.diagnostics, result.output.map_err(Error::from))
+        }
+        (lines, _) => {
+            let parsed = ParsedFile::new(input.as_str())?;
+            let (diagnostics, formatted) = match lines {
+                _ if !only.is_empty() => (
+                    syntax_errors(&parsed),
+                    parsed
+                        .format_definitions(&only, &options)
+                        .map_err(Error::from),
+                ),
+                Some(lines) => (
+                    syntax_errors(&parsed),
+                    parsed.format_lines(&lines, &options).map_err(Error::from),
+                ),
+                None => {
+                    let mut buf = Vec::with_capacity(input.len());
+                    let diagnostics = parsed.format_with_diagnostics(&options, &mut buf)?;
+                    (diagnostics, String::from_utf8(buf).map_err(Error::from))
+                }
+            };
+            (Some(parsed), diagnostics, formatted)
+        }
+    };
 
-    if args.check {
+    let invalid = diagnostics
+        .iter()
+        .any(|v| matches!(v, Diagnostic::SyntaxError { .. }));
+    if invalid && !args.quiet {
+        report_syntax_errors(name, &diagnostics);
+    }
+    warn(args, name, &diagnostics);
+    let formatted = formatted?;
+
+    let done = match invalid {
+        true => Outcome::Invalid,
+        false => Outcome::Formatted,
+    };
+
+    if let Some(stats) = stats {
+        stats.record(name, &input, &formatted);
+    }
+
+    if args.check || args.list_different {
         assert!(!args.in_place);
-        return check(&input, parsed);
+        let header = name.filter(|_| multiple);
+        let final_newline = options.final_newline();
+        let is_formatted = match (args.list_different, args.diff, args.emit) {
+            (true, _, _) => list_different(&input, &formatted, final_newline, name)?,
+            (false, true, _) => unified_diff(&input, &formatted, final_newline, name)?,
+            (false, false, Some(Emit::Checkstyle)) => {
+                checkstyle(&input, &formatted, final_newline, name)?
+            }
+            (false, false, None) if args.quiet => is_unchanged(&input, &formatted, final_newline),
+            (false, false, None) if is_unchanged(&input, &formatted, final_newline) => true,
+            (false, false, None) => {
+                let parsed = parsed.map_or_else(|| ParsedFile::new(input.as_str()), Ok)?;
+                print_diff(&input, &parsed, &formatted, header, args.diff_limit());
+                false
+            }
+        };
+        let status = match is_formatted {
+            true => "checked, formatted",
+            false => "checked, needs formatting",
+        };
+        log(args, name, status, start);
+        return match (is_formatted, invalid) {
+            (_, true) => Ok(Outcome::Invalid),
+            (true, false) => {
+                if let Some((path, cache)) = cache {
+                    cache.insert(path, input.as_bytes(), &cache_options);
+                }
+                Ok(Outcome::Formatted)
+            }
+            (false, false) => Ok(Outcome::Unformatted),
+        };
     }
 
+    if args.interactive {
+        let path = path.expect("--interactive requires --in-place");
+        let parsed = parsed.map_or_else(|| ParsedFile::new(input.as_str()), Ok)?;
+        return interactive(
+            path,
+            &input,
+            encoding,
+            &parsed,
+            formatted,
+            args.diff_limit(),
+            args.temp_dir.as_deref(),
+        )
+        .map(|v| match v {
+            Outcome::Formatted => done,
+            v => v,
+        });
+    }
+
+    // Render the formatted output in the same encoding as the input.
+    let render = |w: &mut dyn Write| -> Result<(), Error> {
+        w.write_all(&encoding.encode(formatted.as_bytes().to_vec())?)
+            .map_err(Error::Write)
+    };
+
     if args.in_place {
         assert!(!args.check);
-        assert!(args.file.is_some()); // Not --stdin
-        return in_place(args, &parsed);
+        let path = path.expect("--in-place conflicts with --stdin");
+        // Leave specs that check mode reports as formatted untouched,
+        // preserving their mtime.
+        let unchanged = is_unchanged(&input, &formatted, options.final_newline());
+        match unchanged {
+            true => log(args, Some(path), "unchanged", start),
+            false => {
+                in_place(path, args.temp_dir.as_deref(), render)?;
+                log(args, Some(path), "written", start);
+            }
+        }
+        if let Some((path, cache)) = cache.filter(|_| !invalid) {
+            let written = if unchanged { &input } else { &formatted };
+            cache.insert(path, written.as_bytes(), &cache_options);
+        }
+        return Ok(done);
+    }
+
+    if let Some(output) = &args.output {
+        in_place(output, args.temp_dir.as_deref(), render)?;
+        log(args, Some(output), "written", start);
+        return Ok(done);
+    }
+
+    render(&mut std::io::stdout().lock())?;
+    log(args, name, "formatted", start);
+    Ok(done)
+}
+
+/// Log the `status` of the spec at `path` (or stdin if [`None`]) to stderr with
+/// the time taken since `start`, if running with `--verbose`.
+fn log(args: &Args, path: Option<&Path>, status: &str, start: Instant) {
+    if args.verbose {
+        let path = path.unwrap_or(Path::new("<stdin>"));
+        eprintln!("{}: {status} in {:.2?}", path.display(), start.elapsed());
+    }
+}
+
+/// Print the `diagnostics` raised formatting the spec at `path` (or stdin if
+/// [`None`]) to stderr, unless running with `--quiet`.
+fn warn(args: &Args, path: Option<&Path>, diagnostics: &[Diagnostic]) {
+    if args.quiet {
+        return;
+    }
+    let path = path.unwrap_or(Path::new("<stdin>"));
+    for d in diagnostics {
+        // Limiting deep indentation is a routine part of the layout, and syntax
+        // errors (and the regions left unformatted by them) are reported with
+        // their location by report_syntax_errors().
+        if matches!(
+            d,
+            Diagnostic::IndentLimited { .. }
+                | Diagnostic::SyntaxError { .. }
+                | Diagnostic::VerbatimRegion { .. }
+        ) {
+            continue;
+        }
+        eprintln!("{}: {d}", path.display());
+    }
+}
+
+/// Returns a [`Diagnostic::SyntaxError`] for each syntax error in `parsed`.
+fn syntax_errors(parsed: &ParsedFile<'_>) -> Vec<Diagnostic> {
+    parsed
+        .syntax_errors()
+        .into_iter()
+        .map(Diagnostic::from)
+        .collect()
+}
+
+/// Print the location of each [`Diagnostic::SyntaxError`] in `diagnostics`,
+/// read from the spec at `path` (or stdin if [`None`]), to stderr.
+fn report_syntax_errors(path: Option<&Path>, diagnostics: &[Diagnostic]) {
+    let path = path.unwrap_or(Path::new("<stdin>"));
+    for d in diagnostics {
+        let Diagnostic::SyntaxError {
+            line,
+            column,
+            excerpt,
+            missing,
+        } = d
+        else {
+            continue;
+        };
+        match missing {
+            Some(kind) => eprintln!("{}:{line}:{column}: missing {kind}", path.display()),
+            None => eprintln!(
+                "{}:{line}:{column}: syntax error: {excerpt}",
+                path.display()
+            ),
+        }
+    }
+}
+
+/// Print the changes from `input` (parsed as `parsed`) to `formatted` for the
+/// spec at `path` and prompt on stdin whether to apply them.
+fn interactive(
+    path: &Path,
+    input: &str,
+    encoding: Encoding,
+    parsed: &ParsedFile<'_>,
+    formatted: String,
+    limit: Option<usize>,
+    temp_dir: Option<&Path>,
+) -> Result<Outcome, Error> {
+    if formatted.trim_ascii() == input.trim_ascii() {
+        return Ok(Outcome::Formatted);
+    }
+
+    print_diff(input, parsed, &formatted, Some(path), limit);
+
+    let mut answer = String::new();
+    loop {
+        eprint!("Apply these changes to {} [y,n,q]? ", path.display());
+
+        answer.clear();
+        if std::io::stdin()
+            .read_line(&mut answer)
+            .map_err(Error::ReadFile)?
+            == 0
+        {
+            // Treat the end of input as a request to stop.
+            eprintln!();
+            return Ok(Outcome::Quit);
+        }
+
+        match answer.trim() {
+            "y" => break,
+            "n" => return Ok(Outcome::Formatted),
+            "q" => return Ok(Outcome::Quit),
+            _ => eprintln!("y - apply the changes\nn - skip this file\nq - quit"),
+        }
+    }
+
+    let out = encoding.encode(formatted.into_bytes())?;
+    in_place(path, temp_dir, |w| w.write_all(&out).map_err(Error::Write))?;
+
+    Ok(Outcome::Formatted)
+}
+
+fn rename(
+    path: &Path,
+    from: &str,
+    to: &str,
+    in_place_output: bool,
+    options: &FormatOptions,
+) -> Result<(), Error> {
+    let input = std::fs::read_to_string(path).map_err(Error::ReadFile)?;
+    let renamed = ParsedFile::new(input.as_str())?.rename(from, to, options)?;
+
+    if in_place_output {
+        return in_place(path, None, |w| {
+            w.write_all(renamed.as_bytes()).map_err(Error::Write)
+        });
+    }
+
+    std::io::stdout()
+        .lock()
+        .write_all(renamed.as_bytes())
+        .map_err(Error::Write)
+}
+
+/// Print the top-level units of each spec in `paths` that are out of the
+/// conventional section order, exiting with [`EXIT_MISPLACED`] if any are
+/// found.
+fn sections(paths: &[PathBuf]) -> Result<(), Error> {
+    let mut out = std::io::stdout().lock();
+    let mut found = false;
+
+    for path in paths {
+        let input = std::fs::read_to_string(path).map_err(Error::ReadFile)?;
+        for v in ParsedFile::new(input.as_str())?.misplaced_sections() {
+            found = true;
+            writeln!(
+                out,
+                "{}:{}: {} ({}) appears after the {} section",
+                path.display(),
+                v.line,
+                v.name,
+                v.section,
+                v.after
+            )
+            .map_err(Error::Write)?;
+        }
+    }
+
+    if found {
+        std::process::exit(EXIT_MISPLACED);
     }
 
-    parsed.format(std::io::stdout().lock())?;
     Ok(())
 }
 
-fn in_place(args: Args, parsed: &ParsedFile<'_>) -> Result<(), Error> {
+/// The line spans of the top-level definitions of a spec.
+#[derive(Debug, Default)]
+struct Definitions<'a> {
+    spans: Vec<(&'a str, Range<usize>)>,
+
+    /// The number of lines of leading whitespace trimmed from the diffed spec.
+    offset: usize,
+}
+
+impl<'a> Definitions<'a> {
+    fn new(input: &str, parsed: &'a ParsedFile<'_>) -> Self {
+        Self {
+            spans: parsed.definitions().map(|v| (v.name, v.lines)).collect(),
+            offset: input[..input.len() - input.trim_ascii_start().len()]
+                .matches('\n')
+                .count(),
+        }
+    }
+
+    /// Return the name of the definition containing the diffed `line`, and
+    /// advance it to the next line.
+    fn owner(&self, line: &mut usize) -> Option<&'a str> {
+        let n = *line + self.offset;
+        *line += 1;
+
+        self.spans
+            .iter()
+            .find(|(_, v)| v.contains(&n))
+            .map(|(name, _)| *name)
+    }
+}
+
+/// Print the format-on-save configuration snippet for `editor` to stdout, or
+/// write it to `output`.
+fn editor_setup(editor: Editor, output: Option<&Path>) -> Result<(), Error> {
+    let snippet = editor.snippet();
+    match output {
+        Some(path) => std::fs::write(path, snippet).map_err(Error::Write),
+        None => std::io::stdout()
+            .lock()
+            .write_all(snippet.as_bytes())
+            .map_err(Error::Write),
+    }
+}
+
+/// Print a roff man page generated from the CLI definition to stdout, or write
+/// it to `output`.
+fn man(output: Option<&Path>) -> Result<(), Error> {
+    let page = clap_mangen::Man::new(Args::command());
+    match output {
+        Some(path) => {
+            let mut buf = vec![];
+            page.render(&mut buf).map_err(Error::Write)?;
+            std::fs::write(path, buf).map_err(Error::Write)
+        }
+        None => page
+            .render(&mut std::io::stdout().lock())
+            .map_err(Error::Write),
+    }
+}
+
+/// Print the ID, name and description of the style rules applied with
+/// `options`, or of all rules (marking those not applied) if `all` is true.
+fn rules(all: bool, options: &FormatOptions) -> Result<(), Error> {
+    let mut out = std::io::stdout().lock();
+
+    for rule in Rule::ALL {
+        let active = rule.is_active(options);
+        if !active && !all {
+            continue;
+        }
+
+        let marker = match active {
+            true => "",
+            false => " (inactive)",
+        };
+        writeln!(
+            out,
+            "{}  {:<28}{}{marker}",
+            rule.id(),
+            rule.name(),
+            rule.description()
+        )
+        .map_err(Error::Write)?;
+    }
+
+    Ok(())
+}
+
+/// Print `options` to stdout in the `tlafmt.toml` format.
+fn print_config(options: &FormatOptions) -> Result<(), Error> {
+    let s = toml::to_string(&Config::from_options(options)).expect("config always serialises");
+
+    std::io::stdout()
+        .lock()
+        .write_all(s.as_bytes())
+        .map_err(Error::Write)
+}
+
+/// Write the output of `render` to the file at `path`, replacing any existing
+/// content.
+///
+/// The output is rendered to a temporary file created in `temp_dir`, or next to
+/// `path` if [`None`].
+fn in_place<F>(path: &Path, temp_dir: Option<&Path>, render: F) -> Result<(), Error>
+where
+    F: FnOnce(&mut dyn Write) -> Result<(), Error>,
+{
+    // Replace the target of a symlink, rather than the link itself.
+    let target = match path.is_symlink() {
+        true => std::fs::canonicalize(path).map_err(Error::ReadFile)?,
+        false => path.to_path_buf(),
+    };
+
+    // Tempfiles across filesystems cannot be atomically moved, so default to
+    // the directory of the target.
+    let temp_dir = temp_dir.unwrap_or_else(|| match target.parent() {
+        Some(v) if !v.as_os_str().is_empty() => v,
+        _ => Path::new("."),
+    });
+
     // For in-place output, first render to a temporary file and then move it to
     // the input path (somewhat) atomically to prevent a ctrl+c or crash during
     // execution from causing the input file to be only half populated.
@@ -108,62 +1778,271 @@ fn in_place(args: Args, parsed: &ParsedFile<'_>) -> Result<(), Error> {
         .prefix(".tlafmt")
         // 6 bytes of randomness here
         .suffix(".rs")
-        // Tempfiles across filesystems can be problematic, so use ./
-        .tempfile_in("./")
-        .map_err(Error::CreateTempFile)?;
+        .tempfile_in(temp_dir)
+        .map_err(|e| Error::CreateTempFile(temp_dir.to_path_buf(), e))?;
 
     let mut buffered = BufWriter::new(&mut file);
-    parsed.format(&mut buffered)?;
+    render(&mut buffered)?;
 
     buffered.flush().map_err(Error::FlushTempFile)?;
     drop(buffered);
 
-    file.persist(args.file.unwrap())
+    metadata::copy(&target, file.as_file()).map_err(Error::CopyMetadata)?;
+
+    file.persist(target)
         .map_err(|v| Error::SaveTempFile(v.error))?;
 
     Ok(())
 }
 
-fn check(input: &str, parsed: ParsedFile<'_>) -> Result<(), Error> {
-    // Allocate a buffer to render the normalised spec into, which will be
-    // approximately the same length as the input text.
-    let mut buf = Vec::with_capacity(input.len());
+/// Returns true if `formatted` matches `input`.
+///
+/// Leading and trailing whitespace is ignored when the output never ends with a
+/// newline (the default), so specs ending with a newline are not reported.
+fn is_unchanged(input: &str, formatted: &str, final_newline: FinalNewline) -> bool {
+    match final_newline {
+        FinalNewline::Never => formatted.trim_ascii() == input.trim_ascii(),
+        FinalNewline::Always | FinalNewline::Preserve => formatted == input,
+    }
+}
+
+/// Returns true if `formatted` matches `input`, otherwise printing a unified
+/// diff of the changes to the spec at `path` (or `<stdin>`) to stdout.
+fn unified_diff(
+    input: &str,
+    formatted: &str,
+    final_newline: FinalNewline,
+    path: Option<&Path>,
+) -> Result<bool, Error> {
+    if is_unchanged(input, formatted, final_newline) {
+        return Ok(true);
+    }
+
+    let path = path.unwrap_or(Path::new("<stdin>"));
+    unified::write(
+        &mut std::io::stdout().lock(),
+        &path.to_string_lossy(),
+        input,
+        formatted,
+    )
+    .map_err(Error::Write)?;
+
+    Ok(false)
+}
+
+/// Returns true if `formatted` matches `input`, otherwise printing a checkstyle
+/// `file` element reporting the changes to `path` (or `<stdin>`) to stdout.
+fn checkstyle(
+    input: &str,
+    formatted: &str,
+    final_newline: FinalNewline,
+    path: Option<&Path>,
+) -> Result<bool, Error> {
+    if is_unchanged(input, formatted, final_newline) {
+        return Ok(true);
+    }
+
+    let path = path.unwrap_or(Path::new("<stdin>"));
+    checkstyle::write_file(
+        &mut std::io::stdout().lock(),
+        &path.to_string_lossy(),
+        input,
+        formatted,
+    )
+    .map_err(Error::Write)?;
+
+    Ok(false)
+}
+
+/// Returns true if `formatted` matches `input`, otherwise printing `path` (or
+/// `<stdin>`) to stdout.
+fn list_different(
+    input: &str,
+    formatted: &str,
+    final_newline: FinalNewline,
+    path: Option<&Path>,
+) -> Result<bool, Error> {
+    if is_unchanged(input, formatted, final_newline) {
+        return Ok(true);
+    }
+
+    let path = path.unwrap_or(Path::new("<stdin>"));
+    writeln!(std::io::stdout().lock(), "{}", path.display()).map_err(Error::Write)?;
+
+    Ok(false)
+}
+
+/// Render the changed words of each removed line in `lines` that is replaced
+/// by an added line, returning the rendered text of each line (or [`None`] if
+/// the line is unchanged or has no counterpart).
+///
+/// The first removed line of each run of changes is paired with the first
+/// added line of the same run, the second with the second, and so on.
+fn highlight_changes(lines: &[diff::Result<&str>], rem: Style, add: Style) -> Vec<Option<String>> {
+    let mut out = vec![None; lines.len()];
 
-    parsed.format(&mut buf)?;
+    let mut i = 0;
+    while i < lines.len() {
+        let removed = lines[i..]
+            .iter()
+            .take_while(|v| matches!(v, diff::Result::Left(_)))
+            .count();
+        let added = lines[i + removed..]
+            .iter()
+            .take_while(|v| matches!(v, diff::Result::Right(_)))
+            .count();
 
-    let input = input.trim_ascii();
+        for n in 0..removed.min(added) {
+            let (diff::Result::Left(old), diff::Result::Right(new)) =
+                (&lines[i + n], &lines[i + removed + n])
+            else {
+                unreachable!("runs contain only removed and added lines");
+            };
 
-    // If the strings match, return early.
-    if buf.trim_ascii() == input.as_bytes() {
-        return Ok(());
+            let v = word_diff::highlight(old, new, rem, add);
+            out[i + n] = Some(v.old);
+            out[i + removed + n] = Some(v.new);
+        }
+
+        i += (removed + added).max(1);
     }
 
-    let buf = String::from_utf8(buf)?;
+    out
+}
+
+/// Print a diff of the changes from `input` (parsed as `parsed`) to
+/// `formatted` to stderr, preceded by `header` (if any).
+///
+/// Changes are grouped by the top-level definition they fall within, each
+/// preceded by a hunk header naming the definition. If `limit` is provided, at
+/// most `limit` lines of diff are printed, followed by a summary of the rest.
+fn print_diff(
+    input: &str,
+    parsed: &ParsedFile<'_>,
+    formatted: &str,
+    header: Option<&Path>,
+    limit: Option<usize>,
+) {
     let mut out = std::io::stderr().lock();
 
+    if let Some(path) = header {
+        writeln!(&mut out, "{}:", path.display()).expect("write to stderr");
+    }
+
     // Define the styles used, or skip styling if used in a script.
     let style_none = Style::new();
-    let (style_add, style_rem) = match out.is_terminal() {
+    let (style_add, style_rem, style_hunk) = match out.is_terminal() {
         true => (
             Style::new().fg_color(Some(Color::Ansi(AnsiColor::Green))),
             Style::new().fg_color(Some(Color::Ansi(AnsiColor::Red))),
+            Style::new().fg_color(Some(Color::Ansi(AnsiColor::Cyan))),
         ),
-        false => (style_none, style_none),
+        false => (style_none, style_none, style_none),
     };
 
-    for diff in diff::lines(input, buf.trim_ascii()) {
-        // Reset the colour of the next line.
-        style_add
-            .write_reset_to(&mut out)
-            .expect("reset stderr colour");
+    // Changed words within a line are only highlighted when the output is
+    // styled.
+    let highlight_words = out.is_terminal();
+
+    // Changes are attributed to the definitions of the input (for removed
+    // and unchanged lines) or the formatted output (for added lines), offset by
+    // the leading whitespace trimmed before diffing.
+    let reparsed = ParsedFile::new(formatted).ok();
+    let input_defs = Definitions::new(input, parsed);
+    let output_defs = match &reparsed {
+        Some(v) => Definitions::new(formatted, v),
+        None => Definitions::default(),
+    };
+
+    let mut lines = vec![];
+    let (mut left, mut right) = (0, 0);
+    for diff in diff::lines(input.trim_ascii(), formatted.trim_ascii()) {
+        let owner = match diff {
+            diff::Result::Left(_) => input_defs.owner(&mut left),
+            diff::Result::Right(_) => output_defs.owner(&mut right),
+            diff::Result::Both(..) => {
+                right += 1;
+                input_defs.owner(&mut left)
+            }
+        };
+        lines.push((owner, left + input_defs.offset, diff));
+    }
+
+    // Group consecutive diff lines by the definition they belong to, alongside
+    // the input line the group starts at. A run of changed lines spanning
+    // several definitions is kept in order as a single group, headed by all of
+    // their names.
+    let is_change = |v: &diff::Result<&str>| !matches!(v, diff::Result::Both(..));
+    type Group<'a> = (Vec<Option<&'a str>>, usize, Vec<diff::Result<&'a str>>);
+    let mut groups: Vec<Group<'_>> = vec![];
+    for (owner, line, diff) in lines {
+        match groups.last_mut() {
+            Some((owners, _, lines))
+                if owners.last() == Some(&owner)
+                    || (is_change(&diff) && lines.last().is_some_and(is_change)) =>
+            {
+                if !owners.contains(&owner) {
+                    owners.push(owner);
+                }
+                lines.push(diff);
+            }
+            _ => groups.push((vec![owner], line, vec![diff])),
+        }
+    }
+
+    // Only print groups containing changes, up to `limit` diff lines.
+    let mut printed = 0;
+    let mut groups = groups
+        .into_iter()
+        .filter(|(_, _, lines)| lines.iter().any(is_change));
+    while let Some((owners, start, lines)) = groups.next() {
+        let names = owners.into_iter().flatten().collect::<Vec<_>>();
+        match names.is_empty() {
+            true => writeln!(&mut out, "{style_hunk}@@ line {start} @@"),
+            false => writeln!(&mut out, "{style_hunk}@@ {} @@", names.join(", ")),
+        }
+        .expect("write to stderr");
+
+        let highlighted = match highlight_words {
+            true => highlight_changes(&lines, style_rem, style_add),
+            false => vec![None; lines.len()],
+        };
+
+        for (i, diff) in lines.iter().enumerate() {
+            // Reset the colour of the next line.
+            style_add
+                .write_reset_to(&mut out)
+                .expect("reset stderr colour");
+
+            if limit.is_some_and(|v| printed >= v) {
+                let remaining = lines[i..].iter().filter(|v| is_change(v)).count()
+                    + groups
+                        .map(|(_, _, v)| v.iter().filter(|v| is_change(v)).count())
+                        .sum::<usize>();
+                writeln!(
+                    &mut out,
+                    "... {remaining} more changed lines, run with --full-diff"
+                )
+                .expect("write to stderr");
+                return;
+            }
+            printed += 1;
 
-        match diff {
-            diff::Result::Left(l) => writeln!(&mut out, "{style_rem}- {l}"),
-            diff::Result::Both(l, _) => writeln!(&mut out, "  {l}"),
-            diff::Result::Right(r) => writeln!(&mut out, "{style_add}+ {r}"),
+            let highlighted = highlighted[i].as_deref();
+            match diff {
+                diff::Result::Left(l) => {
+                    writeln!(&mut out, "{style_rem}- {}", highlighted.unwrap_or(l))
+                }
+                diff::Result::Both(l, _) => writeln!(&mut out, "  {l}"),
+                diff::Result::Right(r) => {
+                    writeln!(&mut out, "{style_add}+ {}", highlighted.unwrap_or(r))
+                }
+            }
+            .expect("write to stderr")
         }
-        .expect("write to stderr")
     }
 
-    std::process::exit(3);
+    style_add
+        .write_reset_to(&mut out)
+        .expect("reset stderr colour");
 }
@@ -0,0 +1,101 @@
+//! Preservation of file metadata when replacing a spec with --in-place.
+
+use std::{fs::File, io::ErrorKind, path::Path};
+
+/// Copy the permissions, ownership and extended attributes (including any
+/// ACLs) of the file at `from` to `to`.
+///
+/// Nothing is copied if `from` does not exist. Ownership and extended
+/// attributes are copied on a best-effort basis, as an unprivileged user may
+/// not be permitted to set them.
+pub(crate) fn copy(from: &Path, to: &File) -> std::io::Result<()> {
+    let meta = match std::fs::metadata(from) {
+        Ok(v) => v,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+
+    // Changing the owner may clear the setuid / setgid bits, so restore the
+    // ownership before the permissions.
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        let _ = std::os::unix::fs::fchown(to, Some(meta.uid()), Some(meta.gid()));
+    }
+
+    to.set_permissions(meta.permissions())?;
+
+    #[cfg(target_os = "linux")]
+    xattr::copy(from, to);
+
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+mod xattr {
+    use std::{
+        ffi::{c_char, CString},
+        fs::File,
+        os::{fd::AsRawFd, unix::ffi::OsStrExt},
+        path::Path,
+    };
+
+    /// Copy all the extended attributes the caller can read from the file at
+    /// `from` to `to`, ignoring any that cannot be read or set.
+    pub(super) fn copy(from: &Path, to: &File) {
+        let Ok(from) = CString::new(from.as_os_str().as_bytes()) else {
+            return;
+        };
+
+        let names = read(|buf, len| {
+            // SAFETY: buf is valid for writes of len bytes.
+            unsafe { libc::listxattr(from.as_ptr(), buf, len) }
+        });
+
+        // The list is a sequence of NUL-terminated attribute names.
+        for name in names.split(|v| *v == 0).filter(|v| !v.is_empty()) {
+            let Ok(name) = CString::new(name) else {
+                continue;
+            };
+
+            let value = read(|buf, len| {
+                // SAFETY: buf is valid for writes of len bytes.
+                unsafe { libc::getxattr(from.as_ptr(), name.as_ptr(), buf.cast(), len) }
+            });
+
+            // SAFETY: value is valid for reads of value.len() bytes.
+            unsafe {
+                libc::fsetxattr(
+                    to.as_raw_fd(),
+                    name.as_ptr(),
+                    value.as_ptr().cast(),
+                    value.len(),
+                    0,
+                );
+            }
+        }
+    }
+
+    /// Read a variable length value with `f`, which is called with a NULL
+    /// buffer to query the length before reading into an allocated buffer.
+    ///
+    /// Returns an empty value on error.
+    fn read<F>(f: F) -> Vec<u8>
+    where
+        F: Fn(*mut c_char, usize) -> isize,
+    {
+        let Ok(len) = usize::try_from(f(std::ptr::null_mut(), 0)) else {
+            return vec![];
+        };
+
+        let mut buf = vec![0_u8; len];
+        match usize::try_from(f(buf.as_mut_ptr().cast(), buf.len())) {
+            Ok(n) => {
+                buf.truncate(n);
+                buf
+            }
+            // The value grew between the calls or could not be read.
+            Err(_) => vec![],
+        }
+    }
+}
@@ -0,0 +1,83 @@
+//! Debug output of the parsed AST or lowered token stream of a spec, used to
+//! diagnose why a spec is formatted the way it is.
+
+use std::{io::Write, path::Path};
+
+use clap::ValueEnum;
+use libtlafmt::{FormatOptions, Node, ParsedFile};
+
+use crate::Error;
+
+/// The representation of a spec printed by the parse subcommand.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum Emit {
+    /// The tree-sitter AST, with the 1-indexed line:column span of each node.
+    #[default]
+    Ast,
+
+    /// The formatter tokens lowered from the AST, with the indentation depth in
+    /// effect for each.
+    Tokens,
+}
+
+/// Print the `emit` representation of the spec at `path`, lowered with
+/// `options`, to stdout.
+pub(crate) fn dump(path: &Path, emit: Emit, options: &FormatOptions) -> Result<(), Error> {
+    let input = std::fs::read_to_string(path).map_err(Error::ReadFile)?;
+    let parsed = ParsedFile::new(&input)?;
+    let mut out = std::io::stdout().lock();
+
+    match emit {
+        Emit::Ast => write_node(&mut out, parsed.tree().root_node(), parsed.input(), 0),
+        Emit::Tokens => {
+            for t in parsed.tokens(options)? {
+                write!(out, "{:>2} {}", t.indent, t.name).map_err(Error::Write)?;
+                if let Some(text) = t.text {
+                    write!(out, " {text:?}").map_err(Error::Write)?;
+                }
+                writeln!(out).map_err(Error::Write)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Write `node` and its descendants to `out`, indented by `depth`.
+///
+/// Leaf nodes are followed by their source text. Anonymous nodes are quoted.
+fn write_node<W: Write>(
+    out: &mut W,
+    node: Node<'_>,
+    input: &str,
+    depth: usize,
+) -> Result<(), Error> {
+    let (start, end) = (node.start_position(), node.end_position());
+    let kind = match (node.is_missing(), node.is_named()) {
+        (true, _) => format!("MISSING {}", node.kind()),
+        (false, true) => node.kind().to_string(),
+        (false, false) => format!("{:?}", node.kind()),
+    };
+
+    write!(
+        out,
+        "{:indent$}{kind} {}:{}-{}:{}",
+        "",
+        start.row + 1,
+        start.column + 1,
+        end.row + 1,
+        end.column + 1,
+        indent = depth * 2,
+    )
+    .map_err(Error::Write)?;
+    if node.child_count() == 0 && node.is_named() {
+        write!(out, " {:?}", &input[node.byte_range()]).map_err(Error::Write)?;
+    }
+    writeln!(out).map_err(Error::Write)?;
+
+    let mut c = node.walk();
+    for child in node.children(&mut c) {
+        write_node(out, child, input, depth + 1)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,113 @@
+//! Expansion of directory arguments into the specs they contain.
+
+use std::path::{Path, PathBuf};
+
+use ignore::WalkBuilder;
+
+use crate::{
+    blame::{git, SPEC_EXTENSION},
+    Error,
+};
+
+/// Expand any directories in `paths` into the TLA+ specs they contain,
+/// recursively and in sorted order.
+///
+/// Directories skip any paths ignored by a `.gitignore` or `.ignore` file
+/// (whether or not they are within a git repository), the repository's
+/// `info/exclude` file or the global git excludes file. Git submodules are
+/// searched as any other directory.
+pub(crate) fn expand(paths: &[PathBuf]) -> Result<Vec<PathBuf>, Error> {
+    let mut out = Vec::with_capacity(paths.len());
+
+    for path in paths {
+        if !path.is_dir() {
+            out.push(path.clone());
+            continue;
+        }
+
+        let mut specs = walk(path)?;
+        specs.retain(|v| v.extension().is_some_and(|v| v == SPEC_EXTENSION) && v.is_file());
+        specs.sort();
+
+        out.extend(specs);
+    }
+
+    Ok(out)
+}
+
+/// Returns the reason the spec at `path` should be skipped when formatting
+/// multiple specs, or [`None`] if it should be formatted.
+///
+/// Symlinks are skipped unless `follow_symlinks` is true, and FIFOs, sockets
+/// and device files are always skipped - reading them may block indefinitely,
+/// and they cannot be replaced when formatting in place.
+pub(crate) fn skip_reason(path: &Path, follow_symlinks: bool) -> Option<&'static str> {
+    if path.is_symlink() && !follow_symlinks {
+        return Some("symlink (use --follow-symlinks to format the target)");
+    }
+
+    // Errors are reported when reading the spec.
+    let meta = std::fs::metadata(path).ok()?;
+    if !meta.is_file() {
+        return Some("special file");
+    }
+
+    None
+}
+
+/// Return the specs in the current git repository that have been added or
+/// modified since `rev` (including uncommitted changes), and any untracked
+/// specs that are not ignored.
+pub(crate) fn changed(rev: &str) -> Result<Vec<PathBuf>, Error> {
+    let root = PathBuf::from(git(&["rev-parse", "--show-toplevel"])?.trim());
+    let root_str = root.to_string_lossy();
+
+    let modified = git(&[
+        "-C",
+        &root_str,
+        "diff",
+        "--name-only",
+        "--diff-filter=ACMR",
+        rev,
+        "--",
+    ])?;
+    let untracked = git(&[
+        "-C",
+        &root_str,
+        "ls-files",
+        "--others",
+        "--exclude-standard",
+    ])?;
+
+    let mut specs = modified
+        .lines()
+        .chain(untracked.lines())
+        .map(|v| root.join(v))
+        .filter(|v| v.extension().is_some_and(|v| v == SPEC_EXTENSION) && v.is_file())
+        .collect::<Vec<_>>();
+    specs.sort();
+    specs.dedup();
+
+    Ok(specs)
+}
+
+/// Return all files within `dir` that are not ignored.
+fn walk(dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let walker = WalkBuilder::new(dir)
+        .hidden(false)
+        .require_git(false)
+        .filter_entry(|v| v.file_name() != ".git")
+        .build();
+
+    let mut out = vec![];
+    for entry in walker {
+        let entry = entry.map_err(|e| Error::Walk(dir.to_path_buf(), std::io::Error::other(e)))?;
+
+        // Symlinked directories are not followed, preventing cycles.
+        if !entry.file_type().is_some_and(|v| v.is_dir()) {
+            out.push(entry.into_path());
+        }
+    }
+
+    Ok(out)
+}
@@ -0,0 +1,295 @@
+//! A side-by-side viewer for pending insta snapshot changes, used when
+//! reviewing the effect of formatting rule changes across the test corpus.
+
+use std::{
+    io::{IsTerminal, Write},
+    path::{Path, PathBuf},
+};
+
+use anstyle::Style;
+use clap::builder::styling::{AnsiColor, Color};
+
+use crate::Error;
+
+/// The file extension of a pending snapshot wrote by insta.
+const PENDING_EXTENSION: &str = "new";
+
+/// The separator rendered between the two columns.
+const SEPARATOR: &str = " │ ";
+
+/// TLA+ keywords highlighted when rendering snapshot content.
+const KEYWORDS: &[&str] = &[
+    "ASSUME",
+    "ASSUMPTION",
+    "AXIOM",
+    "BOOLEAN",
+    "CASE",
+    "CHOOSE",
+    "CONSTANT",
+    "CONSTANTS",
+    "DOMAIN",
+    "ELSE",
+    "ENABLED",
+    "EXCEPT",
+    "EXTENDS",
+    "FALSE",
+    "IF",
+    "IN",
+    "INSTANCE",
+    "LET",
+    "LOCAL",
+    "MODULE",
+    "OTHER",
+    "RECURSIVE",
+    "SF_",
+    "SUBSET",
+    "THEN",
+    "THEOREM",
+    "TRUE",
+    "UNCHANGED",
+    "UNION",
+    "VARIABLE",
+    "VARIABLES",
+    "WF_",
+    "WITH",
+];
+
+/// The styles used to render the diff.
+#[derive(Debug, Clone, Copy)]
+struct Styles {
+    add: Style,
+    rem: Style,
+    keyword: Style,
+    comment: Style,
+    literal: Style,
+}
+
+impl Styles {
+    fn new(enabled: bool) -> Self {
+        let fg = |c| Style::new().fg_color(Some(Color::Ansi(c)));
+        match enabled {
+            true => Self {
+                add: fg(AnsiColor::Green),
+                rem: fg(AnsiColor::Red),
+                keyword: fg(AnsiColor::Magenta).bold(),
+                comment: fg(AnsiColor::BrightBlack),
+                literal: fg(AnsiColor::Cyan),
+            },
+            false => Self {
+                add: Style::new(),
+                rem: Style::new(),
+                keyword: Style::new(),
+                comment: Style::new(),
+                literal: Style::new(),
+            },
+        }
+    }
+}
+
+/// Render the before and after of every pending snapshot within `dir` to
+/// stdout, in two columns spanning `width` characters.
+pub(crate) fn diff(dir: &Path, width: usize) -> Result<(), Error> {
+    let mut out = std::io::stdout().lock();
+    let styles = Styles::new(out.is_terminal());
+
+    let mut pending = vec![];
+    find_pending(dir, &mut pending)?;
+    pending.sort();
+
+    for new_path in pending {
+        let old_path = new_path.with_extension("");
+
+        let new = std::fs::read_to_string(&new_path).map_err(Error::ReadFile)?;
+        let old = match std::fs::read_to_string(&old_path) {
+            Ok(v) => v,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(Error::ReadFile(e)),
+        };
+
+        writeln!(out, "==> {}", old_path.display()).map_err(Error::Write)?;
+        render(
+            &mut out,
+            snapshot_body(&old),
+            snapshot_body(&new),
+            width,
+            styles,
+        )
+        .map_err(Error::Write)?;
+        writeln!(out).map_err(Error::Write)?;
+    }
+
+    Ok(())
+}
+
+/// Recursively search `dir` for pending snapshot files, skipping hidden
+/// directories and build output.
+fn find_pending(dir: &Path, out: &mut Vec<PathBuf>) -> Result<(), Error> {
+    for entry in std::fs::read_dir(dir).map_err(Error::ReadFile)? {
+        let path = entry.map_err(Error::ReadFile)?.path();
+
+        let hidden = path
+            .file_name()
+            .is_some_and(|v| v.to_string_lossy().starts_with('.'));
+
+        if path.is_dir() {
+            if !hidden && !path.ends_with("target") {
+                find_pending(&path, out)?;
+            }
+            continue;
+        }
+
+        let is_pending = path.extension().is_some_and(|v| v == PENDING_EXTENSION)
+            && path
+                .file_stem()
+                .is_some_and(|v| Path::new(v).extension().is_some_and(|v| v == "snap"));
+        if is_pending {
+            out.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Return the content of a snapshot file, without the YAML metadata header or
+/// trailing newlines.
+fn snapshot_body(s: &str) -> &str {
+    s.strip_prefix("---\n")
+        .and_then(|v| v.split_once("\n---\n"))
+        .map(|(_, body)| body)
+        .unwrap_or(s)
+        .trim_end_matches('\n')
+}
+
+/// Render the line diff of `old` and `new` into two columns.
+fn render<W: Write>(
+    out: &mut W,
+    old: &str,
+    new: &str,
+    width: usize,
+    styles: Styles,
+) -> std::io::Result<()> {
+    // Each column is prefixed by a two character change marker.
+    let column = (width.saturating_sub(SEPARATOR.chars().count()) / 2).saturating_sub(2);
+
+    let mut removed = vec![];
+    let mut added = vec![];
+    for diff in diff::lines(old, new) {
+        match diff {
+            diff::Result::Left(l) => removed.push(l),
+            diff::Result::Right(r) => added.push(r),
+            diff::Result::Both(l, r) => {
+                render_changes(out, &mut removed, &mut added, column, styles)?;
+                render_row(out, Some(("  ", l)), Some(("  ", r)), column, styles)?;
+            }
+        }
+    }
+
+    render_changes(out, &mut removed, &mut added, column, styles)
+}
+
+/// Render the buffered run of `removed` and `added` lines side-by-side,
+/// draining both.
+fn render_changes<W: Write>(
+    out: &mut W,
+    removed: &mut Vec<&str>,
+    added: &mut Vec<&str>,
+    column: usize,
+    styles: Styles,
+) -> std::io::Result<()> {
+    let rows = removed.len().max(added.len());
+    for i in 0..rows {
+        render_row(
+            out,
+            removed.get(i).map(|v| ("- ", *v)),
+            added.get(i).map(|v| ("+ ", *v)),
+            column,
+            styles,
+        )?;
+    }
+
+    removed.clear();
+    added.clear();
+
+    Ok(())
+}
+
+/// Render a single row, with `left` and `right` given as a (marker, line)
+/// pair, or [`None`] for an empty cell.
+fn render_row<W: Write>(
+    out: &mut W,
+    left: Option<(&str, &str)>,
+    right: Option<(&str, &str)>,
+    column: usize,
+    styles: Styles,
+) -> std::io::Result<()> {
+    let mut row = String::new();
+
+    for (i, cell) in [left, right].into_iter().enumerate() {
+        if i == 1 {
+            row.push_str(SEPARATOR);
+        }
+
+        let Some((marker, line)) = cell else {
+            row.push_str(&" ".repeat(column + 2));
+            continue;
+        };
+
+        let marker_style = match marker {
+            "- " => styles.rem,
+            "+ " => styles.add,
+            _ => Style::new(),
+        };
+        row.push_str(&format!("{marker_style}{marker}{marker_style:#}"));
+
+        // Truncate the line to fit the column, then pad the left column so
+        // the separator remains aligned.
+        let line = line.chars().take(column).collect::<String>();
+        highlight(&line, &mut row, styles);
+        if i == 0 {
+            row.push_str(&" ".repeat(column - line.chars().count()));
+        }
+    }
+
+    writeln!(out, "{}", row.trim_end())
+}
+
+/// Append `line` to `out` with syntax highlighting applied.
+fn highlight(line: &str, out: &mut String, styles: Styles) {
+    let (code, comment) = match line.find("\\*") {
+        Some(i) => line.split_at(i),
+        None => (line, ""),
+    };
+
+    let mut rest = code;
+    while let Some(c) = rest.chars().next() {
+        let (token, style) = if c == '"' {
+            let end = rest[1..].find('"').map(|v| v + 2).unwrap_or(rest.len());
+            (&rest[..end], Some(styles.literal))
+        } else if c.is_ascii_alphanumeric() || c == '_' {
+            let end = rest
+                .find(|v: char| !(v.is_ascii_alphanumeric() || v == '_'))
+                .unwrap_or(rest.len());
+            let word = &rest[..end];
+            let style = if KEYWORDS.contains(&word) {
+                Some(styles.keyword)
+            } else if word.chars().all(|v| v.is_ascii_digit()) {
+                Some(styles.literal)
+            } else {
+                None
+            };
+            (word, style)
+        } else {
+            (&rest[..c.len_utf8()], None)
+        };
+
+        match style {
+            Some(s) => out.push_str(&format!("{s}{token}{s:#}")),
+            None => out.push_str(token),
+        }
+        rest = &rest[token.len()..];
+    }
+
+    if !comment.is_empty() {
+        out.push_str(&format!("{}{comment}{:#}", styles.comment, styles.comment));
+    }
+}
@@ -0,0 +1,99 @@
+//! A summary of the changes made across a run, printed with `--stats`.
+
+use std::{fmt::Display, path::Path};
+
+/// Counters describing the changes made to the specs in a run.
+#[derive(Debug, Default)]
+pub(crate) struct Stats {
+    /// The number of specs processed, including those skipped by the cache.
+    files: usize,
+
+    /// The number of specs whose formatted output differs from the input.
+    changed: usize,
+
+    /// The number of output lines that differ from the input.
+    lines: usize,
+
+    /// The number of end-of-line comments moved to a different column.
+    comments: usize,
+
+    /// The width of the longest output line, and the spec and 1-indexed line
+    /// it was emitted on.
+    longest: Option<(usize, String, usize)>,
+}
+
+impl Stats {
+    /// Record a spec that was not formatted because it is unchanged since it
+    /// was last formatted.
+    pub(crate) fn skipped(&mut self) {
+        self.files += 1;
+    }
+
+    /// Record the formatting of `input` (read from `path`, or stdin if
+    /// [`None`]) into `formatted`.
+    pub(crate) fn record(&mut self, path: Option<&Path>, input: &str, formatted: &str) {
+        self.files += 1;
+
+        for (i, line) in formatted.lines().enumerate() {
+            let width = line.chars().count();
+            if self.longest.as_ref().is_none_or(|(v, _, _)| width > *v) {
+                let name = path.map_or("<stdin>".into(), |v| v.display().to_string());
+                self.longest = Some((width, name, i + 1));
+            }
+        }
+
+        if input == formatted {
+            return;
+        }
+        self.changed += 1;
+
+        // Match the comments of the lines removed and added within each run of
+        // changes to find realigned comments.
+        let (mut removed, mut added) = (vec![], vec![]);
+        for diff in diff::lines(input, formatted).into_iter().chain([
+            // Terminate the last run of changes.
+            diff::Result::Both("", ""),
+        ]) {
+            match diff {
+                diff::Result::Left(v) => removed.push(v),
+                diff::Result::Right(v) => {
+                    self.lines += 1;
+                    added.push(v);
+                }
+                diff::Result::Both(..) => {
+                    for new in added.drain(..) {
+                        if let Some(i) = removed.iter().position(|old| is_realigned(old, new)) {
+                            removed.swap_remove(i);
+                            self.comments += 1;
+                        }
+                    }
+                    removed.clear();
+                }
+            }
+        }
+    }
+}
+
+/// Returns true if `old` and `new` end with the same comment, placed at a
+/// different column.
+fn is_realigned(old: &str, new: &str) -> bool {
+    match (old.find(r"\*"), new.find(r"\*")) {
+        (Some(a), Some(b)) => a != b && old[a..] == new[b..],
+        _ => false,
+    }
+}
+
+impl Display for Stats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "files scanned:      {}", self.files)?;
+        writeln!(f, "files changed:      {}", self.changed)?;
+        writeln!(f, "lines reformatted:  {}", self.lines)?;
+        writeln!(f, "comments realigned: {}", self.comments)?;
+        match &self.longest {
+            Some((width, path, line)) => {
+                writeln!(f, "longest line:       {width} columns ({path}:{line})")
+            }
+            None => writeln!(f, "longest line:       -"),
+        }
+    }
+}
@@ -0,0 +1,86 @@
+//! Rendering of unified diffs, suitable for `patch` and other standard tools.
+
+use std::{io::Write, ops::Range};
+
+/// The number of unchanged lines printed either side of a change.
+const CONTEXT: usize = 3;
+
+/// Write a unified diff of the changes from `old` to `new` for the file at
+/// `path` to `out`.
+pub(crate) fn write(out: &mut impl Write, path: &str, old: &str, new: &str) -> std::io::Result<()> {
+    let old_lines = old.split_inclusive('\n').collect::<Vec<_>>();
+    let new_lines = new.split_inclusive('\n').collect::<Vec<_>>();
+    let ops = diff::slice(&old_lines, &new_lines);
+
+    let is_change = |v: &diff::Result<&&str>| !matches!(v, diff::Result::Both(..));
+    if !ops.iter().any(is_change) {
+        return Ok(());
+    }
+
+    writeln!(out, "--- a/{path}")?;
+    writeln!(out, "+++ b/{path}")?;
+
+    for hunk in hunks(&ops) {
+        // The 0-indexed line numbers of the first line of the hunk.
+        let (mut old_line, mut new_line) = ops[..hunk.start].iter().fold((0, 0), advance);
+        let (old_len, new_len) = ops[hunk.clone()].iter().fold((0, 0), advance);
+
+        // Empty ranges refer to the line before the change.
+        let start = |line: usize, len: usize| match len {
+            0 => line,
+            _ => line + 1,
+        };
+        writeln!(
+            out,
+            "@@ -{},{old_len} +{},{new_len} @@",
+            start(old_line, old_len),
+            start(new_line, new_len)
+        )?;
+
+        for op in &ops[hunk] {
+            let (prefix, line) = match op {
+                diff::Result::Left(v) => ('-', **v),
+                diff::Result::Right(v) => ('+', **v),
+                diff::Result::Both(v, _) => (' ', **v),
+            };
+            (old_line, new_line) = advance((old_line, new_line), op);
+
+            match line.strip_suffix('\n') {
+                Some(v) => writeln!(out, "{prefix}{v}")?,
+                None => writeln!(out, "{prefix}{line}\n\\ No newline at end of file")?,
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Return the ranges of `ops` that form each hunk, including up to
+/// [`CONTEXT`] unchanged lines either side of the changes.
+fn hunks(ops: &[diff::Result<&&str>]) -> Vec<Range<usize>> {
+    let mut hunks: Vec<Range<usize>> = vec![];
+
+    for (i, _) in ops
+        .iter()
+        .enumerate()
+        .filter(|(_, v)| !matches!(v, diff::Result::Both(..)))
+    {
+        let range = i.saturating_sub(CONTEXT)..(i + 1 + CONTEXT).min(ops.len());
+        match hunks.last_mut() {
+            // Merge changes whose context overlaps.
+            Some(v) if v.end >= range.start => v.end = range.end,
+            _ => hunks.push(range),
+        }
+    }
+
+    hunks
+}
+
+/// Advance the (old, new) line counts past `op`.
+fn advance((old, new): (usize, usize), op: &diff::Result<&&str>) -> (usize, usize) {
+    match op {
+        diff::Result::Left(_) => (old + 1, new),
+        diff::Result::Right(_) => (old, new + 1),
+        diff::Result::Both(..) => (old + 1, new + 1),
+    }
+}
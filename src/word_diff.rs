@@ -0,0 +1,81 @@
+//! Highlighting of the changed words within a pair of changed lines.
+
+use anstyle::Style;
+
+/// A removed line and the added line replacing it, each rendered with the
+/// words that differ between them emphasised.
+#[derive(Debug)]
+pub(crate) struct Highlighted {
+    pub(crate) old: String,
+    pub(crate) new: String,
+}
+
+/// Render `old` in the `rem` style and `new` in the `add` style, emphasising
+/// the words that differ between the two lines.
+pub(crate) fn highlight(old: &str, new: &str, rem: Style, add: Style) -> Highlighted {
+    let old_words = words(old);
+    let new_words = words(new);
+
+    // The words of each line, alongside whether they were changed.
+    let (mut old, mut new) = (vec![], vec![]);
+    for diff in diff::slice(&old_words, &new_words) {
+        match diff {
+            diff::Result::Both(v, _) => {
+                old.push((false, *v));
+                new.push((false, *v));
+            }
+            diff::Result::Left(v) => old.push((true, *v)),
+            diff::Result::Right(v) => new.push((true, *v)),
+        }
+    }
+
+    Highlighted {
+        old: render(&old, rem),
+        new: render(&new, add),
+    }
+}
+
+/// Concatenate `words`, emphasising each run of changed words relative to the
+/// `base` line style.
+fn render(words: &[(bool, &str)], base: Style) -> String {
+    let emphasis = base.bold().underline();
+
+    let mut out = String::new();
+    for run in words.chunk_by(|a, b| a.0 == b.0) {
+        let text = run.iter().map(|(_, v)| *v).collect::<String>();
+        match run[0].0 {
+            true => out.push_str(&format!("{emphasis}{text}{emphasis:#}{base}")),
+            false => out.push_str(&text),
+        }
+    }
+
+    out
+}
+
+/// Split `line` into runs of identifier characters, runs of whitespace, and
+/// runs of a single repeated symbol character (such as `----`).
+fn words(line: &str) -> Vec<&str> {
+    let class = |c: char| match c {
+        c if c.is_alphanumeric() || c == '_' => 0,
+        c if c.is_whitespace() => 1,
+        _ => 2,
+    };
+
+    let mut out = vec![];
+    let mut start = 0;
+    let mut chars = line.char_indices().peekable();
+    while let Some((_, c)) = chars.next() {
+        let Some(&(end, next)) = chars.peek() else {
+            break;
+        };
+        if class(c) != class(next) || (class(c) == 2 && c != next) {
+            out.push(&line[start..end]);
+            start = end;
+        }
+    }
+    if start < line.len() {
+        out.push(&line[start..]);
+    }
+
+    out
+}
@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use assert_cmd::Command;
 use insta::assert_snapshot;
@@ -119,6 +119,129 @@ fn test_in_place() {
     assert_eq!(control, got);
 }
 
+/// The temporary file used by --in-place is created in --temp-dir, if set.
+#[test]
+fn test_in_place_temp_dir() {
+    let wd = dir();
+    let file = wd.path().join("test.tla");
+    std::fs::copy(BAD_PATH, &file).expect("cannot copy file for test");
+
+    let missing = wd.path().join("missing");
+    cmd()
+        .arg("--in-place")
+        .arg("--temp-dir")
+        .arg(&missing)
+        .arg(&file)
+        .assert()
+        .code(predicate::eq(1))
+        .stderr(
+            predicate::str::contains("CreateTempFile")
+                .and(predicate::str::contains(missing.display().to_string())),
+        );
+
+    let temp = wd.path().join("temp");
+    std::fs::create_dir(&temp).unwrap();
+    cmd()
+        .arg("--in-place")
+        .arg("--temp-dir")
+        .arg(&temp)
+        .arg(&file)
+        .assert()
+        .success();
+
+    assert_eq!(std::fs::read_to_string(&file).unwrap(), format(BAD_PATH));
+    assert_eq!(std::fs::read_dir(&temp).unwrap().count(), 0);
+}
+
+/// Already formatted specs are not rewrote by --in-place.
+#[test]
+fn test_in_place_unchanged() {
+    let wd = dir();
+    let file = wd.path().join("test.tla");
+
+    // Trailing newlines are not a formatting change unless configured.
+    for content in [format(BAD_PATH), format!("{}\n", format(BAD_PATH))] {
+        std::fs::write(&file, &content).unwrap();
+
+        let old = std::time::SystemTime::UNIX_EPOCH;
+        std::fs::File::options()
+            .write(true)
+            .open(&file)
+            .unwrap()
+            .set_modified(old)
+            .unwrap();
+
+        cmd()
+            .arg("--in-place")
+            .arg("--verbose")
+            .arg(&file)
+            .assert()
+            .success()
+            .stderr(predicate::str::contains("unchanged"));
+
+        assert_eq!(std::fs::metadata(&file).unwrap().modified().unwrap(), old);
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), content);
+    }
+}
+
+/// The permissions of a file formatted --in-place are preserved.
+#[cfg(unix)]
+#[test]
+fn test_in_place_preserves_permissions() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let wd = dir();
+    let file = wd.path().join("test.tla");
+    std::fs::copy(BAD_PATH, &file).expect("cannot copy file for test");
+    std::fs::set_permissions(&file, std::fs::Permissions::from_mode(0o751)).unwrap();
+
+    cmd().arg("--in-place").arg(&file).assert().success();
+
+    assert_eq!(std::fs::read_to_string(&file).unwrap(), format(BAD_PATH));
+    let mode = std::fs::metadata(&file).unwrap().permissions().mode();
+    assert_eq!(mode & 0o7777, 0o751);
+}
+
+/// Write the formatted output to a named file, leaving the input unchanged.
+#[test]
+fn test_output() {
+    let dir = dir();
+    let unformatted = std::fs::read_to_string(BAD_PATH).unwrap();
+    let control = format(BAD_PATH);
+
+    let out = dir.path().join("out.tla");
+    cmd()
+        .arg("-o")
+        .arg(&out)
+        .arg(BAD_PATH)
+        .assert()
+        .success()
+        .stdout(predicate::eq(""))
+        .stderr(predicate::eq(""));
+    assert_eq!(std::fs::read_to_string(&out).unwrap(), control);
+    assert_eq!(std::fs::read_to_string(BAD_PATH).unwrap(), unformatted);
+
+    // Including from stdin, replacing any existing content.
+    cmd()
+        .arg("--output")
+        .arg(&out)
+        .arg("-")
+        .write_stdin(unformatted)
+        .assert()
+        .success();
+    assert_eq!(std::fs::read_to_string(&out).unwrap(), control);
+
+    // Only a single input can be written to a file.
+    cmd()
+        .arg("-o")
+        .arg(&out)
+        .arg(BAD_PATH)
+        .arg(BAD_PATH)
+        .assert()
+        .code(predicate::eq(2))
+        .stderr(predicate::str::contains("multiple input files"));
+}
+
 /// Reject --in-place with --check.
 #[test]
 fn test_in_place_conflicts_check() {
@@ -137,7 +260,7 @@ fn test_in_place_conflicts_check() {
             "\
 error: the argument '--in-place' cannot be used with '--check'
 
-Usage: tlafmt --in-place <FILE>
+Usage: tlafmt --in-place <FILE>...
 
 For more information, try '--help'.
 ",
@@ -181,6 +304,211 @@ fn test_from_stdin() {
         .code(predicate::eq(0));
 }
 
+/// The final newline policy is enforced by --check, which otherwise ignores
+/// trailing newlines.
+#[test]
+fn test_final_newline() {
+    let control = format(BAD_PATH);
+    assert!(!control.ends_with('\n'));
+
+    cmd()
+        .arg("--stdin")
+        .arg("--check")
+        .write_stdin(format!("{control}\n"))
+        .assert()
+        .success();
+
+    cmd()
+        .arg("--stdin")
+        .arg("--final-newline")
+        .arg("always")
+        .write_stdin(control.clone())
+        .assert()
+        .success()
+        .stdout(predicate::eq(format!("{control}\n")));
+
+    cmd()
+        .arg("--stdin")
+        .arg("--check")
+        .arg("--final-newline")
+        .arg("always")
+        .write_stdin(control.clone())
+        .assert()
+        .code(predicate::eq(3));
+
+    // The newline ending the input is retained.
+    for input in [control.clone(), format!("{control}\n")] {
+        cmd()
+            .arg("--stdin")
+            .arg("--check")
+            .arg("--final-newline")
+            .arg("preserve")
+            .write_stdin(input)
+            .assert()
+            .success();
+    }
+}
+
+/// Specs using CRLF line endings are formatted with CRLF line endings, unless
+/// overridden with --line-ending.
+#[test]
+fn test_crlf() {
+    let control = format(BAD_PATH);
+    let crlf = control.replace('\n', "\r\n");
+
+    // A formatted CRLF spec passes the check.
+    cmd()
+        .arg("--stdin")
+        .arg("--check")
+        .write_stdin(crlf.clone())
+        .assert()
+        .success()
+        .stdout(predicate::eq(""))
+        .stderr(predicate::eq(""));
+
+    // And is formatted with LF line endings when requested.
+    cmd()
+        .arg("--stdin")
+        .arg("--line-ending")
+        .arg("lf")
+        .write_stdin(crlf.clone())
+        .assert()
+        .success()
+        .stdout(predicate::eq(control.clone()));
+
+    // Or the reverse.
+    cmd()
+        .arg("--stdin")
+        .arg("--line-ending")
+        .arg("crlf")
+        .write_stdin(control)
+        .assert()
+        .success()
+        .stdout(predicate::eq(crlf));
+}
+
+/// The --stdin-filepath names the spec read from stdin and resolves the config
+/// files that apply to it.
+#[test]
+fn test_stdin_filepath() {
+    let dir = dir();
+    let nested = dir.path().join("specs");
+    std::fs::create_dir_all(&nested).unwrap();
+    std::fs::write(dir.path().join("tlafmt.toml"), "line_width = 40\n").unwrap();
+
+    let path = nested.join("spec.tla");
+    let input = "---- MODULE B ----\nOp == /\\ x = 1\n====\n";
+
+    cmd()
+        .arg("--stdin")
+        .arg("--stdin-filepath")
+        .arg(&path)
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            "\
+--------------- MODULE B ---------------
+Op ==
+    /\\ x = 1
+========================================",
+        ));
+
+    cmd()
+        .arg("--stdin")
+        .arg("--stdin-filepath")
+        .arg(&path)
+        .arg("--list-different")
+        .write_stdin(input)
+        .assert()
+        .code(predicate::eq(3))
+        .stdout(predicate::eq(format!("{}\n", path.display())));
+
+    // Errors name the spec.
+    std::fs::write(dir.path().join("tlafmt.toml"), "bananas = 42\n").unwrap();
+    cmd()
+        .arg("--stdin")
+        .arg("--stdin-filepath")
+        .arg(&path)
+        .write_stdin(input)
+        .assert()
+        .code(predicate::eq(1))
+        .stdout(predicate::eq(""))
+        .stderr(predicate::str::starts_with(format!("{}: ", path.display())));
+
+    // The path is only accepted with --stdin.
+    cmd()
+        .arg("--stdin-filepath")
+        .arg(&path)
+        .arg(&path)
+        .assert()
+        .code(predicate::eq(2));
+}
+
+/// Output piped to a reader that exits early (such as `head`) stops formatting
+/// quietly.
+#[test]
+fn test_closed_stdout() {
+    use std::{
+        io::Write,
+        process::{Command, Stdio},
+    };
+
+    let unformatted = std::fs::read_to_string(BAD_PATH).unwrap();
+
+    let mut child = Command::new(assert_cmd::cargo::cargo_bin("tlafmt"))
+        .arg("--stdin")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    // Close the read end of stdout before the spec is formatted.
+    drop(child.stdout.take());
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(unformatted.as_bytes())
+        .unwrap();
+
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+    assert_eq!(output.status.code(), Some(0));
+}
+
+/// A "-" FILE reads the spec from stdin.
+#[test]
+fn test_from_stdin_dash() {
+    let unformatted = std::fs::read_to_string(BAD_PATH).unwrap();
+    let control = format(BAD_PATH);
+
+    cmd()
+        .arg("-")
+        .write_stdin(unformatted.clone())
+        .assert()
+        .success()
+        .stdout(predicate::eq(control))
+        .stderr(predicate::eq(""));
+
+    cmd()
+        .arg("--check")
+        .arg("-")
+        .write_stdin(unformatted.clone())
+        .assert()
+        .code(predicate::eq(3));
+
+    // It is subject to the same restrictions as --stdin.
+    cmd()
+        .arg("-")
+        .arg(BAD_PATH)
+        .write_stdin(unformatted)
+        .assert()
+        .code(predicate::eq(2))
+        .stdout(predicate::eq(""));
+}
+
 /// Reject --stdin with a file path.
 #[test]
 fn test_from_stdin_conflicts_path() {
@@ -196,9 +524,9 @@ fn test_from_stdin_conflicts_path() {
         .stdout(predicate::eq(""))
         .stderr(predicate::eq(
             "\
-error: the argument '--stdin' cannot be used with '[FILE]'
+error: the argument '--stdin' cannot be used with '[FILE]...'
 
-Usage: tlafmt --stdin [FILE]
+Usage: tlafmt --stdin [FILE]...
 
 For more information, try '--help'.
 ",
@@ -223,10 +551,2059 @@ fn test_from_stdin_conflicts_in_place() {
             "\
 error: the argument '--stdin' cannot be used with '--in-place'
 
-Usage: tlafmt --stdin [FILE]
+Usage: tlafmt --stdin [FILE]...
 
 For more information, try '--help'.
 ",
         ))
         .code(predicate::eq(2));
 }
+
+/// The --line-width flag changes the width of module headers and dividers.
+#[test]
+fn test_line_width() {
+    let input = "---- MODULE B ----\nA == 1\n----\nB == 2\n====\n";
+
+    let out = cmd()
+        .arg("--stdin")
+        .arg("--line-width")
+        .arg("40")
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stderr(predicate::eq(""))
+        .code(predicate::eq(0))
+        .get_output()
+        .stdout
+        .clone();
+
+    let out = String::from_utf8(out).unwrap();
+    for line in out
+        .lines()
+        .filter(|v| v.starts_with("--") || v.starts_with("=="))
+    {
+        assert_eq!(line.len(), 40, "{line:?}");
+    }
+}
+
+/// Assignments are wrapped at the assignment width from the config file, or
+/// the --assignment-width flag.
+#[test]
+fn test_assignment_width() {
+    let dir = dir();
+    let path = dir.path().join("spec.tla");
+    std::fs::write(
+        &path,
+        format!(
+            "{0} MODULE B {0}\nNext ==\n    /\\ fruit' = << bananas, platanos, mangos, apples, pears, plums, cherries, grapes >>\n    /\\ y' = y\n{1}",
+            "-".repeat(35),
+            "=".repeat(80)
+        ),
+    )
+    .unwrap();
+    std::fs::write(
+        dir.path().join("tlafmt.toml"),
+        "line_wrapping = true\nassignment_width = 100\n",
+    )
+    .unwrap();
+
+    cmd().arg("--check").arg(&path).assert().success();
+    cmd()
+        .arg("--print-config")
+        .arg(&path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\nassignment_width = 100\n"));
+
+    // The flag takes precedence.
+    cmd()
+        .arg("--check")
+        .arg("--assignment-width=80")
+        .arg(&path)
+        .assert()
+        .code(predicate::eq(3));
+}
+
+/// Rename a definition, leaving all other text unchanged.
+#[test]
+fn test_rename() {
+    let dir = dir();
+    let path = dir.path().join("spec.tla");
+    std::fs::write(
+        &path,
+        "\
+---- MODULE B ----
+VARIABLE x
+Init   ==   x = 0
+Next == /\\ x' = x + 1
+        /\\ x < 10
+Spec == Init /\\ [][Next]_x
+====
+",
+    )
+    .unwrap();
+
+    let want = "\
+---- MODULE B ----
+VARIABLE x
+Init   ==   x = 0
+Step == /\\ x' = x + 1
+        /\\ x < 10
+Spec == Init /\\ [][Step]_x
+====
+";
+
+    // Print to stdout.
+    cmd()
+        .arg("rename")
+        .arg(&path)
+        .arg("Next")
+        .arg("Step")
+        .assert()
+        .success()
+        .stdout(predicate::eq(want))
+        .stderr(predicate::eq(""));
+
+    // Rename in place.
+    cmd()
+        .arg("rename")
+        .arg("--in-place")
+        .arg(&path)
+        .arg("Next")
+        .arg("Step")
+        .assert()
+        .success()
+        .stdout(predicate::eq(""));
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), want);
+
+    // The old name no longer exists.
+    cmd()
+        .arg("rename")
+        .arg(&path)
+        .arg("Next")
+        .arg("Step")
+        .assert()
+        .failure()
+        .stdout(predicate::eq(""));
+}
+
+/// Formatting options are read from a tlafmt.toml file in the directory of the
+/// input file or any parent, and CLI flags take precedence.
+#[test]
+fn test_config_discovery() {
+    let dir = dir();
+    let nested = dir.path().join("specs").join("nested");
+    std::fs::create_dir_all(&nested).unwrap();
+
+    let path = nested.join("spec.tla");
+    std::fs::write(&path, "---- MODULE B ----\nOp == /\\ x = 1\n====\n").unwrap();
+
+    std::fs::write(
+        dir.path().join("tlafmt.toml"),
+        "\
+line_width = 40
+indent_width = 2
+collapse_single_item_lists = true
+
+[operator_spacing]
+\"=\" = \"tight\"
+",
+    )
+    .unwrap();
+
+    cmd().arg(&path).assert().success().stdout(predicate::eq(
+        "\
+--------------- MODULE B ---------------
+Op == x=1
+========================================",
+    ));
+
+    // CLI flags override the config file.
+    cmd()
+        .arg("--line-width")
+        .arg("30")
+        .arg(&path)
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            "\
+---------- MODULE B ----------
+Op == x=1
+==============================",
+        ));
+
+    // Boolean flags override the config file in either direction.
+    cmd()
+        .arg("--collapse-single-item-lists=false")
+        .arg(&path)
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            "\
+--------------- MODULE B ---------------
+Op ==
+  /\\ x=1
+========================================",
+        ));
+
+    std::fs::write(
+        dir.path().join("tlafmt.toml"),
+        "line_width = 40\ncollapse_single_item_lists = false\n",
+    )
+    .unwrap();
+    cmd()
+        .arg("--collapse-single-item-lists")
+        .arg(&path)
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            "\
+--------------- MODULE B ---------------
+Op == x = 1
+========================================",
+        ));
+
+    // Invalid config files are rejected.
+    std::fs::write(dir.path().join("tlafmt.toml"), "bananas = 42\n").unwrap();
+    cmd()
+        .arg(&path)
+        .assert()
+        .failure()
+        .stdout(predicate::eq(""))
+        .stderr(predicate::str::contains("unknown field `bananas`"));
+}
+
+/// Formatting options are read from `.editorconfig` files, with `tlafmt.toml`
+/// taking precedence.
+#[test]
+fn test_editorconfig() {
+    let dir = dir();
+    let nested = dir.path().join("specs");
+    std::fs::create_dir_all(&nested).unwrap();
+
+    let path = nested.join("spec.tla");
+    std::fs::write(
+        &path,
+        "---- MODULE B ----\nOp == /\\ x = 1\n      /\\ y = 2\n====\n",
+    )
+    .unwrap();
+
+    std::fs::write(
+        dir.path().join(".editorconfig"),
+        "\
+root = true
+
+[*]
+max_line_length = 20
+
+[*.{tla,cfg}]
+indent_style = tab
+insert_final_newline = true
+
+[*.md]
+max_line_length = 100
+",
+    )
+    .unwrap();
+
+    // A closer file overrides the properties of the root file.
+    std::fs::write(
+        nested.join(".editorconfig"),
+        "[specs/*]\n[*.tla]\nmax_line_length = 30\n",
+    )
+    .unwrap();
+
+    cmd().arg(&path).assert().success().stdout(predicate::eq(
+        "\
+---------- MODULE B ----------
+Op ==
+\t/\\ x = 1
+\t/\\ y = 2
+==============================
+",
+    ));
+
+    std::fs::write(dir.path().join("tlafmt.toml"), "line_width = 40\n").unwrap();
+    cmd().arg(&path).assert().success().stdout(predicate::eq(
+        "\
+--------------- MODULE B ---------------
+Op ==
+\t/\\ x = 1
+\t/\\ y = 2
+========================================
+",
+    ));
+
+    // CLI flags override both.
+    cmd()
+        .arg("--hard-tabs=false")
+        .arg(&path)
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            "\
+--------------- MODULE B ---------------
+Op ==
+    /\\ x = 1
+    /\\ y = 2
+========================================
+",
+        ));
+
+    // The final newline policy of the config file takes precedence over the
+    // insert_final_newline property.
+    std::fs::write(
+        dir.path().join("tlafmt.toml"),
+        "line_width = 40\nfinal_newline = \"never\"\n",
+    )
+    .unwrap();
+    cmd().arg(&path).assert().success().stdout(predicate::eq(
+        "\
+--------------- MODULE B ---------------
+Op ==
+\t/\\ x = 1
+\t/\\ y = 2
+========================================",
+    ));
+}
+
+/// Print the configuration resolved for a file.
+#[test]
+fn test_print_config() {
+    let dir = dir();
+    let path = dir.path().join("spec.tla");
+    std::fs::write(&path, "---- MODULE B ----\n====\n").unwrap();
+    std::fs::write(
+        dir.path().join("tlafmt.toml"),
+        "line_width = 40\n\n[operator_spacing]\n\"++\" = \"tight\"\n",
+    )
+    .unwrap();
+
+    cmd()
+        .arg("--print-config")
+        .arg("--hard-tabs")
+        .arg(&path)
+        .assert()
+        .success()
+        .stderr(predicate::eq(""))
+        .stdout(predicate::eq(
+            "\
+line_width = 40
+indent_width = 4
+max_indent_depth = 255
+hard_tabs = true
+collapse_single_item_lists = false
+align_comments = true
+metadata_comment = false
+align_assumptions = false
+normalise_booleans = false
+standard_call_wrapping = false
+sort_record_fields = false
+line_wrapping = false
+strict = false
+divider_length = \"fill\"
+quantifier_list_layout = \"new-line\"
+line_ending = \"detect\"
+final_newline = \"never\"
+
+[operator_spacing]
+\"++\" = \"tight\"
+",
+        ));
+}
+
+/// Render pending snapshot changes side-by-side.
+#[test]
+fn test_snapshots_diff() {
+    let dir = dir();
+    let snapshots = dir.path().join("snapshots");
+    std::fs::create_dir_all(&snapshots).unwrap();
+
+    std::fs::write(
+        snapshots.join("format__a.snap"),
+        "---\nsource: a\n---\n---- MODULE A ----\nOp == 1   \\* one\n====\n",
+    )
+    .unwrap();
+    std::fs::write(
+        snapshots.join("format__a.snap.new"),
+        "---\nsource: a\n---\n---- MODULE A ----\nOp ==\n    1 \\* one\n====\n",
+    )
+    .unwrap();
+    // Snapshots without pending changes are not rendered.
+    std::fs::write(snapshots.join("format__b.snap"), "---\nsource: b\n---\n").unwrap();
+
+    let want = format!(
+        "\
+==> {}
+  ---- MODULE A ----   │   ---- MODULE A ----
+- Op == 1   \\* one     │ + Op ==
+                       │ +     1 \\* one
+  ====                 │   ====
+
+",
+        snapshots.join("format__a.snap").display()
+    );
+
+    cmd()
+        .arg("snapshots")
+        .arg("diff")
+        .arg("--width")
+        .arg("48")
+        .arg(dir.path())
+        .assert()
+        .success()
+        .stderr(predicate::eq(""))
+        .stdout(predicate::eq(want));
+}
+
+/// Frame each JSON-RPC message in `msgs` for a language server.
+fn lsp_frames(msgs: &[serde_json::Value]) -> String {
+    msgs.iter()
+        .map(|v| {
+            let body = v.to_string();
+            format!("Content-Length: {}\r\n\r\n{body}", body.len())
+        })
+        .collect()
+}
+
+/// Split the framed language server `output` into its messages.
+fn lsp_messages(output: &[u8]) -> Vec<serde_json::Value> {
+    let mut output = std::str::from_utf8(output).unwrap();
+    let mut out = vec![];
+    while let Some(rest) = output.strip_prefix("Content-Length: ") {
+        let (len, rest) = rest.split_once("\r\n\r\n").unwrap();
+        let (body, rest) = rest.split_at(len.parse().unwrap());
+        out.push(serde_json::from_str(body).unwrap());
+        output = rest;
+    }
+    assert_eq!(output, "");
+    out
+}
+
+/// Format documents and report syntax errors over the language server
+/// protocol.
+#[test]
+fn test_lsp() {
+    use serde_json::json;
+
+    let uri = "file:///specs/My%20Spec.tla";
+    let input = lsp_frames(&[
+        json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {} }),
+        json!({ "jsonrpc": "2.0", "method": "initialized", "params": {} }),
+        json!({ "jsonrpc": "2.0", "method": "textDocument/didOpen", "params": {
+            "textDocument": {
+                "uri": uri,
+                "languageId": "tlaplus",
+                "version": 1,
+                "text": "---- MODULE A ----\nOp   ==   1\n====",
+            },
+        }}),
+        json!({ "jsonrpc": "2.0", "id": 2, "method": "textDocument/formatting", "params": {
+            "textDocument": { "uri": uri },
+            "options": { "tabSize": 4, "insertSpaces": true },
+        }}),
+        json!({ "jsonrpc": "2.0", "method": "textDocument/didChange", "params": {
+            "textDocument": { "uri": uri, "version": 2 },
+            "contentChanges": [{ "text": "---- MODULE A ----\nOp == )\n====" }],
+        }}),
+        json!({ "jsonrpc": "2.0", "id": 3, "method": "textDocument/formatting", "params": {
+            "textDocument": { "uri": uri },
+            "options": { "tabSize": 4, "insertSpaces": true },
+        }}),
+        json!({ "jsonrpc": "2.0", "id": 4, "method": "textDocument/hover", "params": {} }),
+        json!({ "jsonrpc": "2.0", "id": 5, "method": "shutdown" }),
+        json!({ "jsonrpc": "2.0", "method": "exit" }),
+    ]);
+
+    let assert = cmd()
+        .arg("lsp")
+        .arg("--line-width=40")
+        .write_stdin(input)
+        .assert()
+        .success();
+    let got = lsp_messages(&assert.get_output().stdout);
+
+    assert_eq!(
+        got,
+        [
+            json!({ "jsonrpc": "2.0", "id": 1, "result": {
+                "capabilities": {
+                    "textDocumentSync": 1,
+                    "documentFormattingProvider": true,
+                    "documentSymbolProvider": true,
+                    "documentOnTypeFormattingProvider": { "firstTriggerCharacter": "\n" },
+                },
+                "serverInfo": { "name": "tlafmt", "version": env!("CARGO_PKG_VERSION") },
+            }}),
+            json!({ "jsonrpc": "2.0", "method": "textDocument/publishDiagnostics", "params": {
+                "uri": uri,
+                "version": 1,
+                "diagnostics": [],
+            }}),
+            json!({ "jsonrpc": "2.0", "id": 2, "result": [{
+                "range": {
+                    "start": { "line": 0, "character": 0 },
+                    "end": { "line": 2, "character": 4 },
+                },
+                "newText": "--------------- MODULE A ---------------\nOp == 1\n========================================",
+            }]}),
+            json!({ "jsonrpc": "2.0", "method": "textDocument/publishDiagnostics", "params": {
+                "uri": uri,
+                "version": 2,
+                "diagnostics": [{
+                    "range": {
+                        "start": { "line": 1, "character": 0 },
+                        "end": { "line": 2, "character": 0 },
+                    },
+                    "severity": 1,
+                    "source": "tlafmt",
+                    "message": "syntax error",
+                }],
+            }}),
+            // Specs with syntax errors are not formatted.
+            json!({ "jsonrpc": "2.0", "id": 3, "result": [] }),
+            json!({ "jsonrpc": "2.0", "id": 4, "error": {
+                "code": -32601,
+                "message": "unsupported method \"textDocument/hover\"",
+            }}),
+            json!({ "jsonrpc": "2.0", "id": 5, "result": null }),
+        ]
+    );
+
+    // Exiting without a shutdown request is an error.
+    cmd()
+        .arg("lsp")
+        .write_stdin(lsp_frames(&[json!({ "jsonrpc": "2.0", "method": "exit" })]))
+        .assert()
+        .code(predicate::eq(1));
+}
+
+/// List the top-level definitions of a document as an outline over the
+/// language server protocol.
+#[test]
+fn test_lsp_document_symbols() {
+    use serde_json::json;
+
+    let uri = "file:///specs/A.tla";
+    let input = lsp_frames(&[
+        json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {} }),
+        json!({ "jsonrpc": "2.0", "method": "textDocument/didOpen", "params": {
+            "textDocument": {
+                "uri": uri,
+                "languageId": "tlaplus",
+                "version": 1,
+                "text": "---- MODULE A ----\nEXTENDS Naturals\nCONSTANT N\nVARIABLE x\n\nInit ==\n    x = N\nLOCAL Next == x' = x + 1\nTHEOREM Init => x = N\n====\n",
+            },
+        }}),
+        json!({ "jsonrpc": "2.0", "id": 2, "method": "textDocument/documentSymbol", "params": {
+            "textDocument": { "uri": uri },
+        }}),
+        json!({ "jsonrpc": "2.0", "id": 3, "method": "shutdown" }),
+        json!({ "jsonrpc": "2.0", "method": "exit" }),
+    ]);
+
+    let assert = cmd().arg("lsp").write_stdin(input).assert().success();
+    let got = lsp_messages(&assert.get_output().stdout);
+
+    let symbol = |name: &str, kind: u8, start: u64, end: (u64, u64), column: u64| {
+        json!({
+            "name": name,
+            "kind": kind,
+            "range": {
+                "start": { "line": start, "character": 0 },
+                "end": { "line": end.0, "character": end.1 },
+            },
+            "selectionRange": {
+                "start": { "line": start, "character": column },
+                "end": { "line": start, "character": column + name.len() as u64 },
+            },
+        })
+    };
+
+    assert_eq!(
+        got[2],
+        json!({ "jsonrpc": "2.0", "id": 2, "result": [
+            symbol("N", 14, 2, (2, 10), 9),
+            symbol("x", 13, 3, (3, 10), 9),
+            symbol("Init", 12, 5, (6, 9), 0),
+            symbol("Next", 12, 7, (7, 24), 6),
+            symbol("THEOREM", 17, 8, (8, 21), 0),
+        ]})
+    );
+}
+
+/// Indent a new line to continue the enclosing list when a newline is typed.
+#[test]
+fn test_lsp_on_type_formatting() {
+    use serde_json::json;
+
+    let uri = "file:///specs/A.tla";
+    let on_type = |id: u64, line: u64| {
+        json!({ "jsonrpc": "2.0", "id": id, "method": "textDocument/onTypeFormatting", "params": {
+            "textDocument": { "uri": uri },
+            "position": { "line": line, "character": 0 },
+            "ch": "\n",
+            "options": { "tabSize": 4, "insertSpaces": true },
+        }})
+    };
+
+    let input = lsp_frames(&[
+        json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {} }),
+        json!({ "jsonrpc": "2.0", "method": "textDocument/didOpen", "params": {
+            "textDocument": {
+                "uri": uri,
+                "languageId": "tlaplus",
+                "version": 1,
+                "text": "---- MODULE A ----\nNext == /\\ x' = 1\n  \n====\n",
+            },
+        }}),
+        on_type(2, 2),
+        // Lines outside of a list are left unchanged.
+        on_type(3, 1),
+        json!({ "jsonrpc": "2.0", "id": 4, "method": "shutdown" }),
+        json!({ "jsonrpc": "2.0", "method": "exit" }),
+    ]);
+
+    let assert = cmd().arg("lsp").write_stdin(input).assert().success();
+    let got = lsp_messages(&assert.get_output().stdout);
+
+    assert_eq!(
+        got[2],
+        json!({ "jsonrpc": "2.0", "id": 2, "result": [{
+            "range": {
+                "start": { "line": 2, "character": 0 },
+                "end": { "line": 2, "character": 2 },
+            },
+            "newText": "        ",
+        }]})
+    );
+    assert_eq!(got[3], json!({ "jsonrpc": "2.0", "id": 3, "result": [] }));
+}
+
+/// Indent a new line with tabs when hard tabs are enabled, leaving existing tab
+/// indentation in place.
+#[test]
+fn test_lsp_on_type_formatting_hard_tabs() {
+    use serde_json::json;
+
+    let uri = "file:///specs/A.tla";
+    let on_type = |id: u64, line: u64| {
+        json!({ "jsonrpc": "2.0", "id": id, "method": "textDocument/onTypeFormatting", "params": {
+            "textDocument": { "uri": uri },
+            "position": { "line": line, "character": 0 },
+            "ch": "\n",
+            "options": { "tabSize": 4, "insertSpaces": false },
+        }})
+    };
+
+    let input = lsp_frames(&[
+        json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {} }),
+        json!({ "jsonrpc": "2.0", "method": "textDocument/didOpen", "params": {
+            "textDocument": {
+                "uri": uri,
+                "languageId": "tlaplus",
+                "version": 1,
+                "text": "---- MODULE A ----\nNext == /\\ x' = 1\n  \n\t    /\\ y' = 2\n====\n",
+            },
+        }}),
+        on_type(2, 2),
+        on_type(3, 3),
+        json!({ "jsonrpc": "2.0", "id": 4, "method": "shutdown" }),
+        json!({ "jsonrpc": "2.0", "method": "exit" }),
+    ]);
+
+    let assert = cmd()
+        .arg("lsp")
+        .arg("--hard-tabs")
+        .write_stdin(input)
+        .assert()
+        .success();
+    let got = lsp_messages(&assert.get_output().stdout);
+
+    assert_eq!(
+        got[2],
+        json!({ "jsonrpc": "2.0", "id": 2, "result": [{
+            "range": {
+                "start": { "line": 2, "character": 0 },
+                "end": { "line": 2, "character": 2 },
+            },
+            "newText": "\t\t",
+        }]})
+    );
+    assert_eq!(got[3], json!({ "jsonrpc": "2.0", "id": 3, "result": [] }));
+}
+
+/// Report the grammar node kinds missing from a set of specs, writing stubs for
+/// them.
+#[test]
+fn test_snapshots_coverage() {
+    let dir = dir();
+    let specs = dir.path().join("specs");
+    std::fs::create_dir_all(&specs).unwrap();
+    std::fs::write(
+        specs.join("A.tla"),
+        "---- MODULE A ----\nOp == {1, 2}\n====\n",
+    )
+    .unwrap();
+
+    let stubs = dir.path().join("stubs");
+    let assert = cmd()
+        .arg("snapshots")
+        .arg("coverage")
+        .arg("--stubs")
+        .arg(&stubs)
+        .arg(&specs)
+        .assert()
+        .success()
+        .stderr(predicate::eq(""));
+
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout).into_owned();
+    assert!(stdout.starts_with("covered "), "{stdout}");
+    assert!(stdout.contains("missing: bound_op\n"), "{stdout}");
+    assert!(!stdout.contains("missing: set_literal\n"), "{stdout}");
+
+    let stub = std::fs::read_to_string(stubs.join("Coverage_bound_op.tla")).unwrap();
+    assert!(stub.starts_with("---- MODULE Coverage_bound_op ----\n"));
+    assert!(!stubs.join("Coverage_set_literal.tla").exists());
+}
+
+/// Emit a .git-blame-ignore-revs entry and manifest for a formatting commit.
+#[test]
+fn test_blame_ignore() {
+    let dir = dir();
+    let git = |args: &[&str]| {
+        let status = std::process::Command::new("git")
+            .args(args)
+            .current_dir(dir.path())
+            .env("GIT_AUTHOR_NAME", "Bananas")
+            .env("GIT_AUTHOR_EMAIL", "bananas@example.com")
+            .env("GIT_AUTHOR_DATE", "2025-01-02T00:00:00Z")
+            .env("GIT_COMMITTER_NAME", "Bananas")
+            .env("GIT_COMMITTER_EMAIL", "bananas@example.com")
+            .status()
+            .unwrap();
+        assert!(status.success());
+    };
+
+    let unformatted = std::fs::read_to_string(BAD_PATH).unwrap();
+    git(&["init", "-q"]);
+    std::fs::write(dir.path().join("a.tla"), &unformatted).unwrap();
+    std::fs::write(dir.path().join("b.tla"), "---- MODULE B ----\n====\n").unwrap();
+    git(&["add", "."]);
+    git(&["commit", "-q", "-m", "initial"]);
+
+    std::fs::write(dir.path().join("a.tla"), format(BAD_PATH)).unwrap();
+    std::fs::write(
+        dir.path().join("b.tla"),
+        "---- MODULE B ----\nX == 1\n====\n",
+    )
+    .unwrap();
+    git(&["commit", "-q", "-a", "-m", "Reformat specs"]);
+
+    let out = cmd()
+        .current_dir(dir.path())
+        .arg("blame-ignore")
+        .arg("--annotate")
+        .arg("manifest.json")
+        .assert()
+        .success()
+        .stderr(predicate::eq(""))
+        .get_output()
+        .stdout
+        .clone();
+    let out = String::from_utf8(out).unwrap();
+
+    let (comment, hash) = out.trim_end().split_once('\n').unwrap();
+    assert_eq!(comment, "# Reformat specs (Bananas, 2025-01-02)");
+    assert_eq!(hash.len(), 40);
+
+    let manifest: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(dir.path().join("manifest.json")).unwrap())
+            .unwrap();
+    assert_eq!(
+        manifest,
+        serde_json::json!({
+            "commit": hash,
+            "files": [
+                {"path": "a.tla", "formatting_only": true},
+                {"path": "b.tla", "formatting_only": false},
+            ],
+        })
+    );
+}
+
+/// Latin-1 encoded specs are detected and formatted, preserving the encoding.
+#[test]
+fn test_latin1_encoding() {
+    // "\* Jos\xe9" in Latin-1.
+    let input = b"---- MODULE B ----\n\\* Jos\xe9\nOp   ==   1\n====\n";
+    let want = [
+        format!("{0} MODULE B {0}\n", "-".repeat(35)).as_bytes(),
+        b"\\* Jos\xe9\nOp == 1\n",
+        "=".repeat(80).as_bytes(),
+    ]
+    .concat();
+
+    cmd()
+        .arg("--stdin")
+        .write_stdin(input.as_slice())
+        .assert()
+        .success()
+        .stderr(predicate::eq(""))
+        .stdout(predicate::eq(want.as_slice()));
+
+    cmd()
+        .arg("--stdin")
+        .arg("--encoding=latin-1")
+        .write_stdin(input.as_slice())
+        .assert()
+        .success()
+        .stdout(predicate::eq(want.as_slice()));
+
+    // Forcing UTF-8 rejects the input.
+    cmd()
+        .arg("--stdin")
+        .arg("--encoding=utf-8")
+        .write_stdin(input.as_slice())
+        .assert()
+        .failure()
+        .stdout(predicate::eq(""));
+}
+
+/// Format multiple files in one invocation, without aborting on per-file
+/// errors.
+#[test]
+fn test_multiple_files() {
+    let dir = dir();
+    let a = dir.path().join("a.tla");
+    let b = dir.path().join("b.tla");
+    let missing = dir.path().join("missing.tla");
+    std::fs::write(&a, "---- MODULE A ----\nX   ==   1\n====\n").unwrap();
+    std::fs::write(&b, "---- MODULE B ----\nY   ==   2\n====\n").unwrap();
+
+    let header = |name| format!("{0} MODULE {name} {0}\n", "-".repeat(35));
+    let footer = "=".repeat(80);
+
+    // Output is concatenated to stdout.
+    cmd()
+        .arg(&a)
+        .arg(&b)
+        .assert()
+        .success()
+        .stderr(predicate::eq(""))
+        .stdout(predicate::eq(format!(
+            "{}X == 1\n{footer}\n{}Y == 2\n{footer}\n",
+            header("A"),
+            header("B")
+        )));
+
+    // Errors are reported per-file, and the remaining files formatted.
+    cmd()
+        .arg(&missing)
+        .arg(&a)
+        .assert()
+        .code(predicate::eq(1))
+        .stderr(predicate::str::starts_with(format!(
+            "{}: failed to read input file",
+            missing.display()
+        )))
+        .stdout(predicate::eq(format!("{}X == 1\n{footer}\n", header("A"))));
+
+    // Check mode reports each unformatted file.
+    cmd().arg("--in-place").arg(&a).assert().success();
+    cmd()
+        .arg("--check")
+        .arg(&a)
+        .arg(&b)
+        .assert()
+        .code(predicate::eq(3))
+        .stderr(predicate::str::contains(format!("{}:\n", b.display())))
+        .stderr(predicate::str::contains(format!("{}:\n", a.display())).not());
+
+    // And in-place formats them all.
+    cmd().arg("--in-place").arg(&a).arg(&b).assert().success();
+    cmd().arg("--check").arg(&a).arg(&b).assert().success();
+}
+
+/// Symlinks and special files are skipped when formatting multiple specs,
+/// unless symlinks are explicitly followed.
+#[cfg(unix)]
+#[test]
+fn test_multiple_files_skip_links() {
+    let dir = dir();
+    let a = dir.path().join("a.tla");
+    let link = dir.path().join("link.tla");
+    let fifo = dir.path().join("fifo.tla");
+    let unformatted = "---- MODULE A ----\nX   ==   1\n====\n";
+    std::fs::write(&a, unformatted).unwrap();
+    std::os::unix::fs::symlink("a.tla", &link).unwrap();
+    let status = std::process::Command::new("mkfifo")
+        .arg(&fifo)
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    cmd()
+        .arg("--in-place")
+        .arg(&link)
+        .arg(&fifo)
+        .assert()
+        .success()
+        .stderr(predicate::eq(format!(
+            "{}: skipped symlink (use --follow-symlinks to format the target)\n\
+             {}: skipped special file\n",
+            link.display(),
+            fifo.display()
+        )));
+    assert_eq!(std::fs::read_to_string(&a).unwrap(), unformatted);
+
+    // Following the link formats the target, leaving the link in place.
+    cmd()
+        .arg("--in-place")
+        .arg("--follow-symlinks")
+        .arg(&link)
+        .arg(&fifo)
+        .assert()
+        .success();
+    assert!(link.is_symlink());
+    assert_ne!(std::fs::read_to_string(&a).unwrap(), unformatted);
+}
+
+/// Format many specs read as JSON-lines from stdin.
+#[test]
+fn test_batch() {
+    let unformatted = std::fs::read_to_string(BAD_PATH).unwrap();
+    let control = format(BAD_PATH);
+
+    let input = format!(
+        "{}\n\nnot json\n{}\n",
+        serde_json::json!({"path": "a.tla", "content": unformatted}),
+        serde_json::json!({"path": "b.tla", "content": control}),
+    );
+
+    let out = cmd()
+        .arg("--batch")
+        .write_stdin(input)
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+
+    let got = String::from_utf8(out)
+        .unwrap()
+        .lines()
+        .map(|v| serde_json::from_str::<serde_json::Value>(v).unwrap())
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        got,
+        [
+            serde_json::json!({"path": "a.tla", "content": control}),
+            serde_json::json!({"path": null, "error": "invalid request: expected ident at line 1 column 2"}),
+            serde_json::json!({"path": "b.tla", "content": control}),
+        ]
+    );
+}
+
+/// Each --daemon response is written before the next request is read.
+#[test]
+fn test_daemon() {
+    use std::{
+        io::{BufRead, BufReader, Write},
+        process::{Command, Stdio},
+    };
+
+    let unformatted = std::fs::read_to_string(BAD_PATH).unwrap();
+    let control = format(BAD_PATH);
+
+    let mut child = Command::new(assert_cmd::cargo::cargo_bin("tlafmt"))
+        .arg("--daemon")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    let mut stdin = child.stdin.take().unwrap();
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+
+    for path in ["a.tla", "b.tla"] {
+        let req = serde_json::json!({"path": path, "content": unformatted});
+        writeln!(stdin, "{req}").unwrap();
+
+        let mut line = String::new();
+        stdout.read_line(&mut line).unwrap();
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&line).unwrap(),
+            serde_json::json!({"path": path, "content": control})
+        );
+    }
+
+    drop(stdin);
+    assert!(child.wait().unwrap().success());
+}
+
+/// Reject --batch with a file path.
+#[test]
+fn test_batch_conflicts_path() {
+    cmd()
+        .arg("--batch")
+        .arg(BAD_PATH)
+        .assert()
+        .failure()
+        .stdout(predicate::eq(""))
+        .code(predicate::eq(2));
+}
+
+/// Report definitions out of the conventional section order.
+#[test]
+fn test_sections() {
+    let dir = dir();
+    let good = dir.path().join("good.tla");
+    let bad = dir.path().join("bad.tla");
+    std::fs::write(
+        &good,
+        "---- MODULE A ----\nVARIABLE x\nInit == x = 0\nNext == x' = x\n====\n",
+    )
+    .unwrap();
+    std::fs::write(
+        &bad,
+        "---- MODULE B ----\nVARIABLE x\nInit == x = 0\nHelper == 1\nCONSTANT N\n====\n",
+    )
+    .unwrap();
+
+    cmd()
+        .arg("sections")
+        .arg(&good)
+        .assert()
+        .success()
+        .stdout(predicate::eq(""));
+
+    cmd()
+        .arg("sections")
+        .arg(&good)
+        .arg(&bad)
+        .assert()
+        .code(predicate::eq(5))
+        .stdout(predicate::eq(format!(
+            "{0}:4: Helper (definitions) appears after the behaviour section\n\
+             {0}:5: N (constants) appears after the behaviour section\n",
+            bad.display()
+        )));
+}
+
+/// Report unformatted lines as checkstyle XML.
+#[test]
+fn test_check_emit_checkstyle() {
+    let dir = dir();
+    let formatted = format!(
+        "{0} MODULE A {0}\nX == 1\n{1}",
+        "-".repeat(35),
+        "=".repeat(80)
+    );
+    let good = dir.path().join("good.tla");
+    let bad = dir.path().join("bad.tla");
+    std::fs::write(&good, &formatted).unwrap();
+    std::fs::write(&bad, formatted.replace("X == 1", "X ==  1\nY ==   \"<y>\"")).unwrap();
+
+    cmd()
+        .arg("--check")
+        .arg("--emit")
+        .arg("checkstyle")
+        .arg(&good)
+        .arg(&bad)
+        .assert()
+        .code(predicate::eq(3))
+        .stdout(predicate::eq(format!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+             <checkstyle version=\"4.3\">\n  \
+             <file name=\"{}\">\n    \
+             <error line=\"2\" severity=\"warning\" message=\"Should be `X == 1&#10;Y == &quot;&lt;y&gt;&quot;`\" source=\"tlafmt\" />\n  \
+             </file>\n\
+             </checkstyle>\n",
+            bad.display()
+        )));
+
+    // A formatted spec produces an empty report.
+    cmd()
+        .arg("--check")
+        .arg("--emit")
+        .arg("checkstyle")
+        .arg(&good)
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<checkstyle version=\"4.3\">\n</checkstyle>\n",
+        ));
+}
+
+/// Place a license header above the module header when missing.
+#[test]
+fn test_license_header() {
+    let dir = dir();
+    let path = dir.path().join("spec.tla");
+    let formatted = format!(
+        "{0} MODULE B {0}\nX == 1\n{1}",
+        "-".repeat(35),
+        "=".repeat(80)
+    );
+    std::fs::write(&path, "---- MODULE B ----\nX ==  1\n====\n").unwrap();
+
+    // From the config file.
+    std::fs::write(
+        dir.path().join("tlafmt.toml"),
+        "license_header = \"\"\"\n\\\\* Copyright Bananas\n\"\"\"\n",
+    )
+    .unwrap();
+    cmd()
+        .arg(&path)
+        .assert()
+        .success()
+        .stdout(predicate::eq(format!("\\* Copyright Bananas\n{formatted}")));
+
+    // The CLI flag takes precedence, and an existing header is not repeated.
+    let header = dir.path().join("header.txt");
+    std::fs::write(&header, "\\* SPDX-License-Identifier: MIT\n").unwrap();
+    std::fs::write(
+        &path,
+        format!("\\* SPDX-License-Identifier: MIT\n\n{formatted}"),
+    )
+    .unwrap();
+    cmd()
+        .arg("--license-header")
+        .arg(&header)
+        .arg("--check")
+        .arg(&path)
+        .assert()
+        .success();
+}
+
+/// Print a summary of the changes across all input files.
+#[test]
+fn test_stats() {
+    let dir = dir();
+    let a = dir.path().join("a.tla");
+    let b = dir.path().join("b.tla");
+    std::fs::write(
+        &a,
+        format!(
+            "{0} MODULE A {0}\nX == 1\n{1}\n",
+            "-".repeat(35),
+            "=".repeat(80)
+        ),
+    )
+    .unwrap();
+    std::fs::write(
+        &b,
+        "---- MODULE B ----\nX == 1 \\* one\nVeryLongName == 2\n\n\n\nY ==  2 \\* two\n====\n",
+    )
+    .unwrap();
+
+    cmd()
+        .arg("--list-different")
+        .arg("--stats")
+        .arg(&a)
+        .arg(&b)
+        .assert()
+        .code(3)
+        .stderr(predicate::eq(format!(
+            "\
+files scanned:      2
+files changed:      1
+lines reformatted:  3
+comments realigned: 1
+longest line:       80 columns ({}:1)
+",
+            a.display()
+        )));
+}
+
+/// Print format-on-save configuration snippets for each supported editor.
+#[test]
+fn test_editor_setup() {
+    for editor in ["vscode", "nvim", "emacs", "helix"] {
+        cmd()
+            .arg("editor-setup")
+            .arg(editor)
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("--stdin"))
+            .stderr(predicate::eq(""));
+    }
+
+    let dir = dir();
+    let path = dir.path().join("languages.toml");
+    cmd()
+        .arg("editor-setup")
+        .arg("helix")
+        .arg("--output")
+        .arg(&path)
+        .assert()
+        .success()
+        .stdout(predicate::eq(""));
+    assert!(std::fs::read_to_string(&path)
+        .unwrap()
+        .contains("[[language]]"));
+
+    cmd()
+        .arg("editor-setup")
+        .arg("notepad")
+        .assert()
+        .code(predicate::eq(2));
+}
+
+/// Print the parsed AST or lowered tokens of a spec.
+#[test]
+fn test_parse() {
+    let dir = dir();
+    let path = dir.path().join("a.tla");
+    std::fs::write(&path, "---- MODULE A ----\nOp == (1 + )\n====\n").unwrap();
+
+    cmd()
+        .arg("parse")
+        .arg(&path)
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("source_file 1:1-4:1\n"))
+        .stdout(predicate::str::contains("    identifier 2:1-2:3 \"Op\"\n"))
+        .stdout(predicate::str::contains("MISSING identifier_ref 2:11-2:11"))
+        .stdout(predicate::str::contains("\"==\" 2:4-2:6\n"));
+
+    cmd()
+        .arg("parse")
+        .arg("--emit")
+        .arg("tokens")
+        .arg(&path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(" 0 Ident \"Op\"\n 0 Eq2 \"==\"\n"));
+
+    cmd()
+        .arg("parse")
+        .arg("--emit")
+        .arg("bananas")
+        .arg(&path)
+        .assert()
+        .code(predicate::eq(2));
+}
+
+#[test]
+fn test_man() {
+    cmd()
+        .arg("man")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(".TH tlafmt 1"))
+        .stdout(predicate::str::contains("\\-\\-in\\-place"))
+        .stdout(predicate::str::contains("editor\\-setup"));
+
+    let dir = dir();
+    let path = dir.path().join("tlafmt.1");
+    cmd()
+        .arg("man")
+        .arg("--output")
+        .arg(&path)
+        .assert()
+        .success()
+        .stdout(predicate::eq(""));
+    assert!(std::fs::read_to_string(&path).unwrap().contains(".SH NAME"));
+}
+
+/// List the style rules applied with the formatting configuration.
+#[test]
+fn test_rules() {
+    let dir = dir();
+
+    let out = cmd()
+        .current_dir(dir.path())
+        .arg("rules")
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let out = String::from_utf8(out).unwrap();
+    assert!(out.starts_with("TF001  module-header "), "{out}");
+    assert!(!out.contains("TF004"), "{out}");
+
+    // Options enable their rules.
+    cmd()
+        .current_dir(dir.path())
+        .arg("rules")
+        .arg("--collapse-single-item-lists")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "TF004  collapse-single-item-lists  Remove the bullet from single item lists\n",
+        ));
+
+    // And inactive rules are listed with --all.
+    cmd()
+        .current_dir(dir.path())
+        .arg("rules")
+        .arg("--all")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "TF004  collapse-single-item-lists  Remove the bullet from single item lists (inactive)\n",
+        ));
+}
+
+/// Prompt for each file with changes before writing it in-place.
+#[test]
+fn test_interactive() {
+    let dir = dir();
+    let unformatted = "---- MODULE A ----\nX   ==   1\n====\n";
+    let formatted = format!(
+        "{0} MODULE A {0}\nX == 1\n{1}",
+        "-".repeat(35),
+        "=".repeat(80)
+    );
+
+    let paths = ["a.tla", "b.tla", "c.tla", "d.tla"].map(|v| dir.path().join(v));
+    for p in &paths {
+        std::fs::write(p, unformatted).unwrap();
+    }
+    // An already formatted file is never prompted for.
+    std::fs::write(&paths[1], &formatted).unwrap();
+
+    // Apply (after an invalid answer), skip, then quit.
+    cmd()
+        .arg("--in-place")
+        .arg("--interactive")
+        .args(&paths)
+        .write_stdin("bananas\ny\nn\nq\n")
+        .assert()
+        .success()
+        .stdout(predicate::eq(""))
+        .stderr(predicate::str::contains("Apply these changes to").count(4))
+        .stderr(predicate::str::contains(format!("{}:\n", paths[1].display())).not());
+
+    let got = paths
+        .each_ref()
+        .map(|v| std::fs::read_to_string(v).unwrap());
+    assert_eq!(got[0], formatted);
+    assert_eq!(got[1], formatted);
+    assert_eq!(got[2], unformatted);
+    assert_eq!(got[3], unformatted);
+
+    // Interactive mode only applies to in-place runs.
+    cmd()
+        .arg("--interactive")
+        .arg(&paths[0])
+        .assert()
+        .code(predicate::eq(2));
+}
+
+/// Directories are searched for specs, skipping any paths ignored by git.
+#[test]
+fn test_directory_respects_gitignore() {
+    let dir = dir();
+    let status = std::process::Command::new("git")
+        .args(["init", "-q"])
+        .current_dir(dir.path())
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let unformatted = "---- MODULE A ----\nX   ==   1\n====\n";
+    std::fs::create_dir_all(dir.path().join("build")).unwrap();
+    std::fs::create_dir_all(dir.path().join("sub")).unwrap();
+    std::fs::write(dir.path().join(".gitignore"), "build/\n").unwrap();
+    std::fs::write(dir.path().join("notes.txt"), "bananas").unwrap();
+    for p in ["a.tla", "build/b.tla", "sub/c.tla"] {
+        std::fs::write(dir.path().join(p), unformatted).unwrap();
+    }
+
+    let stderr = String::from_utf8(
+        cmd()
+            .arg("--check")
+            .arg(dir.path())
+            .assert()
+            .code(predicate::eq(3))
+            .get_output()
+            .stderr
+            .clone(),
+    )
+    .unwrap();
+
+    let headers = stderr
+        .lines()
+        .filter(|v| v.ends_with(".tla:"))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        headers,
+        [
+            format!("{}:", dir.path().join("a.tla").display()),
+            format!("{}:", dir.path().join("sub/c.tla").display()),
+        ]
+    );
+
+    cmd().arg("--in-place").arg(dir.path()).assert().success();
+    cmd().arg("--check").arg(dir.path()).assert().success();
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("build/b.tla")).unwrap(),
+        unformatted
+    );
+}
+
+/// Directories outside of a git repository skip any paths listed in a
+/// `.gitignore` or `.ignore` file.
+#[test]
+fn test_directory_ignore_files_outside_repo() {
+    // Not within the repository of the crate.
+    let dir = tempfile::tempdir().unwrap();
+    let path = |v: &str| dir.path().join(v);
+
+    let unformatted = "---- MODULE A ----\nX   ==   1\n====\n";
+    std::fs::create_dir_all(path("build")).unwrap();
+    std::fs::create_dir_all(path("sub")).unwrap();
+    std::fs::write(path(".gitignore"), "build/\n").unwrap();
+    std::fs::write(path("sub/.ignore"), "skip.tla\n").unwrap();
+    for p in ["a.tla", "build/b.tla", "sub/c.tla", "sub/skip.tla"] {
+        std::fs::write(path(p), unformatted).unwrap();
+    }
+
+    let stderr = String::from_utf8(
+        cmd()
+            .arg("--check")
+            .arg(dir.path())
+            .assert()
+            .code(predicate::eq(3))
+            .get_output()
+            .stderr
+            .clone(),
+    )
+    .unwrap();
+
+    let headers = stderr
+        .lines()
+        .filter(|v| v.ends_with(".tla:"))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        headers,
+        [
+            format!("{}:", path("a.tla").display()),
+            format!("{}:", path("sub/c.tla").display()),
+        ]
+    );
+}
+
+/// Directories are searched into git submodules, and files ignored by git are
+/// skipped even if they are tracked.
+#[test]
+fn test_directory_submodule() {
+    let dir = dir();
+    let path = |v: &str| dir.path().join(v);
+    let git = |dir: &Path, args: &[&str]| {
+        let status = std::process::Command::new("git")
+            .args(["-c", "protocol.file.allow=always"])
+            .args(args)
+            .current_dir(dir)
+            .env("GIT_AUTHOR_NAME", "Bananas")
+            .env("GIT_AUTHOR_EMAIL", "bananas@example.com")
+            .env("GIT_COMMITTER_NAME", "Bananas")
+            .env("GIT_COMMITTER_EMAIL", "bananas@example.com")
+            .status()
+            .unwrap();
+        assert!(status.success());
+    };
+
+    let unformatted = "---- MODULE A ----\nX   ==   1\n====\n";
+
+    // The repository added as a submodule.
+    std::fs::create_dir_all(path("upstream")).unwrap();
+    std::fs::write(path("upstream/b.tla"), unformatted).unwrap();
+    git(&path("upstream"), &["init", "-q"]);
+    git(&path("upstream"), &["add", "."]);
+    git(&path("upstream"), &["commit", "-q", "-m", "initial"]);
+
+    std::fs::create_dir_all(path("repo")).unwrap();
+    std::fs::write(path("repo/a.tla"), unformatted).unwrap();
+    std::fs::write(path("repo/ignored.tla"), unformatted).unwrap();
+    git(&path("repo"), &["init", "-q"]);
+    git(&path("repo"), &["add", "."]);
+    let upstream = path("upstream");
+    let upstream = upstream.to_str().unwrap();
+    git(
+        &path("repo"),
+        &["submodule", "add", "-q", upstream, "vendor"],
+    );
+    git(&path("repo"), &["commit", "-q", "-m", "initial"]);
+    std::fs::write(path("repo/.gitignore"), "ignored.tla\n").unwrap();
+
+    let stderr = String::from_utf8(
+        cmd()
+            .arg("--check")
+            .arg(path("repo"))
+            .assert()
+            .code(predicate::eq(3))
+            .get_output()
+            .stderr
+            .clone(),
+    )
+    .unwrap();
+
+    let headers = stderr
+        .lines()
+        .filter(|v| v.ends_with(".tla:"))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        headers,
+        [
+            format!("{}:", path("repo/a.tla").display()),
+            format!("{}:", path("repo/vendor/b.tla").display()),
+        ]
+    );
+}
+
+/// Long check mode diffs are truncated unless --full-diff is specified.
+#[test]
+fn test_check_truncates_diff() {
+    let input = format!(
+        "---- MODULE A ----\n{}====\n",
+        (0..300)
+            .map(|i| format!("X{i}   ==   {i}\n"))
+            .collect::<String>()
+    );
+
+    let stderr = String::from_utf8(
+        cmd()
+            .arg("--stdin")
+            .arg("--check")
+            .write_stdin(input.clone())
+            .assert()
+            .code(predicate::eq(3))
+            .get_output()
+            .stderr
+            .clone(),
+    )
+    .unwrap();
+
+    // 400 diff lines, with 2 changed lines for the header and footer and
+    // each definition.
+    let diff_lines = stderr
+        .lines()
+        .filter(|v| v.starts_with("- ") || v.starts_with("+ "))
+        .count();
+    assert_eq!(diff_lines, 400);
+    assert!(stderr.ends_with("... 204 more changed lines, run with --full-diff\n"));
+
+    cmd()
+        .arg("--stdin")
+        .arg("--check")
+        .arg("--full-diff")
+        .write_stdin(input)
+        .assert()
+        .code(predicate::eq(3))
+        .stderr(predicate::str::contains("more changed lines").not())
+        .stderr(predicate::str::contains("+ X299 == 299\n"));
+}
+
+/// Quiet mode prints no diff, and verbose mode logs each file processed.
+#[test]
+fn test_quiet_verbose() {
+    cmd()
+        .arg("--check")
+        .arg("--quiet")
+        .arg(BAD_PATH)
+        .assert()
+        .code(predicate::eq(3))
+        .stdout(predicate::eq(""))
+        .stderr(predicate::eq(""));
+
+    cmd()
+        .arg("--check")
+        .arg("--verbose")
+        .arg(BAD_PATH)
+        .assert()
+        .code(predicate::eq(3))
+        .stderr(predicate::str::contains(format!(
+            "{BAD_PATH}: checked, needs formatting in "
+        )));
+
+    let dir = dir();
+    let path = dir.path().join("spec.tla");
+    std::fs::write(&path, "---- MODULE B ----\nX ==  1\n====\n").unwrap();
+    cmd()
+        .arg("-i")
+        .arg("-v")
+        .arg(&path)
+        .assert()
+        .success()
+        .stderr(predicate::str::starts_with(format!(
+            "{}: written in ",
+            path.display()
+        )));
+
+    cmd().arg("-q").arg("-v").arg(&path).assert().code(2);
+}
+
+/// Check mode reports unformatted files without failing with --exit-zero.
+#[test]
+fn test_check_exit_zero() {
+    cmd()
+        .arg("--check")
+        .arg("--exit-zero")
+        .arg(BAD_PATH)
+        .assert()
+        .success()
+        .stdout(predicate::eq(""))
+        .stderr(predicate::str::contains("@@ Integrate @@"));
+
+    // Errors still fail the run.
+    cmd()
+        .arg("--check")
+        .arg("--exit-zero")
+        .arg(BAD_PATH)
+        .arg("missing.tla")
+        .assert()
+        .code(predicate::eq(1));
+
+    // Only applies to check mode.
+    cmd()
+        .arg("--exit-zero")
+        .arg(BAD_PATH)
+        .assert()
+        .code(predicate::eq(2));
+}
+
+/// Specs containing syntax errors are formatted except for the definitions
+/// containing them, exiting with code 4 unless --exit-zero is given in check
+/// mode.
+#[test]
+fn test_exit_code_syntax_error() {
+    let dir = dir();
+    let invalid = dir.path().join("invalid.tla");
+    std::fs::write(&invalid, "---- MODULE A ----\nX == )\n====\n").unwrap();
+
+    cmd()
+        .arg(&invalid)
+        .assert()
+        .code(predicate::eq(4))
+        .stdout(predicate::str::contains("X == )"))
+        .stderr(predicate::eq(format!(
+            "{}:2:1: syntax error: X == )\n",
+            invalid.display()
+        )));
+
+    // The syntax error is not reported in quiet mode.
+    cmd()
+        .arg("--quiet")
+        .arg(&invalid)
+        .assert()
+        .code(predicate::eq(4))
+        .stderr(predicate::eq(""));
+
+    // Syntax errors take precedence over unformatted specs.
+    cmd()
+        .arg("--check")
+        .arg(&invalid)
+        .arg(BAD_PATH)
+        .assert()
+        .code(predicate::eq(4));
+
+    cmd()
+        .arg("--check")
+        .arg("--exit-zero")
+        .arg(&invalid)
+        .assert()
+        .success();
+
+    // And errors take precedence over syntax errors.
+    cmd()
+        .arg(&invalid)
+        .arg("missing.tla")
+        .assert()
+        .code(predicate::eq(1));
+}
+
+/// Specs containing syntax errors are not formatted in strict mode.
+#[test]
+fn test_strict() {
+    let dir = dir();
+    let invalid = dir.path().join("invalid.tla");
+    std::fs::write(&invalid, "---- MODULE A ----\nX == )\n====\n").unwrap();
+
+    cmd()
+        .arg("--strict")
+        .arg(&invalid)
+        .assert()
+        .code(predicate::eq(1))
+        .stdout(predicate::eq(""))
+        .stderr(predicate::str::contains("SyntaxErrors"));
+
+    // A config file may enable strict mode.
+    std::fs::write(dir.path().join("tlafmt.toml"), "strict = true\n").unwrap();
+    cmd()
+        .arg("--check")
+        .arg(&invalid)
+        .assert()
+        .code(predicate::eq(1));
+}
+
+/// Verification of the formatted output can be disabled, without changing the
+/// output.
+#[test]
+fn test_skip_verify() {
+    let dir = dir();
+    let path = dir.path().join("a.tla");
+    std::fs::write(&path, "---- MODULE A ----\nX   ==   /\\ a # b\n====\n").unwrap();
+
+    let want = cmd()
+        .arg(&path)
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+
+    cmd()
+        .arg("--skip-verify")
+        .arg(&path)
+        .assert()
+        .success()
+        .stdout(predicate::eq(want));
+}
+
+/// Formatting the output a second time may be checked to produce the same
+/// output.
+#[test]
+fn test_verify_idempotent() {
+    let dir = dir();
+    let path = dir.path().join("a.tla");
+    std::fs::write(&path, "---- MODULE A ----\nX   ==   /\\ a # b\n====\n").unwrap();
+
+    let want = cmd()
+        .arg(&path)
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+
+    cmd()
+        .arg("--verify-idempotent")
+        .arg(&path)
+        .assert()
+        .success()
+        .stdout(predicate::eq(want));
+}
+
+/// Format only the specs changed in the git repository.
+#[test]
+fn test_changed() {
+    let dir = dir();
+    let git = |args: &[&str]| {
+        let status = std::process::Command::new("git")
+            .args(args)
+            .current_dir(dir.path())
+            .env("GIT_AUTHOR_NAME", "Bananas")
+            .env("GIT_AUTHOR_EMAIL", "bananas@example.com")
+            .env("GIT_COMMITTER_NAME", "Bananas")
+            .env("GIT_COMMITTER_EMAIL", "bananas@example.com")
+            .status()
+            .unwrap();
+        assert!(status.success());
+    };
+
+    let unformatted = "---- MODULE A ----\nX   ==   1\n====\n";
+    let formatted = format!(
+        "{0} MODULE A {0}\nX == 1\n{1}\n",
+        "-".repeat(35),
+        "=".repeat(80)
+    );
+    let path = |v: &str| dir.path().join(v);
+
+    git(&["init", "-q"]);
+    std::fs::write(path("a.tla"), &formatted).unwrap();
+    std::fs::write(path("b.tla"), &formatted).unwrap();
+    std::fs::write(path("ignored.tla"), unformatted).unwrap();
+    std::fs::write(path(".gitignore"), "ignored.tla\n").unwrap();
+    git(&["add", "."]);
+    git(&["commit", "-q", "-m", "initial"]);
+
+    // Nothing has changed.
+    cmd()
+        .arg("--check")
+        .arg("--changed")
+        .current_dir(dir.path())
+        .assert()
+        .success()
+        .stderr(predicate::eq(""));
+
+    // A modified and an untracked spec.
+    std::fs::write(path("b.tla"), unformatted).unwrap();
+    std::fs::write(path("c.tla"), unformatted).unwrap();
+
+    let check = |args: &[&str]| {
+        String::from_utf8(
+            cmd()
+                .arg("--check")
+                .args(args)
+                .current_dir(dir.path())
+                .assert()
+                .code(predicate::eq(3))
+                .get_output()
+                .stderr
+                .clone(),
+        )
+        .unwrap()
+        .lines()
+        .filter(|v| v.ends_with(".tla:"))
+        .map(|v| v.rsplit('/').next().unwrap().to_string())
+        .collect::<Vec<_>>()
+    };
+    assert_eq!(check(&["--changed"]), ["b.tla:", "c.tla:"]);
+
+    // Committed changes are included since an earlier revision.
+    git(&["commit", "-q", "-a", "-m", "modify b"]);
+    assert_eq!(check(&["--changed"]), ["c.tla:"]);
+    assert_eq!(
+        check(&["--changed", "--since", "HEAD~1"]),
+        ["b.tla:", "c.tla:"]
+    );
+    assert_eq!(check(&["--since", "HEAD~1"]), ["b.tla:", "c.tla:"]);
+
+    // Specs are selected from git instead of the provided paths.
+    cmd()
+        .arg("--changed")
+        .arg("a.tla")
+        .current_dir(dir.path())
+        .assert()
+        .code(predicate::eq(2));
+}
+
+#[test]
+fn test_changed_lines() {
+    let dir = dir();
+    let git = |args: &[&str]| {
+        let status = std::process::Command::new("git")
+            .args(args)
+            .current_dir(dir.path())
+            .env("GIT_AUTHOR_NAME", "Bananas")
+            .env("GIT_AUTHOR_EMAIL", "bananas@example.com")
+            .env("GIT_COMMITTER_NAME", "Bananas")
+            .env("GIT_COMMITTER_EMAIL", "bananas@example.com")
+            .status()
+            .unwrap();
+        assert!(status.success());
+    };
+    let path = dir.path().join("a.tla");
+
+    git(&["init", "-q"]);
+    std::fs::write(&path, "---- MODULE A ----\nX   ==   1\nY   ==   2\n====\n").unwrap();
+    git(&["add", "."]);
+    git(&["commit", "-q", "-m", "initial"]);
+
+    // Only the modified definition is reformatted.
+    std::fs::write(&path, "---- MODULE A ----\nX   ==   1\nY   ==   3\n====\n").unwrap();
+    let want = "---- MODULE A ----\nX   ==   1\nY == 3\n====\n";
+
+    cmd()
+        .arg("--changed-lines")
+        .arg("a.tla")
+        .current_dir(dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::eq(want));
+
+    // And the same changes read from a unified diff.
+    let diff = dir.path().join("changes.diff");
+    std::fs::write(
+        &diff,
+        "--- a/a.tla\n+++ b/a.tla\n@@ -3 +3 @@\n-Y   ==   2\n+Y   ==   3\n",
+    )
+    .unwrap();
+
+    cmd()
+        .arg("--changed-lines-from")
+        .arg(&diff)
+        .arg(&path)
+        .assert()
+        .success()
+        .stdout(predicate::eq(want));
+}
+
+/// Reformat only the definitions overlapping the --lines ranges.
+#[test]
+fn test_lines() {
+    let input = "---- MODULE A ----\nX   ==   1\nY   ==   2\nZ   ==   3\n====\n";
+
+    cmd()
+        .arg("--stdin")
+        .arg("--lines")
+        .arg("3:3")
+        .arg("--lines=4:4")
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            "---- MODULE A ----\nX   ==   1\nY == 2\nZ == 3\n====\n",
+        ));
+
+    for range in ["0:1", "3:2", "3", "a:b"] {
+        cmd()
+            .arg("--stdin")
+            .arg("--lines")
+            .arg(range)
+            .write_stdin(input)
+            .assert()
+            .code(predicate::eq(2))
+            .stderr(predicate::str::contains("invalid line range"));
+    }
+}
+
+#[test]
+fn test_only() {
+    let input = "---- MODULE A ----\nX   ==   1\nY   ==   2\nZ   ==   3\n====\n";
+
+    cmd()
+        .arg("--stdin")
+        .arg("--only")
+        .arg("X,Z")
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            "---- MODULE A ----\nX == 1\nY   ==   2\nZ == 3\n====\n",
+        ));
+
+    cmd()
+        .arg("--stdin")
+        .arg("--only")
+        .arg("Bananas")
+        .write_stdin(input)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("UnknownIdent(\"Bananas\")"));
+}
+
+#[test]
+fn test_cache() {
+    let dir = dir();
+    let spec = dir.path().join("a.tla");
+    let cache = dir.path().join("cache");
+    std::fs::write(&spec, "---- MODULE A ----\nX   ==   1\n====\n").unwrap();
+
+    let in_place = || {
+        cmd()
+            .arg("--in-place")
+            .arg("--cache-location")
+            .arg(&cache)
+            .arg(&spec)
+            .assert()
+            .success();
+    };
+
+    // The formatted spec is recorded in the cache.
+    in_place();
+    assert!(std::fs::read_to_string(&cache).unwrap().contains("a.tla"));
+
+    // And is not rewrote by subsequent runs.
+    let old = std::time::SystemTime::UNIX_EPOCH;
+    std::fs::File::options()
+        .write(true)
+        .open(&spec)
+        .unwrap()
+        .set_modified(old)
+        .unwrap();
+    in_place();
+    assert_eq!(std::fs::metadata(&spec).unwrap().modified().unwrap(), old);
+
+    // Changing the options invalidates the cache.
+    cmd()
+        .arg("--check")
+        .arg("--cache-location")
+        .arg(&cache)
+        .arg("--line-width=40")
+        .arg(&spec)
+        .assert()
+        .code(predicate::eq(3));
+
+    // As does changing the spec.
+    std::fs::write(&spec, "---- MODULE A ----\nX   ==   1\n====\n").unwrap();
+    cmd()
+        .arg("--check")
+        .arg("--cache-location")
+        .arg(&cache)
+        .arg(&spec)
+        .assert()
+        .code(predicate::eq(3));
+}
+
+#[test]
+fn test_list_different() {
+    let dir = dir();
+    let path = |v: &str| dir.path().join(v);
+    std::fs::write(path("a.tla"), "---- MODULE A ----\nX   ==   1\n====\n").unwrap();
+    std::fs::write(
+        path("b.tla"),
+        format!(
+            "{0} MODULE B {0}\nX == 1\n{1}\n",
+            "-".repeat(35),
+            "=".repeat(80)
+        ),
+    )
+    .unwrap();
+
+    cmd()
+        .arg("-l")
+        .arg(dir.path())
+        .assert()
+        .code(predicate::eq(3))
+        .stdout(predicate::eq(format!("{}\n", path("a.tla").display())))
+        .stderr(predicate::eq(""));
+
+    cmd()
+        .arg("--list-different")
+        .arg("--exit-zero")
+        .arg(path("b.tla"))
+        .assert()
+        .success()
+        .stdout(predicate::eq(""));
+}
+
+#[test]
+fn test_check_unified_diff() {
+    let dir = dir();
+    let header = format!("{0} MODULE A {0}", "-".repeat(35));
+    let footer = "=".repeat(80);
+    std::fs::write(
+        dir.path().join("a.tla"),
+        format!(
+            "{header}\nX   ==   1\nA == 1\nB == 2\nC == 3\nD == 4\nE == 5\nF == 6\nG == 7\nY   ==   2\n{footer}"
+        ),
+    )
+    .unwrap();
+
+    cmd()
+        .arg("--check")
+        .arg("--diff")
+        .arg("a.tla")
+        .current_dir(dir.path())
+        .assert()
+        .code(predicate::eq(3))
+        .stderr(predicate::eq(""))
+        .stdout(predicate::eq(format!(
+            "\
+--- a/a.tla
++++ b/a.tla
+@@ -1,5 +1,5 @@
+ {header}
+-X   ==   1
++X == 1
+ A == 1
+ B == 2
+ C == 3
+@@ -7,5 +7,5 @@
+ E == 5
+ F == 6
+ G == 7
+-Y   ==   2
++Y == 2
+ {footer}
+\\ No newline at end of file
+"
+        )));
+}